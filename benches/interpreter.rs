@@ -0,0 +1,141 @@
+//! Benchmarks for the interpreter hot loop.
+//!
+//! The crate has no dependencies, so instead of criterion this is a small
+//! hand-rolled harness (`harness = false` in `Cargo.toml`): each benchmark
+//! runs its program repeatedly and reports the median time per run and the
+//! instruction throughput. Run with `cargo bench`.
+
+use std::time::Instant;
+
+use karel::interpreter::{Interpreter, StepResult};
+use karel::parser::preprocess;
+use karel::world::World;
+
+const SAMPLES: usize = 30;
+
+/// Run the program against a fresh clone of the world, returning executed
+/// instruction count.
+fn run_once(lines: &[karel::parser::Line<'_>], world: &World) -> usize {
+    let mut interpreter = Interpreter::new(lines.to_vec(), world.clone()).unwrap();
+    let mut steps = 0;
+    loop {
+        match interpreter.step() {
+            Ok(StepResult::Running) => steps += 1,
+            Ok(StepResult::Finished) => return steps,
+            Err(error) => panic!("benchmark program failed: {error}"),
+        }
+    }
+}
+
+fn benchmark(name: &str, source: &str, world: World) {
+    let lines = preprocess(source);
+    // Warm up and get the step count.
+    let steps = run_once(&lines, &world);
+
+    let mut samples = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        run_once(&lines, &world);
+        samples.push(start.elapsed());
+    }
+    samples.sort();
+    let median = samples[SAMPLES / 2];
+    let throughput = steps as f64 / median.as_secs_f64();
+    println!("{name:24} {steps:>8} steps  median {median:>12?}  {throughput:>12.0} steps/s");
+}
+
+/// Time `preprocess` itself on a large generated program. Preprocessing
+/// borrows from the source instead of copying every line, so this mostly
+/// measures the scan, not the allocator.
+fn benchmark_preprocess(name: &str, lines: usize) {
+    let mut source = String::from("def main # generated\n");
+    for index in 0..lines {
+        source.push_str("  put   # beeper\n  move\n\n");
+        if index % 10 == 0 {
+            source.push_str("# progress marker\n");
+        }
+    }
+    source.push_str("  die\nenddef\n");
+
+    // Warm up.
+    let parsed = preprocess(&source).len();
+
+    let mut samples = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        std::hint::black_box(preprocess(std::hint::black_box(&source)));
+        samples.push(start.elapsed());
+    }
+    samples.sort();
+    let median = samples[SAMPLES / 2];
+    let throughput = parsed as f64 / median.as_secs_f64();
+    println!("{name:24} {parsed:>8} lines  median {median:>12?}  {throughput:>12.0} lines/s");
+}
+
+fn main() {
+    // A tight `while` loop: condition evaluation plus a single move, the
+    // most common shape of the hot loop.
+    let mut corridor = World::new(200, 1);
+    corridor.robot.position = karel::Position::new(0, 0);
+    benchmark(
+        "corridor-walk",
+        "def main\n while! wall\n  move\n endwhile\n die\nenddef",
+        corridor,
+    );
+
+    // Deeply nested `repeat` blocks: stresses the block start/end scanning.
+    benchmark(
+        "nested-repeat",
+        "def main\n repeat 20\n  repeat 20\n   repeat 20\n    turn-left\n   endrepeat\n  endrepeat\n endrepeat\n die\nenddef",
+        World::default(),
+    );
+
+    // Procedure calls: stresses the definition lookup on every call.
+    benchmark(
+        "call-heavy",
+        "def main\n repeat 500\n  call a\n endrepeat\n die\nenddef\n\
+         def a\n call b\nenddef\n\
+         def b\n call c\nenddef\n\
+         def c\n turn-left\nenddef",
+        World::default(),
+    );
+
+    // Beeper churn: put and take on the same tile.
+    benchmark(
+        "beeper-churn",
+        "def main\n repeat 1000\n  put\n  take\n endrepeat\n die\nenddef",
+        World::default(),
+    );
+
+    // Preprocessing throughput on generated programs of increasing size.
+    benchmark_preprocess("preprocess-20k", 10_000);
+    benchmark_preprocess("preprocess-200k", 100_000);
+
+    // Generating and snapshotting a large stress world.
+    benchmark_snapshot();
+}
+
+/// Time generating a checkerboard 1000×1000 world and cloning it, the two
+/// world-side operations stress scenarios lean on.
+fn benchmark_snapshot() {
+    let mut samples = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        let mut world = World::new(1000, 1000);
+        for y in 0..1000 {
+            for x in (y % 2..1000).step_by(2) {
+                world.set_wall(karel::Position::new(x, y), true);
+            }
+        }
+        let snapshot = std::hint::black_box(world.clone());
+        std::hint::black_box(snapshot);
+        samples.push(start.elapsed());
+    }
+    samples.sort();
+    println!(
+        "{:24} {:>8} tiles  median {:>12?}",
+        "world-snapshot-1m",
+        1_000_000,
+        samples[SAMPLES / 2]
+    );
+}
@@ -0,0 +1,75 @@
+/* C API for the Karel interpreter.
+ *
+ * All functions are synchronous and none of them keep references to the
+ * buffers you pass in. A NULL return (or a negative step result) means an
+ * error; karel_last_error() describes the most recent one on this thread.
+ *
+ * Link against the karel_c cdylib built from bindings/c.
+ */
+
+#ifndef KAREL_H
+#define KAREL_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A validated program running against one world. */
+typedef struct KarelSession KarelSession;
+
+/* Step results. */
+#define KAREL_RUNNING 0
+#define KAREL_FINISHED 1
+#define KAREL_ERROR (-1)
+
+/* Robot facings as returned by karel_robot_direction(). */
+#define KAREL_NORTH 0
+#define KAREL_EAST 1
+#define KAREL_SOUTH 2
+#define KAREL_WEST 3
+
+/* Start `program` (Karel source, NUL-terminated UTF-8) in the world
+ * described by `world_json`, or in the default empty 10x10 world when
+ * `world_json` is NULL. Returns NULL on a parse or world error. */
+KarelSession *karel_session_new(const char *program, const char *world_json);
+
+/* Free a session. NULL is allowed. */
+void karel_session_free(KarelSession *session);
+
+/* Execute one instruction. Returns KAREL_RUNNING, KAREL_FINISHED or
+ * KAREL_ERROR; after an error the robot is dead and the session stays
+ * finished. */
+int karel_session_step(KarelSession *session);
+
+/* Run the program to its end. Returns KAREL_FINISHED or KAREL_ERROR. */
+int karel_session_run(KarelSession *session);
+
+/* The 1-based source line about to run, or 0 when finished. */
+int karel_session_current_line(const KarelSession *session);
+
+/* Robot state. */
+int karel_robot_x(const KarelSession *session);
+int karel_robot_y(const KarelSession *session);
+int karel_robot_direction(const KarelSession *session);
+int karel_robot_alive(const KarelSession *session);
+
+/* Beepers on the tile at (x, y), or -1 when out of bounds. */
+int karel_beepers_at(const KarelSession *session, int x, int y);
+
+/* The current world as a JSON document. Free with karel_string_free(). */
+char *karel_session_world_json(const KarelSession *session);
+
+/* Free a string returned by this library. NULL is allowed. */
+void karel_string_free(char *string);
+
+/* The most recent error on this thread, or an empty string. The pointer is
+ * valid until the next failing call on the same thread. */
+const char *karel_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* KAREL_H */
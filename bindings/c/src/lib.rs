@@ -0,0 +1,306 @@
+//! C bindings for the Karel interpreter.
+//!
+//! The exported API is documented in `include/karel.h`. Conventions: every
+//! constructor can fail and then returns null, fallible calls return a
+//! negative value, and [`karel_last_error`] fetches a description of the
+//! most recent failure on the calling thread. Strings handed out must be
+//! freed with [`karel_string_free`].
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+
+use karel::interpreter::{Interpreter, StepResult};
+use karel::{parser, worldfile, Direction, Position, World};
+
+/// An opaque session: a validated program running against one world.
+pub struct KarelSession {
+    interpreter: Interpreter<'static>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Remember `error` for [`karel_last_error`].
+fn set_last_error(error: impl std::fmt::Display) {
+    let message = error.to_string().replace('\0', " ");
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = CString::new(message).expect("NUL bytes were replaced");
+    });
+}
+
+/// Read a NUL-terminated UTF-8 string; on bad input records an error and
+/// returns `None`.
+///
+/// # Safety
+/// `pointer` must be null or point at a NUL-terminated buffer.
+unsafe fn read_string(pointer: *const c_char) -> Option<String> {
+    if pointer.is_null() {
+        set_last_error("unexpected NULL argument");
+        return None;
+    }
+    match CStr::from_ptr(pointer).to_str() {
+        Ok(string) => Some(string.to_string()),
+        Err(error) => {
+            set_last_error(format!("argument is not valid UTF-8: {error}"));
+            None
+        }
+    }
+}
+
+/// # Safety
+/// `program` must be a NUL-terminated buffer; `world_json` must be null or
+/// a NUL-terminated buffer. See `karel.h`.
+#[no_mangle]
+pub unsafe extern "C" fn karel_session_new(
+    program: *const c_char,
+    world_json: *const c_char,
+) -> *mut KarelSession {
+    let Some(source) = read_string(program) else {
+        return std::ptr::null_mut();
+    };
+    let world = if world_json.is_null() {
+        World::default()
+    } else {
+        let Some(world_json) = read_string(world_json) else {
+            return std::ptr::null_mut();
+        };
+        match worldfile::from_json(&world_json) {
+            Ok(world) => world,
+            Err(error) => {
+                set_last_error(error);
+                return std::ptr::null_mut();
+            }
+        }
+    };
+    let lines = parser::preprocess(&source);
+    if let Err(error) = parser::validate(&lines) {
+        set_last_error(error);
+        return std::ptr::null_mut();
+    }
+    // The session outlives the source string, so detach the lines.
+    let lines = lines.into_iter().map(parser::Line::into_owned).collect();
+    match Interpreter::new(lines, world) {
+        Ok(interpreter) => Box::into_raw(Box::new(KarelSession { interpreter })),
+        Err(error) => {
+            set_last_error(error);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `session` must come from [`karel_session_new`] and not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn karel_session_free(session: *mut KarelSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_session_step(session: *mut KarelSession) -> c_int {
+    let Some(session) = session.as_mut() else {
+        set_last_error("unexpected NULL session");
+        return -1;
+    };
+    match session.interpreter.step() {
+        Ok(StepResult::Running) => 0,
+        Ok(StepResult::Finished) => 1,
+        Err(error) => {
+            set_last_error(error);
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_session_run(session: *mut KarelSession) -> c_int {
+    let Some(session) = session.as_mut() else {
+        set_last_error("unexpected NULL session");
+        return -1;
+    };
+    match session.interpreter.run().into_result() {
+        Ok(()) => 1,
+        Err(error) => {
+            set_last_error(error);
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_session_current_line(session: *const KarelSession) -> c_int {
+    match session.as_ref() {
+        Some(session) => session.interpreter.current_line().unwrap_or(0) as c_int,
+        None => 0,
+    }
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_robot_x(session: *const KarelSession) -> c_int {
+    session
+        .as_ref()
+        .map(|session| session.interpreter.world.robot.position.x as c_int)
+        .unwrap_or(-1)
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_robot_y(session: *const KarelSession) -> c_int {
+    session
+        .as_ref()
+        .map(|session| session.interpreter.world.robot.position.y as c_int)
+        .unwrap_or(-1)
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_robot_direction(session: *const KarelSession) -> c_int {
+    let Some(session) = session.as_ref() else {
+        return -1;
+    };
+    match session.interpreter.world.robot.direction {
+        Direction::North => 0,
+        Direction::East => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+    }
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_robot_alive(session: *const KarelSession) -> c_int {
+    session
+        .as_ref()
+        .map(|session| session.interpreter.world.robot.alive as c_int)
+        .unwrap_or(0)
+}
+
+/// # Safety
+/// `session` must be a live session pointer.
+#[no_mangle]
+pub unsafe extern "C" fn karel_beepers_at(
+    session: *const KarelSession,
+    x: c_int,
+    y: c_int,
+) -> c_int {
+    let Some(session) = session.as_ref() else {
+        return -1;
+    };
+    if x < 0 || y < 0 {
+        return -1;
+    }
+    let position = Position::new(x as usize, y as usize);
+    if !session.interpreter.world.in_bounds(position) {
+        return -1;
+    }
+    session.interpreter.world.beepers_at(position) as c_int
+}
+
+/// # Safety
+/// `session` must be a live session pointer; free the result with
+/// [`karel_string_free`].
+#[no_mangle]
+pub unsafe extern "C" fn karel_session_world_json(session: *const KarelSession) -> *mut c_char {
+    let Some(session) = session.as_ref() else {
+        set_last_error("unexpected NULL session");
+        return std::ptr::null_mut();
+    };
+    let json = worldfile::to_json(&session.interpreter.world).to_string();
+    CString::new(json)
+        .expect("JSON contains no NUL bytes")
+        .into_raw()
+}
+
+/// # Safety
+/// `string` must come from this library and not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn karel_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// The most recent error on this thread, or an empty string. Valid until
+/// the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn karel_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cstring(text: &str) -> CString {
+        CString::new(text).unwrap()
+    }
+
+    #[test]
+    fn create_step_and_query() {
+        let program = cstring("def main\n move\n put\n die\nenddef");
+        unsafe {
+            let session = karel_session_new(program.as_ptr(), std::ptr::null());
+            assert!(!session.is_null());
+            assert_eq!(karel_session_step(session), 0); // move
+            assert_eq!(karel_robot_x(session), 1);
+            assert_eq!(karel_session_step(session), 0); // put
+            assert_eq!(karel_beepers_at(session, 1, 0), 1);
+            assert_eq!(karel_session_step(session), 1); // die
+            assert_eq!(karel_robot_alive(session), 0);
+            karel_session_free(session);
+        }
+    }
+
+    #[test]
+    fn errors_are_fetched_as_strings() {
+        let program = cstring("def main\n take\nenddef");
+        unsafe {
+            let session = karel_session_new(program.as_ptr(), std::ptr::null());
+            assert_eq!(karel_session_run(session), -1);
+            let error = CStr::from_ptr(karel_last_error()).to_str().unwrap();
+            assert!(error.contains("no beeper"), "{error}");
+            karel_session_free(session);
+        }
+    }
+
+    #[test]
+    fn invalid_programs_fail_to_create() {
+        let program = cstring("def main\n fly\nenddef");
+        unsafe {
+            let session = karel_session_new(program.as_ptr(), std::ptr::null());
+            assert!(session.is_null());
+            let error = CStr::from_ptr(karel_last_error()).to_str().unwrap();
+            assert!(error.contains("unknown instruction"), "{error}");
+        }
+    }
+
+    #[test]
+    fn world_json_round_trips() {
+        let program = cstring("def main\n die\nenddef");
+        unsafe {
+            let session = karel_session_new(program.as_ptr(), std::ptr::null());
+            let json = karel_session_world_json(session);
+            let text = CStr::from_ptr(json).to_str().unwrap().to_string();
+            karel_string_free(json);
+            karel_session_free(session);
+
+            let world_json = cstring(&text);
+            let again = karel_session_new(program.as_ptr(), world_json.as_ptr());
+            assert!(!again.is_null());
+            karel_session_free(again);
+        }
+    }
+}
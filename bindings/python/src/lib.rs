@@ -0,0 +1,249 @@
+//! Python bindings for the Karel interpreter.
+//!
+//! Exposes just enough to script grading and world generation:
+//!
+//! ```python
+//! import karel
+//!
+//! world = karel.World(5, 5)
+//! world.set_beepers(2, 0, 3)
+//! program = karel.Program("def main\n move\n move\n take\nenddef")
+//! program.run(world)          # raises KarelError on a fatal instruction
+//! assert world.beepers_at(2, 0) == 2
+//! ```
+//!
+//! Parse and runtime failures both surface as `karel.KarelError`.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+use karel_core::interpreter::{Interpreter, StepResult};
+use karel_core::parser::{self, Line};
+use karel_core::render::{render, render_svg, RenderStyle};
+use karel_core::world::{Direction, Position};
+use karel_core::worldfile;
+
+create_exception!(karel, KarelError, PyException, "A Karel parse or runtime error.");
+
+/// A rectangular world with walls, beepers and one robot.
+#[pyclass(name = "World")]
+struct PyWorld {
+    inner: karel_core::World,
+}
+
+#[pymethods]
+impl PyWorld {
+    /// World(width=10, height=10): an empty world, robot at (0, 0) facing east.
+    #[new]
+    #[pyo3(signature = (width = 10, height = 10))]
+    fn new(width: usize, height: usize) -> PyResult<PyWorld> {
+        if width == 0 || height == 0 {
+            return Err(KarelError::new_err("world dimensions must be positive"));
+        }
+        Ok(PyWorld {
+            inner: karel_core::World::new(width, height),
+        })
+    }
+
+    /// Load a world from a `.txt` or `.json` world file.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<PyWorld> {
+        let inner = worldfile::load(std::path::Path::new(path))
+            .map_err(|error| KarelError::new_err(error.to_string()))?;
+        Ok(PyWorld { inner })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.inner.width()
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.inner.height()
+    }
+
+    /// The robot as a (x, y, direction, alive) tuple; direction is one of
+    /// "north", "east", "south", "west".
+    #[getter]
+    fn robot(&self) -> (usize, usize, &'static str, bool) {
+        let robot = self.inner.robot;
+        let direction = match robot.direction {
+            Direction::North => "north",
+            Direction::East => "east",
+            Direction::South => "south",
+            Direction::West => "west",
+        };
+        (robot.position.x, robot.position.y, direction, robot.alive)
+    }
+
+    fn place_robot(&mut self, x: usize, y: usize, direction: &str) -> PyResult<()> {
+        let position = self.position(x, y)?;
+        self.inner.robot.position = position;
+        self.inner.robot.direction = match direction {
+            "north" => Direction::North,
+            "east" => Direction::East,
+            "south" => Direction::South,
+            "west" => Direction::West,
+            other => {
+                return Err(KarelError::new_err(format!("unknown direction {other:?}")))
+            }
+        };
+        Ok(())
+    }
+
+    fn is_wall(&self, x: usize, y: usize) -> bool {
+        self.inner.is_wall(Position::new(x, y))
+    }
+
+    fn set_wall(&mut self, x: usize, y: usize, wall: bool) -> PyResult<()> {
+        let position = self.position(x, y)?;
+        self.inner.set_wall(position, wall);
+        Ok(())
+    }
+
+    fn beepers_at(&self, x: usize, y: usize) -> u8 {
+        self.inner.beepers_at(Position::new(x, y))
+    }
+
+    fn set_beepers(&mut self, x: usize, y: usize, count: u8) -> PyResult<()> {
+        let position = self.position(x, y)?;
+        self.inner.set_beepers(position, count);
+        Ok(())
+    }
+
+    /// The world drawn with ASCII characters, as `karel run` would print it.
+    fn render(&self) -> String {
+        render(&self.inner, RenderStyle::Ascii)
+    }
+
+    /// The world as a standalone SVG document.
+    fn render_svg(&self) -> String {
+        render_svg(&self.inner)
+    }
+
+    /// Save to a `.txt` or `.json` world file.
+    fn save(&self, path: &str) -> PyResult<()> {
+        worldfile::save(&self.inner, std::path::Path::new(path))
+            .map_err(|error| KarelError::new_err(error.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<karel.World {}x{}>",
+            self.inner.width(),
+            self.inner.height()
+        )
+    }
+
+    fn __str__(&self) -> String {
+        self.render()
+    }
+}
+
+impl PyWorld {
+    fn position(&self, x: usize, y: usize) -> PyResult<Position> {
+        let position = Position::new(x, y);
+        if !self.inner.in_bounds(position) {
+            return Err(KarelError::new_err(format!(
+                "({x}, {y}) is outside the {}x{} world",
+                self.inner.width(),
+                self.inner.height()
+            )));
+        }
+        Ok(position)
+    }
+}
+
+/// A validated Karel program, ready to run against worlds.
+#[pyclass(name = "Program")]
+struct PyProgram {
+    lines: Vec<Line<'static>>,
+}
+
+#[pymethods]
+impl PyProgram {
+    /// Program(source): parse and validate Karel source text.
+    #[new]
+    fn new(source: &str) -> PyResult<PyProgram> {
+        let lines = parser::preprocess(source);
+        parser::validate(&lines).map_err(|error| {
+            KarelError::new_err(match error.line() {
+                Some(line) => format!("line {line}: {error}"),
+                None => error.to_string(),
+            })
+        })?;
+        // The program outlives the source string, so detach the lines.
+        let lines = lines.into_iter().map(Line::into_owned).collect();
+        Ok(PyProgram { lines })
+    }
+
+    /// Load and validate a program file.
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<PyProgram> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| KarelError::new_err(format!("{path}: {error}")))?;
+        PyProgram::new(&source)
+    }
+
+    /// Run the program to its end, mutating `world` in place. Raises
+    /// KarelError when the robot dies of an error; the world then holds the
+    /// state it reached.
+    fn run(&self, world: &mut PyWorld) -> PyResult<()> {
+        let mut interpreter = Interpreter::new(self.lines.clone(), world.inner.clone())
+            .map_err(|error| KarelError::new_err(error.to_string()))?;
+        let result = interpreter.run().into_result();
+        world.inner = interpreter.world;
+        result.map_err(|error| KarelError::new_err(error.to_string()))
+    }
+
+    /// Start a step-by-step run against a copy of `world`.
+    fn start(&self, world: &PyWorld) -> PyResult<PySession> {
+        let interpreter = Interpreter::new(self.lines.clone(), world.inner.clone())
+            .map_err(|error| KarelError::new_err(error.to_string()))?;
+        Ok(PySession { interpreter })
+    }
+}
+
+/// A program mid-run; step it and inspect the world after every instruction.
+#[pyclass(name = "Session")]
+struct PySession {
+    interpreter: Interpreter<'static>,
+}
+
+#[pymethods]
+impl PySession {
+    /// Execute one instruction. Returns True while the program is running,
+    /// False once it has finished.
+    fn step(&mut self) -> PyResult<bool> {
+        match self.interpreter.step() {
+            Ok(StepResult::Running) => Ok(true),
+            Ok(StepResult::Finished) => Ok(false),
+            Err(error) => Err(KarelError::new_err(error.to_string())),
+        }
+    }
+
+    /// The 1-based source line about to run, or None when finished.
+    #[getter]
+    fn current_line(&self) -> Option<usize> {
+        self.interpreter.current_line()
+    }
+
+    /// A copy of the world as it looks right now.
+    #[getter]
+    fn world(&self) -> PyWorld {
+        PyWorld {
+            inner: self.interpreter.world.clone(),
+        }
+    }
+}
+
+#[pymodule]
+fn karel(py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyWorld>()?;
+    module.add_class::<PyProgram>()?;
+    module.add_class::<PySession>()?;
+    module.add("KarelError", py.get_type_bound::<KarelError>())?;
+    Ok(())
+}
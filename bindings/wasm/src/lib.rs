@@ -0,0 +1,110 @@
+//! WebAssembly bindings for the Karel interpreter.
+//!
+//! Thin `wasm-bindgen` wrappers over the core crate: parse and validate
+//! source, step a program against a world, serialize worlds as JSON and
+//! render them as SVG. Structured results cross the boundary as JSON
+//! strings so the JavaScript side needs no generated types.
+
+use wasm_bindgen::prelude::*;
+
+use karel::interpreter::{Interpreter, StepResult};
+use karel::json::Value;
+use karel::{parser, render_svg, worldfile, World};
+
+/// Validate `source` and return the diagnostics as a JSON array of
+/// `{line, column, message}` objects. An empty array means the program
+/// is well formed.
+#[wasm_bindgen]
+pub fn check(source: &str) -> String {
+    let diagnostics: Vec<Value> = parser::check(&parser::preprocess(source))
+        .into_iter()
+        .map(|diagnostic| {
+            Value::object([
+                ("line", diagnostic.error.line().unwrap_or(1).into()),
+                ("column", diagnostic.column.into()),
+                ("message", diagnostic.error.to_string().into()),
+            ])
+        })
+        .collect();
+    Value::Array(diagnostics).to_string()
+}
+
+/// Classify `source` for highlighting: a JSON array of
+/// `{line, start, end, kind}` objects, columns 1-based.
+#[wasm_bindgen]
+pub fn highlight(source: &str) -> String {
+    let tokens: Vec<Value> = karel::highlight(source)
+        .into_iter()
+        .map(|(span, kind)| {
+            Value::object([
+                ("line", span.line.into()),
+                ("start", span.start.into()),
+                ("end", span.end.into()),
+                ("kind", format!("{kind:?}").to_lowercase().into()),
+            ])
+        })
+        .collect();
+    Value::Array(tokens).to_string()
+}
+
+/// A program running against a world, stepped from JavaScript.
+#[wasm_bindgen]
+pub struct Session {
+    interpreter: Interpreter<'static>,
+}
+
+#[wasm_bindgen]
+impl Session {
+    /// Start `source` in the world described by `world_json` (the same JSON
+    /// format `karel run --format json` emits), or in the default empty
+    /// world when `world_json` is empty.
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: &str, world_json: &str) -> Result<Session, JsError> {
+        let world = if world_json.is_empty() {
+            World::default()
+        } else {
+            worldfile::from_json(world_json).map_err(|error| JsError::new(&error.to_string()))?
+        };
+        let lines = parser::preprocess(source);
+        parser::validate(&lines).map_err(|error| JsError::new(&error.to_string()))?;
+        // The session outlives the source string, so detach the lines.
+        let lines = lines.into_iter().map(parser::Line::into_owned).collect();
+        let interpreter =
+            Interpreter::new(lines, world).map_err(|error| JsError::new(&error.to_string()))?;
+        Ok(Session { interpreter })
+    }
+
+    /// Execute one instruction. Returns `"running"` or `"finished"`; a
+    /// runtime error is thrown as a JavaScript exception.
+    pub fn step(&mut self) -> Result<String, JsError> {
+        match self.interpreter.step() {
+            Ok(StepResult::Running) => Ok("running".to_string()),
+            Ok(StepResult::Finished) => Ok("finished".to_string()),
+            Err(error) => Err(JsError::new(&error.to_string())),
+        }
+    }
+
+    /// The 1-based source line about to run, or 0 when finished.
+    pub fn current_line(&self) -> usize {
+        self.interpreter.current_line().unwrap_or(0)
+    }
+
+    /// The current world as JSON.
+    pub fn world_json(&self) -> String {
+        worldfile::to_json(&self.interpreter.world).to_string()
+    }
+
+    /// The current world as a standalone SVG document.
+    pub fn world_svg(&self) -> String {
+        render_svg(&self.interpreter.world)
+    }
+}
+
+/// Render a world (as JSON) to SVG without running anything, for world
+/// editors and previews.
+#[wasm_bindgen]
+pub fn world_to_svg(world_json: &str) -> Result<String, JsError> {
+    let world =
+        worldfile::from_json(world_json).map_err(|error| JsError::new(&error.to_string()))?;
+    Ok(render_svg(&world))
+}
@@ -0,0 +1,27 @@
+//! Run arbitrary bytes as an (unvalidated!) program for a bounded number of
+//! steps, so interpreter error paths see malformed programs too.
+//! Run with `cargo fuzz run interpreter`.
+
+#![no_main]
+
+use karel::interpreter::{Interpreter, StepResult};
+use karel::{parser, World};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    // Deliberately skip validation: stepping must stay panic-free even on
+    // programs `check` would reject.
+    let Ok(mut interpreter) = Interpreter::new(parser::preprocess(source), World::default())
+    else {
+        return;
+    };
+    for _ in 0..10_000 {
+        match interpreter.step() {
+            Ok(StepResult::Running) => {}
+            Ok(StepResult::Finished) | Err(_) => break,
+        }
+    }
+});
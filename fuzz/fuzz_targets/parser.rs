@@ -0,0 +1,10 @@
+//! Preprocess and check arbitrary bytes as Karel source.
+//! Run with `cargo fuzz run parser`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = karel::parser::parse_for_fuzzing(data);
+});
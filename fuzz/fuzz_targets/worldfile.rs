@@ -0,0 +1,10 @@
+//! Parse arbitrary bytes as a world file, in both the text and JSON format.
+//! Run with `cargo fuzz run worldfile`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = karel::worldfile::parse_for_fuzzing(data);
+});
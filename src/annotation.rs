@@ -0,0 +1,157 @@
+//! Teacher annotations embedded in program comments.
+//!
+//! Starter code often wants to carry more than code: an inline hint for the
+//! student, a rubric note for the grader. Those travel as structured
+//! comments — `#! hint: try using repeat` — which the ordinary preprocessor
+//! strips like any other comment, so annotated programs run unchanged
+//! everywhere. [`extract`] is the separate pass that reads them back out of
+//! the raw source and attaches each one to the span of lines it sits above:
+//! a single statement, or a whole block when it precedes the block's
+//! opener.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// One `#! key: value` annotation and the region of source it annotates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// What kind of annotation this is (`hint`, `rubric`, ...). The crate
+    /// assigns no meaning to keys; that is the platform's vocabulary.
+    pub key: String,
+    /// The annotation's text, trimmed.
+    pub value: String,
+    /// First and last line of the annotated region, 1-based and inclusive:
+    /// the next statement below the annotation, extended to the matching
+    /// end when that statement opens a block. An annotation with nothing
+    /// below it annotates its own line.
+    pub span: (usize, usize),
+}
+
+/// Extract every `#! key: value` annotation from raw source. `#!` lines
+/// that do not fit the shape are ordinary comments and are ignored.
+pub fn extract(source: &str) -> Vec<Annotation> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut annotations = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(body) = line.trim_start().strip_prefix("#!") else {
+            continue;
+        };
+        let Some((key, value)) = body.split_once(':') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            continue;
+        }
+        annotations.push(Annotation {
+            key: key.to_string(),
+            value: value.trim().to_string(),
+            span: annotated_span(&lines, index),
+        });
+    }
+    annotations
+}
+
+/// The 1-based span the annotation on `lines[index]` attaches to: the next
+/// line holding code, through the matching end if that line opens a block.
+fn annotated_span(lines: &[&str], index: usize) -> (usize, usize) {
+    let Some(start) = (index + 1..lines.len()).find(|&next| is_code(lines[next])) else {
+        return (index + 1, index + 1);
+    };
+    (start + 1, block_end(lines, start) + 1)
+}
+
+/// Does this line hold code once comments and whitespace are gone?
+fn is_code(line: &str) -> bool {
+    !line.split('#').next().unwrap_or("").trim().is_empty()
+}
+
+/// The last line of the block opened at `start`, or `start` itself for a
+/// plain statement. Mirrors the block pairing the parser enforces; an
+/// unclosed block runs to the end of the file.
+fn block_end(lines: &[&str], start: usize) -> usize {
+    if !opens_block(first_word(lines[start])) {
+        return start;
+    }
+    let mut depth = 0usize;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        let word = first_word(line);
+        if opens_block(word) {
+            depth += 1;
+        } else if matches!(word, "enddef" | "endif" | "endwhile" | "endrepeat") {
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                return index;
+            }
+        }
+    }
+    lines.len() - 1
+}
+
+fn opens_block(word: &str) -> bool {
+    matches!(word, "def" | "if" | "if!" | "while" | "while!" | "repeat")
+}
+
+fn first_word(line: &str) -> &str {
+    let text = line.split('#').next().unwrap_or("");
+    text.split_whitespace().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_hint_attaches_to_the_statement_below_it() {
+        let source = "def main\n\
+                      \x20#! hint: the wall is closer than you think\n\
+                      \x20move\n\
+                      enddef";
+        let annotations = extract(source);
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].key, "hint");
+        assert_eq!(annotations[0].value, "the wall is closer than you think");
+        assert_eq!(annotations[0].span, (3, 3));
+    }
+
+    #[test]
+    fn an_annotation_above_a_block_covers_the_whole_block() {
+        let source = "def main\n\
+                      \x20#! rubric: loop structure, 2 points\n\
+                      \x20repeat 3\n\
+                      \x20 move\n\
+                      \x20endrepeat\n\
+                      enddef";
+        let annotations = extract(source);
+        assert_eq!(annotations[0].span, (3, 5));
+    }
+
+    #[test]
+    fn annotations_skip_blank_lines_and_plain_comments() {
+        let source = "#! hint: start by defining main\n\
+                      \n\
+                      # just a comment\n\
+                      def main\n\
+                      enddef";
+        let annotations = extract(source);
+        assert_eq!(annotations[0].span, (4, 5));
+    }
+
+    #[test]
+    fn malformed_structured_comments_are_ordinary_comments() {
+        // No colon, empty key, key with spaces: none of these is an
+        // annotation, and none of them is an error either.
+        let source = "#! remember the walls\n\
+                      #! : orphan value\n\
+                      #! two words: value\n\
+                      def main\n\
+                      enddef";
+        assert!(extract(source).is_empty());
+    }
+
+    #[test]
+    fn a_trailing_annotation_attaches_to_its_own_line() {
+        let source = "def main\n move\nenddef\n#! note: graded out of 10";
+        assert_eq!(extract(source)[0].span, (4, 4));
+    }
+}
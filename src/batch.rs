@@ -0,0 +1,128 @@
+//! Running many Karel programs (or many worlds) across threads.
+//!
+//! Grading the same program over dozens of worlds, or a classroom of
+//! submissions over one world, is embarrassingly parallel: every run owns
+//! its world and only shares the preprocessed program. The crate has no
+//! dependencies, so instead of rayon this uses `std::thread::scope`, which
+//! lets the workers borrow the shared lines directly — no `Arc`, no
+//! channels. Enabled by the `parallel` feature.
+//!
+//! Every run is bounded by [`grade::STEP_BUDGET`] so one non-terminating
+//! submission cannot hang the whole batch.
+
+use std::thread;
+
+use crate::grade::STEP_BUDGET;
+use crate::interpreter::{Interpreter, RunStatus};
+use crate::parser::{self, Line, ParseError};
+use crate::world::World;
+
+/// The outcome of one run: the final world, or a message describing why the
+/// robot died (including the step budget running out).
+pub type RunResult = Result<World, String>;
+
+/// Run one program over every world in parallel. Fails up front if the
+/// program does not validate; the per-world results come back in order.
+pub fn run_on_worlds(source: &str, worlds: &[World]) -> Result<Vec<RunResult>, ParseError> {
+    let lines = parser::preprocess(source);
+    parser::validate(&lines)?;
+    Ok(parallel_map(worlds, |world| run_one(&lines, world.clone())))
+}
+
+/// Run every program over copies of one world in parallel, in order. A
+/// program that does not validate gets its parse error as the result, so
+/// one broken submission does not fail the batch.
+pub fn run_programs(sources: &[String], world: &World) -> Vec<RunResult> {
+    parallel_map(sources, |source| {
+        let lines = parser::preprocess(source);
+        parser::validate(&lines).map_err(|error| error.to_string())?;
+        run_one(&lines, world.clone())
+    })
+}
+
+fn run_one(lines: &[Line<'_>], world: World) -> RunResult {
+    let mut interpreter =
+        Interpreter::new(lines.to_vec(), world).map_err(|error| error.to_string())?;
+    let outcome = interpreter.run_bounded(STEP_BUDGET);
+    match outcome.status {
+        RunStatus::Completed | RunStatus::Halted => Ok(outcome.final_snapshot),
+        RunStatus::Failed(error) => Err(error.to_string()),
+        RunStatus::LimitHit => Err(format!("step limit of {STEP_BUDGET} exceeded")),
+    }
+}
+
+/// Map `job` over `items` on as many threads as the machine offers,
+/// preserving order. Small batches stay on the calling thread.
+fn parallel_map<T: Sync, R: Send>(items: &[T], job: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = thread::available_parallelism()
+        .map(|parallelism| parallelism.get())
+        .unwrap_or(1)
+        .min(items.len());
+    if workers <= 1 {
+        return items.iter().map(job).collect();
+    }
+    let chunk_size = items.len().div_ceil(workers);
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| chunk.iter().map(&job).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("batch workers do not panic"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Position;
+
+    const WALK: &str = "def main\n while! wall\n  move\n endwhile\n die\nenddef";
+
+    #[test]
+    fn one_program_over_many_worlds() {
+        let worlds: Vec<World> = (1..40).map(|width| World::new(width, 1)).collect();
+        let results = run_on_worlds(WALK, &worlds).unwrap();
+        assert_eq!(results.len(), worlds.len());
+        for (width, result) in (1..40).zip(&results) {
+            let world = result.as_ref().unwrap();
+            assert_eq!(world.robot.position, Position::new(width - 1, 0));
+        }
+    }
+
+    #[test]
+    fn many_programs_over_one_world() {
+        let sources: Vec<String> = vec![
+            WALK.to_string(),
+            "def main\n move\nenddef".to_string(),
+            "def main\n broken\nenddef".to_string(),
+            "def main\n take\nenddef".to_string(),
+        ];
+        let results = run_programs(&sources, &World::new(5, 1));
+        assert_eq!(results[0].as_ref().unwrap().robot.position, Position::new(4, 0));
+        assert_eq!(results[1].as_ref().unwrap().robot.position, Position::new(1, 0));
+        assert!(results[2].as_ref().unwrap_err().contains("unknown instruction"));
+        assert!(results[3].as_ref().unwrap_err().contains("beeper"));
+    }
+
+    #[test]
+    fn runaway_programs_hit_the_step_budget() {
+        // turn-left forever: never finishes, never errors.
+        let spin = "def main\n while! beeper\n  turn-left\n endwhile\nenddef";
+        let results = run_on_worlds(spin, &[World::default()]).unwrap();
+        assert!(results[0].as_ref().unwrap_err().contains("step limit"));
+    }
+
+    #[test]
+    fn invalid_batch_program_fails_up_front() {
+        assert!(run_on_worlds("def main\n move", &[World::default()]).is_err());
+    }
+
+    #[test]
+    fn interpreter_state_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Interpreter<'static, World>>();
+    }
+}
@@ -0,0 +1,331 @@
+//! Campaign files: an ordered course of tasks with unlockable levels.
+//!
+//! A campaign strings tasks into a game: levels unlock as earlier ones are
+//! passed, and progress persists in a profile file so a student can put the
+//! course down and pick it back up. The file format is the same TOML slice
+//! the [task files](crate::task) use:
+//!
+//! ```toml
+//! name = "Karel 101"
+//! tasks = ["collect.toml", "maze.toml", "bonus.toml after 1"]
+//! ```
+//!
+//! Each entry names a task file next to the campaign file. By default a
+//! level unlocks once every level before it is passed; `FILE after N`
+//! overrides that to "once any `N` levels are passed", so side quests can
+//! open early. The profile file is plain text, one passed level per line.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::storage::Storage;
+use crate::task::{self, Task, TaskError};
+
+/// One level of a campaign: a task and its unlock condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    /// The task file name, as listed in the campaign (also the name progress
+    /// is recorded under).
+    pub file: String,
+    /// The task itself, loaded eagerly like a task loads its worlds.
+    pub task: Task,
+    /// How many levels must be passed before this one unlocks.
+    pub requires: usize,
+}
+
+/// An ordered course of tasks; see the module docs for the file format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Campaign {
+    pub name: String,
+    pub levels: Vec<Level>,
+}
+
+/// An error in a campaign file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CampaignError {
+    /// A line that is not `key = value` or uses an unknown key.
+    BadSyntax { line: usize },
+    /// A task entry that is not `FILE` or `FILE after N`.
+    BadLevel { entry: String },
+    /// A referenced task file could not be read or parsed.
+    BadTask { file: String, error: TaskError },
+    /// A task file that could not be read at all.
+    Unreadable { file: String, reason: String },
+    /// The campaign lists no tasks.
+    NoLevels,
+    /// A profile line that does not name a level of this campaign.
+    BadProfile { line: usize },
+}
+
+impl fmt::Display for CampaignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CampaignError::BadSyntax { line } => write!(f, "line {line}: expected `key = value`"),
+            CampaignError::BadLevel { entry } => {
+                write!(f, "bad task entry `{entry}` (expected `FILE` or `FILE after N`)")
+            }
+            CampaignError::BadTask { file, error } => write!(f, "task `{file}`: {error}"),
+            CampaignError::Unreadable { file, reason } => write!(f, "task `{file}`: {reason}"),
+            CampaignError::NoLevels => write!(f, "the campaign lists no tasks"),
+            CampaignError::BadProfile { line } => {
+                write!(f, "profile line {line} names no level of this campaign")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CampaignError {}
+
+impl Campaign {
+    /// Parse a campaign file. Task files are resolved relative to
+    /// `directory` and loaded eagerly, so a broken course fails here and not
+    /// halfway through a play session.
+    pub fn parse(source: &str, directory: &Path) -> Result<Campaign, CampaignError> {
+        let mut name = String::new();
+        let mut entries: Vec<String> = Vec::new();
+
+        for (index, raw) in source.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(CampaignError::BadSyntax { line: index + 1 });
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "name" => {
+                    name = task::parse_string(value)
+                        .ok_or(CampaignError::BadSyntax { line: index + 1 })?;
+                }
+                "tasks" => {
+                    entries = task::parse_string_array(value)
+                        .ok_or(CampaignError::BadSyntax { line: index + 1 })?;
+                }
+                _ => return Err(CampaignError::BadSyntax { line: index + 1 }),
+            }
+        }
+        if entries.is_empty() {
+            return Err(CampaignError::NoLevels);
+        }
+
+        let mut levels = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let words: Vec<&str> = entry.split_whitespace().collect();
+            let (file, requires) = match words[..] {
+                [file] => (file, index),
+                [file, "after", count] => match count.parse() {
+                    Ok(count) => (file, count),
+                    Err(_) => return Err(CampaignError::BadLevel { entry: entry.clone() }),
+                },
+                _ => return Err(CampaignError::BadLevel { entry: entry.clone() }),
+            };
+            let task = Task::load(&directory.join(file)).map_err(|error| {
+                CampaignError::BadTask { file: file.to_string(), error }
+            })?;
+            levels.push(Level {
+                file: file.to_string(),
+                task,
+                requires,
+            });
+        }
+        Ok(Campaign { name, levels })
+    }
+
+    /// Read and parse a campaign file from disk.
+    pub fn load(path: &Path) -> Result<Campaign, CampaignError> {
+        let source = std::fs::read_to_string(path).map_err(|error| CampaignError::Unreadable {
+            file: path.display().to_string(),
+            reason: error.to_string(),
+        })?;
+        let directory = path.parent().unwrap_or(Path::new("."));
+        Campaign::parse(&source, directory)
+    }
+
+    /// Is the level at `index` open, given the progress so far?
+    pub fn unlocked(&self, index: usize, progress: &Progress) -> bool {
+        progress.count() >= self.levels[index].requires
+    }
+
+    /// The first level that is unlocked but not yet passed: what the student
+    /// should play next. `None` means the campaign is finished (or the rest
+    /// is locked, which a well-formed course never arranges).
+    pub fn next(&self, progress: &Progress) -> Option<(usize, &Level)> {
+        self.levels
+            .iter()
+            .enumerate()
+            .find(|(index, level)| {
+                self.unlocked(*index, progress) && !progress.passed(&level.file)
+            })
+    }
+
+    /// Read progress from a [`Storage`] backend; a key with nothing stored
+    /// is a fresh start, like a missing profile file.
+    pub fn load_progress(
+        &self,
+        storage: &dyn Storage,
+        key: &str,
+    ) -> Result<Progress, CampaignError> {
+        let source = storage.read(key).map_err(|error| CampaignError::Unreadable {
+            file: key.to_string(),
+            reason: error.to_string(),
+        })?;
+        match source {
+            Some(source) => self.progress(&source),
+            None => Ok(Progress::default()),
+        }
+    }
+
+    /// Parse a profile file against this campaign; see [`Progress`].
+    pub fn progress(&self, source: &str) -> Result<Progress, CampaignError> {
+        let mut progress = Progress::default();
+        for (index, raw) in source.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if !self.levels.iter().any(|level| level.file == line) {
+                return Err(CampaignError::BadProfile { line: index + 1 });
+            }
+            progress.record(line);
+        }
+        Ok(progress)
+    }
+}
+
+/// Which levels have been passed, as recorded in a profile file: one level
+/// file name per line, `#` comments allowed. [`Campaign::progress`] parses
+/// one; [`Progress::to_profile`] writes one back.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Progress {
+    passed: Vec<String>,
+}
+
+impl Progress {
+    /// Has this level been passed?
+    pub fn passed(&self, level: &str) -> bool {
+        self.passed.iter().any(|done| done == level)
+    }
+
+    /// How many levels have been passed.
+    pub fn count(&self) -> usize {
+        self.passed.len()
+    }
+
+    /// Record a passed level. Recording it twice is harmless.
+    pub fn record(&mut self, level: &str) {
+        if !self.passed(level) {
+            self.passed.push(level.to_string());
+        }
+    }
+
+    /// Write the profile into a [`Storage`] backend.
+    pub fn save_to(&self, storage: &mut dyn Storage, key: &str) -> Result<(), std::io::Error> {
+        storage.write(key, &self.to_profile())
+    }
+
+    /// The profile file contents for this progress.
+    pub fn to_profile(&self) -> String {
+        let mut out = String::from("# karel campaign progress\n");
+        for level in &self.passed {
+            out.push_str(level);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A campaign of three one-world tasks in a fresh temp directory.
+    fn course(name: &str) -> (Campaign, std::path::PathBuf) {
+        let directory = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("w.txt"), ">1\n").unwrap();
+        for task in ["a.toml", "b.toml", "c.toml"] {
+            std::fs::write(
+                directory.join(task),
+                "name = \"Level\"\nworlds = [\"w.txt\"]\ngoals = [\"no-beepers\"]\n",
+            )
+            .unwrap();
+        }
+        let source = "name = \"Course\"\ntasks = [\"a.toml\", \"b.toml\", \"c.toml after 1\"]\n";
+        (Campaign::parse(source, &directory).unwrap(), directory)
+    }
+
+    #[test]
+    fn levels_unlock_as_earlier_ones_pass() {
+        let (campaign, _) = course("karel-campaign-unlock");
+        assert_eq!(campaign.name, "Course");
+        let mut progress = Progress::default();
+
+        assert!(campaign.unlocked(0, &progress));
+        assert!(!campaign.unlocked(1, &progress));
+        assert_eq!(campaign.next(&progress).unwrap().0, 0);
+
+        progress.record("a.toml");
+        // `b` needs one pass; `c` says `after 1`, so it is open too.
+        assert!(campaign.unlocked(1, &progress));
+        assert!(campaign.unlocked(2, &progress));
+        assert_eq!(campaign.next(&progress).unwrap().0, 1);
+
+        progress.record("b.toml");
+        progress.record("c.toml");
+        assert_eq!(campaign.next(&progress), None);
+    }
+
+    #[test]
+    fn progress_survives_the_profile_roundtrip() {
+        let (campaign, _) = course("karel-campaign-profile");
+        let mut progress = Progress::default();
+        progress.record("a.toml");
+        progress.record("a.toml");
+        assert_eq!(progress.count(), 1);
+
+        let reloaded = campaign.progress(&progress.to_profile()).unwrap();
+        assert_eq!(reloaded, progress);
+        assert_eq!(
+            campaign.progress("a.toml\nz.toml\n"),
+            Err(CampaignError::BadProfile { line: 2 })
+        );
+    }
+
+    #[test]
+    fn progress_goes_through_any_storage_backend() {
+        use crate::storage::MemoryStorage;
+
+        let (campaign, _) = course("karel-campaign-storage");
+        let mut storage = MemoryStorage::new();
+        // Nothing stored yet: a fresh start, not an error.
+        let mut progress = campaign.load_progress(&storage, "course.profile").unwrap();
+        assert_eq!(progress.count(), 0);
+
+        progress.record("a.toml");
+        progress.save_to(&mut storage, "course.profile").unwrap();
+        let reloaded = campaign.load_progress(&storage, "course.profile").unwrap();
+        assert_eq!(reloaded, progress);
+    }
+
+    #[test]
+    fn broken_campaigns_fail_at_load_time() {
+        let (_, directory) = course("karel-campaign-broken");
+        assert_eq!(
+            Campaign::parse("name = \"x\"\n", &directory),
+            Err(CampaignError::NoLevels)
+        );
+        assert_eq!(
+            Campaign::parse("tasks = [\"a.toml sometime\"]\n", &directory),
+            Err(CampaignError::BadLevel { entry: "a.toml sometime".to_string() })
+        );
+        assert!(matches!(
+            Campaign::parse("tasks = [\"missing.toml\"]\n", &directory),
+            Err(CampaignError::BadTask { .. })
+        ));
+        assert_eq!(
+            Campaign::parse("levels = []\n", &directory),
+            Err(CampaignError::BadSyntax { line: 1 })
+        );
+    }
+}
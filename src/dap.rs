@@ -0,0 +1,649 @@
+//! A Debug Adapter Protocol server for Karel programs.
+//!
+//! Lets VS Code and other DAP clients set breakpoints, step into and over
+//! procedure calls and inspect the robot through their native debugging UI.
+//! Uses the same `Content-Length` framing as the LSP side; the protocol loop
+//! lives in [`serve`] and [`Adapter::handle`] is pure and tested without I/O.
+
+use std::io::{self, Read, Write};
+
+use crate::interpreter::Interpreter;
+use crate::json::{self, Value};
+use crate::lsp::{read_message, write_message};
+use crate::parser;
+use crate::watch;
+use crate::worldfile;
+use crate::World;
+
+/// Safety net so a runaway program cannot hang the editor on `continue`.
+const STEP_BUDGET: usize = crate::grade::STEP_BUDGET;
+
+/// The debug adapter: one debuggee at a time, driven by client requests.
+#[derive(Default)]
+pub struct Adapter {
+    seq: usize,
+    program_path: String,
+    interpreter: Option<Interpreter<'static>>,
+    /// Armed breakpoints, with their optional guard conditions.
+    breakpoints: Vec<Breakpoint>,
+    /// Watch expressions re-evaluated after every step; see [`watch`].
+    watches: watch::Watches,
+    disconnected: bool,
+}
+
+impl Adapter {
+    pub fn new() -> Adapter {
+        Adapter::default()
+    }
+
+    /// Should the serve loop exit?
+    pub fn exited(&self) -> bool {
+        self.disconnected
+    }
+
+    /// Handle one request and return the messages to send back: the
+    /// response, plus any events the request triggered.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Value::Object(request) = message else {
+            return Vec::new();
+        };
+        let command = match request.get("command") {
+            Some(Value::String(command)) => command.clone(),
+            _ => return Vec::new(),
+        };
+        let request_seq = match request.get("seq") {
+            Some(Value::Number(seq)) => *seq as usize,
+            _ => 0,
+        };
+        let arguments = request.get("arguments");
+
+        let mut events = Vec::new();
+        let body = match command.as_str() {
+            "initialize" => {
+                events.push(self.event("initialized", Value::object([])));
+                Ok(Value::object([
+                    ("supportsConfigurationDoneRequest", true.into()),
+                ]))
+            }
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => Ok(self.set_breakpoints(arguments)),
+            "setWatches" => Ok(self.set_watches(arguments)),
+            "evaluate" => self.evaluate(arguments),
+            "configurationDone" => {
+                // Always stop on entry: the first continue or step starts
+                // the program.
+                events.push(self.stopped("entry"));
+                Ok(Value::object([]))
+            }
+            "threads" => Ok(Value::object([(
+                "threads",
+                vec![Value::object([
+                    ("id", 1usize.into()),
+                    ("name", "main".into()),
+                ])]
+                .into(),
+            )])),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(Value::object([(
+                "scopes",
+                vec![Value::object([
+                    ("name", "Robot".into()),
+                    ("variablesReference", 1usize.into()),
+                    ("expensive", false.into()),
+                ])]
+                .into(),
+            )])),
+            "variables" => Ok(self.variables()),
+            "continue" => {
+                events.extend(self.advance(Stepping::Continue));
+                Ok(Value::object([("allThreadsContinued", true.into())]))
+            }
+            "next" => {
+                events.extend(self.advance(Stepping::Over));
+                Ok(Value::object([]))
+            }
+            "stepIn" => {
+                events.extend(self.advance(Stepping::Into));
+                Ok(Value::object([]))
+            }
+            "stepOut" => {
+                events.extend(self.advance(Stepping::Out));
+                Ok(Value::object([]))
+            }
+            "disconnect" => {
+                self.disconnected = true;
+                Ok(Value::object([]))
+            }
+            _ => Ok(Value::object([])),
+        };
+
+        let response = match body {
+            Ok(body) => self.message(
+                "response",
+                Value::object([
+                    ("request_seq", request_seq.into()),
+                    ("success", true.into()),
+                    ("command", command.into()),
+                    ("body", body),
+                ]),
+            ),
+            Err(message) => self.message(
+                "response",
+                Value::object([
+                    ("request_seq", request_seq.into()),
+                    ("success", false.into()),
+                    ("command", command.into()),
+                    ("message", message.into()),
+                ]),
+            ),
+        };
+        let mut replies = vec![response];
+        replies.extend(events);
+        replies
+    }
+
+    /// Load the program (and optional world) named in the launch arguments.
+    fn launch(&mut self, arguments: Option<&Value>) -> Result<Value, String> {
+        let Some(Value::Object(arguments)) = arguments else {
+            return Err("launch needs arguments".to_string());
+        };
+        let Some(Value::String(program_path)) = arguments.get("program") else {
+            return Err("launch needs a `program` path".to_string());
+        };
+        let source = std::fs::read_to_string(program_path)
+            .map_err(|error| format!("{program_path}: {error}"))?;
+        let world = match arguments.get("world") {
+            Some(Value::String(world_path)) => worldfile::load(std::path::Path::new(world_path))
+                .map_err(|error| format!("{world_path}: {error}"))?,
+            _ => World::default(),
+        };
+        let lines = parser::preprocess(&source);
+        parser::validate(&lines).map_err(|error| match error.line() {
+            Some(line) => format!("{program_path}:{line}: {error}"),
+            None => format!("{program_path}: {error}"),
+        })?;
+        // The adapter outlives the source string, so detach the lines.
+        let lines = lines.into_iter().map(parser::Line::into_owned).collect();
+        self.interpreter =
+            Some(Interpreter::new(lines, world).map_err(|error| error.to_string())?);
+        self.program_path = program_path.clone();
+        Ok(Value::object([]))
+    }
+
+    fn set_breakpoints(&mut self, arguments: Option<&Value>) -> Value {
+        self.breakpoints.clear();
+        let mut verified = Vec::new();
+        if let Some(Value::Object(arguments)) = arguments {
+            if let Some(Value::Array(breakpoints)) = arguments.get("breakpoints") {
+                for breakpoint in breakpoints {
+                    let Value::Object(breakpoint) = breakpoint else {
+                        continue;
+                    };
+                    let Some(Value::Number(line)) = breakpoint.get("line") else {
+                        continue;
+                    };
+                    let line = *line as usize;
+                    // A breakpoint may carry a condition in the `if`
+                    // grammar; a condition that does not parse leaves the
+                    // breakpoint unverified and unarmed rather than firing
+                    // unconditionally.
+                    let condition = match breakpoint.get("condition") {
+                        Some(Value::String(text)) if !text.trim().is_empty() => {
+                            match watch::Expression::parse(text) {
+                                Ok(condition) if condition.is_condition() => Some(condition),
+                                _ => {
+                                    verified.push(Value::object([
+                                        ("verified", false.into()),
+                                        ("line", line.into()),
+                                        (
+                                            "message",
+                                            format!("`{text}` is not a condition").into(),
+                                        ),
+                                    ]));
+                                    continue;
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+                    verified.push(Value::object([
+                        ("verified", true.into()),
+                        ("line", line.into()),
+                    ]));
+                    self.breakpoints.push(Breakpoint { line, condition });
+                }
+            }
+        }
+        Value::object([("breakpoints", verified.into())])
+    }
+
+    /// Replace the session's watch expressions (a custom request shaped
+    /// like `setBreakpoints`): execution pauses when one fires.
+    fn set_watches(&mut self, arguments: Option<&Value>) -> Value {
+        self.watches.clear();
+        let mut verified = Vec::new();
+        if let Some(Value::Object(arguments)) = arguments {
+            if let Some(Value::Array(expressions)) = arguments.get("expressions") {
+                for expression in expressions {
+                    if let Value::String(expression) = expression {
+                        verified.push(Value::object([
+                            ("expression", expression.as_str().into()),
+                            ("verified", self.watches.add(expression).is_ok().into()),
+                        ]));
+                    }
+                }
+            }
+        }
+        Value::object([("watches", verified.into())])
+    }
+
+    /// Evaluate a watch expression once against the current world, for the
+    /// client's watch panel.
+    fn evaluate(&mut self, arguments: Option<&Value>) -> Result<Value, String> {
+        let Some(Value::Object(arguments)) = arguments else {
+            return Err("evaluate needs arguments".to_string());
+        };
+        let Some(Value::String(text)) = arguments.get("expression") else {
+            return Err("evaluate needs an `expression`".to_string());
+        };
+        let expression = watch::Expression::parse(text).map_err(|error| error.to_string())?;
+        let Some(interpreter) = &self.interpreter else {
+            return Err("no program is running".to_string());
+        };
+        Ok(Value::object([
+            ("result", expression.observe(&interpreter.world).to_string().into()),
+            ("variablesReference", 0usize.into()),
+        ]))
+    }
+
+    fn stack_trace(&self) -> Value {
+        let frames: Vec<Value> = match &self.interpreter {
+            Some(interpreter) => interpreter
+                .backtrace()
+                .into_iter()
+                .enumerate()
+                .map(|(id, (name, line))| {
+                    Value::object([
+                        ("id", id.into()),
+                        ("name", name.into()),
+                        ("line", line.into()),
+                        ("column", 1usize.into()),
+                        (
+                            "source",
+                            Value::object([("path", self.program_path.as_str().into())]),
+                        ),
+                    ])
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        Value::object([
+            ("totalFrames", frames.len().into()),
+            ("stackFrames", frames.into()),
+        ])
+    }
+
+    fn variables(&self) -> Value {
+        let variables: Vec<Value> = match &self.interpreter {
+            Some(interpreter) => {
+                let world = &interpreter.world;
+                let robot = world.robot;
+                let variable = |name: &str, value: String| {
+                    Value::object([
+                        ("name", name.into()),
+                        ("value", value.into()),
+                        ("variablesReference", 0usize.into()),
+                    ])
+                };
+                vec![
+                    variable("x", robot.position.x.to_string()),
+                    variable("y", robot.position.y.to_string()),
+                    variable("direction", format!("{:?}", robot.direction).to_lowercase()),
+                    variable("alive", robot.alive.to_string()),
+                    variable("beepers here", world.beepers_at(robot.position).to_string()),
+                ]
+            }
+            None => Vec::new(),
+        };
+        Value::object([("variables", variables.into())])
+    }
+
+    /// Step the debuggee according to `stepping` and return the events that
+    /// tell the client why execution stopped.
+    fn advance(&mut self, stepping: Stepping) -> Vec<Value> {
+        let Some(start_depth) = self.interpreter.as_ref().map(Interpreter::call_depth) else {
+            return Vec::new();
+        };
+        for _ in 0..STEP_BUDGET {
+            let step = self.interpreter.as_mut().expect("debuggee is loaded").step();
+            match step {
+                Ok(crate::StepResult::Running) => {}
+                Ok(crate::StepResult::Finished) => {
+                    return vec![self.event("terminated", Value::object([]))];
+                }
+                Err(error) => {
+                    let message = self.event(
+                        "output",
+                        Value::object([
+                            ("category", "stderr".into()),
+                            ("output", format!("{error}\n").into()),
+                        ]),
+                    );
+                    return vec![message, self.stopped("exception")];
+                }
+            }
+            let interpreter = self.interpreter.as_ref().expect("debuggee is loaded");
+            let at_breakpoint = interpreter.current_line().is_some_and(|line| {
+                self.breakpoints.iter().any(|breakpoint| {
+                    breakpoint.line == line
+                        && breakpoint.condition.as_ref().is_none_or(|condition| {
+                            condition.observe(&interpreter.world)
+                                == watch::Observation::Flag(true)
+                        })
+                })
+            });
+            if at_breakpoint {
+                return vec![self.stopped("breakpoint")];
+            }
+            let fired = self.watches.observe(&interpreter.world);
+            if let Some(trigger) = fired.first() {
+                let note = self.event(
+                    "output",
+                    Value::object([
+                        ("category", "console".into()),
+                        (
+                            "output",
+                            format!(
+                                "watch `{}`: {} -> {}\n",
+                                trigger.expression, trigger.from, trigger.to
+                            )
+                            .into(),
+                        ),
+                    ]),
+                );
+                return vec![note, self.stopped("data breakpoint")];
+            }
+            let depth = interpreter.call_depth();
+            let done = match stepping {
+                Stepping::Into => true,
+                Stepping::Over => depth <= start_depth,
+                Stepping::Out => depth < start_depth,
+                Stepping::Continue => false,
+            };
+            if done {
+                return vec![self.stopped("step")];
+            }
+        }
+        // Out of budget: treat it like hitting pause so the client can
+        // inspect the (probably looping) program.
+        vec![self.stopped("pause")]
+    }
+
+    fn stopped(&mut self, reason: &str) -> Value {
+        self.event(
+            "stopped",
+            Value::object([
+                ("reason", reason.into()),
+                ("threadId", 1usize.into()),
+                ("allThreadsStopped", true.into()),
+            ]),
+        )
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Value {
+        self.message(
+            "event",
+            Value::object([("event", event.into()), ("body", body)]),
+        )
+    }
+
+    fn message(&mut self, kind: &str, fields: Value) -> Value {
+        self.seq += 1;
+        let mut entry = match fields {
+            Value::Object(entry) => entry,
+            _ => unreachable!("message fields are an object"),
+        };
+        entry.insert("seq".to_string(), self.seq.into());
+        entry.insert("type".to_string(), kind.into());
+        Value::Object(entry)
+    }
+}
+
+/// One armed breakpoint: a line, and optionally a condition that must hold
+/// for it to fire.
+struct Breakpoint {
+    line: usize,
+    condition: Option<watch::Expression>,
+}
+
+/// What kind of step a request asked for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stepping {
+    /// Run until a breakpoint, an error or the end.
+    Continue,
+    /// One instruction, following `call`s inside.
+    Into,
+    /// One instruction, running called procedures to completion.
+    Over,
+    /// Run until the current procedure returns.
+    Out,
+}
+
+/// Read framed DAP requests from `reader` and write the replies to `writer`
+/// until the client disconnects.
+pub fn serve(reader: impl Read, mut writer: impl Write) -> io::Result<()> {
+    let mut reader = io::BufReader::new(reader);
+    let mut adapter = Adapter::new();
+    while !adapter.exited() {
+        let Some(message) = read_message(&mut reader)? else {
+            break;
+        };
+        let Ok(message) = json::parse(&message) else {
+            continue;
+        };
+        for reply in adapter.handle(&message) {
+            write_message(&mut writer, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(seq: usize, command: &str, arguments: Value) -> Value {
+        Value::object([
+            ("seq", seq.into()),
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", arguments),
+        ])
+    }
+
+    /// An adapter with a freshly launched scratch program.
+    fn launched(source: &str) -> (Adapter, tempdir::Guard) {
+        let guard = tempdir::create();
+        let program = guard.path.join("program.kl");
+        std::fs::write(&program, source).unwrap();
+        let mut adapter = Adapter::new();
+        let replies = adapter.handle(&request(
+            1,
+            "launch",
+            Value::object([("program", program.to_str().unwrap().into())]),
+        ));
+        assert!(replies[0].to_string().contains("\"success\":true"));
+        (adapter, guard)
+    }
+
+    /// A throwaway directory that cleans up after itself.
+    mod tempdir {
+        use std::path::PathBuf;
+
+        pub struct Guard {
+            pub path: PathBuf,
+        }
+
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                let _ = std::fs::remove_dir_all(&self.path);
+            }
+        }
+
+        pub fn create() -> Guard {
+            let path = std::env::temp_dir().join(format!(
+                "karel-dap-test-{}-{:?}",
+                std::process::id(),
+                std::thread::current().id(),
+            ));
+            std::fs::create_dir_all(&path).unwrap();
+            Guard { path }
+        }
+    }
+
+    #[test]
+    fn initialize_responds_and_announces_readiness() {
+        let mut adapter = Adapter::new();
+        let replies = adapter.handle(&request(1, "initialize", Value::object([])));
+        assert!(replies[0]
+            .to_string()
+            .contains("supportsConfigurationDoneRequest"));
+        assert!(replies[1].to_string().contains("\"event\":\"initialized\""));
+    }
+
+    #[test]
+    fn continue_stops_at_a_breakpoint() {
+        let (mut adapter, _guard) =
+            launched("def main\n move\n move\n move\n die\nenddef");
+        adapter.handle(&request(
+            2,
+            "setBreakpoints",
+            Value::object([(
+                "breakpoints",
+                vec![Value::object([("line", 3usize.into())])].into(),
+            )]),
+        ));
+        let replies = adapter.handle(&request(3, "continue", Value::object([])));
+        assert!(replies[1].to_string().contains("\"reason\":\"breakpoint\""));
+        let trace = adapter.handle(&request(4, "stackTrace", Value::object([])));
+        assert!(trace[0].to_string().contains("\"line\":3"));
+    }
+
+    #[test]
+    fn step_over_skips_the_called_procedure() {
+        let (mut adapter, _guard) = launched(
+            "def main\n call spin\n die\nenddef\ndef spin\n turn-left\n turn-left\nenddef",
+        );
+        // Step over the `call spin` on line 2: we should land on line 3.
+        let replies = adapter.handle(&request(2, "next", Value::object([])));
+        assert!(replies[1].to_string().contains("\"reason\":\"step\""));
+        let trace = adapter.handle(&request(3, "stackTrace", Value::object([])));
+        let trace = trace[0].to_string();
+        assert!(trace.contains("\"line\":3"), "{trace}");
+        assert!(!trace.contains("spin"), "{trace}");
+    }
+
+    #[test]
+    fn step_in_enters_the_called_procedure() {
+        let (mut adapter, _guard) =
+            launched("def main\n call spin\n die\nenddef\ndef spin\n turn-left\nenddef");
+        let replies = adapter.handle(&request(2, "stepIn", Value::object([])));
+        assert!(replies[1].to_string().contains("\"reason\":\"step\""));
+        let trace = adapter.handle(&request(3, "stackTrace", Value::object([])));
+        let trace = trace[0].to_string();
+        assert!(trace.contains("spin"), "{trace}");
+        assert!(trace.contains("main"), "{trace}");
+    }
+
+    #[test]
+    fn conditional_breakpoints_fire_only_when_their_condition_holds() {
+        let (mut adapter, _guard) =
+            launched("def main\n repeat 3\n  put\n endrepeat\n die\nenddef");
+        let replies = adapter.handle(&request(
+            2,
+            "setBreakpoints",
+            Value::object([(
+                "breakpoints",
+                vec![
+                    Value::object([
+                        ("line", 3usize.into()),
+                        ("condition", "beeper >= 2".into()),
+                    ]),
+                    Value::object([
+                        ("line", 4usize.into()),
+                        ("condition", "position".into()),
+                    ]),
+                ]
+                .into(),
+            )]),
+        ));
+        let verified = replies[0].to_string();
+        // A value expression is not a condition; that breakpoint stays off.
+        assert!(verified.contains("`position` is not a condition"), "{verified}");
+
+        // The guarded line is reached three times; only the third pile of
+        // beepers satisfies the condition.
+        let replies = adapter.handle(&request(3, "continue", Value::object([])));
+        assert!(replies[1].to_string().contains("\"reason\":\"breakpoint\""));
+        let evaluated = adapter.handle(&request(
+            4,
+            "evaluate",
+            Value::object([("expression", "beepers-at 0 0".into())]),
+        ));
+        assert!(evaluated[0].to_string().contains("\"result\":\"2\""));
+    }
+
+    #[test]
+    fn continue_pauses_when_a_watch_fires() {
+        let (mut adapter, _guard) =
+            launched("def main\n move\n put\n move\n die\nenddef");
+        let replies = adapter.handle(&request(
+            2,
+            "setWatches",
+            Value::object([(
+                "expressions",
+                vec![Value::from("beepers-at 1 0"), Value::from("robot.mood")].into(),
+            )]),
+        ));
+        let verified = replies[0].to_string();
+        assert!(verified.contains("\"verified\":true"), "{verified}");
+        assert!(verified.contains("\"verified\":false"), "{verified}");
+
+        // The `put` on tile (1, 0) changes the watched count.
+        let replies = adapter.handle(&request(3, "continue", Value::object([])));
+        assert!(replies[1].to_string().contains("watch `beepers-at 1 0`: 0 -> 1"));
+        assert!(replies[2].to_string().contains("\"reason\":\"data breakpoint\""));
+
+        let evaluated = adapter.handle(&request(
+            4,
+            "evaluate",
+            Value::object([("expression", "beepers-at 1 0".into())]),
+        ));
+        assert!(evaluated[0].to_string().contains("\"result\":\"1\""));
+    }
+
+    #[test]
+    fn runtime_errors_stop_with_an_exception() {
+        let (mut adapter, _guard) = launched("def main\n take\nenddef");
+        let replies = adapter.handle(&request(2, "continue", Value::object([])));
+        let output = replies[1].to_string();
+        assert!(output.contains("no beeper"), "{output}");
+        assert!(replies[2].to_string().contains("\"reason\":\"exception\""));
+    }
+
+    #[test]
+    fn finishing_terminates_the_session() {
+        let (mut adapter, _guard) = launched("def main\n die\nenddef");
+        let replies = adapter.handle(&request(2, "continue", Value::object([])));
+        assert!(replies[1].to_string().contains("\"event\":\"terminated\""));
+    }
+
+    #[test]
+    fn variables_describe_the_robot() {
+        let (mut adapter, _guard) = launched("def main\n die\nenddef");
+        let replies = adapter.handle(&request(2, "variables", Value::object([])));
+        let variables = replies[0].to_string();
+        assert!(variables.contains("\"name\":\"direction\""), "{variables}");
+        assert!(variables.contains("east"), "{variables}");
+    }
+}
@@ -0,0 +1,199 @@
+//! Driving a run from an event loop without blocking it.
+//!
+//! [`Interpreter::run`] monopolises its thread until the program ends, which
+//! is wrong for a GUI or a browser: the event loop must keep painting while
+//! Karel walks. [`RunFuture`] is the same run as a future that executes one
+//! instruction per poll and then yields, so any executor — including the
+//! single-threaded ones WASM offers — can interleave it with everything
+//! else. An optional [delay](RunFuture::with_delay) holds the next step
+//! back, turning the run into an animation at a chosen pace; the future
+//! re-schedules itself and re-checks the system clock on each poll, so an
+//! executor with real timers may prefer to sleep between polls itself.
+//!
+//! No executor ships here — the crate stays dependency-free. The tests
+//! drive the future with a bare poll loop, which any host can copy.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::environment::Environment;
+use crate::interpreter::{Interpreter, RunOutcome, RunReport, RunStatus};
+use crate::world::World;
+
+/// A run in progress: each poll executes at most one instruction and yields.
+/// Resolves to the same [`RunOutcome`] a blocking run produces.
+///
+/// Created with [`RunFuture::new`]; configure with
+/// [`with_delay`](RunFuture::with_delay) and
+/// [`bounded`](RunFuture::bounded) before the first poll.
+pub struct RunFuture<'i, 'p, E: Environment = World> {
+    interpreter: &'i mut Interpreter<'p, E>,
+    limit: usize,
+    delay: Option<Duration>,
+    /// When the next step may run; `None` means immediately.
+    next_step_at: Option<Instant>,
+    /// `None` once the future has resolved; polling again then is a bug.
+    report: Option<RunReport>,
+    /// When the first poll ran, for [`RunReport::wall_time`].
+    started: Option<Instant>,
+}
+
+impl<'i, 'p, E: Environment> RunFuture<'i, 'p, E> {
+    /// Run the program to its end, one instruction per poll. The
+    /// interpreter is borrowed for the run, like a blocking one.
+    pub fn new(interpreter: &'i mut Interpreter<'p, E>) -> RunFuture<'i, 'p, E> {
+        RunFuture {
+            interpreter,
+            limit: usize::MAX,
+            delay: None,
+            next_step_at: None,
+            report: Some(RunReport::default()),
+            started: None,
+        }
+    }
+
+    /// Hold every step back by `delay`, so the run plays as an animation.
+    pub fn with_delay(mut self, delay: Duration) -> RunFuture<'i, 'p, E> {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Give up with [`RunStatus::LimitHit`] after `limit` steps, like
+    /// [`Interpreter::run_bounded`]. The interpreter stays live, so a new
+    /// future can resume it with a fresh budget.
+    pub fn bounded(mut self, limit: usize) -> RunFuture<'i, 'p, E> {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<E: Environment + Clone> Future for RunFuture<'_, '_, E> {
+    type Output = RunOutcome<E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<RunOutcome<E>> {
+        // Nothing here is address-sensitive; the future is `Unpin`.
+        let this = self.get_mut();
+        let report = this
+            .report
+            .as_mut()
+            .expect("RunFuture polled after completion");
+
+        let started = *this.started.get_or_insert_with(Instant::now);
+        let finish = |status: RunStatus, report: &mut Option<RunReport>, world: &E| {
+            let mut report = report.take().unwrap_or_default();
+            report.wall_time = started.elapsed();
+            Poll::Ready(RunOutcome {
+                status,
+                report,
+                final_snapshot: world.clone(),
+            })
+        };
+
+        if this.interpreter.finished() {
+            let status = if this.interpreter.halted() {
+                RunStatus::Halted
+            } else {
+                RunStatus::Completed
+            };
+            return finish(status, &mut this.report, &this.interpreter.world);
+        }
+        if report.steps == this.limit {
+            return finish(RunStatus::LimitHit, &mut this.report, &this.interpreter.world);
+        }
+        if let Some(at) = this.next_step_at {
+            if Instant::now() < at {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+
+        match this.interpreter.step() {
+            Ok(_) => {
+                report.steps += 1;
+                report.max_call_depth = report.max_call_depth.max(this.interpreter.call_depth());
+                report.peak_memory =
+                    report.peak_memory.max(this.interpreter.resident_memory());
+            }
+            Err(error) => {
+                report.steps += 1;
+                return finish(
+                    RunStatus::Failed(error),
+                    &mut this.report,
+                    &this.interpreter.world,
+                );
+            }
+        }
+        this.next_step_at = this.delay.map(|delay| Instant::now() + delay);
+        // Yield even when ready to go on: that is the whole point.
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::preprocess;
+    use crate::world::Position;
+
+    /// The smallest possible executor: poll in a loop with a no-op waker.
+    fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+        let waker = std::task::Waker::noop();
+        let mut context = Context::from_waker(waker);
+        loop {
+            if let Poll::Ready(output) = Pin::new(&mut future).poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn the_future_resolves_to_the_blocking_outcome() {
+        let source = "def main\n repeat 3\n  move\n endrepeat\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(5, 1)).unwrap();
+        let outcome = block_on(RunFuture::new(&mut interpreter));
+        assert_eq!(outcome.status, RunStatus::Completed);
+        assert_eq!(outcome.final_snapshot.robot.position, Position::new(3, 0));
+    }
+
+    #[test]
+    fn each_poll_runs_at_most_one_step() {
+        let source = "def main\n move\n move\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(5, 1)).unwrap();
+        let waker = std::task::Waker::noop();
+        let mut context = Context::from_waker(waker);
+        {
+            let mut future = RunFuture::new(&mut interpreter);
+            assert!(Pin::new(&mut future).poll(&mut context).is_pending());
+        }
+        assert_eq!(interpreter.world.robot.position, Position::new(1, 0));
+        assert!(!interpreter.finished());
+    }
+
+    #[test]
+    fn a_bounded_future_reports_the_limit() {
+        let source = "def main\n while! beeper\n  turn-left\n endwhile\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        let outcome = block_on(RunFuture::new(&mut interpreter).bounded(50));
+        assert_eq!(outcome.status, RunStatus::LimitHit);
+        assert!(!interpreter.finished());
+    }
+
+    #[test]
+    fn a_delay_holds_the_next_step_back() {
+        let source = "def main\n move\n move\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(5, 1)).unwrap();
+        let start = Instant::now();
+        let delay = Duration::from_millis(5);
+        let outcome = block_on(RunFuture::new(&mut interpreter).with_delay(delay));
+        assert_eq!(outcome.status, RunStatus::Completed);
+        // Two delayed gaps at minimum (after each `move`; `enddef` ends it).
+        assert!(start.elapsed() >= delay * 2);
+    }
+}
@@ -0,0 +1,185 @@
+//! The state machine behind `karel edit`: a cursor over a world and the
+//! edits each key makes. Terminal input and output live in the binary; this
+//! module is pure state so the editing rules can be tested.
+
+use crate::render::{render_with_cursor, RenderStyle};
+use crate::world::{Direction, Position, World, MAX_BEEPERS_PER_TILE};
+
+/// An editing action, already decoded from whatever key the terminal sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorAction {
+    MoveCursor(Direction),
+    /// Put a wall on the cursor tile, or remove it if there is one.
+    ToggleWall,
+    /// Move the robot to the cursor; if it already stands there, rotate it
+    /// left instead.
+    PlaceRobot,
+    /// One more beeper on the cursor tile (up to the maximum).
+    AddBeeper,
+    /// One beeper less on the cursor tile.
+    RemoveBeeper,
+    /// Set an exact number of beepers on the cursor tile.
+    SetBeepers(u8),
+    /// Clear the cursor tile: no wall, no beepers.
+    ClearTile,
+}
+
+/// A world being edited, with a cursor and a dirty flag.
+pub struct Editor {
+    world: World,
+    cursor: Position,
+    dirty: bool,
+}
+
+impl Editor {
+    pub fn new(world: World) -> Editor {
+        let cursor = world.robot.position;
+        Editor {
+            world,
+            cursor,
+            dirty: false,
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn cursor(&self) -> Position {
+        self.cursor
+    }
+
+    /// Has the world been changed since the last [`Editor::mark_saved`]?
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Apply one editing action. Actions that would do nothing (moving the
+    /// cursor off the edge, walling in the robot) are ignored.
+    pub fn apply(&mut self, action: EditorAction) {
+        match action {
+            EditorAction::MoveCursor(direction) => {
+                if let Some(position) = self.cursor.neighbour(direction) {
+                    if self.world.in_bounds(position) {
+                        self.cursor = position;
+                    }
+                }
+            }
+            EditorAction::ToggleWall => {
+                // The robot and a wall cannot share a tile.
+                if self.world.robot.position == self.cursor {
+                    return;
+                }
+                let wall = !self.world.is_wall(self.cursor);
+                self.world.set_wall(self.cursor, wall);
+                if wall {
+                    self.world.set_beepers(self.cursor, 0);
+                }
+                self.dirty = true;
+            }
+            EditorAction::PlaceRobot => {
+                if self.world.robot.position == self.cursor {
+                    self.world.robot.direction = self.world.robot.direction.left();
+                } else {
+                    self.world.set_wall(self.cursor, false);
+                    self.world.robot.position = self.cursor;
+                }
+                self.dirty = true;
+            }
+            EditorAction::AddBeeper => {
+                if !self.world.is_wall(self.cursor) && self.world.put_beeper(self.cursor) {
+                    self.dirty = true;
+                }
+            }
+            EditorAction::RemoveBeeper => {
+                if self.world.take_beeper(self.cursor) {
+                    self.dirty = true;
+                }
+            }
+            EditorAction::SetBeepers(count) => {
+                if !self.world.is_wall(self.cursor) {
+                    self.world
+                        .set_beepers(self.cursor, count.min(MAX_BEEPERS_PER_TILE));
+                    self.dirty = true;
+                }
+            }
+            EditorAction::ClearTile => {
+                self.world.set_wall(self.cursor, false);
+                self.world.set_beepers(self.cursor, 0);
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// The world with the cursor highlighted, ready to print.
+    pub fn render(&self, style: RenderStyle) -> String {
+        render_with_cursor(&self.world, style, Some(self.cursor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_stays_inside_the_world() {
+        let mut editor = Editor::new(World::new(2, 2));
+        editor.apply(EditorAction::MoveCursor(Direction::North));
+        assert_eq!(editor.cursor(), Position::new(0, 0));
+        editor.apply(EditorAction::MoveCursor(Direction::East));
+        editor.apply(EditorAction::MoveCursor(Direction::East));
+        assert_eq!(editor.cursor(), Position::new(1, 0));
+    }
+
+    #[test]
+    fn toggle_wall_clears_beepers_and_spares_the_robot() {
+        let mut editor = Editor::new(World::new(3, 1));
+        editor.apply(EditorAction::ToggleWall);
+        assert!(!editor.world().is_wall(Position::new(0, 0)), "robot tile");
+
+        editor.apply(EditorAction::MoveCursor(Direction::East));
+        editor.apply(EditorAction::AddBeeper);
+        editor.apply(EditorAction::ToggleWall);
+        assert!(editor.world().is_wall(Position::new(1, 0)));
+        assert_eq!(editor.world().beepers_at(Position::new(1, 0)), 0);
+        editor.apply(EditorAction::ToggleWall);
+        assert!(!editor.world().is_wall(Position::new(1, 0)));
+    }
+
+    #[test]
+    fn placing_the_robot_twice_rotates_it() {
+        let mut editor = Editor::new(World::new(3, 1));
+        editor.apply(EditorAction::MoveCursor(Direction::East));
+        editor.apply(EditorAction::PlaceRobot);
+        assert_eq!(editor.world().robot.position, Position::new(1, 0));
+        assert_eq!(editor.world().robot.direction, Direction::East);
+        editor.apply(EditorAction::PlaceRobot);
+        assert_eq!(editor.world().robot.direction, Direction::North);
+    }
+
+    #[test]
+    fn beepers_do_not_go_on_walls() {
+        let mut editor = Editor::new(World::new(3, 1));
+        editor.apply(EditorAction::MoveCursor(Direction::East));
+        editor.apply(EditorAction::ToggleWall);
+        editor.apply(EditorAction::AddBeeper);
+        editor.apply(EditorAction::SetBeepers(4));
+        assert_eq!(editor.world().beepers_at(Position::new(1, 0)), 0);
+    }
+
+    #[test]
+    fn dirty_tracks_changes() {
+        let mut editor = Editor::new(World::new(2, 1));
+        assert!(!editor.dirty());
+        editor.apply(EditorAction::MoveCursor(Direction::East));
+        assert!(!editor.dirty(), "moving the cursor is not a change");
+        editor.apply(EditorAction::AddBeeper);
+        assert!(editor.dirty());
+        editor.mark_saved();
+        assert!(!editor.dirty());
+    }
+}
@@ -0,0 +1,307 @@
+//! A high-level facade over the parser and interpreter.
+//!
+//! GUI embedders should not have to care that parsing and execution are
+//! separate passes, or which dialect a file is in. [`Engine`] bundles the
+//! whole pipeline behind a builder:
+//!
+//! ```
+//! use karel::engine::Engine;
+//!
+//! let mut engine = Engine::builder()
+//!     .program("def main\n move\n die\nenddef")
+//!     .build()
+//!     .unwrap();
+//! engine.run().unwrap();
+//! assert_eq!(engine.world().robot.position.x, 1);
+//! ```
+//!
+//! The builder is the stable public surface; internals behind it are free
+//! to keep evolving.
+
+use std::fmt;
+
+use crate::importer::{self, ImportError};
+use crate::interpreter::{Interpreter, RuntimeError, StepResult};
+use crate::parser::{self, ParseError};
+use crate::world::World;
+
+/// The source language of a program fed to the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// The native line-oriented language from the README.
+    #[default]
+    Native,
+    /// Java-flavored Karel (Karel J Robot, Karel++), converted on build.
+    Java,
+}
+
+/// Execution limits. The default is no limits at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Stop with [`EngineError::StepLimit`] after this many instructions.
+    pub max_steps: Option<usize>,
+}
+
+/// What a single executed instruction looked like, handed to the listener
+/// after every step.
+pub struct StepEvent<'a> {
+    /// How many instructions have run so far, this one included.
+    pub step: usize,
+    /// 1-based source line of the executed instruction.
+    pub line: usize,
+    /// The instruction text.
+    pub instruction: &'a str,
+    /// The world after the instruction.
+    pub world: &'a World,
+}
+
+/// Anything that can go wrong between source text and a finished run.
+#[derive(Debug)]
+pub enum EngineError {
+    /// The Java-style source could not be converted.
+    Import(ImportError),
+    /// The program failed validation.
+    Parse(ParseError),
+    /// The robot died of a fatal instruction.
+    Runtime(RuntimeError),
+    /// The step limit from [`Limits`] was exhausted.
+    StepLimit { steps: usize },
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Import(error) => write!(f, "{error}"),
+            EngineError::Parse(error) => match error.line() {
+                Some(line) => write!(f, "line {line}: {error}"),
+                None => write!(f, "{error}"),
+            },
+            EngineError::Runtime(error) => write!(f, "{error}"),
+            EngineError::StepLimit { steps } => {
+                write!(f, "the program was stopped after {steps} steps")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<ParseError> for EngineError {
+    fn from(error: ParseError) -> EngineError {
+        EngineError::Parse(error)
+    }
+}
+
+impl From<RuntimeError> for EngineError {
+    fn from(error: RuntimeError) -> EngineError {
+        EngineError::Runtime(error)
+    }
+}
+
+type Listener = Box<dyn FnMut(&StepEvent<'_>)>;
+
+/// Configuration collected by [`Engine::builder`].
+#[derive(Default)]
+pub struct EngineBuilder {
+    world: Option<World>,
+    source: String,
+    dialect: Dialect,
+    limits: Limits,
+    listener: Option<Listener>,
+}
+
+impl EngineBuilder {
+    /// The world to run in; defaults to the empty 10x10 world.
+    pub fn world(mut self, world: World) -> EngineBuilder {
+        self.world = Some(world);
+        self
+    }
+
+    /// The program source text.
+    pub fn program(mut self, source: &str) -> EngineBuilder {
+        self.source = source.to_string();
+        self
+    }
+
+    /// The dialect `program` is written in; defaults to [`Dialect::Native`].
+    pub fn dialect(mut self, dialect: Dialect) -> EngineBuilder {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Execution limits; defaults to no limits.
+    pub fn limits(mut self, limits: Limits) -> EngineBuilder {
+        self.limits = limits;
+        self
+    }
+
+    /// A callback invoked after every executed instruction.
+    pub fn listener(mut self, listener: impl FnMut(&StepEvent<'_>) + 'static) -> EngineBuilder {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Convert, validate and load the program. All static errors surface
+    /// here; a built engine only fails at runtime.
+    pub fn build(self) -> Result<Engine, EngineError> {
+        let source = match self.dialect {
+            Dialect::Native => self.source,
+            Dialect::Java => importer::from_java(&self.source).map_err(EngineError::Import)?,
+        };
+        let lines = parser::preprocess(&source);
+        parser::validate(&lines)?;
+        let world = self.world.unwrap_or_default();
+        // The engine outlives the source string, so detach the lines.
+        let lines = lines.into_iter().map(parser::Line::into_owned).collect();
+        let interpreter = Interpreter::new(lines, world)?;
+        Ok(Engine {
+            interpreter,
+            limits: self.limits,
+            listener: self.listener,
+            steps: 0,
+        })
+    }
+}
+
+/// A loaded program and its world, ready to step or run.
+pub struct Engine {
+    interpreter: Interpreter<'static>,
+    limits: Limits,
+    listener: Option<Listener>,
+    steps: usize,
+}
+
+impl Engine {
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    /// The world as it looks right now.
+    pub fn world(&self) -> &World {
+        &self.interpreter.world
+    }
+
+    /// How many instructions have been executed.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Has the program ended?
+    pub fn finished(&self) -> bool {
+        self.interpreter.finished()
+    }
+
+    /// The 1-based source line about to run, if any.
+    pub fn current_line(&self) -> Option<usize> {
+        self.interpreter.current_line()
+    }
+
+    /// Execute one instruction, honoring the limits and notifying the
+    /// listener.
+    pub fn step(&mut self) -> Result<StepResult, EngineError> {
+        if self
+            .limits
+            .max_steps
+            .is_some_and(|max_steps| self.steps >= max_steps)
+        {
+            return Err(EngineError::StepLimit { steps: self.steps });
+        }
+        let line = self.interpreter.current_line().unwrap_or(0);
+        let instruction = self
+            .interpreter
+            .current_instruction()
+            .unwrap_or_default()
+            .to_string();
+        let result = self.interpreter.step()?;
+        if result == StepResult::Running || !instruction.is_empty() {
+            self.steps += 1;
+            if let Some(listener) = self.listener.as_mut() {
+                listener(&StepEvent {
+                    step: self.steps,
+                    line,
+                    instruction: &instruction,
+                    world: &self.interpreter.world,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// Run the program to its end.
+    pub fn run(&mut self) -> Result<(), EngineError> {
+        while self.step()? == StepResult::Running {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn builder_defaults_run_a_native_program() {
+        let mut engine = Engine::builder()
+            .program("def main\n move\n move\n die\nenddef")
+            .build()
+            .unwrap();
+        engine.run().unwrap();
+        assert_eq!(engine.world().robot.position.x, 2);
+        assert!(engine.finished());
+    }
+
+    #[test]
+    fn java_dialect_is_converted_on_build() {
+        let mut engine = Engine::builder()
+            .program("void task() { move(); putBeeper(); turnOff(); }")
+            .dialect(Dialect::Java)
+            .build()
+            .unwrap();
+        engine.run().unwrap();
+        assert_eq!(
+            engine.world().beepers_at(crate::Position::new(1, 0)),
+            1
+        );
+    }
+
+    #[test]
+    fn step_limit_stops_infinite_loops() {
+        let mut engine = Engine::builder()
+            .program("def main\n while! north\n  turn-left\n  turn-left\n endwhile\nenddef")
+            .limits(Limits {
+                max_steps: Some(100),
+            })
+            .build()
+            .unwrap();
+        let error = engine.run().unwrap_err();
+        assert!(matches!(error, EngineError::StepLimit { steps: 100 }));
+    }
+
+    #[test]
+    fn listener_sees_every_instruction() {
+        let seen: Rc<RefCell<Vec<(usize, String)>>> = Rc::default();
+        let sink = Rc::clone(&seen);
+        let mut engine = Engine::builder()
+            .program("def main\n move\n die\nenddef")
+            .listener(move |event| {
+                sink.borrow_mut()
+                    .push((event.line, event.instruction.to_string()));
+            })
+            .build()
+            .unwrap();
+        engine.run().unwrap();
+        let seen = seen.borrow();
+        assert_eq!(seen[0], (2, "move".to_string()));
+        assert_eq!(seen[1], (3, "die".to_string()));
+    }
+
+    #[test]
+    fn static_errors_surface_at_build_time() {
+        match Engine::builder().program("def main\n fly\nenddef").build() {
+            Err(EngineError::Parse(_)) => {}
+            Err(other) => panic!("unexpected error: {other}"),
+            Ok(_) => panic!("an invalid program built successfully"),
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! The boundary between the interpreter and the place Karel acts in.
+//!
+//! The interpreter itself only ever *performs actions* and *answers checks*;
+//! everything else is up to the [`Environment`] it runs against. The
+//! in-memory [`World`] grid is the default implementation, but the same
+//! trait can drive a physical robot or turtle over serial — which is why
+//! this module, like the rest of the language core, works without `std`.
+
+use crate::world::{Direction, World};
+
+/// Something the robot does to its surroundings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Move one tile forward.
+    Move,
+    /// Turn 90° to the left.
+    TurnLeft,
+    /// Take one beeper from the current tile.
+    Take,
+    /// Put one beeper on the current tile.
+    Put,
+    /// Emit one audible beep from the current tile. Never fails; the world
+    /// records where it happened so goals can count beeps.
+    Beep,
+    /// Switch the robot off. Also performed when a fatal error kills it.
+    Die,
+}
+
+impl Action {
+    /// Every action, in declaration order; [`Action::index`] is the
+    /// position in this list.
+    pub const ALL: [Action; 6] = [
+        Action::Move,
+        Action::TurnLeft,
+        Action::Take,
+        Action::Put,
+        Action::Beep,
+        Action::Die,
+    ];
+
+    /// The lowercase name of the action, as the language spells it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Move => "move",
+            Action::TurnLeft => "turn-left",
+            Action::Take => "take",
+            Action::Put => "put",
+            Action::Beep => "beep",
+            Action::Die => "die",
+        }
+    }
+
+    /// The action with the given [`name`](Action::name).
+    pub fn parse(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// The position of this action in [`Action::ALL`], for per-action
+    /// tables like cost models.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Something the robot observes about its surroundings; each maps to one
+/// condition of the language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// Is the way ahead blocked (`wall`)?
+    WallAhead,
+    /// Is the robot facing the given direction (`north`, `east`, ...)?
+    Facing(Direction),
+    /// Is there at least one beeper here (`beeper`)?
+    OnBeeper,
+    /// Are the next `tiles` tiles ahead free of walls and inside the world
+    /// (`clear N`)?
+    Clear { tiles: usize },
+    /// Does the number of beepers here compare as asked (`beeper >= 3`)?
+    Beepers { comparison: Comparison, count: usize },
+    /// Is there at least one beeper on the tile directly ahead
+    /// (`beeper-ahead`)? Looking at a wall or out of the world sees none.
+    BeeperAhead,
+    /// Is the robot inside a named region (`in-region home`)? The name
+    /// cannot live here without costing `Copy`, so it stays on the source
+    /// line and the interpreter routes this through
+    /// [`Environment::in_region`]; `check` itself answers `false`.
+    InRegion,
+}
+
+/// A numeric comparison, as written between `beeper` and a count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// The comparison an operator token stands for, if it is one.
+    pub fn parse(operator: &str) -> Option<Comparison> {
+        match operator {
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            "=" => Some(Comparison::Equal),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            ">" => Some(Comparison::Greater),
+            _ => None,
+        }
+    }
+
+    /// Whether `left OP right` holds.
+    pub fn holds(self, left: usize, right: usize) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+/// Why an [`Action`] could not be performed. The interpreter turns these
+/// into [`RuntimeError`](crate::RuntimeError)s with source locations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionFailure {
+    /// `Move` into a wall or off the world.
+    Blocked,
+    /// `Take` where there is no beeper.
+    NoBeeper,
+    /// `Put` where no more beepers fit.
+    TileFull,
+    /// `Move` onto another robot's tile, under
+    /// [`CollisionPolicy::Error`](crate::world::CollisionPolicy::Error).
+    Collision,
+}
+
+/// Where a program's actions land: a simulated grid, a real robot, a test
+/// double. Implementations decide what the actions physically mean; the
+/// interpreter only sequences them.
+pub trait Environment {
+    /// Perform one action, or report why it is impossible. After a failure
+    /// the environment should be left unchanged.
+    fn perform(&mut self, action: Action) -> Result<(), ActionFailure>;
+
+    /// Answer one observation about the current surroundings.
+    fn check(&self, check: Check) -> bool;
+
+    /// The direction the robot is facing, if the environment has a notion
+    /// of orientation; `print direction` reports it. `None` by default, for
+    /// environments that are not grids.
+    fn facing(&self) -> Option<Direction> {
+        None
+    }
+
+    /// Is the robot inside the named region (`in-region home`)? `false` by
+    /// default, for environments without regions.
+    fn in_region(&self, _name: &str) -> bool {
+        false
+    }
+}
+
+/// A mutable borrow acts as the environment it points to, so an interpreter
+/// can be built over `&mut world` and dropped when the run is over — the
+/// caller keeps the world, can inspect it between runs, and can lend it to
+/// the next program, instead of the interpreter owning it for good.
+impl<E: Environment + ?Sized> Environment for &mut E {
+    fn perform(&mut self, action: Action) -> Result<(), ActionFailure> {
+        (**self).perform(action)
+    }
+
+    fn check(&self, check: Check) -> bool {
+        (**self).check(check)
+    }
+
+    fn facing(&self) -> Option<Direction> {
+        (**self).facing()
+    }
+
+    fn in_region(&self, name: &str) -> bool {
+        (**self).in_region(name)
+    }
+}
+
+impl Environment for World {
+    fn perform(&mut self, action: Action) -> Result<(), ActionFailure> {
+        let result = match action {
+            Action::Move => {
+                let ahead = self.robot.position.neighbour(self.robot.direction);
+                match ahead {
+                    Some(position) if !self.is_wall(position) => {
+                        match self.resolve_collision(self.robot.position, position) {
+                            Ok(destination) => {
+                                self.note_visit(self.robot.position);
+                                self.robot.position = destination;
+                                self.note_visit(destination);
+                                Ok(())
+                            }
+                            Err(()) => Err(ActionFailure::Collision),
+                        }
+                    }
+                    _ => Err(ActionFailure::Blocked),
+                }
+            }
+            Action::TurnLeft => {
+                self.robot.direction = self.robot.direction.left();
+                Ok(())
+            }
+            Action::Take => {
+                if self.take_beeper(self.robot.position) {
+                    self.note_take();
+                    Ok(())
+                } else {
+                    Err(ActionFailure::NoBeeper)
+                }
+            }
+            Action::Put => {
+                if self.put_beeper(self.robot.position) {
+                    self.note_put(self.robot.position);
+                    Ok(())
+                } else {
+                    Err(ActionFailure::TileFull)
+                }
+            }
+            Action::Beep => {
+                self.note_beep();
+                Ok(())
+            }
+            Action::Die => {
+                self.robot.alive = false;
+                Ok(())
+            }
+        };
+        if result.is_ok() {
+            self.note_action(action);
+            self.tick();
+        }
+        result
+    }
+
+    fn check(&self, check: Check) -> bool {
+        match check {
+            Check::WallAhead => match self.robot.position.neighbour(self.robot.direction) {
+                Some(ahead) => self.is_wall(ahead),
+                None => true,
+            },
+            Check::Facing(direction) => self.robot.direction == direction,
+            Check::OnBeeper => self.beepers_at(self.robot.position) > 0,
+            Check::Clear { tiles } => {
+                let mut position = self.robot.position;
+                for _ in 0..tiles {
+                    match position.neighbour(self.robot.direction) {
+                        Some(ahead) if !self.is_wall(ahead) => position = ahead,
+                        _ => return false,
+                    }
+                }
+                true
+            }
+            Check::Beepers { comparison, count } => {
+                comparison.holds(usize::from(self.beepers_at(self.robot.position)), count)
+            }
+            Check::BeeperAhead => match self.robot.position.neighbour(self.robot.direction) {
+                Some(ahead) => self.beepers_at(ahead) > 0,
+                None => false,
+            },
+            // The interpreter routes region queries through `in_region`,
+            // where the name is available.
+            Check::InRegion => false,
+        }
+    }
+
+    fn facing(&self) -> Option<Direction> {
+        Some(self.robot.facing())
+    }
+
+    fn in_region(&self, name: &str) -> bool {
+        self.region_contains(name, self.robot.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::preprocess;
+
+    /// An environment that records actions and always sees open space: the
+    /// shape a serial-port robot driver would take.
+    #[derive(Default, Clone)]
+    struct Recorder {
+        actions: Vec<Action>,
+    }
+
+    impl Environment for Recorder {
+        fn perform(&mut self, action: Action) -> Result<(), ActionFailure> {
+            self.actions.push(action);
+            Ok(())
+        }
+
+        fn check(&self, _check: Check) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn world_perform_matches_the_grid_rules() {
+        let mut world = World::new(2, 1);
+        assert_eq!(world.perform(Action::Move), Ok(()));
+        assert_eq!(world.perform(Action::Move), Err(ActionFailure::Blocked));
+        assert_eq!(world.perform(Action::Take), Err(ActionFailure::NoBeeper));
+        assert_eq!(world.perform(Action::Put), Ok(()));
+        assert!(world.check(Check::OnBeeper));
+        assert!(world.check(Check::WallAhead));
+        // Only the two performed actions ticked the world clock.
+        assert_eq!(world.ticks(), 2);
+    }
+
+    #[test]
+    fn a_borrowed_world_outlives_its_interpreters() {
+        let mut world = World::new(5, 1);
+        let lines = preprocess("def main\n move\nenddef");
+
+        let mut interpreter = Interpreter::new(lines.clone(), &mut world).unwrap();
+        while interpreter.step().unwrap() == crate::interpreter::StepResult::Running {}
+        drop(interpreter);
+
+        // The borrow ended with the run: the world is ours to inspect and
+        // to lend to the next program.
+        assert_eq!(world.robot.position.x, 1);
+        let mut interpreter = Interpreter::new(lines, &mut world).unwrap();
+        while interpreter.step().unwrap() == crate::interpreter::StepResult::Running {}
+        assert_eq!(world.robot.position.x, 2);
+    }
+
+    #[test]
+    fn the_interpreter_runs_against_any_environment() {
+        let lines = preprocess("def main\n move\n if! beeper\n  put\n endif\n die\nenddef");
+        let mut interpreter = Interpreter::new(lines, Recorder::default()).unwrap();
+        interpreter.run().into_result().unwrap();
+        assert_eq!(
+            interpreter.world.actions,
+            vec![Action::Move, Action::Put, Action::Die]
+        );
+    }
+}
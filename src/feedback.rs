@@ -0,0 +1,391 @@
+//! Student-friendly explanations for every error the crate can report.
+//!
+//! [`RuntimeError`] and [`ParseError`] render as terse diagnostics — right
+//! for a terminal, wrong for a first-semester student. Front-ends kept
+//! rewriting them, each with its own copy; this module is the one place
+//! that maps every error variant to an explanation of *what happened* and
+//! *what to try*, with the error's details (names, keywords, limits)
+//! available as named arguments.
+//!
+//! Localization is a hook, not a dependency: every [`Feedback`] carries a
+//! stable [`key`](Feedback::key) and its arguments, so a catalog of
+//! translated templates can be looked up by key and filled with
+//! [`Feedback::fill`]. The built-in English text is what you get when no
+//! catalog has anything better.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::interpreter::RuntimeError;
+use crate::parser::ParseError;
+
+/// One error, explained. See [`runtime`] and [`syntax`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feedback {
+    /// Stable identifier of the error kind (`"hit-wall"`,
+    /// `"missing-main"`), for keying translation catalogs and analytics.
+    /// Keys never change meaning once shipped.
+    pub key: &'static str,
+    /// The source line the error points at, when it points at one.
+    pub line: Option<usize>,
+    /// The error's details as (placeholder, value), for [`Feedback::fill`].
+    pub args: Vec<(&'static str, String)>,
+    /// What happened, in student terms. English; translate via the key.
+    pub what: String,
+    /// What to look at or try next. English; translate via the key.
+    pub advice: String,
+}
+
+impl Feedback {
+    /// Fill `{placeholder}`s in a template from this feedback's arguments
+    /// — the hook a localized catalog renders its templates through.
+    /// Unknown placeholders stay as written, so a stale template degrades
+    /// visibly instead of silently.
+    pub fn fill(&self, template: &str) -> String {
+        let mut out = template.to_string();
+        for (name, value) in &self.args {
+            out = out.replace(&format!("{{{name}}}"), value);
+        }
+        out
+    }
+}
+
+fn explain(
+    key: &'static str,
+    line: Option<usize>,
+    args: Vec<(&'static str, String)>,
+    what: String,
+    advice: String,
+) -> Feedback {
+    Feedback { key, line, args, what, advice }
+}
+
+/// Explain a runtime error.
+pub fn runtime(error: &RuntimeError) -> Feedback {
+    match error {
+        RuntimeError::HitWall { line } => explain(
+            "hit-wall",
+            Some(*line),
+            Vec::new(),
+            "Karel walked into a wall and broke.".to_string(),
+            "Check with `if! wall` (or `clear`) before every `move` that might face a wall.".to_string(),
+        ),
+        RuntimeError::NoBeeperToTake { line } => explain(
+            "no-beeper",
+            Some(*line),
+            Vec::new(),
+            "Karel tried to take a beeper from a tile that has none.".to_string(),
+            "Guard the `take` with `if beeper`, or re-check the route that was supposed to lead to one.".to_string(),
+        ),
+        RuntimeError::TooManyBeepers { line } => explain(
+            "tile-full",
+            Some(*line),
+            Vec::new(),
+            "This tile already holds as many beepers as it can.".to_string(),
+            "Put the beeper somewhere else, or take some first.".to_string(),
+        ),
+        RuntimeError::RobotCollision { line } => explain(
+            "robot-collision",
+            Some(*line),
+            Vec::new(),
+            "Karel walked into another robot.".to_string(),
+            "In shared worlds, check the tile ahead before moving onto it.".to_string(),
+        ),
+        RuntimeError::CallDepthExceeded { line, limit } => explain(
+            "call-depth",
+            Some(*line),
+            alloc::vec![("limit", limit.to_string())],
+            format!("Procedures called procedures {limit} levels deep without coming back."),
+            "A procedure probably calls itself with no way to stop; give the recursion an `if` that ends it.".to_string(),
+        ),
+        RuntimeError::LoopLimitExceeded { line, limit } => explain(
+            "loop-limit",
+            Some(*line),
+            alloc::vec![("limit", limit.to_string())],
+            format!("This loop repeated more than {limit} times."),
+            "The loop's condition never changes; make sure its body moves Karel toward ending it.".to_string(),
+        ),
+        RuntimeError::UnknownProcedure { line, name } => explain(
+            "unknown-procedure",
+            Some(*line),
+            alloc::vec![("name", name.clone())],
+            format!("There is no procedure called `{name}` to call."),
+            format!("Define it with `def {name} ... enddef`, or fix the spelling in the `call`."),
+        ),
+        RuntimeError::UnknownLabel { line, name } => explain(
+            "unknown-label",
+            Some(*line),
+            alloc::vec![("name", name.clone())],
+            format!("There is no `label {name}` for this `goto` to jump to."),
+            "Labels are for generated code; make the generator emit the target before the jump.".to_string(),
+        ),
+        RuntimeError::UnknownInstruction { line, instruction } => explain(
+            "unknown-instruction",
+            Some(*line),
+            alloc::vec![("instruction", instruction.clone())],
+            format!("`{instruction}` is not an instruction Karel knows."),
+            "Compare it with the instruction list; one letter off is enough.".to_string(),
+        ),
+        RuntimeError::NotImplemented { line } => explain(
+            "not-implemented",
+            Some(*line),
+            Vec::new(),
+            "The program reached a `???` placeholder — this part is not written yet.".to_string(),
+            "Replace the `???` with the instructions that belong there.".to_string(),
+        ),
+        RuntimeError::MalformedBlock { line } => explain(
+            "malformed-block",
+            Some(*line),
+            Vec::new(),
+            "A block began or ended in a way the interpreter could not follow.".to_string(),
+            "Run the checker first: it points at the exact `def`/`if`/`while` that never closes.".to_string(),
+        ),
+        RuntimeError::MissingMain => explain(
+            "missing-main",
+            None,
+            Vec::new(),
+            "The program has no `def main`, so there is nowhere to start.".to_string(),
+            "Wrap the program's first steps in `def main ... enddef`.".to_string(),
+        ),
+        RuntimeError::UnknownEntryPoint { name } => explain(
+            "unknown-entry-point",
+            None,
+            alloc::vec![("name", name.clone())],
+            format!("The run was asked to start at `def {name}`, which does not exist."),
+            "Check the entry point the platform or task configured.".to_string(),
+        ),
+    }
+}
+
+/// Explain a syntax error.
+pub fn syntax(error: &ParseError) -> Feedback {
+    match error {
+        ParseError::NestedDefinition { line } => explain(
+            "nested-definition",
+            Some(*line),
+            Vec::new(),
+            "A `def` began inside another `def`.".to_string(),
+            "Close the first procedure with `enddef` before starting the next one.".to_string(),
+        ),
+        ParseError::InstructionOutsideDefinition { line } => explain(
+            "instruction-outside-definition",
+            Some(*line),
+            Vec::new(),
+            "This instruction stands outside of any procedure.".to_string(),
+            "Every instruction lives between a `def name` and its `enddef`.".to_string(),
+        ),
+        ParseError::UnmatchedBlockEnd { line, keyword } => explain(
+            "unmatched-block-end",
+            Some(*line),
+            alloc::vec![("keyword", keyword.clone())],
+            format!("This `{keyword}` has no block to close."),
+            format!("Delete it, or add the missing `{}` above it.", keyword.trim_start_matches("end")),
+        ),
+        ParseError::UnclosedBlock { line, keyword } => explain(
+            "unclosed-block",
+            Some(*line),
+            alloc::vec![("keyword", keyword.clone())],
+            format!("This `{keyword}` block is never closed."),
+            format!("Add `end{keyword}` where the block should stop."),
+        ),
+        ParseError::UnknownInstruction { line, instruction } => explain(
+            "unknown-instruction",
+            Some(*line),
+            alloc::vec![("instruction", instruction.clone())],
+            format!("`{instruction}` is not an instruction Karel knows."),
+            "Compare it with the instruction list; one letter off is enough.".to_string(),
+        ),
+        ParseError::UnknownCondition { line, condition } => explain(
+            "unknown-condition",
+            Some(*line),
+            alloc::vec![("condition", condition.clone())],
+            format!("`{condition}` is not a condition `if` or `while` can test."),
+            "Karel can test `wall`, the four directions, `beeper`, `beeper-ahead`, `clear N` and `in-region NAME`.".to_string(),
+        ),
+        ParseError::BadRepeatCount { line } => explain(
+            "bad-repeat-count",
+            Some(*line),
+            Vec::new(),
+            "`repeat` needs to know how many times, as a positive number.".to_string(),
+            "Write `repeat 3` — and if the count should vary, a `while` fits better.".to_string(),
+        ),
+        ParseError::BadClearDistance { line } => explain(
+            "bad-clear-distance",
+            Some(*line),
+            Vec::new(),
+            "`clear` needs a positive number of tiles to look ahead.".to_string(),
+            "Write `clear 2` to ask whether the next two tiles are free.".to_string(),
+        ),
+        ParseError::BadPrintItem { line } => explain(
+            "bad-print-item",
+            Some(*line),
+            Vec::new(),
+            "`print` can only print `direction`.".to_string(),
+            "Write `print direction`, or drop the line.".to_string(),
+        ),
+        ParseError::BadBeeperComparison { line } => explain(
+            "bad-beeper-comparison",
+            Some(*line),
+            Vec::new(),
+            "A beeper comparison was not of the form `beeper OP count`.".to_string(),
+            "Write it like `beeper >= 3`, with one of `<`, `<=`, `=`, `>=`, `>`.".to_string(),
+        ),
+        ParseError::BadRegionName { line } => explain(
+            "bad-region-name",
+            Some(*line),
+            Vec::new(),
+            "`in-region` needs exactly one region name.".to_string(),
+            "Write `in-region home`, matching a region the world defines.".to_string(),
+        ),
+        ParseError::BadName { line } => explain(
+            "bad-name",
+            Some(*line),
+            Vec::new(),
+            "This statement needs exactly one name after it.".to_string(),
+            "Write `def walk`, `call walk` — one word, no spaces.".to_string(),
+        ),
+        ParseError::DuplicateDefinition { line, name } => explain(
+            "duplicate-definition",
+            Some(*line),
+            alloc::vec![("name", name.clone())],
+            format!("The procedure `{name}` is defined twice."),
+            "Rename one of the two, or merge them.".to_string(),
+        ),
+        ParseError::UnknownProcedure { line, name } => explain(
+            "unknown-procedure",
+            Some(*line),
+            alloc::vec![("name", name.clone())],
+            format!("There is no procedure called `{name}` to call."),
+            format!("Define it with `def {name} ... enddef`, or fix the spelling in the `call`."),
+        ),
+        ParseError::DuplicateLabel { line, name } => explain(
+            "duplicate-label",
+            Some(*line),
+            alloc::vec![("name", name.clone())],
+            format!("The label `{name}` is defined twice, so `goto {name}` is ambiguous."),
+            "Labels are for generated code; make the generator emit unique names.".to_string(),
+        ),
+        ParseError::UnknownLabel { line, name } => explain(
+            "unknown-label",
+            Some(*line),
+            alloc::vec![("name", name.clone())],
+            format!("There is no `label {name}` for this `goto` to jump to."),
+            "Labels are for generated code; make the generator emit the target before the jump.".to_string(),
+        ),
+        ParseError::TrailingTokens { line, keyword } => explain(
+            "trailing-tokens",
+            Some(*line),
+            alloc::vec![("keyword", keyword.clone())],
+            format!("`{keyword}` takes no arguments, but something follows it on the line."),
+            "Delete the extra words; each line holds one instruction and nothing else.".to_string(),
+        ),
+        ParseError::MisnamedMain { line, name } => explain(
+            "misnamed-main",
+            Some(*line),
+            alloc::vec![("name", name.clone())],
+            format!("`def {name}` is almost `def main`, but names are case-sensitive."),
+            "Rename it to `def main`.".to_string(),
+        ),
+        ParseError::EmptyProgram => explain(
+            "empty-program",
+            None,
+            Vec::new(),
+            "The program has no instructions at all.".to_string(),
+            "Write the first steps inside a `def main ... enddef` block.".to_string(),
+        ),
+        ParseError::MissingMain => explain(
+            "missing-main",
+            None,
+            Vec::new(),
+            "The program has no `def main`, so there is nowhere to start.".to_string(),
+            "Wrap the program's first steps in `def main ... enddef`.".to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_runtime_error_is_explained() {
+        let samples = [
+            RuntimeError::HitWall { line: 1 },
+            RuntimeError::NoBeeperToTake { line: 1 },
+            RuntimeError::TooManyBeepers { line: 1 },
+            RuntimeError::RobotCollision { line: 1 },
+            RuntimeError::CallDepthExceeded { line: 1, limit: 64 },
+            RuntimeError::LoopLimitExceeded { line: 1, limit: 64 },
+            RuntimeError::UnknownProcedure { line: 1, name: "x".to_string() },
+            RuntimeError::UnknownLabel { line: 1, name: "x".to_string() },
+            RuntimeError::UnknownInstruction { line: 1, instruction: "x".to_string() },
+            RuntimeError::NotImplemented { line: 1 },
+            RuntimeError::MalformedBlock { line: 1 },
+            RuntimeError::MissingMain,
+            RuntimeError::UnknownEntryPoint { name: "x".to_string() },
+        ];
+        let mut keys = Vec::new();
+        for error in &samples {
+            let feedback = runtime(error);
+            assert!(!feedback.what.is_empty() && !feedback.advice.is_empty());
+            assert_eq!(feedback.line, error_line(error));
+            keys.push(feedback.key);
+        }
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), samples.len(), "keys must be distinct");
+    }
+
+    fn error_line(error: &RuntimeError) -> Option<usize> {
+        match error {
+            RuntimeError::MissingMain | RuntimeError::UnknownEntryPoint { .. } => None,
+            _ => Some(1),
+        }
+    }
+
+    #[test]
+    fn every_syntax_error_is_explained() {
+        let samples = [
+            ParseError::NestedDefinition { line: 1 },
+            ParseError::InstructionOutsideDefinition { line: 1 },
+            ParseError::UnmatchedBlockEnd { line: 1, keyword: "endif".to_string() },
+            ParseError::UnclosedBlock { line: 1, keyword: "while".to_string() },
+            ParseError::UnknownInstruction { line: 1, instruction: "x".to_string() },
+            ParseError::UnknownCondition { line: 1, condition: "x".to_string() },
+            ParseError::BadRepeatCount { line: 1 },
+            ParseError::BadClearDistance { line: 1 },
+            ParseError::BadPrintItem { line: 1 },
+            ParseError::BadBeeperComparison { line: 1 },
+            ParseError::BadRegionName { line: 1 },
+            ParseError::BadName { line: 1 },
+            ParseError::DuplicateDefinition { line: 1, name: "x".to_string() },
+            ParseError::UnknownProcedure { line: 1, name: "x".to_string() },
+            ParseError::DuplicateLabel { line: 1, name: "x".to_string() },
+            ParseError::UnknownLabel { line: 1, name: "x".to_string() },
+            ParseError::TrailingTokens { line: 1, keyword: "move".to_string() },
+            ParseError::MisnamedMain { line: 1, name: "Main".to_string() },
+            ParseError::EmptyProgram,
+            ParseError::MissingMain,
+        ];
+        for error in &samples {
+            let feedback = syntax(error);
+            assert!(!feedback.what.is_empty() && !feedback.advice.is_empty());
+            assert_eq!(feedback.line, error.line());
+        }
+    }
+
+    #[test]
+    fn templates_fill_their_placeholders_by_name() {
+        let feedback = runtime(&RuntimeError::UnknownProcedure {
+            line: 4,
+            name: "walk".to_string(),
+        });
+        assert_eq!(
+            feedback.fill("Es gibt keine Prozedur namens `{name}`."),
+            "Es gibt keine Prozedur namens `walk`."
+        );
+        // A placeholder the error does not provide stays visible.
+        assert_eq!(feedback.fill("{limit}"), "{limit}");
+    }
+}
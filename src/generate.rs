@@ -0,0 +1,175 @@
+//! Deterministic world generation.
+//!
+//! One task, many worlds: when every student gets their own variant of a
+//! world, copying a classmate's beeper-by-beeper answer stops working, but
+//! grading must still be reproducible — the grader has to rebuild exactly
+//! the world the student saw. A [`WorldGenerator`] is therefore a pure
+//! function of its seed: the same seed always produces the same world, on
+//! every platform, forever. Seeds come either as numbers or derived from a
+//! student identifier with [`WorldGenerator::for_seed_str`].
+//!
+//! The generator scatters walls and beepers over an otherwise empty world;
+//! the robot's start tile is always left clear so every variant is at
+//! least enterable. Anything fancier belongs in a world file.
+
+use crate::world::{Position, World};
+
+/// A seeded source of worlds. Construct with [`for_seed`](WorldGenerator::for_seed)
+/// or [`for_seed_str`](WorldGenerator::for_seed_str), shape with the
+/// builder methods, then call [`generate`](WorldGenerator::generate).
+#[derive(Debug, Clone)]
+pub struct WorldGenerator {
+    state: u64,
+    width: usize,
+    height: usize,
+    walls: usize,
+    beepers: usize,
+}
+
+impl WorldGenerator {
+    /// A generator for the given seed. Equal seeds give equal worlds.
+    pub fn for_seed(seed: u64) -> WorldGenerator {
+        WorldGenerator {
+            state: seed,
+            width: 10,
+            height: 10,
+            walls: 0,
+            beepers: 0,
+        }
+    }
+
+    /// A generator seeded from a string — typically a student identifier
+    /// like `alice@school` — so each student gets their own reproducible
+    /// variant of the same task. The derivation is FNV-1a, fixed for good:
+    /// changing it would silently change every student's world.
+    pub fn for_seed_str(id: &str) -> WorldGenerator {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in id.bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        WorldGenerator::for_seed(hash)
+    }
+
+    /// Set the world's dimensions (default 10x10).
+    pub fn size(mut self, width: usize, height: usize) -> WorldGenerator {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Scatter this many wall tiles (default none).
+    pub fn walls(mut self, walls: usize) -> WorldGenerator {
+        self.walls = walls;
+        self
+    }
+
+    /// Scatter this many beepers (default none). Several may land on the
+    /// same tile, up to the tile's capacity.
+    pub fn beepers(mut self, beepers: usize) -> WorldGenerator {
+        self.beepers = beepers;
+        self
+    }
+
+    /// Build the world. Walls go on distinct empty tiles, beepers anywhere
+    /// but on walls; the robot's start at (0, 0) stays clear of both. Asking
+    /// for more walls or beepers than the world can hold places as many as
+    /// fit.
+    pub fn generate(mut self) -> World {
+        let mut world = World::new(self.width, self.height);
+        let tiles = self.width * self.height;
+        let mut placed = 0;
+        // Cap the attempts so an overfull request terminates instead of
+        // looping on a world with no free tile left.
+        for _ in 0..tiles * 8 {
+            if placed == self.walls {
+                break;
+            }
+            let position = self.position();
+            if position != Position::new(0, 0) && !world.is_wall(position) {
+                world.set_wall(position, true);
+                placed += 1;
+            }
+        }
+        let mut dropped = 0;
+        for _ in 0..tiles * 8 {
+            if dropped == self.beepers {
+                break;
+            }
+            let position = self.position();
+            if !world.is_wall(position) && world.put_beeper(position) {
+                dropped += 1;
+            }
+        }
+        world
+    }
+
+    /// The next pseudo-random position. The sequence is splitmix64, chosen
+    /// for being tiny, seedable and identical everywhere; this is variety,
+    /// not cryptography.
+    fn position(&mut self) -> Position {
+        let x = self.next() as usize % self.width;
+        let y = self.next() as usize % self.height;
+        Position::new(x, y)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_beepers(world: &World) -> usize {
+        (0..world.height())
+            .flat_map(|y| (0..world.width()).map(move |x| Position::new(x, y)))
+            .map(|position| usize::from(world.beepers_at(position)))
+            .sum()
+    }
+
+    #[test]
+    fn the_same_seed_always_builds_the_same_world() {
+        let build = || WorldGenerator::for_seed(7).size(8, 6).walls(5).beepers(9).generate();
+        assert_eq!(build().snapshot(), build().snapshot());
+    }
+
+    #[test]
+    fn a_student_id_is_a_seed() {
+        let alice = WorldGenerator::for_seed_str("alice@school").size(6, 6).beepers(4);
+        let again = WorldGenerator::for_seed_str("alice@school").size(6, 6).beepers(4);
+        let bob = WorldGenerator::for_seed_str("bob@school").size(6, 6).beepers(4);
+        assert_eq!(alice.clone().generate().snapshot(), again.generate().snapshot());
+        // Different students get different worlds. (Not guaranteed for
+        // every pair of strings, but these two had better differ.)
+        assert_ne!(alice.generate().snapshot(), bob.generate().snapshot());
+    }
+
+    #[test]
+    fn the_requested_counts_are_placed() {
+        let world = WorldGenerator::for_seed(3).size(8, 8).walls(6).beepers(10).generate();
+        let walls = (0..8)
+            .flat_map(|y| (0..8).map(move |x| Position::new(x, y)))
+            .filter(|position| world.is_wall(*position))
+            .count();
+        assert_eq!(walls, 6);
+        assert_eq!(total_beepers(&world), 10);
+    }
+
+    #[test]
+    fn the_start_tile_stays_clear_of_walls() {
+        // More walls than tiles: every tile but the start gets one.
+        let world = WorldGenerator::for_seed(1).size(3, 3).walls(100).generate();
+        assert!(!world.is_wall(Position::new(0, 0)));
+        let walls = (0..3)
+            .flat_map(|y| (0..3).map(move |x| Position::new(x, y)))
+            .filter(|position| world.is_wall(*position))
+            .count();
+        assert_eq!(walls, 8);
+    }
+}
@@ -0,0 +1,244 @@
+//! Record-and-compare golden tests for lesson packs.
+//!
+//! A golden file captures what a reference solution does in a given world:
+//! a hash of its full replay trace plus the final world. [`assert_golden!`]
+//! records the file on its first run and compares against it afterwards, so
+//! a lesson repo gets regression coverage from one line per exercise:
+//!
+//! ```no_run
+//! let world = karel::World::default();
+//! karel::assert_golden!("def main\n move\n die\nenddef", world, "golden/solution.txt");
+//! ```
+//!
+//! The file is plain text — a `trace-hash` line followed by the final world
+//! in the worldfile text format — so diffs in review stay readable.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::grade::STEP_BUDGET;
+use crate::interpreter::{Interpreter, StepResult};
+use crate::parser;
+use crate::trace::Recorder;
+use crate::world::World;
+use crate::worldfile;
+
+/// Why a golden run could not be recorded or did not match.
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The golden file could not be read or written.
+    Io(std::io::Error),
+    /// The program does not parse or start.
+    Program(String),
+    /// The golden file itself is not in the expected format.
+    BadFile { path: String },
+    /// The run no longer matches the recording.
+    Mismatch {
+        what: &'static str,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Io(error) => write!(f, "{error}"),
+            GoldenError::Program(message) => write!(f, "{message}"),
+            GoldenError::BadFile { path } => {
+                write!(f, "`{path}` is not a golden file (expected a `trace-hash` line)")
+            }
+            GoldenError::Mismatch {
+                what,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{what} changed: the golden file has {expected}, this run produced {actual}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<std::io::Error> for GoldenError {
+    fn from(error: std::io::Error) -> GoldenError {
+        GoldenError::Io(error)
+    }
+}
+
+/// FNV-1a, 64 bit: small, dependency-free and stable across platforms,
+/// which is all a golden hash needs.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Run `source` in a copy of `world` and return the trace hash and final
+/// world text. Runtime errors and the step budget end the run but still
+/// produce a deterministic recording — a crash is as golden as a success.
+fn run(source: &str, world: &World) -> Result<(u64, String), GoldenError> {
+    let lines = parser::preprocess(source);
+    parser::validate(&lines).map_err(|error| GoldenError::Program(error.to_string()))?;
+    let mut interpreter = Interpreter::new(lines, world.clone())
+        .map_err(|error| GoldenError::Program(error.to_string()))?;
+    let mut buffer = Vec::new();
+    let mut recorder = Recorder::new(&mut buffer, &interpreter.world)?;
+    let mut steps = 0usize;
+    let outcome = loop {
+        if steps >= STEP_BUDGET {
+            break Some(format!("step limit of {STEP_BUDGET} exceeded"));
+        }
+        let line = interpreter.current_line().unwrap_or(0);
+        let instruction = interpreter
+            .current_instruction()
+            .unwrap_or_default()
+            .to_string();
+        match interpreter.step() {
+            Ok(StepResult::Running) => {
+                steps += 1;
+                recorder.record(line, &instruction, &interpreter.world)?;
+            }
+            Ok(StepResult::Finished) => break None,
+            Err(error) => break Some(error.to_string()),
+        }
+    };
+    recorder.finish(outcome.as_deref())?;
+    Ok((fnv1a(&buffer), worldfile::to_text(&interpreter.world)))
+}
+
+/// Run the reference solution and write its golden file, creating parent
+/// directories as needed.
+pub fn record(source: &str, world: &World, path: &Path) -> Result<(), GoldenError> {
+    let (hash, final_world) = run(source, world)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, format!("trace-hash {hash:016x}\n{final_world}"))?;
+    Ok(())
+}
+
+/// Re-run the solution and compare against the golden file.
+pub fn check(source: &str, world: &World, path: &Path) -> Result<(), GoldenError> {
+    let golden = std::fs::read_to_string(path)?;
+    let bad_file = || GoldenError::BadFile {
+        path: path.display().to_string(),
+    };
+    let (first, expected_world) = golden.split_once('\n').ok_or_else(bad_file)?;
+    let expected_hash = first.strip_prefix("trace-hash ").ok_or_else(bad_file)?;
+    let (hash, final_world) = run(source, world)?;
+    let hash = format!("{hash:016x}");
+    if hash != expected_hash.trim() {
+        return Err(GoldenError::Mismatch {
+            what: "the replay trace",
+            expected: format!("hash {}", expected_hash.trim()),
+            actual: format!("hash {hash}"),
+        });
+    }
+    if final_world != expected_world {
+        return Err(GoldenError::Mismatch {
+            what: "the final world",
+            expected: format!("\n{expected_world}"),
+            actual: format!("\n{final_world}"),
+        });
+    }
+    Ok(())
+}
+
+/// Assert that a solution still behaves as recorded. If the golden file
+/// does not exist yet, it is recorded instead and the assertion passes —
+/// commit the file and the next run compares against it.
+#[macro_export]
+macro_rules! assert_golden {
+    ($source:expr, $world:expr, $path:expr) => {{
+        let path = ::std::path::Path::new($path);
+        let result = if path.exists() {
+            $crate::golden::check($source, &$world, path)
+        } else {
+            $crate::golden::record($source, &$world, path)
+        };
+        if let Err(error) = result {
+            panic!("golden test `{}` failed: {error}", path.display());
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const SOLUTION: &str = "def main\n move\n put\n die\nenddef";
+
+    /// A scratch golden file path, cleaned up on drop.
+    struct GoldenFile {
+        path: PathBuf,
+    }
+
+    impl Drop for GoldenFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn golden_file(name: &str) -> GoldenFile {
+        GoldenFile {
+            path: std::env::temp_dir()
+                .join(format!("karel-golden-{}-{name}.txt", std::process::id())),
+        }
+    }
+
+    #[test]
+    fn recording_then_checking_passes() {
+        let file = golden_file("roundtrip");
+        let world = World::new(4, 1);
+        record(SOLUTION, &world, &file.path).unwrap();
+        assert!(check(SOLUTION, &world, &file.path).is_ok());
+    }
+
+    #[test]
+    fn a_changed_solution_is_a_mismatch() {
+        let file = golden_file("changed");
+        let world = World::new(4, 1);
+        record(SOLUTION, &world, &file.path).unwrap();
+        let changed = "def main\n move\n move\n die\nenddef";
+        assert!(matches!(
+            check(changed, &world, &file.path),
+            Err(GoldenError::Mismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn the_macro_records_on_first_use() {
+        let file = golden_file("macro");
+        let world = World::new(4, 1);
+        let path = file.path.display().to_string();
+        assert_golden!(SOLUTION, world, &path);
+        assert!(file.path.exists());
+        assert_golden!(SOLUTION, world, &path);
+    }
+
+    #[test]
+    fn crashing_solutions_are_still_golden() {
+        let file = golden_file("crash");
+        let world = World::new(2, 1);
+        let crashes = "def main\n move\n move\n move\nenddef";
+        record(crashes, &world, &file.path).unwrap();
+        assert!(check(crashes, &world, &file.path).is_ok());
+    }
+
+    #[test]
+    fn garbage_files_are_rejected() {
+        let file = golden_file("garbage");
+        std::fs::write(&file.path, "not a golden file\n").unwrap();
+        assert!(matches!(
+            check(SOLUTION, &World::new(4, 1), &file.path),
+            Err(GoldenError::BadFile { .. })
+        ));
+    }
+}
@@ -0,0 +1,577 @@
+//! Grading student submissions against a [`Task`].
+//!
+//! A submission is run once in every world of the task with a step budget,
+//! so a submission stuck in an endless loop gets a clean "step limit" verdict
+//! instead of hanging the whole batch.
+
+use crate::interpreter::{Interpreter, StepResult};
+use crate::json::Value;
+use crate::lint::{self, Lint, Severity};
+use crate::lock;
+use crate::metrics::{self, ProgramMetrics};
+use crate::modules;
+use crate::parser;
+use crate::task::{GoalScore, Task};
+
+/// How many instructions a submission may execute per world before it is
+/// considered stuck.
+pub const STEP_BUDGET: usize = 1_000_000;
+
+/// The outcome of running one submission in one world.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorldResult {
+    /// File name of the world, as listed in the task.
+    pub world: String,
+    /// All goals were met and the run ended without an error.
+    pub passed: bool,
+    /// How many of the task's goals held in the final world.
+    pub goals_met: usize,
+    /// The weighted partial-credit view of the same goals; see
+    /// [`Task::score`](crate::task::Task::score).
+    pub score: GoalScore,
+    /// Total cost of the run under the task's [`CostModel`]
+    /// (`crate::task::CostModel`); the plain tick count unless the task
+    /// weights actions.
+    pub cost: usize,
+    /// Why the run failed, when it did.
+    pub error: Option<String>,
+}
+
+/// The outcome of grading one submission against all worlds of a task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmissionReport {
+    /// The submission file name (or other identifier given by the caller).
+    pub submission: String,
+    /// Per-world results; empty when the program did not even validate.
+    pub results: Vec<WorldResult>,
+    /// Structural complexity of the submission (see [`metrics`]); empty
+    /// when the program did not validate. Lets a teacher require
+    /// decomposition next to correctness.
+    pub metrics: ProgramMetrics,
+    /// Style findings under the task's lint configuration; a finding at
+    /// [`Severity::Error`] fails the submission like a wrong answer would.
+    pub lints: Vec<Lint>,
+    /// The validation error, when there was one.
+    pub parse_error: Option<String>,
+}
+
+impl SubmissionReport {
+    /// Did the submission solve the task: valid, passed in every world, and
+    /// free of error-severity lints?
+    pub fn passed(&self) -> bool {
+        self.parse_error.is_none()
+            && self.results.iter().all(|result| result.passed)
+            && self.lints.iter().all(|lint| lint.severity != Severity::Error)
+    }
+
+    /// The report as a JSON value, for machine-readable summaries.
+    pub fn to_json(&self, goals_total: usize) -> Value {
+        Value::object([
+            ("submission", Value::from(self.submission.clone())),
+            ("passed", Value::from(self.passed())),
+            ("parse_error", Value::from(self.parse_error.clone())),
+            (
+                "lints",
+                Value::Array(
+                    self.lints
+                        .iter()
+                        .map(|lint| {
+                            Value::object([
+                                ("line", Value::from(lint.line)),
+                                ("rule", Value::from(lint.rule.name())),
+                                ("severity", Value::from(lint.severity.name())),
+                                ("message", Value::from(lint.message.clone())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "metrics",
+                Value::Array(
+                    self.metrics
+                        .procedures
+                        .iter()
+                        .map(|procedure| {
+                            Value::object([
+                                ("name", Value::from(procedure.name.clone())),
+                                ("lines", Value::from(procedure.lines)),
+                                ("max_nesting", Value::from(procedure.max_nesting)),
+                                ("cyclomatic", Value::from(procedure.cyclomatic)),
+                                (
+                                    "calls",
+                                    Value::Array(
+                                        procedure
+                                            .calls
+                                            .iter()
+                                            .map(|call| Value::from(call.clone()))
+                                            .collect(),
+                                    ),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "worlds",
+                Value::Array(
+                    self.results
+                        .iter()
+                        .map(|result| {
+                            Value::object([
+                                ("world", Value::from(result.world.clone())),
+                                ("passed", Value::from(result.passed)),
+                                ("goals_met", Value::from(result.goals_met)),
+                                ("goals_total", Value::from(goals_total)),
+                                ("score", Value::from(result.score.fraction())),
+                                ("cost", Value::from(result.cost)),
+                                ("error", Value::from(result.error.clone())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+/// Grade one submission (as source text) against the task.
+pub fn grade(task: &Task, submission: &str, source: &str) -> SubmissionReport {
+    // A submission that tampered with the starter's locked regions is
+    // rejected before it runs, like a submission that does not parse.
+    if let Some((_, starter)) = &task.starter {
+        if let Err(error) = lock::verify(starter, source) {
+            return SubmissionReport {
+                submission: submission.to_string(),
+                results: Vec::new(),
+                metrics: ProgramMetrics::default(),
+                lints: Vec::new(),
+                parse_error: Some(error.to_string()),
+            };
+        }
+    }
+    let mut files = vec![(submission, source)];
+    if let Some((file, hooks)) = &task.hooks {
+        files.push((file.as_str(), hooks.as_str()));
+    }
+    if task.stdlib {
+        files.push((modules::STDLIB_FILE, modules::STDLIB));
+    }
+    let (lines, map) = if files.len() > 1 {
+        match modules::link(&files) {
+            Ok(linked) => linked,
+            Err(error) => {
+                return SubmissionReport {
+                    submission: submission.to_string(),
+                    results: Vec::new(),
+                    metrics: ProgramMetrics::default(),
+                    lints: Vec::new(),
+                    parse_error: Some(error.to_string()),
+                }
+            }
+        }
+    } else {
+        (parser::preprocess(source), parser::SourceMap::default())
+    };
+    if let Err(error) = parser::validate(&lines) {
+        let message = match error.line() {
+            Some(line) => format!("line {line}: {error}"),
+            None => error.to_string(),
+        };
+        return SubmissionReport {
+            submission: submission.to_string(),
+            results: Vec::new(),
+            metrics: ProgramMetrics::default(),
+            lints: Vec::new(),
+            parse_error: Some(message),
+        };
+    }
+
+    let mut results = Vec::new();
+    for (world_name, world) in &task.worlds {
+        results.push(shielded(task, world_name, &lines, &map, world.clone()));
+    }
+    // Style and structure are judged on the student's own file, not on any
+    // library linked beside it.
+    let own: Vec<parser::Line<'_>> = lines.iter().filter(|line| line.file == 0).cloned().collect();
+    SubmissionReport {
+        submission: submission.to_string(),
+        results,
+        metrics: metrics::measure(&own),
+        lints: lint::lint(&own, &task.lints),
+        parse_error: None,
+    }
+}
+
+/// With the `shield` feature, a panic anywhere in one world's run is caught
+/// and reported as that world's error, so a grading server survives bugs a
+/// malformed submission manages to tickle. Without the feature this is
+/// [`grade_in_world`] itself and a panic unwinds as usual.
+#[cfg(feature = "shield")]
+fn shielded(
+    task: &Task,
+    world_name: &str,
+    lines: &[parser::Line<'_>],
+    map: &parser::SourceMap,
+    world: crate::world::World,
+) -> WorldResult {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        grade_in_world(task, world_name, lines, map, world)
+    }))
+    .unwrap_or_else(|_| WorldResult {
+        world: world_name.to_string(),
+        passed: false,
+        goals_met: 0,
+        // There is no final world to score: every goal counts as unmet.
+        score: GoalScore {
+            breakdown: (0..task.goals.len()).map(|_| (1, false)).collect(),
+        },
+        cost: 0,
+        error: Some("internal error: the run panicked".to_string()),
+    })
+}
+
+#[cfg(not(feature = "shield"))]
+use self::grade_in_world as shielded;
+
+fn grade_in_world(
+    task: &Task,
+    world_name: &str,
+    lines: &[parser::Line<'_>],
+    map: &parser::SourceMap,
+    mut world: crate::world::World,
+) -> WorldResult {
+    for event in &task.events {
+        world.schedule(*event);
+    }
+
+    // The teacher's `before` hook, the student's `main` and the teacher's
+    // `after` hook run back to back in the same world, sharing the step
+    // budget. An error in a hook is the task's fault, not the student's,
+    // and says so.
+    let mut error = None;
+    let mut steps = 0usize;
+    if let Some(before) = &task.before {
+        error = run_phase(task, lines, map, &mut world, &mut steps, before, Some("setup"));
+    }
+    // The student pays only for their own actions, not the setup's.
+    let setup_cost = task.costs.total(&world);
+    if error.is_none() {
+        error = run_phase(task, lines, map, &mut world, &mut steps, "main", None);
+    }
+    let cost = task.costs.total(&world) - setup_cost;
+    if error.is_none() {
+        if let Some(after) = &task.after {
+            error = run_phase(task, lines, map, &mut world, &mut steps, after, Some("cleanup"));
+        }
+    }
+
+    let score = task.score(&world);
+    let goals_met = score.breakdown.iter().filter(|(_, met)| *met).count();
+    WorldResult {
+        world: world_name.to_string(),
+        passed: error.is_none() && goals_met == task.goals.len(),
+        goals_met,
+        score,
+        cost,
+        error,
+    }
+}
+
+/// Run one phase of a graded run — a teacher hook or the student's `main` —
+/// to its end over the borrowed world. `blame` names the phase in any error
+/// so hook failures are not pinned on the student; `None` leaves the
+/// student's errors as they were. Independently, a runtime error in a
+/// scaffold file (the hooks file, the standard library) is attributed to
+/// that file through the source map, whoever called into it.
+fn run_phase(
+    task: &Task,
+    lines: &[parser::Line<'_>],
+    map: &parser::SourceMap,
+    world: &mut crate::world::World,
+    steps: &mut usize,
+    entry: &str,
+    blame: Option<&str>,
+) -> Option<String> {
+    let attribute = |message: String| match blame {
+        Some(phase) => format!("task {phase} failed: {message}"),
+        None => message,
+    };
+    // Hooks live in the task's hooks file, so a bare name refers to that
+    // module; a dotted name (or the student's `main`) is taken as written.
+    let entry = match &task.hooks {
+        Some((file, _)) if blame.is_some() && !entry.contains('.') => {
+            format!("{}.{entry}", modules::module_name(file))
+        }
+        _ => entry.to_string(),
+    };
+    let mut interpreter = match Interpreter::with_entry(lines.to_vec(), world, &entry) {
+        Ok(interpreter) => interpreter,
+        Err(error) => return Some(attribute(error.to_string())),
+    };
+    loop {
+        if *steps >= STEP_BUDGET {
+            return Some(attribute(format!("step limit of {STEP_BUDGET} exceeded")));
+        }
+        let file = interpreter.current_file().unwrap_or(0);
+        match interpreter.step() {
+            Ok(StepResult::Running) => *steps += 1,
+            Ok(StepResult::Finished) => return None,
+            Err(runtime_error) => {
+                return Some(attribute(map.attribute(file, &runtime_error.to_string())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{CostModel, Goal};
+    use crate::world::{Position, World};
+
+    fn beeper_task() -> Task {
+        let mut world = World::new(4, 1);
+        world.set_beepers(Position::new(2, 0), 1);
+        Task {
+            name: "collect".to_string(),
+            worlds: vec![("w".to_string(), world)],
+            goals: vec![Goal::NoBeepers, Goal::RobotAt(Position::new(2, 0))],
+            goal_weights: Vec::new(),
+            events: Vec::new(),
+            costs: CostModel::default(),
+            lints: lint::Config::default(),
+            stdlib: false,
+            hooks: None,
+            before: None,
+            after: None,
+            starter: None,
+        }
+    }
+
+    #[test]
+    fn correct_submission_passes() {
+        let report = grade(
+            &beeper_task(),
+            "good.kl",
+            "def main\n move\n move\n take\n die\nenddef",
+        );
+        assert!(report.passed());
+        assert_eq!(report.results[0].goals_met, 2);
+    }
+
+    #[test]
+    fn partially_correct_submission_reports_goal_counts() {
+        // Collects the beeper but walks one tile too far.
+        let report = grade(
+            &beeper_task(),
+            "late.kl",
+            "def main\n move\n move\n take\n move\n die\nenddef",
+        );
+        assert!(!report.passed());
+        assert_eq!(report.results[0].goals_met, 1);
+        assert_eq!(report.results[0].error, None);
+        // Both goals weigh 1, so meeting one of two is half credit.
+        assert!((report.results[0].score.fraction() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn goal_weights_skew_the_partial_credit() {
+        let mut task = beeper_task();
+        task.goal_weights = vec![3, 1];
+        // Collects the beeper (weight 3) but overshoots the tile (weight 1).
+        let report = grade(
+            &task,
+            "late.kl",
+            "def main\n move\n move\n take\n move\n die\nenddef",
+        );
+        let score = &report.results[0].score;
+        assert_eq!(score.breakdown, vec![(3, true), (1, false)]);
+        assert!((score.fraction() - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_submission_reports_parse_error() {
+        let report = grade(&beeper_task(), "bad.kl", "def main\n fly\nenddef");
+        assert!(!report.passed());
+        assert!(report.parse_error.is_some());
+        assert!(report.results.is_empty());
+    }
+
+    #[test]
+    fn tampered_locked_regions_fail_like_a_parse_error() {
+        let mut task = beeper_task();
+        task.starter = Some((
+            "starter.kl".to_string(),
+            "#! lock begin\ndef main\n call solve\nenddef\n#! lock end\ndef solve\nenddef"
+                .to_string(),
+        ));
+        // Keeping the locked `main` intact passes; rewriting it does not.
+        let honest = "def main\n call solve\nenddef\ndef solve\n move\n move\n take\n die\nenddef";
+        assert!(grade(&task, "good.kl", honest).passed());
+        let report = grade(&task, "sneaky.kl", "def main\n move\n move\n take\n die\nenddef");
+        assert!(!report.passed());
+        assert!(report.parse_error.as_deref().unwrap().contains("locked region"));
+        assert!(report.results.is_empty());
+    }
+
+    #[test]
+    fn endless_loop_hits_the_step_budget() {
+        let report = grade(
+            &beeper_task(),
+            "loop.kl",
+            "def main\n while! north\n  turn-left\n  turn-left\n endwhile\nenddef",
+        );
+        assert!(!report.passed());
+        let error = report.results[0].error.as_deref().unwrap();
+        assert!(error.contains("step limit"), "{error}");
+    }
+
+    #[test]
+    fn weighted_costs_price_the_run() {
+        use crate::environment::Action;
+
+        let solution = "def main\n move\n move\n take\n die\nenddef";
+        // Unweighted, the cost is the tick count.
+        let report = grade(&beeper_task(), "good.kl", solution);
+        assert_eq!(report.results[0].cost, 4);
+
+        let mut task = beeper_task();
+        task.costs.set(Action::Move, 3);
+        let report = grade(&task, "good.kl", solution);
+        assert_eq!(report.results[0].cost, 8);
+    }
+
+    #[test]
+    fn the_report_measures_decomposition() {
+        let report = grade(
+            &beeper_task(),
+            "tidy.kl",
+            "def main\n call approach\n take\n die\nenddef\ndef approach\n move\n move\nenddef",
+        );
+        assert!(report.passed());
+        // The teacher's check: did `main` delegate?
+        let main = report.metrics.procedure("main").unwrap();
+        assert_eq!(main.calls, ["approach"]);
+        assert_eq!(report.metrics.procedure_count(), 2);
+        assert!(report.to_json(2).to_string().contains("\"cyclomatic\":1"));
+    }
+
+    #[test]
+    fn stdlib_tasks_accept_use_std() {
+        let source = "use std\ndef main\n call go-to-wall\n call turn-around\n move\n take\n die\nenddef";
+        // Without the library the program does not even validate.
+        let report = grade(&beeper_task(), "walker.kl", source);
+        assert!(report.parse_error.is_some());
+
+        let mut task = beeper_task();
+        task.stdlib = true;
+        let report = grade(&task, "walker.kl", source);
+        assert!(report.passed(), "{:?}", report);
+        // The library is not the student's code: no metrics, no lints for it.
+        assert_eq!(report.metrics.procedure_count(), 1);
+        assert!(report.lints.is_empty());
+    }
+
+    #[test]
+    fn error_severity_lints_fail_the_submission() {
+        // Correct, but leaves an uncalled procedure behind.
+        let source = "def main\n move\n move\n take\n die\nenddef\ndef spare\n move\nenddef";
+        let report = grade(&beeper_task(), "sloppy.kl", source);
+        assert_eq!(report.lints.len(), 1);
+        // A warning by default; promoted to an error it fails the run.
+        assert!(report.passed());
+        let mut task = beeper_task();
+        task.lints.set(lint::Rule::DeadCode, Severity::Error);
+        let report = grade(&task, "sloppy.kl", source);
+        assert!(!report.passed());
+        assert!(report.to_json(2).to_string().contains("\"dead-code\""));
+    }
+
+    /// `scatter` lays a beeper one tile ahead and walks back; `sweep`
+    /// expects to be standing on one.
+    const HOOKS: &str = "def scatter\n move\n put\n turn-left\n turn-left\n move\n turn-left\n turn-left\nenddef\n\
+                         def sweep\n take\nenddef";
+
+    fn hooked_task() -> Task {
+        Task {
+            name: "hooked".to_string(),
+            worlds: vec![("w".to_string(), World::new(3, 1))],
+            goals: vec![Goal::NoBeepers],
+            goal_weights: Vec::new(),
+            events: Vec::new(),
+            costs: CostModel::default(),
+            lints: lint::Config::default(),
+            stdlib: false,
+            hooks: Some(("hooks.kl".to_string(), HOOKS.to_string())),
+            before: Some("scatter".to_string()),
+            after: None,
+            starter: None,
+        }
+    }
+
+    #[test]
+    fn hooks_run_around_the_student_code() {
+        // The world starts empty; only `scatter` provides the beeper the
+        // student has to fetch.
+        let report = grade(&hooked_task(), "fetch.kl", "def main\n move\n take\nenddef");
+        assert!(report.passed(), "{:?}", report.results);
+        // The setup's seven actions are not billed to the student.
+        assert_eq!(report.results[0].cost, 2);
+    }
+
+    #[test]
+    fn hook_failures_are_blamed_on_the_task() {
+        // The cleanup hook `take`s where the student already collected.
+        let mut task = hooked_task();
+        task.after = Some("sweep".to_string());
+        let report = grade(&task, "fetch.kl", "def main\n move\n take\nenddef");
+        assert!(!report.passed());
+        let error = report.results[0].error.as_deref().unwrap();
+        assert!(error.starts_with("task cleanup failed:"), "{error}");
+
+        // A hook naming no procedure is the task author's mistake too.
+        let mut task = hooked_task();
+        task.before = Some("missing".to_string());
+        let report = grade(&task, "fetch.kl", "def main\n move\n take\nenddef");
+        let error = report.results[0].error.as_deref().unwrap();
+        assert!(error.starts_with("task setup failed:"), "{error}");
+        assert!(error.contains("hooks.missing"), "{error}");
+
+        // The student's own errors keep their plain attribution.
+        let report = grade(&hooked_task(), "grab.kl", "def main\n take\nenddef");
+        let error = report.results[0].error.as_deref().unwrap();
+        assert!(!error.starts_with("task"), "{error}");
+    }
+
+    #[test]
+    fn scaffolding_bugs_are_not_pinned_on_the_student() {
+        // The student calls into the hooks module mid-`main` and the error
+        // fires there: the verdict names the hooks file, not the student.
+        let report = grade(
+            &hooked_task(),
+            "trusting.kl",
+            "def main\n move\n take\n call hooks.sweep\nenddef",
+        );
+        assert!(!report.passed());
+        let error = report.results[0].error.as_deref().unwrap();
+        assert!(error.starts_with("in provided code (`hooks.kl`):"), "{error}");
+
+        // The same mistake in the student's own file stays theirs.
+        let report = grade(
+            &hooked_task(),
+            "greedy.kl",
+            "def main\n move\n take\n take\nenddef",
+        );
+        let error = report.results[0].error.as_deref().unwrap();
+        assert!(!error.starts_with("in provided code"), "{error}");
+    }
+
+    #[test]
+    fn runtime_error_is_reported() {
+        let report = grade(&beeper_task(), "crash.kl", "def main\n take\nenddef");
+        assert!(!report.passed());
+        assert!(report.results[0].error.as_deref().unwrap().contains("beeper"));
+    }
+}
@@ -0,0 +1,195 @@
+//! The accepted grammar, as data.
+//!
+//! Dialect implementations, editor tooling and the LSP all need to know
+//! what the parser accepts, and prose documentation drifts. This module
+//! states the grammar as a table of [`Production`]s — each with its EBNF
+//! rule and conformance snippets the real parser is tested against — and
+//! renders the whole thing as an EBNF document with [`ebnf`]. When the
+//! parser grows a form, it grows here too, and the conformance suite keeps
+//! the two honest.
+
+use alloc::string::String;
+
+/// One production of the grammar: its EBNF rule and snippets that pin down
+/// what the parser does and does not accept.
+///
+/// The snippets are complete programs, so external implementations can run
+/// them through their own front end unmodified: every `valid` program
+/// passes [`check`](crate::parser::check) with no diagnostics, every
+/// `invalid` one draws at least one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Production {
+    pub name: &'static str,
+    /// The right-hand side, in EBNF.
+    pub rule: &'static str,
+    /// Complete programs the parser accepts, exercising this production.
+    pub valid: &'static [&'static str],
+    /// Complete programs the parser rejects by this production's rules.
+    pub invalid: &'static [&'static str],
+}
+
+/// Every production of the language, top-down. `label` and `goto` are
+/// included: they are low-level forms for generated code, but they are part
+/// of what the parser accepts.
+pub const PRODUCTIONS: &[Production] = &[
+    Production {
+        name: "program",
+        rule: "{ definition }",
+        valid: &["def main\nenddef", "def main\nenddef\ndef helper\n move\nenddef"],
+        invalid: &["", "move", "def helper\n move\nenddef"],
+    },
+    Production {
+        name: "definition",
+        rule: "\"def\" name newline { statement } \"enddef\"",
+        valid: &["def main\n move\nenddef"],
+        invalid: &[
+            "def main\n move",
+            "def main\n def nested\n enddef\nenddef",
+            "def main\nenddef\ndef main\nenddef",
+        ],
+    },
+    Production {
+        name: "statement",
+        rule: "instruction | conditional | loop | repetition | call | label | goto",
+        valid: &["def main\n move\n call helper\nenddef\ndef helper\n beep\nenddef"],
+        invalid: &["def main\n fly\nenddef"],
+    },
+    Production {
+        name: "instruction",
+        rule: "\"move\" | \"turn-left\" | \"take\" | \"put\" | \"beep\" | \"die\" | \"print direction\" | \"???\"",
+        valid: &["def main\n move\n turn-left\n take\n put\n beep\n print direction\n ???\n die\nenddef"],
+        invalid: &["def main\n print beepers\nenddef"],
+    },
+    Production {
+        name: "conditional",
+        rule: "( \"if\" | \"if!\" ) condition newline { statement } \"endif\"",
+        valid: &["def main\n if wall\n  turn-left\n endif\n if! beeper\n  move\n endif\nenddef"],
+        invalid: &["def main\n if wall\n  move\nenddef", "def main\n endif\nenddef"],
+    },
+    Production {
+        name: "loop",
+        rule: "( \"while\" | \"while!\" ) condition newline { statement } \"endwhile\"",
+        valid: &["def main\n while! wall\n  move\n endwhile\nenddef"],
+        invalid: &["def main\n while goblin\n  move\n endwhile\nenddef"],
+    },
+    Production {
+        name: "repetition",
+        rule: "\"repeat\" number newline { statement } \"endrepeat\"",
+        valid: &["def main\n repeat 3\n  move\n endrepeat\nenddef"],
+        invalid: &[
+            "def main\n repeat 0\n  move\n endrepeat\nenddef",
+            "def main\n repeat many\n  move\n endrepeat\nenddef",
+        ],
+    },
+    Production {
+        name: "call",
+        rule: "\"call\" name",
+        valid: &["def main\n call helper\nenddef\ndef helper\n move\nenddef"],
+        invalid: &["def main\n call ghost\nenddef", "def main\n call\nenddef"],
+    },
+    Production {
+        name: "label",
+        rule: "\"label\" name",
+        valid: &["def main\n label top\n goto top\nenddef"],
+        invalid: &["def main\n label\nenddef", "def main\n label a\n label a\nenddef"],
+    },
+    Production {
+        name: "goto",
+        rule: "\"goto\" name",
+        valid: &["def main\n goto out\n move\n label out\nenddef"],
+        invalid: &["def main\n goto nowhere\nenddef"],
+    },
+    Production {
+        name: "condition",
+        rule: "\"wall\" | \"north\" | \"south\" | \"east\" | \"west\" | \"beeper\" \
+               | \"beeper-ahead\" | \"beeper\" comparison number | \"clear\" number \
+               | \"in-region\" name",
+        valid: &[
+            "def main\n if wall\n endif\n if north\n endif\n if south\n endif\n if east\n endif\n if west\n endif\nenddef",
+            "def main\n if beeper\n endif\n if beeper-ahead\n endif\n if beeper >= 2\n endif\nenddef",
+            "def main\n if clear 3\n endif\n if in-region home\n endif\nenddef",
+        ],
+        invalid: &[
+            "def main\n if upside-down\n endif\nenddef",
+            "def main\n if beeper ~ 2\n endif\nenddef",
+            "def main\n if clear 0\n endif\nenddef",
+            "def main\n if in-region\n endif\nenddef",
+        ],
+    },
+    Production {
+        name: "comparison",
+        rule: "\"<\" | \"<=\" | \"=\" | \">=\" | \">\"",
+        valid: &[
+            "def main\n if beeper < 1\n endif\n if beeper <= 1\n endif\n if beeper = 1\n endif\n if beeper >= 1\n endif\n if beeper > 1\n endif\nenddef",
+        ],
+        invalid: &["def main\n if beeper == 1\n endif\nenddef"],
+    },
+];
+
+/// The grammar as one EBNF document, one production per line. Comments
+/// (`#` to the end of the line) and blank lines are stripped before any of
+/// this applies; the document says so up front.
+pub fn ebnf() -> String {
+    let mut out = String::from(
+        "(* Lines are the unit of parsing: comments (\"#\" to the end of the\n   line) and blank lines are stripped first. *)\n",
+    );
+    for production in PRODUCTIONS {
+        out.push_str(production.name);
+        out.push_str(" = ");
+        out.push_str(production.rule);
+        out.push_str(" ;\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    /// The conformance suite proper: the parser agrees with every snippet
+    /// the grammar table claims about it.
+    #[test]
+    fn the_parser_conforms_to_the_grammar_table() {
+        for production in PRODUCTIONS {
+            for valid in production.valid {
+                let diagnostics = parser::check(&parser::preprocess(valid));
+                assert!(
+                    diagnostics.is_empty(),
+                    "`{}` snippet rejected: {valid:?} -> {:?}",
+                    production.name,
+                    diagnostics
+                );
+            }
+            for invalid in production.invalid {
+                assert!(
+                    !parser::check(&parser::preprocess(invalid)).is_empty(),
+                    "`{}` snippet accepted: {invalid:?}",
+                    production.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_grammar_names_every_condition_the_parser_knows() {
+        let condition = PRODUCTIONS
+            .iter()
+            .find(|production| production.name == "condition")
+            .unwrap();
+        for keyword in parser::CONDITIONS {
+            assert!(
+                condition.rule.contains(&alloc::format!("\"{keyword}\"")),
+                "condition `{keyword}` missing from the grammar"
+            );
+        }
+    }
+
+    #[test]
+    fn the_ebnf_document_lists_every_production() {
+        let document = ebnf();
+        for production in PRODUCTIONS {
+            assert!(document.contains(&alloc::format!("{} = ", production.name)));
+        }
+    }
+}
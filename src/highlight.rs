@@ -0,0 +1,226 @@
+//! Classifying source text for syntax highlighting.
+//!
+//! Editors and web front-ends tend to re-implement the grammar as regular
+//! expressions and drift out of sync with the real parser. [`highlight`]
+//! classifies every token with the same rules the parser uses, so colors
+//! always match what the interpreter will actually accept.
+
+use alloc::vec::Vec;
+
+/// A half-open byte range on one source line. Columns are 1-based to match
+/// the parser's diagnostics; `end` points one past the last byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// 1-based source line.
+    pub line: usize,
+    /// 1-based column of the first byte.
+    pub start: usize,
+    /// 1-based column one past the last byte.
+    pub end: usize,
+}
+
+/// What a token is, as far as coloring is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// An instruction or block keyword (`move`, `def`, `while!`, ...).
+    Keyword,
+    /// A condition after `if`/`while` (`wall`, `beeper`, ...).
+    Condition,
+    /// The name after `def` or `call`.
+    ProcedureName,
+    /// The count after `repeat`.
+    Number,
+    /// `#` and everything after it.
+    Comment,
+    /// A token the parser would reject.
+    Error,
+}
+
+pub(crate) const KEYWORDS: &[&str] = &[
+    "def", "enddef", "move", "turn-left", "take", "put", "beep", "die", "print", "call", "if", "if!",
+    "endif", "while", "while!", "endwhile", "repeat", "endrepeat",
+];
+
+/// Classify every token of `source`. Tokens come out in source order;
+/// whitespace is not covered by any span.
+pub fn highlight(source: &str) -> Vec<(Span, TokenKind)> {
+    let mut tokens = Vec::new();
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let code = match raw.find('#') {
+            Some(comment_start) => {
+                tokens.push((
+                    Span {
+                        line,
+                        start: comment_start + 1,
+                        end: raw.len() + 1,
+                    },
+                    TokenKind::Comment,
+                ));
+                &raw[..comment_start]
+            }
+            None => raw,
+        };
+
+        let words: Vec<(usize, &str)> = split_words(code).collect();
+        let Some(&(_, keyword)) = words.first() else {
+            continue;
+        };
+        for (position, &(offset, word)) in words.iter().enumerate() {
+            let span = Span {
+                line,
+                start: offset + 1,
+                end: offset + word.len() + 1,
+            };
+            let kind = if position == 0 {
+                if KEYWORDS.contains(&word) {
+                    TokenKind::Keyword
+                } else {
+                    TokenKind::Error
+                }
+            } else {
+                let condition = words.get(1).map(|&(_, word)| word);
+                argument_kind(keyword, condition, position, word)
+            };
+            tokens.push((span, kind));
+        }
+    }
+    // The comment token is pushed before the code tokens of its line, but
+    // it always comes after them in the source.
+    tokens.sort_by_key(|(span, _)| (span.line, span.start));
+    tokens
+}
+
+/// Classify the `position`-th word after `keyword` on a line. `condition`
+/// is the line's second word, which decides what the words after an
+/// `if`/`while` condition may be.
+fn argument_kind(keyword: &str, condition: Option<&str>, position: usize, word: &str) -> TokenKind {
+    match (keyword, position) {
+        ("def" | "call", 1) => TokenKind::ProcedureName,
+        ("if" | "if!" | "while" | "while!", 1) => {
+            if crate::parser::CONDITIONS.contains(&word) || word == "clear" || word == "in-region" {
+                TokenKind::Condition
+            } else {
+                TokenKind::Error
+            }
+        }
+        // The region name of `in-region`: a user-chosen name, like the one
+        // after `call`.
+        ("if" | "if!" | "while" | "while!", 2) if condition == Some("in-region") => {
+            TokenKind::ProcedureName
+        }
+        // The look-ahead distance of `clear`, or the operator of a `beeper`
+        // comparison.
+        ("if" | "if!" | "while" | "while!", 2) => {
+            if word.parse::<usize>().is_ok_and(|tiles| tiles > 0) {
+                TokenKind::Number
+            } else if crate::environment::Comparison::parse(word).is_some() {
+                TokenKind::Condition
+            } else {
+                TokenKind::Error
+            }
+        }
+        // The count of a `beeper` comparison.
+        ("if" | "if!" | "while" | "while!", 3) => {
+            if word.parse::<usize>().is_ok() {
+                TokenKind::Number
+            } else {
+                TokenKind::Error
+            }
+        }
+        ("print", 1) => {
+            if word == "direction" {
+                TokenKind::Condition
+            } else {
+                TokenKind::Error
+            }
+        }
+        ("repeat", 1) => {
+            if word.parse::<usize>().is_ok_and(|count| count > 0) {
+                TokenKind::Number
+            } else {
+                TokenKind::Error
+            }
+        }
+        // Everything else takes no arguments.
+        _ => TokenKind::Error,
+    }
+}
+
+/// The whitespace-separated words of `text` with their byte offsets.
+fn split_words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace()
+        .scan(0usize, move |search_from, word| {
+            let offset = text[*search_from..].find(word).expect("word came from text")
+                + *search_from;
+            *search_from = offset + word.len();
+            Some((offset, word))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        highlight(source).into_iter().map(|(_, kind)| kind).collect()
+    }
+
+    #[test]
+    fn keywords_names_and_numbers() {
+        assert_eq!(
+            kinds("def main\n repeat 3\n  move\n endrepeat\nenddef"),
+            vec![
+                TokenKind::Keyword,
+                TokenKind::ProcedureName,
+                TokenKind::Keyword,
+                TokenKind::Number,
+                TokenKind::Keyword,
+                TokenKind::Keyword,
+                TokenKind::Keyword,
+            ]
+        );
+    }
+
+    #[test]
+    fn conditions_and_negated_keywords() {
+        assert_eq!(
+            kinds("while! wall"),
+            vec![TokenKind::Keyword, TokenKind::Condition]
+        );
+        assert_eq!(
+            kinds("if beeperz"),
+            vec![TokenKind::Keyword, TokenKind::Error]
+        );
+    }
+
+    #[test]
+    fn comments_cover_the_hash_to_the_end() {
+        let tokens = highlight("move # go!\n");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].1, TokenKind::Keyword);
+        let (span, kind) = tokens[1];
+        assert_eq!(kind, TokenKind::Comment);
+        assert_eq!((span.start, span.end), (6, 11));
+    }
+
+    #[test]
+    fn unknown_instructions_and_stray_arguments_are_errors() {
+        assert_eq!(kinds("fly"), vec![TokenKind::Error]);
+        assert_eq!(
+            kinds("move fast"),
+            vec![TokenKind::Keyword, TokenKind::Error]
+        );
+        assert_eq!(
+            kinds("repeat zero"),
+            vec![TokenKind::Keyword, TokenKind::Error]
+        );
+    }
+
+    #[test]
+    fn spans_use_one_based_columns() {
+        let tokens = highlight("  call helper");
+        assert_eq!(tokens[0].0, Span { line: 1, start: 3, end: 7 });
+        assert_eq!(tokens[1].0, Span { line: 1, start: 8, end: 14 });
+    }
+}
@@ -0,0 +1,242 @@
+//! Suggesting the next action toward a task's goals.
+//!
+//! A teaching front-end with a "hint" button needs to know what a stuck
+//! student could do next. [`suggest`] answers that with one action;
+//! [`plan`] returns the whole shortest sequence it found. Both run a
+//! breadth-first search over cloned worlds — cloning is cheap (see
+//! [`World`]), so trying an action is just a fork and a `perform` — and
+//! give up honestly once the search budget is spent, so a goal the robot
+//! cannot reach by acting alone never hangs the caller.
+//!
+//! The search only moves the robot; it does not write programs. A hint of
+//! `turn-left` means "face another way", not "insert `turn-left` here" —
+//! mapping suggestions onto the student's source is the front-end's job.
+//! The one exception is [`solve`] plus [`as_program`], which turn a whole
+//! task into straight-line sample solutions — the way a task generator
+//! proves its output is solvable before a student ever sees it.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::environment::{Action, Environment};
+use crate::task::{Goal, Task};
+use crate::world::World;
+
+/// How many world states the search may expand before concluding there is
+/// no hint to give. Generous enough for classroom-sized worlds; small
+/// enough that a hopeless goal answers quickly.
+pub const SEARCH_BUDGET: usize = 100_000;
+
+/// The single next action that makes progress toward the goals, if the
+/// search found a way to meet them. `None` when the goals are already met,
+/// out of reach, or beyond the budget.
+pub fn suggest(world: &World, goals: &[Goal]) -> Option<Action> {
+    plan(world, goals)?.first().copied()
+}
+
+/// A shortest sequence of actions after which every goal holds, found by
+/// breadth-first search from the current state. An empty plan means the
+/// goals are already met; `None` means the search found no way within
+/// [`SEARCH_BUDGET`].
+pub fn plan(world: &World, goals: &[Goal]) -> Option<Vec<Action>> {
+    if met(world, goals) {
+        return Some(Vec::new());
+    }
+
+    // `beep` never helps unless a goal listens for it, so only branch on it
+    // when one does; `die` never helps at all.
+    let mut candidates = vec![Action::Move, Action::TurnLeft, Action::Take, Action::Put];
+    if goals
+        .iter()
+        .any(|goal| matches!(goal, Goal::Beeps(_) | Goal::BeepAt(_)))
+    {
+        candidates.push(Action::Beep);
+    }
+
+    let mut seen = BTreeSet::new();
+    seen.insert(key(world));
+    let mut frontier = VecDeque::new();
+    frontier.push_back((world.clone(), Vec::new()));
+    let mut expanded = 0usize;
+
+    while let Some((state, path)) = frontier.pop_front() {
+        expanded += 1;
+        if expanded > SEARCH_BUDGET {
+            return None;
+        }
+        for &action in &candidates {
+            let mut next = state.clone();
+            if next.perform(action).is_err() {
+                continue;
+            }
+            let mut path = path.clone();
+            path.push(action);
+            if met(&next, goals) {
+                return Some(path);
+            }
+            if seen.insert(key(&next)) {
+                frontier.push_back((next, path));
+            }
+        }
+    }
+    None
+}
+
+/// A reference solution for one world of a task: the action trace the
+/// solver found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// File name of the world, as listed in the task.
+    pub world: String,
+    /// Actions that meet every goal, shortest first found.
+    pub plan: Vec<Action>,
+}
+
+/// Solve every world of the task, scheduled events included. `None` when
+/// any world has no solution within [`SEARCH_BUDGET`] — the check a task
+/// generator runs before handing an exercise to students.
+pub fn solve(task: &Task) -> Option<Vec<Solution>> {
+    let mut solutions = Vec::new();
+    for (name, world) in &task.worlds {
+        let mut world = world.clone();
+        for event in &task.events {
+            world.schedule(*event);
+        }
+        solutions.push(Solution {
+            world: name.clone(),
+            plan: plan(&world, &task.goals)?,
+        });
+    }
+    Some(solutions)
+}
+
+/// A plan as a runnable program, one instruction per line: the sample
+/// solution shown alongside a generated task.
+pub fn as_program(plan: &[Action]) -> String {
+    let mut source = String::from("def main\n");
+    for action in plan {
+        source.push(' ');
+        source.push_str(action.name());
+        source.push('\n');
+    }
+    source.push_str("enddef\n");
+    source
+}
+
+fn met(world: &World, goals: &[Goal]) -> bool {
+    goals.iter().all(|goal| goal.is_met(world))
+}
+
+/// Everything that distinguishes two search states, in `Ord`-friendly form.
+/// Ticks are included because scheduled events and NPCs make the future
+/// depend on the clock, and beeps because goals can count them.
+type Key = (usize, usize, u8, usize, Vec<u8>, Vec<(usize, usize)>);
+
+fn key(world: &World) -> Key {
+    (
+        world.robot.position.x,
+        world.robot.position.y,
+        world.robot.direction as u8,
+        world.ticks(),
+        (0..world.height())
+            .flat_map(|y| (0..world.width()).map(move |x| (x, y)))
+            .map(|(x, y)| world.beepers_at(crate::world::Position::new(x, y)))
+            .collect(),
+        world
+            .beeps()
+            .iter()
+            .map(|beep| (beep.x, beep.y))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Position;
+
+    #[test]
+    fn the_hint_walks_toward_the_beeper() {
+        let mut world = World::new(4, 1);
+        world.set_beepers(Position::new(2, 0), 1);
+        let goals = [Goal::NoBeepers, Goal::RobotAt(Position::new(2, 0))];
+        assert_eq!(
+            plan(&world, &goals),
+            Some(vec![Action::Move, Action::Move, Action::Take])
+        );
+        assert_eq!(suggest(&world, &goals), Some(Action::Move));
+    }
+
+    #[test]
+    fn turning_counts_as_progress() {
+        // The target is due south; facing east, the shortest way starts
+        // with three left turns.
+        let world = World::new(1, 3);
+        let goals = [Goal::RobotAt(Position::new(0, 2))];
+        let plan = plan(&world, &goals).unwrap();
+        assert_eq!(plan.len(), 5);
+        assert_eq!(plan[0], Action::TurnLeft);
+    }
+
+    #[test]
+    fn beep_is_only_tried_when_a_goal_listens() {
+        let world = World::new(2, 1);
+        assert_eq!(plan(&world, &[Goal::Beeps(1)]), Some(vec![Action::Beep]));
+        // Without a beep goal the same world has nothing left to do.
+        assert_eq!(plan(&world, &[]), Some(Vec::new()));
+        assert_eq!(suggest(&world, &[]), None);
+    }
+
+    #[test]
+    fn the_solver_proves_a_task_solvable_with_a_sample_program() {
+        let mut world = World::new(4, 1);
+        world.set_beepers(Position::new(2, 0), 1);
+        let task = Task {
+            name: "collect".to_string(),
+            worlds: vec![("w".to_string(), world)],
+            goals: vec![Goal::NoBeepers, Goal::RobotAt(Position::new(2, 0))],
+            goal_weights: Vec::new(),
+            events: Vec::new(),
+            costs: crate::task::CostModel::default(),
+            lints: crate::lint::Config::default(),
+            stdlib: false,
+            hooks: None,
+            before: None,
+            after: None,
+            starter: None,
+        };
+
+        let solutions = solve(&task).unwrap();
+        assert_eq!(solutions.len(), 1);
+        let source = as_program(&solutions[0].plan);
+        assert_eq!(source, "def main\n move\n move\n take\nenddef\n");
+
+        // The sample solution really does pass its own task.
+        assert!(crate::grade::grade(&task, "sample.kl", &source).passed());
+    }
+
+    #[test]
+    fn an_impossible_task_is_reported_before_students_see_it() {
+        let task = Task {
+            name: "broken".to_string(),
+            worlds: vec![("w".to_string(), World::new(2, 2))],
+            goals: vec![Goal::RobotAt(Position::new(9, 9))],
+            goal_weights: Vec::new(),
+            events: Vec::new(),
+            costs: crate::task::CostModel::default(),
+            lints: crate::lint::Config::default(),
+            stdlib: false,
+            hooks: None,
+            before: None,
+            after: None,
+            starter: None,
+        };
+        assert_eq!(solve(&task), None);
+    }
+
+    #[test]
+    fn unreachable_goals_yield_no_hint() {
+        let world = World::new(2, 2);
+        assert_eq!(plan(&world, &[Goal::RobotAt(Position::new(9, 9))]), None);
+        assert_eq!(suggest(&world, &[Goal::RobotAt(Position::new(9, 9))]), None);
+    }
+}
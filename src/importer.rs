@@ -0,0 +1,393 @@
+//! Importing Java-flavored Karel dialects (Karel J Robot, Karel++).
+//!
+//! Textbooks in that tradition write programs as a class with `void`
+//! methods, `if (frontIsClear())`, `while` and `loop`/`for` counts. This
+//! module maps that surface syntax onto our line-oriented language and
+//! emits plain Karel source, so imported programs go through the same
+//! validation and interpreter as native ones.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// An error while reading Java-style source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    /// 1-based source line the error points at.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ImportError {}
+
+/// One token of the Java-style source, with the line it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    line: usize,
+    text: String,
+}
+
+/// Split the source into identifiers, numbers and single-character
+/// punctuation, dropping `//` and `/* */` comments.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    let mut line = 1;
+    let bytes = source.as_bytes();
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            _ if c.is_whitespace() => {}
+            '/' if bytes.get(offset + 1) == Some(&b'/') => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+            '/' if bytes.get(offset + 1) == Some(&b'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let start = offset;
+                let mut end = offset + c.len_utf8();
+                while let Some(&(next, c)) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        end = next + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token {
+                    line,
+                    text: source[start..end].to_string(),
+                });
+            }
+            _ => tokens.push(Token {
+                line,
+                text: c.to_string(),
+            }),
+        }
+    }
+    tokens
+}
+
+/// Instructions of the Java dialects and their Karel spellings.
+const INSTRUCTIONS: &[(&str, &str)] = &[
+    ("move", "move"),
+    ("turnLeft", "turn-left"),
+    ("pickBeeper", "take"),
+    ("putBeeper", "put"),
+    ("turnOff", "die"),
+];
+
+/// Predicates of the Java dialects as (Karel condition, negated).
+const PREDICATES: &[(&str, (&str, bool))] = &[
+    ("frontIsClear", ("wall", true)),
+    ("frontIsBlocked", ("wall", false)),
+    ("beepersPresent", ("beeper", false)),
+    ("noBeepersPresent", ("beeper", true)),
+    ("nextToABeeper", ("beeper", false)),
+    ("notNextToABeeper", ("beeper", true)),
+    ("facingNorth", ("north", false)),
+    ("facingSouth", ("south", false)),
+    ("facingEast", ("east", false)),
+    ("facingWest", ("west", false)),
+    ("notFacingNorth", ("north", true)),
+    ("notFacingSouth", ("south", true)),
+    ("notFacingEast", ("east", true)),
+    ("notFacingWest", ("west", true)),
+];
+
+/// Convert Java-style Karel source into native Karel source.
+///
+/// The class wrapper is dropped, `void` methods become `def` blocks (a
+/// method named `task` becomes `main`), and statements are mapped one to
+/// one. `else` has no Karel equivalent and is reported as an error.
+pub fn from_java(source: &str) -> Result<String, ImportError> {
+    Importer {
+        tokens: tokenize(source),
+        position: 0,
+        out: String::new(),
+    }
+    .convert()
+}
+
+struct Importer {
+    tokens: Vec<Token>,
+    position: usize,
+    out: String,
+}
+
+impl Importer {
+    fn convert(mut self) -> Result<String, ImportError> {
+        while self.position < self.tokens.len() {
+            let token = self.tokens[self.position].text.clone();
+            match token.as_str() {
+                // Class wrapper and modifiers carry no meaning here.
+                "class" | "extends" | "public" | "private" | "import" => {
+                    self.position += 1;
+                    // Skip the names after the keyword up to `{`, `;` or
+                    // the next keyword.
+                    while self
+                        .peek()
+                        .is_some_and(|text| text != "{" && text != ";" && text != "void")
+                    {
+                        self.position += 1;
+                    }
+                    if self.peek().is_some_and(|text| text != "void") {
+                        self.position += 1;
+                    }
+                }
+                "}" | ";" => self.position += 1,
+                "void" => {
+                    self.position += 1;
+                    self.method()?;
+                }
+                _ => {
+                    return Err(self.error(format!("expected a method, found `{token}`")));
+                }
+            }
+        }
+        Ok(self.out)
+    }
+
+    fn method(&mut self) -> Result<(), ImportError> {
+        let name = self.identifier("a method name")?;
+        let name = if name == "task" { "main".to_string() } else { name };
+        self.expect("(")?;
+        self.expect(")")?;
+        self.expect("{")?;
+        self.out.push_str(&format!("def {name}\n"));
+        self.block(1)?;
+        self.out.push_str("enddef\n");
+        Ok(())
+    }
+
+    /// A `{ ... }` body, with the opening brace already consumed.
+    fn block(&mut self, depth: usize) -> Result<(), ImportError> {
+        loop {
+            let Some(token) = self.peek().map(str::to_string) else {
+                return Err(self.error("unexpected end of input inside a block".to_string()));
+            };
+            let indent = " ".repeat(depth);
+            match token.as_str() {
+                "}" => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                "if" | "while" => {
+                    self.position += 1;
+                    self.expect("(")?;
+                    let negated_twice = if self.peek() == Some("!") {
+                        self.position += 1;
+                        true
+                    } else {
+                        false
+                    };
+                    let predicate = self.identifier("a condition")?;
+                    self.expect("(")?;
+                    self.expect(")")?;
+                    self.expect(")")?;
+                    self.expect("{")?;
+                    let Some((_, (condition, negated))) = PREDICATES
+                        .iter()
+                        .find(|(java_name, _)| *java_name == predicate)
+                    else {
+                        return Err(self.error(format!("unknown condition `{predicate}`")));
+                    };
+                    let negated = negated ^ negated_twice;
+                    let bang = if negated { "!" } else { "" };
+                    self.out.push_str(&format!("{indent}{token}{bang} {condition}\n"));
+                    self.block(depth + 1)?;
+                    if self.peek() == Some("else") {
+                        return Err(self.error("`else` has no Karel equivalent".to_string()));
+                    }
+                    self.out.push_str(&format!("{indent}end{token}\n"));
+                }
+                "loop" => {
+                    self.position += 1;
+                    self.expect("(")?;
+                    let count = self.identifier("a repetition count")?;
+                    if count.parse::<usize>().is_err() {
+                        return Err(
+                            self.error(format!("`loop` needs a number, found `{count}`"))
+                        );
+                    }
+                    self.expect(")")?;
+                    self.expect("{")?;
+                    self.out.push_str(&format!("{indent}repeat {count}\n"));
+                    self.block(depth + 1)?;
+                    self.out.push_str(&format!("{indent}endrepeat\n"));
+                }
+                "for" => {
+                    // Only the counting idiom `for (int i = 0; i < N; i++)`
+                    // maps onto `repeat`.
+                    self.position += 1;
+                    self.expect("(")?;
+                    let mut count = None;
+                    let mut seen_less_than = false;
+                    while self.peek().is_some_and(|text| text != ")") {
+                        let text = self.tokens[self.position].text.clone();
+                        if seen_less_than && count.is_none() {
+                            count = text.parse::<usize>().ok();
+                        }
+                        seen_less_than |= text == "<";
+                        self.position += 1;
+                    }
+                    self.expect(")")?;
+                    self.expect("{")?;
+                    let Some(count) = count else {
+                        return Err(self.error(
+                            "only counting `for (...; i < N; ...)` loops are supported"
+                                .to_string(),
+                        ));
+                    };
+                    self.out.push_str(&format!("{indent}repeat {count}\n"));
+                    self.block(depth + 1)?;
+                    self.out.push_str(&format!("{indent}endrepeat\n"));
+                }
+                "else" => {
+                    return Err(self.error("`else` has no Karel equivalent".to_string()));
+                }
+                _ => {
+                    let name = self.identifier("an instruction")?;
+                    self.expect("(")?;
+                    self.expect(")")?;
+                    self.expect(";")?;
+                    match INSTRUCTIONS
+                        .iter()
+                        .find(|(java_name, _)| *java_name == name)
+                    {
+                        Some((_, instruction)) => {
+                            self.out.push_str(&format!("{indent}{instruction}\n"));
+                        }
+                        None => {
+                            // Any other call is a method defined in the
+                            // same class.
+                            self.out.push_str(&format!("{indent}call {name}\n"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|token| token.text.as_str())
+    }
+
+    fn identifier(&mut self, expected: &str) -> Result<String, ImportError> {
+        match self.tokens.get(self.position) {
+            Some(token) if token.text.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                self.position += 1;
+                Ok(self.tokens[self.position - 1].text.clone())
+            }
+            _ => Err(self.error(format!("expected {expected}"))),
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), ImportError> {
+        if self.peek() == Some(text) {
+            self.position += 1;
+            Ok(())
+        } else {
+            let found = self.peek().unwrap_or("end of input").to_string();
+            Err(self.error(format!("expected `{text}`, found `{found}`")))
+        }
+    }
+
+    fn error(&self, message: String) -> ImportError {
+        let line = self
+            .tokens
+            .get(self.position.min(self.tokens.len().saturating_sub(1)))
+            .map(|token| token.line)
+            .unwrap_or(1);
+        ImportError { line, message }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn methods_and_instructions_are_mapped() {
+        let karel = from_java(
+            "class Runner extends Robot {\n\
+             \x20 void task() {\n\
+             \x20   move();\n\
+             \x20   pickBeeper();\n\
+             \x20   turnRight();\n\
+             \x20   turnOff();\n\
+             \x20 }\n\
+             \x20 void turnRight() {\n\
+             \x20   turnLeft(); turnLeft(); turnLeft();\n\
+             \x20 }\n\
+             }",
+        )
+        .unwrap();
+        assert!(karel.contains("def main\n"), "{karel}");
+        assert!(karel.contains(" take\n"), "{karel}");
+        assert!(karel.contains(" call turnRight\n"), "{karel}");
+        assert!(karel.contains("def turnRight\n"), "{karel}");
+        // The imported program passes native validation.
+        assert!(parser::validate(&parser::preprocess(&karel)).is_ok());
+    }
+
+    #[test]
+    fn predicates_translate_with_the_right_negation() {
+        let karel = from_java(
+            "void task() { while (frontIsClear()) { move(); } if (!facingNorth()) { turnLeft(); } }",
+        )
+        .unwrap();
+        assert!(karel.contains("while! wall\n"), "{karel}");
+        assert!(karel.contains("if! north\n"), "{karel}");
+    }
+
+    #[test]
+    fn counting_loops_become_repeat() {
+        let karel =
+            from_java("void task() { loop (4) { move(); } for (int i = 0; i < 3; i++) { putBeeper(); } }")
+                .unwrap();
+        assert!(karel.contains("repeat 4\n"), "{karel}");
+        assert!(karel.contains("repeat 3\n"), "{karel}");
+    }
+
+    #[test]
+    fn else_is_rejected_with_a_clear_message() {
+        let error = from_java(
+            "void task() { if (facingNorth()) { move(); } else { turnLeft(); } }",
+        )
+        .unwrap_err();
+        assert!(error.message.contains("no Karel equivalent"), "{error}");
+    }
+
+    #[test]
+    fn comments_are_ignored_and_lines_are_tracked() {
+        let error = from_java("// header\n/* block\ncomment */\nvoid task() { fly() }").unwrap_err();
+        assert_eq!(error.line, 4);
+    }
+}
@@ -0,0 +1,246 @@
+//! Driving a world one command at a time instead of running a whole program.
+//!
+//! A [`Session`] keeps a world, a set of procedure definitions made on the
+//! fly, and enough history to undo commands. It is the backend for the
+//! `karel repl` command, but is usable by any embedder that wants an
+//! interactive Karel (a GUI, a notebook, ...).
+
+use std::fmt;
+
+use crate::parser::{self, Line, ParseError};
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::world::World;
+
+/// Why an interactive command was rejected or failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    /// The snippet did not validate (unknown instruction, unclosed block...).
+    Parse(ParseError),
+    /// The snippet ran, but the robot hit an error. The world is left
+    /// untouched, as if the command had never been given.
+    Runtime(RuntimeError),
+    /// The snippet was empty.
+    Empty,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Parse(error) => write!(f, "{error}"),
+            SessionError::Runtime(error) => write!(f, "{error}"),
+            SessionError::Empty => write!(f, "nothing to execute"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<ParseError> for SessionError {
+    fn from(error: ParseError) -> SessionError {
+        SessionError::Parse(error)
+    }
+}
+
+impl From<RuntimeError> for SessionError {
+    fn from(error: RuntimeError) -> SessionError {
+        SessionError::Runtime(error)
+    }
+}
+
+/// An interactive Karel session.
+///
+/// Commands are executed transactionally: a command that fails at runtime
+/// leaves the world exactly as it was, so a student can try again instead of
+/// watching the robot die.
+pub struct Session {
+    world: World,
+    initial_world: World,
+    /// World states before each successful command, newest last.
+    history: Vec<World>,
+    /// Procedures defined during the session, as (name, preprocessed lines).
+    definitions: Vec<(String, Vec<Line<'static>>)>,
+}
+
+impl Session {
+    pub fn new(world: World) -> Session {
+        Session {
+            initial_world: world.clone(),
+            world,
+            history: Vec::new(),
+            definitions: Vec::new(),
+        }
+    }
+
+    /// The current state of the world.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Names of the procedures defined so far, in definition order.
+    pub fn defined_procedures(&self) -> impl Iterator<Item = &str> {
+        self.definitions.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Execute a snippet of instructions (anything that could appear inside a
+    /// `def` body, possibly several lines). On success the world advances and
+    /// the previous state is kept for [`Session::undo`].
+    pub fn execute(&mut self, snippet: &str) -> Result<(), SessionError> {
+        let body = parser::preprocess(snippet);
+        if body.is_empty() {
+            return Err(SessionError::Empty);
+        }
+
+        // Wrap the snippet into a synthetic `main` after the session's
+        // definitions, and run it like a regular program.
+        let mut lines = Vec::new();
+        for (_, definition) in &self.definitions {
+            lines.extend(definition.iter().cloned());
+        }
+        lines.push(synthetic_line("def main"));
+        lines.extend(body);
+        lines.push(synthetic_line("enddef"));
+        parser::validate(&lines)?;
+
+        let mut interpreter = Interpreter::new(lines, self.world.clone())?;
+        interpreter.run().into_result()?;
+        self.history.push(std::mem::replace(&mut self.world, interpreter.world));
+        Ok(())
+    }
+
+    /// Define (or redefine) a procedure from a full `def name ... enddef`
+    /// block. The body is validated against the already known procedures.
+    pub fn define(&mut self, source: &str) -> Result<String, SessionError> {
+        // Definitions outlive their source string, so detach the lines.
+        let definition: Vec<Line<'static>> = parser::preprocess(source)
+            .into_iter()
+            .map(Line::into_owned)
+            .collect();
+        let name = match definition.first().map(|line| line.text.as_ref()) {
+            Some(first) => match first.strip_prefix("def ") {
+                Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+                _ => return Err(SessionError::Parse(ParseError::BadName { line: 1 })),
+            },
+            None => return Err(SessionError::Empty),
+        };
+
+        // Validate the new definition together with the existing ones (minus
+        // any previous version of the same procedure) and a dummy `main`, so
+        // calls between session procedures work.
+        let mut lines = Vec::new();
+        for (known, existing) in &self.definitions {
+            if *known != name {
+                lines.extend(existing.iter().cloned());
+            }
+        }
+        lines.extend(definition.iter().cloned());
+        lines.push(synthetic_line("def main"));
+        lines.push(synthetic_line("enddef"));
+        parser::validate(&lines)?;
+
+        match self.definitions.iter_mut().find(|(known, _)| *known == name) {
+            Some(entry) => entry.1 = definition,
+            None => self.definitions.push((name.clone(), definition)),
+        }
+        Ok(name)
+    }
+
+    /// Take back the last successful command. Returns `false` when there is
+    /// nothing to undo. Definitions are never undone.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(previous) => {
+                self.world = previous;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Throw away all commands (but not definitions) and start over from the
+    /// world the session was created with.
+    pub fn reset(&mut self) {
+        self.world = self.initial_world.clone();
+        self.history.clear();
+    }
+}
+
+fn synthetic_line(text: &'static str) -> Line<'static> {
+    Line {
+        file: 0,
+        number: 0,
+        column: 1,
+        text: text.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{Direction, Position};
+
+    #[test]
+    fn commands_advance_the_world() {
+        let mut session = Session::new(World::default());
+        session.execute("move").unwrap();
+        session.execute("move\nturn-left").unwrap();
+        assert_eq!(session.world().robot.position, Position::new(2, 0));
+        assert_eq!(session.world().robot.direction, Direction::North);
+    }
+
+    #[test]
+    fn failed_command_leaves_world_untouched() {
+        let mut session = Session::new(World::default());
+        session.execute("move").unwrap();
+        let result = session.execute("take");
+        assert!(matches!(result, Err(SessionError::Runtime(_))));
+        assert_eq!(session.world().robot.position, Position::new(1, 0));
+        assert!(session.world().robot.alive);
+    }
+
+    #[test]
+    fn undo_restores_previous_state() {
+        let mut session = Session::new(World::default());
+        session.execute("put").unwrap();
+        session.execute("move").unwrap();
+        assert!(session.undo());
+        assert_eq!(session.world().robot.position, Position::new(0, 0));
+        assert_eq!(session.world().beepers_at(Position::new(0, 0)), 1);
+        assert!(session.undo());
+        assert_eq!(session.world().beepers_at(Position::new(0, 0)), 0);
+        assert!(!session.undo());
+    }
+
+    #[test]
+    fn reset_returns_to_initial_world() {
+        let mut session = Session::new(World::default());
+        session.execute("move\nput").unwrap();
+        session.reset();
+        assert_eq!(session.world(), &World::default());
+    }
+
+    #[test]
+    fn procedures_can_be_defined_and_called() {
+        let mut session = Session::new(World::default());
+        session.define("def right\n turn-left\n turn-left\n turn-left\nenddef").unwrap();
+        session.execute("call right\nmove").unwrap();
+        assert_eq!(session.world().robot.direction, Direction::South);
+        assert_eq!(session.world().robot.position, Position::new(0, 1));
+    }
+
+    #[test]
+    fn redefining_a_procedure_replaces_it() {
+        let mut session = Session::new(World::default());
+        session.define("def thing\n move\nenddef").unwrap();
+        session.define("def thing\n turn-left\nenddef").unwrap();
+        session.execute("call thing").unwrap();
+        assert_eq!(session.world().robot.position, Position::new(0, 0));
+        assert_eq!(session.defined_procedures().count(), 1);
+    }
+
+    #[test]
+    fn unknown_call_is_a_parse_error() {
+        let mut session = Session::new(World::default());
+        let result = session.execute("call nowhere");
+        assert!(matches!(result, Err(SessionError::Parse(_))));
+    }
+}
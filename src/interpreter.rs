@@ -0,0 +1,1287 @@
+//! Executing a preprocessed Karel program against a [`World`].
+//!
+//! The interpreter walks the preprocessed lines directly, but matches up
+//! blocks and resolves `call` targets once at construction: every block
+//! opener knows the index of its matching end (and vice versa), and every
+//! procedure name maps to its `def` line. Skipping a false `if` or entering
+//! a `call` is then a table lookup instead of a scan over the program, and
+//! the hot loop allocates nothing — strings are only built on error paths.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::environment::{Action, ActionFailure, Check, Comparison, Environment};
+use crate::parser::Line;
+use crate::world::{Direction, World};
+
+/// An error that stops the robot while the program is running.
+///
+/// These are the situations the README documents as fatal for the robot,
+/// plus the ones that can only happen when an invalid program is executed
+/// without being validated first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// `move` into a wall or out of the world.
+    HitWall { line: usize },
+    /// `take` on a tile with no beeper.
+    NoBeeperToTake { line: usize },
+    /// `put` on a tile that already has the maximum number of beepers.
+    TooManyBeepers { line: usize },
+    /// `move` onto another robot's tile, in a world whose collision policy
+    /// is [`error`](crate::world::CollisionPolicy::Error).
+    RobotCollision { line: usize },
+    /// `call` nesting went past the limit set with
+    /// [`Interpreter::limit_call_depth`]; almost always runaway recursion.
+    CallDepthExceeded { line: usize, limit: usize },
+    /// A single loop completed more iterations than the limit set with
+    /// [`Interpreter::limit_loop_iterations`]; almost always a `while`
+    /// whose condition can never become false.
+    LoopLimitExceeded { line: usize, limit: usize },
+    /// `call` of a procedure that does not exist.
+    UnknownProcedure { line: usize, name: String },
+    /// `goto` of a label that does not exist.
+    UnknownLabel { line: usize, name: String },
+    /// A line that is not a known instruction.
+    UnknownInstruction { line: usize, instruction: String },
+    /// A `???` placeholder was reached: the program is a solution template
+    /// and this part has not been filled in yet.
+    NotImplemented { line: usize },
+    /// A block end could not be found; the program was not validated.
+    MalformedBlock { line: usize },
+    /// There is no `def main` to start from.
+    MissingMain,
+    /// The entry point given to [`Interpreter::with_entry`] is not defined.
+    UnknownEntryPoint { name: String },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::HitWall { line } => {
+                write!(f, "line {line}: Karel walked into a wall")
+            }
+            RuntimeError::NoBeeperToTake { line } => {
+                write!(f, "line {line}: there is no beeper here to take")
+            }
+            RuntimeError::TooManyBeepers { line } => {
+                write!(f, "line {line}: this tile cannot hold any more beepers")
+            }
+            RuntimeError::RobotCollision { line } => {
+                write!(f, "line {line}: Karel walked into another robot")
+            }
+            RuntimeError::CallDepthExceeded { line, limit } => {
+                write!(f, "line {line}: call depth exceeded {limit} (is a procedure calling itself forever?)")
+            }
+            RuntimeError::LoopLimitExceeded { line, limit } => {
+                write!(f, "line {line}: this loop ran more than {limit} iterations (does its condition ever change?)")
+            }
+            RuntimeError::UnknownProcedure { line, name } => {
+                write!(f, "line {line}: call of unknown procedure `{name}`")
+            }
+            RuntimeError::UnknownLabel { line, name } => {
+                write!(f, "line {line}: goto of unknown label `{name}`")
+            }
+            RuntimeError::UnknownInstruction { line, instruction } => {
+                write!(f, "line {line}: unknown instruction `{instruction}`")
+            }
+            RuntimeError::NotImplemented { line } => {
+                write!(f, "line {line}: not implemented yet (replace the `???`)")
+            }
+            RuntimeError::MalformedBlock { line } => {
+                write!(f, "line {line}: block structure is broken (was the program validated?)")
+            }
+            RuntimeError::MissingMain => write!(f, "there is no `def main` to start from"),
+            RuntimeError::UnknownEntryPoint { name } => {
+                write!(f, "there is no `def {name}` to start from")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RuntimeError {}
+
+/// What a single [`Interpreter::step`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    /// An instruction was executed and the program goes on.
+    Running,
+    /// The program is over: `die` was executed or `main` ran to its end.
+    Finished,
+}
+
+/// How a [`run`](Interpreter::run) ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunStatus {
+    /// `main` ran to its `enddef`.
+    Completed,
+    /// The program switched the robot off with `die`.
+    Halted,
+    /// A runtime error killed the robot.
+    Failed(RuntimeError),
+    /// The step limit given to [`Interpreter::run_bounded`] ran out; the
+    /// interpreter is still live and can keep stepping.
+    LimitHit,
+}
+
+/// Statistics collected over one run: what it cost in steps, stack, memory
+/// and time, so operators of shared grading machines can see what a typical
+/// run needs before setting limits.
+///
+/// There is no allocation count: the stepping hot loop allocates nothing
+/// (a test enforces it), so a run's only allocations are the rare growths
+/// of the stacks and output buffer that [`peak_memory`](RunReport::peak_memory)
+/// already sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RunReport {
+    /// Instructions executed, control flow included.
+    pub steps: usize,
+    /// The deepest `call` nesting the run reached.
+    pub max_call_depth: usize,
+    /// The most bytes the run's own bookkeeping held at once (see
+    /// [`Interpreter::resident_memory`]); the world and the program are
+    /// accounted separately by whoever owns them.
+    pub peak_memory: usize,
+    /// Wall time of the run. Only measured with the `std` feature; zero
+    /// without, since `no_std` targets have no portable clock.
+    pub wall_time: core::time::Duration,
+}
+
+/// Everything a run leaves behind: how it ended, what it cost and the world
+/// it produced, so callers do not have to re-query the mutable environment
+/// afterwards.
+#[derive(Debug, Clone)]
+pub struct RunOutcome<E = World> {
+    pub status: RunStatus,
+    pub report: RunReport,
+    /// The environment as the run left it.
+    pub final_snapshot: E,
+}
+
+impl<E> RunOutcome<E> {
+    /// Collapse the outcome into the old `run()` shape for callers that only
+    /// care about fatal errors. [`RunStatus::LimitHit`] counts as success
+    /// here; use [`Interpreter::run_bounded`]'s status when that matters.
+    pub fn into_result(self) -> Result<(), RuntimeError> {
+        match self.status {
+            RunStatus::Failed(error) => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// One source line interned into a directly dispatchable form, so the hot
+/// loop never splits or compares strings. Anything that does not intern
+/// cleanly becomes [`Statement::Unknown`] and only errors if it is reached,
+/// mirroring how the interpreter has always treated unvalidated programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Statement {
+    /// `move`, `turn-left`, `take` or `put`.
+    Perform(Action),
+    Die,
+    /// `print direction`.
+    PrintDirection,
+    /// `call` with the pre-resolved index of the target `def` line;
+    /// `None` when the procedure is not defined anywhere.
+    Call { target: Option<usize> },
+    /// `label`: a named jump target; executing it does nothing. Low-level,
+    /// for generated code — student-facing material does not mention it.
+    Label,
+    /// `goto` with the pre-resolved index of the target `label` line;
+    /// `None` when the label is not defined anywhere.
+    Goto { target: Option<usize> },
+    /// `???`: a teacher's placeholder in a solution template. Valid to
+    /// parse, fatal to reach.
+    Hole,
+    EndDef,
+    If { check: Check, negated: bool },
+    EndIf,
+    While { check: Check, negated: bool },
+    EndWhile,
+    Repeat { count: usize },
+    EndRepeat,
+    /// Not a known instruction (or a `def` line reached by falling through).
+    Unknown,
+}
+
+/// Runs one program against one world, one instruction at a time.
+///
+/// "World" is anything implementing [`Environment`]; the in-memory grid is
+/// the default, so plain `Interpreter` means what it always did. A `&mut`
+/// to an environment is itself an environment, so callers that want the
+/// world back afterwards can lend it per run instead of handing it over.
+pub struct Interpreter<'p, E: Environment = World> {
+    lines: Vec<Line<'p>>,
+    /// `lines` interned for dispatch, index for index.
+    statements: Vec<Statement>,
+    /// For each line index, the index of its matching block end (for
+    /// openers) or opener (for ends). `None` for non-block lines and for
+    /// blocks that never close, which only unvalidated programs have.
+    matching: Vec<Option<usize>>,
+    pub world: E,
+    /// Index into `lines` of the next instruction to execute.
+    position: usize,
+    /// Return addresses (indices into `lines`) of active `call`s.
+    call_stack: Vec<usize>,
+    /// Active `repeat` blocks as (index of the `repeat` line, iterations left).
+    repeat_stack: Vec<(usize, usize)>,
+    /// Error out when `call` nesting goes deeper than this; `None` leaves
+    /// recursion unbounded, as it always was.
+    max_call_depth: Option<usize>,
+    /// Error out when a single loop completes more iterations than this;
+    /// `None` is unbounded.
+    max_loop_iterations: Option<usize>,
+    /// Iterations completed per active `while`, keyed by the opener's index.
+    /// Only maintained when a loop limit is set, so the default hot loop
+    /// stays allocation-free.
+    loop_counters: BTreeMap<usize, usize>,
+    finished: bool,
+    /// Whether `die` (rather than the end of `main`) finished the run.
+    halted: bool,
+    /// Lines produced by `print`, waiting to be taken by the host.
+    output: Vec<String>,
+}
+
+impl<'p, E: Environment> Interpreter<'p, E> {
+    /// Prepare to run the program starting at `def main`.
+    ///
+    /// Fails only when there is no `main`; everything else is reported while
+    /// stepping, so an invalid program still produces the world state it
+    /// reached before the error.
+    pub fn new(lines: Vec<Line<'p>>, world: E) -> Result<Interpreter<'p, E>, RuntimeError> {
+        Interpreter::with_entry(lines, world, "main")
+    }
+
+    /// Like [`new`](Interpreter::new), but start at `def entry` instead of
+    /// `def main` — so a platform can run a task's `setup` and the
+    /// student's `solve` as separate runs over the same world.
+    pub fn with_entry(
+        lines: Vec<Line<'p>>,
+        world: E,
+        entry: &str,
+    ) -> Result<Interpreter<'p, E>, RuntimeError> {
+        let definitions = index_definitions(&lines);
+        let labels = index_labels(&lines);
+        let matching = index_blocks(&lines);
+        let statements = intern(&lines, &definitions, &labels);
+        let main = *definitions.get(entry).ok_or_else(|| match entry {
+            "main" => RuntimeError::MissingMain,
+            _ => RuntimeError::UnknownEntryPoint {
+                name: entry.to_string(),
+            },
+        })?;
+        Ok(Interpreter {
+            lines,
+            statements,
+            matching,
+            world,
+            position: main + 1,
+            call_stack: Vec::new(),
+            repeat_stack: Vec::new(),
+            max_call_depth: None,
+            max_loop_iterations: None,
+            loop_counters: BTreeMap::new(),
+            finished: false,
+            halted: false,
+            output: Vec::new(),
+        })
+    }
+
+    /// Fail the run with [`RuntimeError::CallDepthExceeded`] when `call`
+    /// nesting goes deeper than `limit`. Off by default; graders switch it
+    /// on to tell runaway recursion apart from a program that is merely
+    /// slow, instead of burning the whole step budget on it.
+    pub fn limit_call_depth(&mut self, limit: usize) {
+        self.max_call_depth = Some(limit);
+    }
+
+    /// Fail the run with [`RuntimeError::LoopLimitExceeded`] when any single
+    /// `while` completes more than `limit` iterations. Off by default.
+    /// `repeat` is bounded by its count and is not tracked.
+    pub fn limit_loop_iterations(&mut self, limit: usize) {
+        self.max_loop_iterations = Some(limit);
+    }
+
+    /// Has the program ended (normally or through `die`)?
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether `die` (rather than the end of `main`) ended the program, for
+    /// drivers outside this module that rebuild a [`RunStatus`].
+    #[cfg(feature = "async")]
+    pub(crate) fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Take the lines `print` produced since the last call, in execution
+    /// order. The host decides where they go — stdout, a log, a panel.
+    pub fn take_output(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.output)
+    }
+
+    /// The original source line number of the instruction that will run next,
+    /// if the program is still running.
+    pub fn current_line(&self) -> Option<usize> {
+        if self.finished {
+            return None;
+        }
+        self.lines.get(self.position).map(|line| line.number)
+    }
+
+    /// The [`Line::file`] id of the instruction that will run next, for
+    /// resolving [`Interpreter::current_line`] through a
+    /// [`SourceMap`](crate::parser::SourceMap) when the program was merged
+    /// from several files.
+    pub fn current_file(&self) -> Option<usize> {
+        if self.finished {
+            return None;
+        }
+        self.lines.get(self.position).map(|line| line.file)
+    }
+
+    /// The text of the instruction that will run next, if the program is
+    /// still running.
+    pub fn current_instruction(&self) -> Option<&str> {
+        if self.finished {
+            return None;
+        }
+        self.lines.get(self.position).map(|line| line.text.as_ref())
+    }
+
+    /// How many `call`s are currently active.
+    pub fn call_depth(&self) -> usize {
+        self.call_stack.len()
+    }
+
+    /// How many bytes the run's own bookkeeping holds right now: the call
+    /// and repeat stacks and the buffered `print` output. This is the
+    /// memory a sandbox should budget for a run on top of the world and
+    /// the program, and what [`RunReport::peak_memory`] tracks the peak of.
+    pub fn resident_memory(&self) -> usize {
+        self.call_stack.len() * core::mem::size_of::<usize>()
+            + self.repeat_stack.len() * core::mem::size_of::<(usize, usize)>()
+            + self.output.iter().map(String::len).sum::<usize>()
+    }
+
+    /// The active frames as (procedure name, source line), innermost first:
+    /// the instruction about to run, then the line each `call` will return
+    /// behind. Empty once the program has finished.
+    pub fn backtrace(&self) -> Vec<(String, usize)> {
+        if self.finished {
+            return Vec::new();
+        }
+        let mut frames = vec![self.frame(self.position)];
+        for return_position in self.call_stack.iter().rev() {
+            // The return address points just past the `call` line.
+            frames.push(self.frame(return_position.saturating_sub(1)));
+        }
+        frames
+    }
+
+    /// The frame for the instruction at `position`: the name of the
+    /// enclosing `def` and the source line number.
+    fn frame(&self, position: usize) -> (String, usize) {
+        let line = self.lines.get(position).map(|line| line.number).unwrap_or(0);
+        for index in (0..=position.min(self.lines.len().saturating_sub(1))).rev() {
+            let mut words = self.lines[index].text.split_whitespace();
+            if words.next() == Some("def") {
+                if let Some(name) = words.next() {
+                    return (name.to_string(), line);
+                }
+            }
+        }
+        ("?".to_string(), line)
+    }
+
+    /// A branch of this interpreter: the same program at the same position,
+    /// with its own copy of the world. Stepping the fork leaves the
+    /// original untouched, so a tool can try "what if the robot moved now?"
+    /// from the current state and discard the branch afterwards. Cloning
+    /// the default [`World`] only bumps reference counts (see its docs), so
+    /// forking is cheap enough to do per candidate move.
+    pub fn fork(&self) -> Interpreter<'p, E>
+    where
+        E: Clone,
+    {
+        Interpreter {
+            lines: self.lines.clone(),
+            statements: self.statements.clone(),
+            matching: self.matching.clone(),
+            world: self.world.clone(),
+            position: self.position,
+            call_stack: self.call_stack.clone(),
+            repeat_stack: self.repeat_stack.clone(),
+            max_call_depth: self.max_call_depth,
+            max_loop_iterations: self.max_loop_iterations,
+            loop_counters: self.loop_counters.clone(),
+            finished: self.finished,
+            halted: self.halted,
+            output: self.output.clone(),
+        }
+    }
+
+    /// Run the program to its end and report how it went.
+    pub fn run(&mut self) -> RunOutcome<E>
+    where
+        E: Clone,
+    {
+        self.run_bounded(usize::MAX)
+    }
+
+    /// Like [`run`](Interpreter::run), but give up after `limit` steps with
+    /// [`RunStatus::LimitHit`]. The interpreter stays live after a limit hit,
+    /// so the run can be resumed with a fresh budget.
+    pub fn run_bounded(&mut self, limit: usize) -> RunOutcome<E>
+    where
+        E: Clone,
+    {
+        let mut report = RunReport::default();
+        #[cfg(feature = "std")]
+        let started = std::time::Instant::now();
+        let status = loop {
+            if self.finished {
+                break if self.halted { RunStatus::Halted } else { RunStatus::Completed };
+            }
+            if report.steps == limit {
+                break RunStatus::LimitHit;
+            }
+            match self.step() {
+                Ok(_) => {
+                    report.steps += 1;
+                    report.max_call_depth = report.max_call_depth.max(self.call_depth());
+                    report.peak_memory = report.peak_memory.max(self.resident_memory());
+                }
+                Err(error) => {
+                    report.steps += 1;
+                    break RunStatus::Failed(error);
+                }
+            }
+        };
+        #[cfg(feature = "std")]
+        {
+            report.wall_time = started.elapsed();
+        }
+        RunOutcome { status, report, final_snapshot: self.world.clone() }
+    }
+
+    /// Execute a single instruction.
+    ///
+    /// On an error the robot dies and the interpreter stays finished; further
+    /// steps keep returning [`StepResult::Finished`].
+    pub fn step(&mut self) -> Result<StepResult, RuntimeError> {
+        if self.finished {
+            return Ok(StepResult::Finished);
+        }
+        match self.execute_current() {
+            Ok(result) => {
+                if result == StepResult::Finished {
+                    self.finish();
+                }
+                Ok(result)
+            }
+            Err(error) => {
+                self.finish();
+                // A fatal instruction kills the robot.
+                let _ = self.world.perform(Action::Die);
+                Err(error)
+            }
+        }
+    }
+
+    /// Like [`step`](Interpreter::step), but a `call` executes to completion
+    /// as one step: the debugger's "step over". Any other statement behaves
+    /// exactly like `step`. Like [`run`](Interpreter::run), this does not
+    /// bound the callee; a front-end that must survive endless loops should
+    /// drive `step` itself with a budget.
+    pub fn step_over(&mut self) -> Result<StepResult, RuntimeError> {
+        let depth = self.call_depth();
+        let mut result = self.step()?;
+        while result == StepResult::Running && self.call_depth() > depth {
+            result = self.step()?;
+        }
+        Ok(result)
+    }
+
+    /// Run until the current procedure returns to its caller: the debugger's
+    /// "step out". In `main` there is nothing to return to, so the program
+    /// runs to its end.
+    pub fn step_out(&mut self) -> Result<StepResult, RuntimeError> {
+        let depth = self.call_depth();
+        loop {
+            let result = self.step()?;
+            if result == StepResult::Finished || self.call_depth() < depth {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    fn execute_current(&mut self) -> Result<StepResult, RuntimeError> {
+        let Some(&statement) = self.statements.get(self.position) else {
+            // Ran past the last line; only possible in unvalidated programs.
+            return Ok(StepResult::Finished);
+        };
+        let number = self.lines[self.position].number;
+
+        match statement {
+            Statement::Perform(action) => {
+                self.world
+                    .perform(action)
+                    .map_err(|failure| action_error(failure, number))?;
+                #[cfg(feature = "tracing")]
+                if let Some(subscriber) = crate::log::subscriber() {
+                    subscriber.event(action, number);
+                }
+                self.position += 1;
+            }
+            Statement::PrintDirection => {
+                let facing = match self.world.facing() {
+                    Some(direction) => direction.name(),
+                    None => "unknown",
+                };
+                self.output.push(facing.to_string());
+                self.position += 1;
+            }
+            Statement::Die => {
+                let _ = self.world.perform(Action::Die);
+                self.halted = true;
+                #[cfg(feature = "tracing")]
+                if let Some(subscriber) = crate::log::subscriber() {
+                    subscriber.event(Action::Die, number);
+                }
+                return Ok(StepResult::Finished);
+            }
+            Statement::Label => self.position += 1,
+            Statement::Hole => return Err(RuntimeError::NotImplemented { line: number }),
+            Statement::Goto { target } => {
+                let target = target.ok_or_else(|| RuntimeError::UnknownLabel {
+                    line: number,
+                    name: self.second_word(self.position),
+                })?;
+                // The label itself does nothing; land just past it, like a
+                // `call` lands past its `def`.
+                self.position = target + 1;
+            }
+            Statement::Call { target } => {
+                let target = target.ok_or_else(|| RuntimeError::UnknownProcedure {
+                    line: number,
+                    name: self.second_word(self.position),
+                })?;
+                if let Some(limit) = self.max_call_depth {
+                    if self.call_stack.len() >= limit {
+                        return Err(RuntimeError::CallDepthExceeded { line: number, limit });
+                    }
+                }
+                #[cfg(feature = "tracing")]
+                if let Some(subscriber) = crate::log::subscriber() {
+                    // Borrow the name rather than going through `second_word`:
+                    // the hook must not allocate on the caller's behalf.
+                    let name = self.lines[self.position]
+                        .text
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or("");
+                    subscriber.enter_span(name, number);
+                }
+                self.call_stack.push(self.position + 1);
+                self.position = target + 1;
+            }
+            Statement::EndDef => match self.call_stack.pop() {
+                Some(return_position) => {
+                    #[cfg(feature = "tracing")]
+                    if let Some(subscriber) = crate::log::subscriber() {
+                        subscriber.exit_span();
+                    }
+                    self.position = return_position;
+                }
+                None => return Ok(StepResult::Finished),
+            },
+            Statement::If { check, negated } | Statement::While { check, negated } => {
+                let held = match check {
+                    // The region name rides on the source line ("if
+                    // in-region home"), borrowed here so the hot loop stays
+                    // allocation-free.
+                    Check::InRegion => {
+                        let name = self.lines[self.position]
+                            .text
+                            .split_whitespace()
+                            .nth(2)
+                            .unwrap_or("");
+                        self.world.in_region(name)
+                    }
+                    check => self.world.check(check),
+                };
+                if held != negated {
+                    self.position += 1;
+                } else {
+                    // A `while` that exits forgets its iteration count, so
+                    // re-entering it later starts from zero again.
+                    if matches!(statement, Statement::While { .. }) {
+                        self.loop_counters.remove(&self.position);
+                    }
+                    self.position = self.matching_line(self.position)? + 1;
+                }
+            }
+            Statement::EndIf => self.position += 1,
+            Statement::EndWhile => {
+                // Jump back to the `while` so its condition is re-evaluated.
+                let opener = self.matching_line(self.position)?;
+                if let Some(limit) = self.max_loop_iterations {
+                    let count = self.loop_counters.entry(opener).or_insert(0);
+                    *count += 1;
+                    if *count > limit {
+                        // Blame the `while` line, not its end: that is the
+                        // loop the feedback should point at.
+                        return Err(RuntimeError::LoopLimitExceeded {
+                            line: self.lines[opener].number,
+                            limit,
+                        });
+                    }
+                }
+                self.position = opener;
+            }
+            Statement::Repeat { count } => {
+                self.repeat_stack.push((self.position, count));
+                self.position += 1;
+            }
+            Statement::EndRepeat => match self.repeat_stack.last_mut() {
+                Some((start, remaining)) if *remaining > 1 => {
+                    *remaining -= 1;
+                    self.position = *start + 1;
+                }
+                Some(_) => {
+                    self.repeat_stack.pop();
+                    self.position += 1;
+                }
+                None => return Err(RuntimeError::MalformedBlock { line: number }),
+            },
+            Statement::Unknown => {
+                return Err(RuntimeError::UnknownInstruction {
+                    line: number,
+                    instruction: self.lines[self.position].text.to_string(),
+                })
+            }
+        }
+        Ok(StepResult::Running)
+    }
+
+    /// The second word of the line at `position`, for error messages on the
+    /// slow path (`call` of an unknown procedure).
+    fn second_word(&self, position: usize) -> String {
+        self.lines[position]
+            .text
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or("")
+            .to_string()
+    }
+
+    /// The pre-resolved partner of the block line at `index`: the end for an
+    /// opener, the opener for an end. Unmatched lines mean the program was
+    /// never validated.
+    fn matching_line(&self, index: usize) -> Result<usize, RuntimeError> {
+        self.matching
+            .get(index)
+            .copied()
+            .flatten()
+            .ok_or_else(|| RuntimeError::MalformedBlock {
+                line: self.lines.get(index).map(|line| line.number).unwrap_or(0),
+            })
+    }
+}
+
+/// Attach a source line to an [`ActionFailure`] from the environment.
+fn action_error(failure: ActionFailure, line: usize) -> RuntimeError {
+    match failure {
+        ActionFailure::Blocked => RuntimeError::HitWall { line },
+        ActionFailure::NoBeeper => RuntimeError::NoBeeperToTake { line },
+        ActionFailure::TileFull => RuntimeError::TooManyBeepers { line },
+        ActionFailure::Collision => RuntimeError::RobotCollision { line },
+    }
+}
+
+fn first_word(text: &str) -> &str {
+    text.split_whitespace().next().unwrap_or("")
+}
+
+/// Map every `def <name>` line to its index. The first definition of a name
+/// wins, matching what the old linear scan found; validation rejects
+/// duplicates anyway.
+fn index_definitions(lines: &[Line<'_>]) -> BTreeMap<String, usize> {
+    let mut definitions = BTreeMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut words = line.text.split_whitespace();
+        if words.next() == Some("def") {
+            if let (Some(name), None) = (words.next(), words.next()) {
+                definitions.entry(name.to_string()).or_insert(index);
+            }
+        }
+    }
+    definitions
+}
+
+/// Map every `label <name>` line to its index, as [`index_definitions`]
+/// does for procedures.
+fn index_labels(lines: &[Line<'_>]) -> BTreeMap<String, usize> {
+    let mut labels = BTreeMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut words = line.text.split_whitespace();
+        if words.next() == Some("label") {
+            if let (Some(name), None) = (words.next(), words.next()) {
+                labels.entry(name.to_string()).or_insert(index);
+            }
+        }
+    }
+    labels
+}
+
+/// The [`Check`] the words after `if`/`while` stand for, if they form a
+/// known condition.
+pub(crate) fn condition_check(condition: &[&str]) -> Option<Check> {
+    match condition {
+        ["wall"] => Some(Check::WallAhead),
+        ["north"] => Some(Check::Facing(Direction::North)),
+        ["south"] => Some(Check::Facing(Direction::South)),
+        ["east"] => Some(Check::Facing(Direction::East)),
+        ["west"] => Some(Check::Facing(Direction::West)),
+        ["beeper"] => Some(Check::OnBeeper),
+        ["beeper-ahead"] => Some(Check::BeeperAhead),
+        ["beeper", operator, count] => Some(Check::Beepers {
+            comparison: Comparison::parse(operator)?,
+            count: count.parse().ok()?,
+        }),
+        ["clear", tiles] => match tiles.parse::<usize>() {
+            Ok(tiles) if tiles > 0 => Some(Check::Clear { tiles }),
+            _ => None,
+        },
+        // The region name stays on the source line; see `Check::InRegion`.
+        ["in-region", _name] => Some(Check::InRegion),
+        _ => None,
+    }
+}
+
+/// Intern every line into a [`Statement`], resolving conditions and call
+/// targets once so stepping never looks at the text again.
+fn intern(
+    lines: &[Line<'_>],
+    definitions: &BTreeMap<String, usize>,
+    labels: &BTreeMap<String, usize>,
+) -> Vec<Statement> {
+    lines
+        .iter()
+        .map(|line| {
+            let mut words = line.text.split_whitespace();
+            // Preprocessed lines are never empty, but `new` accepts any
+            // `Vec<Line>`; a hand-built empty line must not panic here.
+            let Some(keyword) = words.next() else {
+                return Statement::Unknown;
+            };
+            let rest: Vec<&str> = words.collect();
+            match (keyword, rest.as_slice()) {
+                ("move", []) => Statement::Perform(Action::Move),
+                ("turn-left", []) => Statement::Perform(Action::TurnLeft),
+                ("take", []) => Statement::Perform(Action::Take),
+                ("put", []) => Statement::Perform(Action::Put),
+                ("beep", []) => Statement::Perform(Action::Beep),
+                ("die", []) => Statement::Die,
+                ("print", ["direction"]) => Statement::PrintDirection,
+                ("call", [name]) => Statement::Call {
+                    target: definitions.get(*name).copied(),
+                },
+                ("label", [_name]) => Statement::Label,
+                ("???", []) => Statement::Hole,
+                ("goto", [name]) => Statement::Goto {
+                    target: labels.get(*name).copied(),
+                },
+                ("enddef", []) => Statement::EndDef,
+                ("if" | "if!", condition) => match condition_check(condition) {
+                    Some(check) => Statement::If {
+                        check,
+                        negated: keyword.ends_with('!'),
+                    },
+                    None => Statement::Unknown,
+                },
+                ("endif", []) => Statement::EndIf,
+                ("while" | "while!", condition) => match condition_check(condition) {
+                    Some(check) => Statement::While {
+                        check,
+                        negated: keyword.ends_with('!'),
+                    },
+                    None => Statement::Unknown,
+                },
+                ("endwhile", []) => Statement::EndWhile,
+                ("repeat", [count]) => match count.parse::<usize>() {
+                    Ok(count) => Statement::Repeat { count },
+                    Err(_) => Statement::Unknown,
+                },
+                ("endrepeat", []) => Statement::EndRepeat,
+                _ => Statement::Unknown,
+            }
+        })
+        .collect()
+}
+
+/// Pair up every block opener with its end in one pass over the program.
+/// Lines left unpaired (broken nesting, unclosed blocks) stay `None` and
+/// surface as [`RuntimeError::MalformedBlock`] only if they are reached.
+fn index_blocks(lines: &[Line<'_>]) -> Vec<Option<usize>> {
+    let mut matching = vec![None; lines.len()];
+    // Stack of open blocks as (index, expected end keyword).
+    let mut open: Vec<(usize, &str)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let keyword = first_word(&line.text).trim_end_matches('!');
+        match keyword {
+            "def" => open.push((index, "enddef")),
+            "if" => open.push((index, "endif")),
+            "while" => open.push((index, "endwhile")),
+            "repeat" => open.push((index, "endrepeat")),
+            "enddef" | "endif" | "endwhile" | "endrepeat" => {
+                if let Some((opener, expected)) = open.last().copied() {
+                    if expected == keyword {
+                        open.pop();
+                        matching[opener] = Some(index);
+                        matching[index] = Some(opener);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    matching
+}
+
+/// A global allocator that counts this thread's allocations, so tests can
+/// assert that the stepping hot loop performs none. Only active in test
+/// builds; the counter is thread-local because tests run concurrently.
+#[cfg(test)]
+mod counting_allocator {
+    use core::cell::Cell;
+    use std::alloc::{GlobalAlloc, Layout, System};
+
+    std::thread_local! {
+        static COUNT: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct CountingAllocator;
+
+    // SAFETY: delegates everything to `System`; the counter has no effect
+    // on the returned memory.
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            COUNT.with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Heap allocations made by this thread so far.
+    pub fn allocations() -> usize {
+        COUNT.with(Cell::get)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::preprocess;
+    use crate::world::{Direction, Position, World};
+
+    fn run_program(source: &str, world: World) -> Result<World, RuntimeError> {
+        let mut interpreter = Interpreter::new(preprocess(source), world)?;
+        interpreter.run().into_result()?;
+        Ok(interpreter.world)
+    }
+
+    #[test]
+    fn goto_jumps_over_the_labelled_gap() {
+        let source = "def main\n goto skip\n move\n label skip\n put\nenddef";
+        let world = run_program(source, World::new(3, 1)).unwrap();
+        // The `move` between the jump and its label never ran.
+        assert_eq!(world.robot.position, Position::new(0, 0));
+        assert_eq!(world.beepers_at(Position::new(0, 0)), 1);
+    }
+
+    #[test]
+    fn a_reached_placeholder_stops_the_run_at_its_line() {
+        // Everything before the `???` runs; the hole itself is the error.
+        let source = "def main\n move\n ???\n move\nenddef";
+        let error = run_program(source, World::new(3, 1)).unwrap_err();
+        assert_eq!(error, RuntimeError::NotImplemented { line: 3 });
+        assert_eq!(
+            error.to_string(),
+            "line 3: not implemented yet (replace the `???`)"
+        );
+    }
+
+    #[test]
+    fn goto_of_an_unknown_label_errors() {
+        let source = "def main\n goto nowhere\nenddef";
+        assert_eq!(
+            run_program(source, World::new(3, 1)),
+            Err(RuntimeError::UnknownLabel { line: 2, name: "nowhere".to_string() })
+        );
+    }
+
+    #[test]
+    fn move_and_turn() {
+        let source = "def main\n move\n move\n turn-left\n move\n die\nenddef";
+        // Start one row down: turn-left faces north and the last move goes up.
+        let mut world = World::new(5, 5);
+        world.robot.position = Position::new(0, 1);
+        let world = run_program(source, world).unwrap();
+        assert_eq!(world.robot.direction, Direction::North);
+        assert_eq!(world.robot.position, Position::new(2, 0));
+    }
+
+    #[test]
+    fn moving_into_wall_is_fatal() {
+        let mut world = World::new(3, 3);
+        world.set_wall(Position::new(1, 0), true);
+        let result = run_program("def main\n move\nenddef", world);
+        assert_eq!(result, Err(RuntimeError::HitWall { line: 2 }));
+    }
+
+    #[test]
+    fn moving_out_of_world_is_fatal() {
+        let result = run_program(
+            "def main\n turn-left\n move\nenddef",
+            World::new(3, 3),
+        );
+        assert_eq!(result, Err(RuntimeError::HitWall { line: 3 }));
+    }
+
+    #[test]
+    fn put_take_and_beeper_condition() {
+        let source = "def main\n put\n if beeper\n  take\n endif\n if beeper\n  put\n  put\n endif\n die\nenddef";
+        let world = run_program(source, World::default()).unwrap();
+        assert_eq!(world.beepers_at(Position::new(0, 0)), 0);
+    }
+
+    #[test]
+    fn repeat_runs_body_exactly_n_times() {
+        let source = "def main\n repeat 5\n  put\n  move\n endrepeat\n die\nenddef";
+        let world = run_program(source, World::new(6, 1)).unwrap();
+        for x in 0..5 {
+            assert_eq!(world.beepers_at(Position::new(x, 0)), 1, "tile {x}");
+        }
+        assert_eq!(world.robot.position, Position::new(5, 0));
+    }
+
+    #[test]
+    fn while_walks_up_to_wall() {
+        let source = "def main\n while! wall\n  move\n endwhile\n die\nenddef";
+        let world = run_program(source, World::new(7, 1)).unwrap();
+        assert_eq!(world.robot.position, Position::new(6, 0));
+    }
+
+    #[test]
+    fn nested_repeat_draws_square() {
+        // Walk a full square: the robot ends where it started.
+        let source = "def main\n repeat 4\n  repeat 3\n   move\n  endrepeat\n  turn-left\n endrepeat\n die\nenddef";
+        let mut world = World::new(5, 5);
+        world.robot.position = Position::new(0, 4);
+        let world = run_program(source, world).unwrap();
+        assert_eq!(world.robot.position, Position::new(0, 4));
+    }
+
+    #[test]
+    fn calls_and_recursion() {
+        let source = "def main\n call right\n move\n die\nenddef\ndef right\n turn-left\n turn-left\n turn-left\nenddef";
+        let mut world = World::new(3, 3);
+        world.robot.position = Position::new(0, 0);
+        let world = run_program(source, world).unwrap();
+        assert_eq!(world.robot.direction, Direction::South);
+        assert_eq!(world.robot.position, Position::new(0, 1));
+    }
+
+    #[test]
+    fn runaway_recursion_hits_the_call_depth_limit() {
+        let source = "def main\n call forever\nenddef\ndef forever\n call forever\nenddef";
+        let mut interpreter = Interpreter::new(preprocess(source), World::default()).unwrap();
+        interpreter.limit_call_depth(10);
+        assert_eq!(
+            interpreter.run().status,
+            RunStatus::Failed(RuntimeError::CallDepthExceeded { line: 5, limit: 10 })
+        );
+    }
+
+    #[test]
+    fn an_endless_while_hits_the_loop_limit() {
+        // `turn-left` never puts a beeper down, so the loop cannot end.
+        let source = "def main\n while! beeper\n  turn-left\n endwhile\nenddef";
+        let mut interpreter = Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        interpreter.limit_loop_iterations(100);
+        assert_eq!(
+            interpreter.run().status,
+            RunStatus::Failed(RuntimeError::LoopLimitExceeded { line: 2, limit: 100 })
+        );
+    }
+
+    #[test]
+    fn loops_that_end_reset_their_iteration_count() {
+        // The inner walk runs up to the limit each time round the outer
+        // repeat; only a loop that never exits may trip it.
+        let source = "def main\n repeat 3\n  while! wall\n   move\n  endwhile\n  turn-left\n  turn-left\n endrepeat\nenddef";
+        let mut interpreter = Interpreter::new(preprocess(source), World::new(5, 1)).unwrap();
+        interpreter.limit_loop_iterations(4);
+        assert_eq!(interpreter.run().status, RunStatus::Completed);
+    }
+
+    #[test]
+    fn falling_off_main_ends_the_program() {
+        let source = "def main\n move\nenddef";
+        let world = run_program(source, World::default()).unwrap();
+        assert_eq!(world.robot.position, Position::new(1, 0));
+        assert!(world.robot.alive);
+    }
+
+    #[test]
+    fn error_kills_the_robot() {
+        let mut interpreter =
+            Interpreter::new(preprocess("def main\n take\nenddef"), World::default()).unwrap();
+        let outcome = interpreter.run();
+        assert_eq!(
+            outcome.status,
+            RunStatus::Failed(RuntimeError::NoBeeperToTake { line: 2 })
+        );
+        assert!(!outcome.final_snapshot.robot.alive);
+        assert!(!interpreter.world.robot.alive);
+        assert!(interpreter.finished());
+    }
+
+    #[test]
+    fn hand_built_empty_lines_error_instead_of_panicking() {
+        // `preprocess` never yields empty lines, but `new` accepts any
+        // `Vec<Line>`, so a hand-built one must fail like any unknown
+        // instruction rather than bring the process down.
+        let line = |number, text: &'static str| Line {
+            file: 0,
+            number,
+            column: 1,
+            text: text.into(),
+        };
+        let lines = vec![line(1, "def main"), line(2, ""), line(3, "enddef")];
+        let mut interpreter = Interpreter::new(lines, World::default()).unwrap();
+        assert_eq!(
+            interpreter.run().status,
+            RunStatus::Failed(RuntimeError::UnknownInstruction {
+                line: 2,
+                instruction: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn clear_looks_several_tiles_ahead() {
+        // A wall two tiles ahead: `clear 2` sees it, `clear 1` does not.
+        let source = "def main\n while clear 2\n  move\n endwhile\n if clear 1\n  put\n endif\nenddef";
+        let mut world = World::new(10, 1);
+        world.set_wall(Position::new(5, 0), true);
+        let world = run_program(source, world).unwrap();
+        assert_eq!(world.robot.position, Position::new(3, 0));
+        assert_eq!(world.beepers_at(Position::new(3, 0)), 1);
+    }
+
+    #[test]
+    fn beeper_comparisons_count_the_pile() {
+        let source = "def main\n while beeper < 3\n  put\n endwhile\n if beeper = 3\n  move\n endif\nenddef";
+        let world = run_program(source, World::default()).unwrap();
+        assert_eq!(world.beepers_at(Position::new(0, 0)), 3);
+        assert_eq!(world.robot.position, Position::new(1, 0));
+    }
+
+    #[test]
+    fn beeper_ahead_peeks_without_moving() {
+        let source = "def main\n if beeper-ahead\n  move\n  take\n endif\n if! beeper-ahead\n  put\n endif\nenddef";
+        let mut world = World::new(5, 1);
+        world.set_beepers(Position::new(1, 0), 1);
+        let world = run_program(source, world).unwrap();
+        // Took the beeper ahead, then saw an empty tile ahead and put one.
+        assert_eq!(world.robot.position, Position::new(1, 0));
+        assert_eq!(world.beepers_at(Position::new(1, 0)), 1);
+    }
+
+    #[test]
+    fn beep_records_where_it_sounded() {
+        let source = "def main\n beep\n move\n beep\n beep\nenddef";
+        let world = run_program(source, World::new(3, 1)).unwrap();
+        assert_eq!(
+            world.beeps(),
+            [Position::new(0, 0), Position::new(1, 0), Position::new(1, 0)]
+        );
+        // `beep` is an action like any other: it ticks the clock.
+        assert_eq!(world.ticks(), 4);
+    }
+
+    #[test]
+    fn in_region_tests_the_named_rectangle() {
+        let source = "def main\n while! in-region goal\n  move\n endwhile\n if in-region elsewhere\n  put\n endif\nenddef";
+        let mut world = World::new(6, 1);
+        world.add_region("goal", Position::new(3, 0), Position::new(4, 0));
+        let world = run_program(source, world).unwrap();
+        // Walked to the first tile of `goal`; the unknown region held nothing.
+        assert_eq!(world.robot.position, Position::new(3, 0));
+        assert_eq!(world.beepers_at(Position::new(3, 0)), 0);
+    }
+
+    #[test]
+    fn a_fork_explores_without_disturbing_the_original() {
+        let source = "def main\n move\n move\n take\n die\nenddef";
+        let mut world = World::new(4, 1);
+        world.set_beepers(Position::new(2, 0), 1);
+        let mut interpreter = Interpreter::new(preprocess(source), world).unwrap();
+        interpreter.step().unwrap();
+
+        // A hint system asks: what happens if the program just keeps going?
+        let mut branch = interpreter.fork();
+        branch.run().into_result().unwrap();
+        assert_eq!(branch.world.robot.position, Position::new(2, 0));
+        assert!(!branch.world.robot.alive);
+
+        // The original is still mid-run, one move in, beeper untouched.
+        assert!(!interpreter.finished());
+        assert_eq!(interpreter.world.robot.position, Position::new(1, 0));
+        assert_eq!(interpreter.world.beepers_at(Position::new(2, 0)), 1);
+        interpreter.run().into_result().unwrap();
+        assert_eq!(interpreter.world.beepers_at(Position::new(2, 0)), 0);
+    }
+
+    #[test]
+    fn step_over_runs_a_call_as_one_step() {
+        let source =
+            "def main\n call spin\n move\n die\nenddef\ndef spin\n turn-left\n turn-left\nenddef";
+        let mut interpreter = Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        // The whole of `spin` runs, but control never appears inside it.
+        interpreter.step_over().unwrap();
+        assert_eq!(interpreter.call_depth(), 0);
+        assert_eq!(interpreter.current_line(), Some(3));
+        assert_eq!(interpreter.world.robot.direction, Direction::West);
+    }
+
+    #[test]
+    fn step_out_returns_to_the_caller() {
+        let source =
+            "def main\n call spin\n beep\n die\nenddef\ndef spin\n turn-left\n turn-left\nenddef";
+        let mut interpreter = Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        // One plain step enters `spin`; stepping out finishes it.
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.call_depth(), 1);
+        interpreter.step_out().unwrap();
+        assert_eq!(interpreter.call_depth(), 0);
+        assert_eq!(interpreter.current_line(), Some(3));
+        // In `main` there is no caller: stepping out runs to the end.
+        assert_eq!(interpreter.step_out(), Ok(StepResult::Finished));
+        assert!(interpreter.finished());
+    }
+
+    #[test]
+    fn print_direction_reports_the_facing() {
+        let source = "def main\n print direction\n turn-left\n print direction\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::default()).unwrap();
+        interpreter.run().into_result().unwrap();
+        assert_eq!(interpreter.take_output(), vec!["east", "north"]);
+        // Taking drains the buffer.
+        assert!(interpreter.take_output().is_empty());
+    }
+
+    #[test]
+    fn the_outcome_reports_how_the_run_went() {
+        let source = "def main\n call twice\n die\nenddef\ndef twice\n move\n move\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::default()).unwrap();
+        let outcome = interpreter.run();
+        assert_eq!(outcome.status, RunStatus::Halted);
+        assert_eq!(outcome.report.max_call_depth, 1);
+        assert_eq!(outcome.final_snapshot.robot.position, Position::new(2, 0));
+        assert!(!outcome.final_snapshot.robot.alive);
+
+        let source = "def main\n move\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::default()).unwrap();
+        assert_eq!(interpreter.run().status, RunStatus::Completed);
+    }
+
+    #[test]
+    fn the_report_accounts_for_memory_and_time() {
+        // One active call and one active repeat at the same time.
+        let source = "def main\n repeat 2\n  call step\n endrepeat\nenddef\n\
+                      def step\n move\n print direction\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(5, 1)).unwrap();
+        let outcome = interpreter.run();
+        assert_eq!(outcome.status, RunStatus::Completed);
+        assert!(
+            outcome.report.peak_memory
+                >= core::mem::size_of::<usize>() + core::mem::size_of::<(usize, usize)>(),
+            "peak was {}",
+            outcome.report.peak_memory
+        );
+        assert!(outcome.report.wall_time > core::time::Duration::ZERO);
+
+        // A straight-line program books no stack and prints nothing.
+        let mut interpreter =
+            Interpreter::new(preprocess("def main\n move\nenddef"), World::new(3, 1)).unwrap();
+        assert_eq!(interpreter.run().report.peak_memory, 0);
+    }
+
+    #[test]
+    fn a_limited_run_can_be_resumed() {
+        let source = "def main\n repeat 10\n  move\n endrepeat\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(20, 1)).unwrap();
+        let outcome = interpreter.run_bounded(3);
+        assert_eq!(outcome.status, RunStatus::LimitHit);
+        assert_eq!(outcome.report.steps, 3);
+        assert_eq!(interpreter.run().status, RunStatus::Completed);
+        assert_eq!(interpreter.world.robot.position, Position::new(10, 0));
+    }
+
+    #[test]
+    fn stepping_the_hot_loop_does_not_allocate() {
+        // Actions, beeper churn, a repeat block: the common hot-loop mix.
+        let source =
+            "def main\n repeat 100000\n  turn-left\n  put\n  take\n endrepeat\n die\nenddef";
+        let mut interpreter = Interpreter::new(preprocess(source), World::default()).unwrap();
+        // A few warm-up steps let the repeat stack reach its final size.
+        for _ in 0..16 {
+            interpreter.step().unwrap();
+        }
+        let before = super::counting_allocator::allocations();
+        for _ in 0..10_000 {
+            interpreter.step().unwrap();
+        }
+        assert_eq!(
+            super::counting_allocator::allocations(),
+            before,
+            "stepping allocated on the heap"
+        );
+    }
+
+    #[test]
+    fn unvalidated_broken_block_fails_when_reached() {
+        // `if` with no `endif`: validation would reject this, but running it
+        // anyway must fail cleanly once the false branch needs its end.
+        let result = run_program("def main\n if wall\n move\nenddef", World::default());
+        assert_eq!(result, Err(RuntimeError::MalformedBlock { line: 2 }));
+    }
+
+    #[test]
+    fn missing_main_is_reported() {
+        let result = Interpreter::new(preprocess("def other\n move\nenddef"), World::default());
+        assert!(matches!(result, Err(RuntimeError::MissingMain)));
+    }
+}
@@ -0,0 +1,434 @@
+//! A tiny JSON reader and writer.
+//!
+//! The crate deliberately has no dependencies, and the JSON we exchange
+//! (grade summaries, run reports, world files) is simple, so this small value
+//! type plus a serializer and a recursive-descent parser is all there is. It
+//! writes compact, valid JSON with escaped strings; pretty-printing is left
+//! to downstream tools like `jq`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A JSON value. Objects keep their keys sorted, which makes the output
+/// stable and easy to diff in tests and grading pipelines.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Convenience constructor for objects.
+    pub fn object(entries: impl IntoIterator<Item = (&'static str, Value)>) -> Value {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Value {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::Number(value)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Value {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        match value {
+            Some(value) => value.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Number(value) => {
+                // Whole numbers print as integers, which is what every
+                // consumer of our output expects for counts.
+                if value.fract() == 0.0 && value.abs() < 1e15 {
+                    write!(f, "{}", *value as i64)
+                } else {
+                    write!(f, "{value}")
+                }
+            }
+            Value::String(value) => write_escaped(f, value),
+            Value::Array(values) => {
+                f.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(entries) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_escaped(f, key)?;
+                    f.write_str(":")?;
+                    write!(f, "{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_escaped(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for character in string.chars() {
+        match character {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            control if (control as u32) < 0x20 => {
+                write!(f, "\\u{:04x}", control as u32)?;
+            }
+            other => write!(f, "{other}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// An error while parsing JSON, with the byte offset it happened at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid JSON at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// How deep arrays and objects may nest. Our own documents are a few levels
+/// deep at most; the limit exists so hostile input errors out instead of
+/// overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Parse a JSON document. Trailing content after the value is an error.
+pub fn parse(source: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser {
+        bytes: source.as_bytes(),
+        offset: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.offset != parser.bytes.len() {
+        return Err(parser.error("trailing characters after the value"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.offset) {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.offset).copied()
+    }
+
+    fn expect(&mut self, byte: u8, message: &'static str) -> Result<(), JsonError> {
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn eat_literal(&mut self, literal: &str) -> bool {
+        if self.bytes[self.offset..].starts_with(literal.as_bytes()) {
+            self.offset += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        match self.peek().ok_or_else(|| self.error("unexpected end of input"))? {
+            b'n' if self.eat_literal("null") => Ok(Value::Null),
+            b't' if self.eat_literal("true") => Ok(Value::Bool(true)),
+            b'f' if self.eat_literal("false") => Ok(Value::Bool(false)),
+            b'"' => self.string().map(Value::String),
+            b'[' => self.array(),
+            b'{' => self.object(),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    /// Bump the nesting depth for one array or object, erroring instead of
+    /// recursing without bound on input like `[[[[...`.
+    fn enter(&mut self) -> Result<(), JsonError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        Ok(())
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'[', "expected `[`")?;
+        self.enter()?;
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.offset += 1;
+            self.depth -= 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b']') => {
+                    self.offset += 1;
+                    self.depth -= 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'{', "expected `{`")?;
+        self.enter()?;
+        let mut entries = BTreeMap::new();
+        if self.peek() == Some(b'}') {
+            self.offset += 1;
+            self.depth -= 1;
+            return Ok(Value::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':', "expected `:` after object key")?;
+            entries.insert(key, self.value()?);
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b'}') => {
+                    self.offset += 1;
+                    self.depth -= 1;
+                    return Ok(Value::Object(entries));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"', "expected a string")?;
+        let mut string = String::new();
+        loop {
+            let start = self.offset;
+            while let Some(&byte) = self.bytes.get(self.offset) {
+                if byte == b'"' || byte == b'\\' {
+                    break;
+                }
+                self.offset += 1;
+            }
+            // The input is valid UTF-8 and we only stop at ASCII bytes, so
+            // the chunk boundary is a character boundary.
+            string.push_str(std::str::from_utf8(&self.bytes[start..self.offset]).unwrap());
+            match self.bytes.get(self.offset) {
+                Some(b'"') => {
+                    self.offset += 1;
+                    return Ok(string);
+                }
+                Some(b'\\') => {
+                    self.offset += 1;
+                    let escape = self
+                        .bytes
+                        .get(self.offset)
+                        .ok_or_else(|| self.error("unterminated escape"))?;
+                    self.offset += 1;
+                    match escape {
+                        b'"' => string.push('"'),
+                        b'\\' => string.push('\\'),
+                        b'/' => string.push('/'),
+                        b'n' => string.push('\n'),
+                        b'r' => string.push('\r'),
+                        b't' => string.push('\t'),
+                        b'b' => string.push('\u{8}'),
+                        b'f' => string.push('\u{c}'),
+                        b'u' => {
+                            let digits = self
+                                .bytes
+                                .get(self.offset..self.offset + 4)
+                                .and_then(|digits| std::str::from_utf8(digits).ok())
+                                .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                                .ok_or_else(|| self.error("bad \\u escape"))?;
+                            self.offset += 4;
+                            // Surrogate pairs are not worth supporting here.
+                            string.push(
+                                char::from_u32(digits)
+                                    .ok_or_else(|| self.error("bad \\u escape"))?,
+                            );
+                        }
+                        _ => return Err(self.error("unknown escape")),
+                    }
+                }
+                _ => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.offset;
+        if self.bytes.get(self.offset) == Some(&b'-') {
+            self.offset += 1;
+        }
+        while let Some(b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') =
+            self.bytes.get(self.offset)
+        {
+            self.offset += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.offset])
+            .ok()
+            .and_then(|text| text.parse::<f64>().ok())
+            .map(Value::Number)
+            .ok_or_else(|| self.error("bad number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_roundtrips_serialized_output() {
+        let value = Value::object([
+            ("name", Value::from("quote \" and \\ newline\n")),
+            ("passed", Value::from(true)),
+            ("score", Value::from(0.5)),
+            ("nothing", Value::Null),
+            ("list", Value::from(vec![1usize, 2, 3])),
+        ]);
+        assert_eq!(parse(&value.to_string()), Ok(value));
+    }
+
+    #[test]
+    fn parse_accepts_whitespace_everywhere() {
+        let value = parse(" { \"a\" : [ 1 , -2.5 ] , \"b\" : null } ").unwrap();
+        let Value::Object(entries) = value else { panic!() };
+        assert_eq!(entries["a"], Value::Array(vec![Value::Number(1.0), Value::Number(-2.5)]));
+        assert_eq!(entries["b"], Value::Null);
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert!(parse("").is_err());
+        assert!(parse("{").is_err());
+        assert!(parse("[1,]").is_err());
+        assert!(parse("\"unterminated").is_err());
+        assert!(parse("true false").is_err());
+    }
+
+    #[test]
+    fn serializes_nested_structures() {
+        let value = Value::object([
+            ("name", Value::from("newline\nhere")),
+            ("passed", Value::from(true)),
+            ("score", Value::from(0.5)),
+            ("steps", Value::from(12usize)),
+            ("worlds", Value::from(vec!["a", "b"])),
+            ("error", Value::Null),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{"error":null,"name":"newline\nhere","passed":true,"score":0.5,"steps":12,"worlds":["a","b"]}"#
+        );
+    }
+
+    #[test]
+    fn integers_print_without_decimal_point() {
+        assert_eq!(Value::from(3usize).to_string(), "3");
+        assert_eq!(Value::from(1.25).to_string(), "1.25");
+    }
+
+    #[test]
+    fn deeply_nested_input_errors_instead_of_overflowing_the_stack() {
+        let deep = "[".repeat(10_000);
+        assert!(parse(&deep).is_err());
+        let nested_objects = "{\"a\":".repeat(10_000);
+        assert!(parse(&nested_objects).is_err());
+    }
+
+    #[test]
+    fn nesting_under_the_limit_still_parses() {
+        let mut source = "[".repeat(100);
+        source.push_str(&"]".repeat(100));
+        assert!(parse(&source).is_ok());
+    }
+}
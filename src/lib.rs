@@ -0,0 +1,95 @@
+//! Karel language interpreter.
+//!
+//! This version of Karel is slightly altered to protect sanity of innocent
+//! people. See the README for the language description.
+//!
+//! The language core — [`world`], [`parser`], [`interpreter`], plus the
+//! [`highlight`], [`transpile`] and [`importer`] passes — only needs `alloc`
+//! and works on embedded targets with the default `std` feature disabled.
+//! Everything that touches files, terminals or protocols requires `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod annotation;
+#[cfg(feature = "parallel")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod campaign;
+#[cfg(feature = "std")]
+pub mod dap;
+#[cfg(feature = "async")]
+pub mod driver;
+#[cfg(feature = "std")]
+pub mod editor;
+#[cfg(feature = "std")]
+pub mod engine;
+pub mod environment;
+pub mod feedback;
+pub mod generate;
+#[cfg(feature = "std")]
+pub mod golden;
+#[cfg(feature = "std")]
+pub mod grade;
+pub mod grammar;
+pub mod highlight;
+#[cfg(feature = "std")]
+pub mod hint;
+pub mod importer;
+#[cfg(feature = "std")]
+pub mod interactive;
+pub mod interpreter;
+#[cfg(feature = "std")]
+pub mod json;
+pub mod lint;
+pub mod locale;
+pub mod lock;
+#[cfg(feature = "tracing")]
+pub mod log;
+#[cfg(feature = "std")]
+pub mod lsp;
+pub mod metrics;
+pub mod minimize;
+pub mod modules;
+#[cfg(feature = "std")]
+pub mod multiplayer;
+pub mod parser;
+#[cfg(feature = "std")]
+pub mod profile;
+pub mod program;
+pub mod refactor;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod scaffold;
+pub mod scheduler;
+#[cfg(feature = "std")]
+pub mod server;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod storage;
+#[cfg(feature = "std")]
+pub mod task;
+#[cfg(feature = "std")]
+pub mod tournament;
+#[cfg(feature = "std")]
+pub mod trace;
+pub mod transpile;
+pub mod watch;
+pub mod world;
+#[cfg(feature = "std")]
+pub mod worldfile;
+
+#[cfg(feature = "std")]
+pub use engine::Engine;
+pub use highlight::{highlight, Span, TokenKind};
+pub use interpreter::{Interpreter, RunOutcome, RunReport, RunStatus, RuntimeError, StepResult};
+pub use parser::ParseError;
+pub use program::Program;
+#[cfg(feature = "std")]
+pub use render::{render, render_svg, RenderStyle};
+pub use world::{Direction, Position, Robot, World};
@@ -0,0 +1,419 @@
+//! Style lints: legal programs that could be written better.
+//!
+//! The validation pass in [`parser`](crate::parser) rejects programs that
+//! cannot run; this pass flags programs that run fine but teach bad habits
+//! — magic `repeat` counts, procedures that do too much, copy-pasted
+//! sequences, code the robot can never reach. Each finding is advisory and
+//! carries a severity, so a task can turn individual rules off or promote
+//! them to errors, and editors surface them through the same diagnostics
+//! channel as syntax errors.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::parser::Line;
+
+/// One style rule the linter knows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// A `repeat` count large enough that a condition would read better.
+    MagicRepeat,
+    /// A procedure longer than the configured limit.
+    LongProcedure,
+    /// The same instruction sequence written out twice; extract a procedure.
+    DuplicatedSequence,
+    /// Code the robot can never reach: lines after `die`, procedures
+    /// nothing calls.
+    DeadCode,
+}
+
+impl Rule {
+    /// Every rule, in the order findings are reported.
+    pub const ALL: [Rule; 4] = [
+        Rule::MagicRepeat,
+        Rule::LongProcedure,
+        Rule::DuplicatedSequence,
+        Rule::DeadCode,
+    ];
+
+    /// The kebab-case name of the rule, as task files spell it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::MagicRepeat => "magic-repeat",
+            Rule::LongProcedure => "long-procedure",
+            Rule::DuplicatedSequence => "duplicated-sequence",
+            Rule::DeadCode => "dead-code",
+        }
+    }
+
+    /// The rule with the given [`name`](Rule::name).
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+/// How seriously a finding should be taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Worth fixing, but the program still counts.
+    Warning,
+    /// As bad as a syntax error; a strict task can fail submissions on it.
+    Error,
+}
+
+impl Severity {
+    /// The lowercase name of the severity, as task files spell it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warn",
+            Severity::Error => "error",
+        }
+    }
+
+    /// The severity with the given [`name`](Severity::name).
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "warn" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Which rules run, how seriously, and where their thresholds sit. The
+/// default warns on everything with classroom-sized limits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Enabled rules with their severities; a rule missing here is off.
+    rules: Vec<(Rule, Severity)>,
+    /// `repeat` counts above this trip [`Rule::MagicRepeat`].
+    pub max_repeat_count: usize,
+    /// Procedure bodies longer than this trip [`Rule::LongProcedure`].
+    pub max_procedure_lines: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            rules: Rule::ALL
+                .into_iter()
+                .map(|rule| (rule, Severity::Warning))
+                .collect(),
+            max_repeat_count: 8,
+            max_procedure_lines: 12,
+        }
+    }
+}
+
+impl Config {
+    /// The severity of a rule, or `None` when it is off.
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        self.rules
+            .iter()
+            .find(|(candidate, _)| *candidate == rule)
+            .map(|(_, severity)| *severity)
+    }
+
+    /// Enable the rule at the given severity, replacing any earlier setting.
+    pub fn set(&mut self, rule: Rule, severity: Severity) {
+        match self.rules.iter_mut().find(|(candidate, _)| *candidate == rule) {
+            Some(entry) => entry.1 = severity,
+            None => self.rules.push((rule, severity)),
+        }
+    }
+
+    /// Turn the rule off.
+    pub fn disable(&mut self, rule: Rule) {
+        self.rules.retain(|(candidate, _)| *candidate != rule);
+    }
+}
+
+/// One finding: where, which rule, how serious, and what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// 1-based source line of the offending instruction.
+    pub line: usize,
+    /// 1-based column of the instruction on that line.
+    pub column: usize,
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Lint a preprocessed program under the given configuration. The program
+/// need not be valid — like [`parser::check`](crate::parser::check), the
+/// linter reports what it can and leaves rejecting broken programs to
+/// validation.
+pub fn lint(lines: &[Line<'_>], config: &Config) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    magic_repeats(lines, config, &mut lints);
+    long_procedures(lines, config, &mut lints);
+    duplicated_sequences(lines, config, &mut lints);
+    dead_code(lines, config, &mut lints);
+    lints.sort_by_key(|lint| lint.line);
+    lints
+}
+
+fn report(
+    lints: &mut Vec<Lint>,
+    config: &Config,
+    rule: Rule,
+    line: &Line<'_>,
+    message: String,
+) {
+    if let Some(severity) = config.severity(rule) {
+        lints.push(Lint {
+            line: line.number,
+            column: line.column,
+            rule,
+            severity,
+            message,
+        });
+    }
+}
+
+fn magic_repeats(lines: &[Line<'_>], config: &Config, lints: &mut Vec<Lint>) {
+    for line in lines {
+        if let ["repeat", count] = line.text.split_whitespace().collect::<Vec<_>>()[..] {
+            if count.parse::<usize>().is_ok_and(|count| count > config.max_repeat_count) {
+                report(
+                    lints,
+                    config,
+                    Rule::MagicRepeat,
+                    line,
+                    format!(
+                        "`repeat {count}` is hard to check by eye; \
+                         prefer a `while` over a condition"
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn long_procedures(lines: &[Line<'_>], config: &Config, lints: &mut Vec<Lint>) {
+    for (index, name) in definitions(lines) {
+        let body = lines[index + 1..]
+            .iter()
+            .take_while(|line| first_word(line) != "enddef")
+            .count();
+        if body > config.max_procedure_lines {
+            report(
+                lints,
+                config,
+                Rule::LongProcedure,
+                &lines[index],
+                format!(
+                    "procedure `{name}` is {body} lines long (limit {}); \
+                     split it into helpers",
+                    config.max_procedure_lines
+                ),
+            );
+        }
+    }
+}
+
+/// The shortest sequence worth extracting into a procedure.
+const DUPLICATE_WINDOW: usize = 3;
+
+fn duplicated_sequences(lines: &[Line<'_>], config: &Config, lints: &mut Vec<Lint>) {
+    // A clone may not cross a procedure boundary, and once a duplicate is
+    // reported the windows overlapping it stay quiet. A matching window is
+    // grown as far as the copies stay identical, so the suggestion names
+    // the whole duplicated stretch, not just its first three lines.
+    let boundary = |index: usize| matches!(first_word(&lines[index]), "def" | "enddef");
+    let crosses_boundary = |start: usize| (start..start + DUPLICATE_WINDOW).any(boundary);
+    let mut muted_until = 0usize;
+    for later in DUPLICATE_WINDOW..lines.len().saturating_sub(DUPLICATE_WINDOW - 1) {
+        if later < muted_until || crosses_boundary(later) {
+            continue;
+        }
+        let matches_earlier = (0..later - DUPLICATE_WINDOW + 1).find(|&earlier| {
+            !crosses_boundary(earlier)
+                && (0..DUPLICATE_WINDOW)
+                    .all(|offset| lines[earlier + offset].text == lines[later + offset].text)
+        });
+        if let Some(earlier) = matches_earlier {
+            let mut length = DUPLICATE_WINDOW;
+            while later + length < lines.len()
+                && earlier + length < later
+                && !boundary(earlier + length)
+                && !boundary(later + length)
+                && lines[earlier + length].text == lines[later + length].text
+            {
+                length += 1;
+            }
+            report(
+                lints,
+                config,
+                Rule::DuplicatedSequence,
+                &lines[later],
+                format!(
+                    "lines {}-{} repeat lines {}-{}; \
+                     extract a procedure and `call` it",
+                    lines[later].number,
+                    lines[later + length - 1].number,
+                    lines[earlier].number,
+                    lines[earlier + length - 1].number,
+                ),
+            );
+            muted_until = later + length;
+        }
+    }
+}
+
+fn dead_code(lines: &[Line<'_>], config: &Config, lints: &mut Vec<Lint>) {
+    // Lines directly after `die` in the same block can never run.
+    for (index, line) in lines.iter().enumerate() {
+        if first_word(line) != "die" {
+            continue;
+        }
+        if let Some(next) = lines.get(index + 1) {
+            if !matches!(
+                first_word(next),
+                "endif" | "endwhile" | "endrepeat" | "enddef"
+            ) {
+                report(
+                    lints,
+                    config,
+                    Rule::DeadCode,
+                    next,
+                    "unreachable: the robot is already switched off".to_string(),
+                );
+            }
+        }
+    }
+
+    // Procedures nothing calls (apart from the entry point) can never run.
+    let called: Vec<&str> = lines
+        .iter()
+        .filter(|line| first_word(line) == "call")
+        .filter_map(|line| line.text.split_whitespace().nth(1))
+        .collect();
+    for (index, name) in definitions(lines) {
+        if name != "main" && !called.contains(&name) {
+            report(
+                lints,
+                config,
+                Rule::DeadCode,
+                &lines[index],
+                format!("procedure `{name}` is never called"),
+            );
+        }
+    }
+}
+
+fn first_word<'l>(line: &'l Line<'_>) -> &'l str {
+    line.text.split_whitespace().next().unwrap_or("")
+}
+
+/// Every `def` line as (index into `lines`, procedure name).
+fn definitions<'l>(lines: &'l [Line<'_>]) -> impl Iterator<Item = (usize, &'l str)> {
+    lines.iter().enumerate().filter_map(|(index, line)| {
+        let mut words = line.text.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("def"), Some(name)) => Some((index, name)),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::preprocess;
+
+    fn rules(source: &str) -> Vec<Rule> {
+        lint(&preprocess(source), &Config::default())
+            .into_iter()
+            .map(|lint| lint.rule)
+            .collect()
+    }
+
+    #[test]
+    fn a_tidy_program_is_clean() {
+        let source = "def main\n repeat 3\n  call step\n endrepeat\n die\nenddef\ndef step\n move\n take\nenddef";
+        assert_eq!(rules(source), []);
+    }
+
+    #[test]
+    fn magic_repeat_counts_are_flagged() {
+        let lints = lint(&preprocess("def main\n repeat 40\n  move\n endrepeat\nenddef"), &Config::default());
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rule, Rule::MagicRepeat);
+        assert_eq!(lints[0].line, 2);
+        assert_eq!(lints[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn long_procedures_are_flagged_at_their_def() {
+        // Thirteen lines with no three-line window repeating, so only the
+        // length is at fault.
+        let body = " move\n take\n put\n move\n put\n take\n move\n beep\n take\n beep\n put\n beep\n turn-left\n";
+        let source = format!("def main\n{body}enddef");
+        let lints = lint(&preprocess(&source), &Config::default());
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rule, Rule::LongProcedure);
+        assert_eq!(lints[0].line, 1);
+        assert!(lints[0].message.contains("13 lines"), "{}", lints[0].message);
+    }
+
+    #[test]
+    fn copy_pasted_sequences_suggest_a_procedure() {
+        let source = "def main\n move\n take\n put\n turn-left\n move\n take\n put\nenddef";
+        let lints = lint(&preprocess(source), &Config::default());
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rule, Rule::DuplicatedSequence);
+        assert_eq!(lints[0].line, 6);
+        assert!(
+            lints[0].message.contains("lines 6-8 repeat lines 2-4"),
+            "{}",
+            lints[0].message
+        );
+    }
+
+    #[test]
+    fn clones_across_procedures_are_reported_in_full() {
+        // Four identical lines in two procedures: one suggestion covering
+        // the whole stretch, not several overlapping three-line ones.
+        let source = "def main\n move\n take\n turn-left\n put\n call rest\nenddef\n\
+                      def rest\n move\n take\n turn-left\n put\nenddef";
+        let lints = lint(&preprocess(source), &Config::default());
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rule, Rule::DuplicatedSequence);
+        assert!(
+            lints[0].message.contains("lines 9-12 repeat lines 2-5"),
+            "{}",
+            lints[0].message
+        );
+    }
+
+    #[test]
+    fn dead_code_after_die_and_uncalled_procedures() {
+        let source = "def main\n die\n move\nenddef\ndef spare\n move\nenddef";
+        let lints = lint(&preprocess(source), &Config::default());
+        let found: Vec<(Rule, usize)> = lints.iter().map(|lint| (lint.rule, lint.line)).collect();
+        assert_eq!(found, [(Rule::DeadCode, 3), (Rule::DeadCode, 5)]);
+    }
+
+    #[test]
+    fn rules_can_be_silenced_or_promoted() {
+        let source = "def main\n repeat 40\n  move\n endrepeat\nenddef";
+        let mut config = Config::default();
+        config.set(Rule::MagicRepeat, Severity::Error);
+        assert_eq!(lint(&preprocess(source), &config)[0].severity, Severity::Error);
+        config.disable(Rule::MagicRepeat);
+        assert_eq!(lint(&preprocess(source), &config), []);
+    }
+
+    #[test]
+    fn thresholds_are_configurable() {
+        let config = Config {
+            max_repeat_count: 2,
+            ..Config::default()
+        };
+        let source = "def main\n repeat 3\n  move\n endrepeat\nenddef";
+        assert_eq!(lint(&preprocess(source), &config).len(), 1);
+    }
+}
@@ -0,0 +1,436 @@
+//! Message catalogs, so diagnostics can speak the student's language.
+//!
+//! The audience is school kids who may not read English. A [`Catalog`] maps
+//! the stable keys of [`feedback`](crate::feedback) — plus a handful of CLI
+//! strings under `cli.*` — to templates in one language; [`Catalog::explain`]
+//! renders a [`Feedback`] through them and falls back to the built-in
+//! English text for anything the catalog does not carry. Catalogs are plain
+//! static tables, selected at runtime by language tag with
+//! [`Catalog::from_name`]; adding a language means adding one table here.
+
+use alloc::format;
+use alloc::string::String;
+
+use crate::feedback::Feedback;
+
+/// One language's message table.
+#[derive(Debug, Clone, Copy)]
+pub struct Catalog {
+    /// The primary language subtag (`"en"`, `"cs"`).
+    pub language: &'static str,
+    messages: &'static [(&'static str, &'static str)],
+}
+
+/// Every catalog the crate ships, English first.
+pub const CATALOGS: &[&Catalog] = &[&ENGLISH, &CZECH];
+
+impl Catalog {
+    /// Look a catalog up by language tag. Region and encoding suffixes are
+    /// ignored, so `cs`, `cs_CZ` and `cs_CZ.UTF-8` (the shape of `$LANG`)
+    /// all find the Czech catalog.
+    pub fn from_name(name: &str) -> Option<&'static Catalog> {
+        let primary = name
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or(name)
+            .to_ascii_lowercase();
+        CATALOGS
+            .iter()
+            .find(|catalog| catalog.language == primary)
+            .copied()
+    }
+
+    /// The raw template stored under `key`, if this catalog carries one.
+    pub fn get(&self, key: &str) -> Option<&'static str> {
+        self.messages
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, template)| *template)
+    }
+
+    /// Render a feedback's explanation and advice in this language,
+    /// falling back per string to the built-in English text.
+    pub fn explain(&self, feedback: &Feedback) -> (String, String) {
+        let localized = |suffix: &str, builtin: &str| {
+            self.get(&format!("{}.{suffix}", feedback.key))
+                .map(|template| feedback.fill(template))
+                .unwrap_or_else(|| String::from(builtin))
+        };
+        (
+            localized("what", &feedback.what),
+            localized("advice", &feedback.advice),
+        )
+    }
+
+    /// Pick the plural form a count takes in this language: Czech needs
+    /// three forms (`one`, `few`, `other`) where English needs two, and the
+    /// call site should not have to know which. Catalogs store counted
+    /// messages under `key.one`, `key.few`, `key.other`.
+    pub fn plural_form(&self, count: usize) -> &'static str {
+        match self.language {
+            "cs" => match count {
+                1 => "one",
+                2..=4 => "few",
+                _ => "other",
+            },
+            _ => {
+                if count == 1 {
+                    "one"
+                } else {
+                    "other"
+                }
+            }
+        }
+    }
+
+    /// Render the counted message stored under `key` (`cli.errors` and the
+    /// like), choosing the plural form for `count` and substituting it.
+    pub fn count(&self, key: &str, count: usize) -> Option<String> {
+        let template = self
+            .get(&format!("{key}.{}", self.plural_form(count)))
+            .or_else(|| self.get(&format!("{key}.other")))?;
+        Some(template.replace("{count}", &format!("{count}")))
+    }
+}
+
+/// English. Diagnostics fall back to the text built into
+/// [`feedback`](crate::feedback), so only the CLI strings live here.
+pub const ENGLISH: Catalog = Catalog {
+    language: "en",
+    messages: &[
+        ("cli.errors.one", "1 error found"),
+        ("cli.errors.other", "{count} errors found"),
+        ("cli.help", "help"),
+    ],
+};
+
+/// Czech.
+pub const CZECH: Catalog = Catalog {
+    language: "cs",
+    messages: &[
+        ("cli.errors.one", "nalezena 1 chyba"),
+        ("cli.errors.few", "nalezeny {count} chyby"),
+        ("cli.errors.other", "nalezeno {count} chyb"),
+        ("cli.help", "rada"),
+        ("hit-wall.what", "Karel narazil do zdi a rozbil se."),
+        (
+            "hit-wall.advice",
+            "Před každým `move`, který může mířit do zdi, se zeptej `if! wall` (nebo `clear`).",
+        ),
+        (
+            "no-beeper.what",
+            "Karel se pokusil sebrat bzučák z políčka, kde žádný není.",
+        ),
+        (
+            "no-beeper.advice",
+            "Chraň `take` podmínkou `if beeper`, nebo zkontroluj cestu, která k bzučáku měla vést.",
+        ),
+        (
+            "tile-full.what",
+            "Na tomto políčku už je tolik bzučáků, kolik se jich tam vejde.",
+        ),
+        (
+            "tile-full.advice",
+            "Polož bzučák jinam, nebo nejdřív nějaké seber.",
+        ),
+        ("robot-collision.what", "Karel narazil do jiného robota."),
+        (
+            "robot-collision.advice",
+            "Ve sdílených světech se před vstupem na políčko podívej, jestli je volné.",
+        ),
+        (
+            "call-depth.what",
+            "Procedury volaly procedury do hloubky {limit} úrovní a nevrátily se.",
+        ),
+        (
+            "call-depth.advice",
+            "Nějaká procedura nejspíš volá sama sebe bez konce; dej rekurzi `if`, který ji ukončí.",
+        ),
+        (
+            "loop-limit.what",
+            "Tato smyčka se zopakovala víc než {limit}krát.",
+        ),
+        (
+            "loop-limit.advice",
+            "Podmínka smyčky se nikdy nezmění; tělo smyčky musí Karla posouvat k jejímu konci.",
+        ),
+        (
+            "unknown-procedure.what",
+            "Procedura `{name}` neexistuje, není co volat.",
+        ),
+        (
+            "unknown-procedure.advice",
+            "Definuj ji pomocí `def {name} ... enddef`, nebo oprav překlep v `call`.",
+        ),
+        (
+            "unknown-label.what",
+            "Neexistuje žádný `label {name}`, kam by tento `goto` skočil.",
+        ),
+        (
+            "unknown-label.advice",
+            "Návěští jsou pro generovaný kód; generátor musí cíl vypsat dřív než skok.",
+        ),
+        (
+            "unknown-instruction.what",
+            "`{instruction}` není příkaz, který Karel zná.",
+        ),
+        (
+            "unknown-instruction.advice",
+            "Porovnej ho se seznamem příkazů; stačí jedno písmeno jinak.",
+        ),
+        (
+            "not-implemented.what",
+            "Program došel k zástupnému `???` — tato část ještě není napsaná.",
+        ),
+        (
+            "not-implemented.advice",
+            "Nahraď `???` příkazy, které tam patří.",
+        ),
+        (
+            "malformed-block.what",
+            "Blok začal nebo skončil tak, že ho interpret nedokázal sledovat.",
+        ),
+        (
+            "malformed-block.advice",
+            "Spusť nejdřív kontrolu: ukáže přesně, který `def`/`if`/`while` se nikdy nezavře.",
+        ),
+        (
+            "missing-main.what",
+            "Program nemá `def main`, takže není kde začít.",
+        ),
+        (
+            "missing-main.advice",
+            "Zabal první kroky programu do `def main ... enddef`.",
+        ),
+        (
+            "unknown-entry-point.what",
+            "Běh měl začít u `def {name}`, který neexistuje.",
+        ),
+        (
+            "unknown-entry-point.advice",
+            "Zkontroluj vstupní bod nastavený platformou nebo úlohou.",
+        ),
+        (
+            "nested-definition.what",
+            "Uvnitř jednoho `def` začal další `def`.",
+        ),
+        (
+            "nested-definition.advice",
+            "Ukonči první proceduru pomocí `enddef`, než začneš další.",
+        ),
+        (
+            "instruction-outside-definition.what",
+            "Tento příkaz stojí mimo jakoukoli proceduru.",
+        ),
+        (
+            "instruction-outside-definition.advice",
+            "Každý příkaz patří mezi `def jmeno` a jeho `enddef`.",
+        ),
+        (
+            "unmatched-block-end.what",
+            "Tento `{keyword}` nemá žádný blok, který by uzavíral.",
+        ),
+        (
+            "unmatched-block-end.advice",
+            "Smaž ho, nebo nad něj doplň chybějící začátek bloku.",
+        ),
+        (
+            "unclosed-block.what",
+            "Tento blok `{keyword}` není nikdy uzavřen.",
+        ),
+        (
+            "unclosed-block.advice",
+            "Doplň `end{keyword}` tam, kde má blok končit.",
+        ),
+        (
+            "unknown-condition.what",
+            "`{condition}` není podmínka, kterou umí `if` nebo `while` otestovat.",
+        ),
+        (
+            "unknown-condition.advice",
+            "Karel umí otestovat `wall`, čtyři světové strany, `beeper`, `beeper-ahead`, `clear N` a `in-region NAME`.",
+        ),
+        (
+            "bad-repeat-count.what",
+            "`repeat` potřebuje vědět kolikrát, jako kladné číslo.",
+        ),
+        (
+            "bad-repeat-count.advice",
+            "Napiš `repeat 3` — a pokud se počet má měnit, hodí se spíš `while`.",
+        ),
+        (
+            "bad-clear-distance.what",
+            "`clear` potřebuje kladný počet políček, na která se má podívat.",
+        ),
+        (
+            "bad-clear-distance.advice",
+            "Napiš `clear 2`, když se ptáš, jestli jsou další dvě políčka volná.",
+        ),
+        ("bad-print-item.what", "`print` umí vypsat jen `direction`."),
+        (
+            "bad-print-item.advice",
+            "Napiš `print direction`, nebo řádek smaž.",
+        ),
+        (
+            "bad-beeper-comparison.what",
+            "Porovnání bzučáků nemělo tvar `beeper OP počet`.",
+        ),
+        (
+            "bad-beeper-comparison.advice",
+            "Napiš ho jako `beeper >= 3`, s jedním z `<`, `<=`, `=`, `>=`, `>`.",
+        ),
+        (
+            "bad-region-name.what",
+            "`in-region` potřebuje právě jedno jméno oblasti.",
+        ),
+        (
+            "bad-region-name.advice",
+            "Napiš `in-region domov`, podle oblasti, kterou svět definuje.",
+        ),
+        (
+            "bad-name.what",
+            "Tento příkaz potřebuje právě jedno jméno za sebou.",
+        ),
+        (
+            "bad-name.advice",
+            "Napiš `def chuze`, `call chuze` — jedno slovo, bez mezer.",
+        ),
+        (
+            "duplicate-definition.what",
+            "Procedura `{name}` je definována dvakrát.",
+        ),
+        (
+            "duplicate-definition.advice",
+            "Jednu z nich přejmenuj, nebo je slouč.",
+        ),
+        (
+            "duplicate-label.what",
+            "Návěští `{name}` je definováno dvakrát, takže `goto {name}` je nejednoznačné.",
+        ),
+        (
+            "duplicate-label.advice",
+            "Návěští jsou pro generovaný kód; generátor musí vypisovat jedinečná jména.",
+        ),
+        (
+            "trailing-tokens.what",
+            "`{keyword}` nebere žádné argumenty, ale na řádku za ním něco následuje.",
+        ),
+        (
+            "trailing-tokens.advice",
+            "Smaž přebytečná slova; na řádku je jeden příkaz a nic víc.",
+        ),
+        (
+            "misnamed-main.what",
+            "`def {name}` je skoro `def main`, ale na velikosti písmen záleží.",
+        ),
+        ("misnamed-main.advice", "Přejmenuj ho na `def main`."),
+        (
+            "empty-program.what",
+            "Program neobsahuje vůbec žádné příkazy.",
+        ),
+        (
+            "empty-program.advice",
+            "Napiš první kroky dovnitř bloku `def main ... enddef`.",
+        ),
+    ],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feedback;
+    use crate::interpreter::RuntimeError;
+    use crate::parser::ParseError;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn catalogs_are_found_by_any_shape_of_language_tag() {
+        assert_eq!(Catalog::from_name("cs").unwrap().language, "cs");
+        assert_eq!(Catalog::from_name("cs_CZ.UTF-8").unwrap().language, "cs");
+        assert_eq!(Catalog::from_name("en-US").unwrap().language, "en");
+        assert!(Catalog::from_name("tlh").is_none());
+    }
+
+    #[test]
+    fn czech_explanations_substitute_the_error_details() {
+        let feedback = feedback::runtime(&RuntimeError::UnknownProcedure {
+            line: 4,
+            name: "chuze".to_string(),
+        });
+        let (what, advice) = CZECH.explain(&feedback);
+        assert_eq!(what, "Procedura `chuze` neexistuje, není co volat.");
+        assert!(advice.contains("def chuze"));
+    }
+
+    #[test]
+    fn english_falls_back_to_the_built_in_feedback_text() {
+        let feedback = feedback::runtime(&RuntimeError::HitWall { line: 2 });
+        let (what, advice) = ENGLISH.explain(&feedback);
+        assert_eq!(what, feedback.what);
+        assert_eq!(advice, feedback.advice);
+    }
+
+    #[test]
+    fn the_czech_catalog_covers_every_feedback_key() {
+        let runtime_samples = [
+            RuntimeError::HitWall { line: 1 },
+            RuntimeError::NoBeeperToTake { line: 1 },
+            RuntimeError::TooManyBeepers { line: 1 },
+            RuntimeError::RobotCollision { line: 1 },
+            RuntimeError::CallDepthExceeded { line: 1, limit: 1 },
+            RuntimeError::LoopLimitExceeded { line: 1, limit: 1 },
+            RuntimeError::UnknownProcedure { line: 1, name: "x".to_string() },
+            RuntimeError::UnknownLabel { line: 1, name: "x".to_string() },
+            RuntimeError::UnknownInstruction { line: 1, instruction: "x".to_string() },
+            RuntimeError::NotImplemented { line: 1 },
+            RuntimeError::MalformedBlock { line: 1 },
+            RuntimeError::MissingMain,
+            RuntimeError::UnknownEntryPoint { name: "x".to_string() },
+        ];
+        let syntax_samples = [
+            ParseError::NestedDefinition { line: 1 },
+            ParseError::InstructionOutsideDefinition { line: 1 },
+            ParseError::UnmatchedBlockEnd { line: 1, keyword: "endif".to_string() },
+            ParseError::UnclosedBlock { line: 1, keyword: "while".to_string() },
+            ParseError::UnknownInstruction { line: 1, instruction: "x".to_string() },
+            ParseError::UnknownCondition { line: 1, condition: "x".to_string() },
+            ParseError::BadRepeatCount { line: 1 },
+            ParseError::BadClearDistance { line: 1 },
+            ParseError::BadPrintItem { line: 1 },
+            ParseError::BadBeeperComparison { line: 1 },
+            ParseError::BadRegionName { line: 1 },
+            ParseError::BadName { line: 1 },
+            ParseError::DuplicateDefinition { line: 1, name: "x".to_string() },
+            ParseError::UnknownProcedure { line: 1, name: "x".to_string() },
+            ParseError::DuplicateLabel { line: 1, name: "x".to_string() },
+            ParseError::UnknownLabel { line: 1, name: "x".to_string() },
+            ParseError::TrailingTokens { line: 1, keyword: "move".to_string() },
+            ParseError::MisnamedMain { line: 1, name: "Main".to_string() },
+            ParseError::EmptyProgram,
+            ParseError::MissingMain,
+        ];
+        let keys: Vec<&str> = runtime_samples
+            .iter()
+            .map(|error| feedback::runtime(error).key)
+            .chain(syntax_samples.iter().map(|error| feedback::syntax(error).key))
+            .collect();
+        for key in keys {
+            for suffix in ["what", "advice"] {
+                assert!(
+                    CZECH.get(&format!("{key}.{suffix}")).is_some(),
+                    "no Czech text for `{key}.{suffix}`"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counted_messages_pick_the_right_plural_form() {
+        assert_eq!(ENGLISH.count("cli.errors", 1).unwrap(), "1 error found");
+        assert_eq!(ENGLISH.count("cli.errors", 3).unwrap(), "3 errors found");
+        assert_eq!(CZECH.count("cli.errors", 1).unwrap(), "nalezena 1 chyba");
+        assert_eq!(CZECH.count("cli.errors", 3).unwrap(), "nalezeny 3 chyby");
+        assert_eq!(CZECH.count("cli.errors", 7).unwrap(), "nalezeno 7 chyb");
+    }
+}
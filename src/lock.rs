@@ -0,0 +1,215 @@
+//! Locked regions of starter code.
+//!
+//! Some starter files carry code the student must leave alone: a harness
+//! that sets the stage, a procedure the rubric grades around. The teacher
+//! fences it off with marker comments —
+//!
+//! ```text
+//! #! lock begin
+//! def harness
+//!  move
+//! enddef
+//! #! lock end
+//! ```
+//!
+//! — and [`verify`] checks a submission against the starter's fences. The
+//! comparison works on preprocessed statements, not raw text, so students
+//! may reindent, comment, or move a locked region without tripping it; only
+//! changing, deleting or reordering the locked statements themselves is a
+//! violation. The markers are ordinary comments to everything else, so
+//! locked files run, check and grade unchanged.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::parser;
+
+/// What went wrong with a locked starter file or a submission against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockError {
+    /// `#! lock begin` inside an already open lock.
+    NestedBegin { line: usize },
+    /// `#! lock end` with no open lock.
+    UnmatchedEnd { line: usize },
+    /// `#! lock begin` that is never closed.
+    UnclosedBegin { line: usize },
+    /// The submission no longer contains this locked region unmodified.
+    RegionModified { start: usize, end: usize },
+}
+
+impl core::fmt::Display for LockError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LockError::NestedBegin { line } => {
+                write!(f, "line {line}: `#! lock begin` inside a locked region")
+            }
+            LockError::UnmatchedEnd { line } => {
+                write!(f, "line {line}: `#! lock end` without a `#! lock begin`")
+            }
+            LockError::UnclosedBegin { line } => {
+                write!(f, "line {line}: `#! lock begin` is never closed")
+            }
+            LockError::RegionModified { start, end } => write!(
+                f,
+                "the locked region at lines {start}-{end} of the starter code was modified"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LockError {}
+
+/// One locked region of a starter file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// The marker lines fencing the region in the starter, 1-based.
+    pub start: usize,
+    pub end: usize,
+    /// The region's statements in canonical form: preprocessed, so
+    /// indentation and comments are already gone.
+    pub statements: Vec<String>,
+}
+
+/// The locked regions a starter file declares, in order.
+pub fn regions(starter: &str) -> Result<Vec<Region>, LockError> {
+    let mut regions = Vec::new();
+    let mut open: Option<usize> = None;
+    for (index, line) in starter.lines().enumerate() {
+        let number = index + 1;
+        match line.trim() {
+            "#! lock begin" => match open {
+                None => open = Some(number),
+                Some(_) => return Err(LockError::NestedBegin { line: number }),
+            },
+            "#! lock end" => match open.take() {
+                Some(start) => regions.push(Region {
+                    start,
+                    end: number,
+                    statements: Vec::new(),
+                }),
+                None => return Err(LockError::UnmatchedEnd { line: number }),
+            },
+            _ => {}
+        }
+    }
+    if let Some(line) = open {
+        return Err(LockError::UnclosedBegin { line });
+    }
+    for statement in parser::preprocess(starter) {
+        if let Some(region) = regions
+            .iter_mut()
+            .find(|region| region.start < statement.number && statement.number < region.end)
+        {
+            region.statements.push(statement.text.to_string());
+        }
+    }
+    Ok(regions)
+}
+
+/// Check that a submission still contains every locked region of the
+/// starter, statement for statement. A region counts as intact when its
+/// statement sequence appears contiguously somewhere in the submission, so
+/// everything outside the fences stays the student's to rearrange.
+pub fn verify(starter: &str, submission: &str) -> Result<(), LockError> {
+    let regions = regions(starter)?;
+    let statements: Vec<String> = parser::preprocess(submission)
+        .into_iter()
+        .map(|line| line.text.to_string())
+        .collect();
+    for region in regions {
+        if !contains_run(&statements, &region.statements) {
+            return Err(LockError::RegionModified {
+                start: region.start,
+                end: region.end,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Does `haystack` contain `needle` as a contiguous run?
+fn contains_run(haystack: &[String], needle: &[String]) -> bool {
+    needle.is_empty()
+        || haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTER: &str = "#! lock begin\n\
+                           def harness\n\
+                           \x20move\n\
+                           \x20call solve\n\
+                           enddef\n\
+                           #! lock end\n\
+                           def solve\n\
+                           \x20# your code here\n\
+                           enddef";
+
+    #[test]
+    fn reformatting_a_locked_region_is_not_a_modification() {
+        let submission = "def solve\n\
+                          \x20take\n\
+                          enddef\n\
+                          # the harness, reindented and commented\n\
+                          def harness\n\
+                          \x20  move  # forward!\n\
+                          \x20  call solve\n\
+                          enddef";
+        assert_eq!(verify(STARTER, submission), Ok(()));
+    }
+
+    #[test]
+    fn editing_a_locked_statement_is_rejected() {
+        let submission = "def harness\n\
+                          \x20move\n\
+                          \x20move\n\
+                          \x20call solve\n\
+                          enddef\n\
+                          def solve\n\
+                          enddef";
+        assert_eq!(
+            verify(STARTER, submission),
+            Err(LockError::RegionModified { start: 1, end: 6 })
+        );
+    }
+
+    #[test]
+    fn deleting_a_locked_region_is_rejected() {
+        let error = verify(STARTER, "def solve\n take\nenddef").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the locked region at lines 1-6 of the starter code was modified"
+        );
+    }
+
+    #[test]
+    fn lock_markers_must_pair_up() {
+        assert_eq!(
+            regions("#! lock begin\ndef main\nenddef"),
+            Err(LockError::UnclosedBegin { line: 1 })
+        );
+        assert_eq!(
+            regions("def main\nenddef\n#! lock end"),
+            Err(LockError::UnmatchedEnd { line: 3 })
+        );
+        assert_eq!(
+            regions("#! lock begin\n#! lock begin\n#! lock end"),
+            Err(LockError::NestedBegin { line: 2 })
+        );
+    }
+
+    #[test]
+    fn regions_carry_their_canonical_statements() {
+        let regions = regions(STARTER).unwrap();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(
+            regions[0].statements,
+            ["def harness", "move", "call solve", "enddef"]
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Structured logging hooks in the span/event shape of the `tracing`
+//! ecosystem.
+//!
+//! The crate stays dependency-free, so this module does not link against
+//! `tracing` itself. Instead an embedder installs one process-wide
+//! [`Subscriber`]; every interpreter then opens a span per procedure call
+//! and emits an event per action, which is exactly the surface needed to
+//! forward into `tracing`, `log`, or a grading service's own pipeline.
+//! With the `tracing` feature disabled (the default) none of the hooks
+//! exist and the hot loop is unchanged.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Receives spans and events from running interpreters.
+///
+/// Implementations must be cheap and must not allocate needlessly: the
+/// hooks run inside the interpreter's hot loop. Spans are strictly nested,
+/// so a subscriber can keep a plain stack per thread of interest.
+pub trait Subscriber: Send + Sync {
+    /// A source was checked: `lines` of code produced `diagnostics` problems.
+    fn checked(&self, _lines: usize, _diagnostics: usize) {}
+
+    /// A `call` reached its target: a span for `procedure` opens.
+    fn enter_span(&self, procedure: &str, line: usize);
+
+    /// The procedure returned through its `enddef`: the innermost span closes.
+    fn exit_span(&self);
+
+    /// One action executed against the environment.
+    fn event(&self, action: crate::environment::Action, line: usize);
+}
+
+static SUBSCRIBER: OnceLock<&'static dyn Subscriber> = OnceLock::new();
+
+/// Install the process-wide subscriber.
+///
+/// Like `log::set_logger`, installation happens at most once per process;
+/// a second call reports [`SubscriberSetError`] and changes nothing.
+pub fn set_subscriber(subscriber: &'static dyn Subscriber) -> Result<(), SubscriberSetError> {
+    SUBSCRIBER.set(subscriber).map_err(|_| SubscriberSetError)
+}
+
+/// The installed subscriber, if any.
+pub(crate) fn subscriber() -> Option<&'static dyn Subscriber> {
+    SUBSCRIBER.get().copied()
+}
+
+/// A subscriber was already installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriberSetError;
+
+impl fmt::Display for SubscriberSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a logging subscriber was already installed")
+    }
+}
+
+impl std::error::Error for SubscriberSetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Action;
+    use crate::interpreter::Interpreter;
+    use crate::parser;
+    use crate::world::World;
+    use std::cell::Cell;
+    use std::sync::Mutex;
+
+    std::thread_local! {
+        // Tests share the process-wide subscriber; only the thread that
+        // installed it records, so parallel tests stay unaffected.
+        static RECORDING: Cell<bool> = const { Cell::new(false) };
+    }
+
+    struct Recorder {
+        entries: Mutex<Vec<String>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn checked(&self, lines: usize, diagnostics: usize) {
+            if RECORDING.get() {
+                let entry = format!("checked {lines} lines, {diagnostics} diagnostics");
+                self.entries.lock().unwrap().push(entry);
+            }
+        }
+
+        fn enter_span(&self, procedure: &str, line: usize) {
+            if RECORDING.get() {
+                self.entries.lock().unwrap().push(format!("enter {procedure} at {line}"));
+            }
+        }
+
+        fn exit_span(&self) {
+            if RECORDING.get() {
+                self.entries.lock().unwrap().push("exit".to_string());
+            }
+        }
+
+        fn event(&self, action: Action, line: usize) {
+            if RECORDING.get() {
+                self.entries.lock().unwrap().push(format!("{action:?} at {line}"));
+            }
+        }
+    }
+
+    static RECORDER: Recorder = Recorder { entries: Mutex::new(Vec::new()) };
+
+    #[test]
+    fn spans_and_events_reach_the_subscriber() {
+        // A single test owns the whole lifecycle: installation is
+        // process-wide and can only happen once.
+        set_subscriber(&RECORDER).unwrap();
+        assert_eq!(set_subscriber(&RECORDER), Err(SubscriberSetError));
+
+        RECORDING.set(true);
+        let source = "def main\n call twice\n die\nenddef\ndef twice\n move\n move\nenddef";
+        let lines = parser::preprocess(source);
+        parser::validate(&lines).unwrap();
+        let mut interpreter = Interpreter::new(lines, World::new(5, 5)).unwrap();
+        interpreter.run().into_result().unwrap();
+        RECORDING.set(false);
+
+        let entries = RECORDER.entries.lock().unwrap();
+        assert_eq!(
+            *entries,
+            vec![
+                "checked 8 lines, 0 diagnostics".to_string(),
+                "enter twice at 2".to_string(),
+                "Move at 6".to_string(),
+                "Move at 7".to_string(),
+                "exit".to_string(),
+                "Die at 3".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,674 @@
+//! A Language Server Protocol server for Karel programs.
+//!
+//! Speaks JSON-RPC over stdio with the standard `Content-Length` framing.
+//! Documents are synced whole, but each one is backed by
+//! [`parser::Incremental`], which diffs the new text against the old and
+//! re-tokenizes only the changed lines — a one-line edit in a large lesson
+//! file stays cheap. The server offers diagnostics, go-to-definition for
+//! `call` targets, document symbols for `def`s, hover docs for keywords and
+//! completion. The protocol plumbing lives in [`serve`]; everything below
+//! [`Server::handle`] is pure and tested without any I/O.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::json::{self, Value};
+use crate::lint;
+use crate::parser;
+
+/// Hover documentation for every keyword and condition of the language.
+const KEYWORD_DOCS: &[(&str, &str)] = &[
+    ("def", "`def name` starts a procedure definition, ended by `enddef`."),
+    ("enddef", "Ends a `def` block. Acts as a return when reached."),
+    ("move", "Move one tile forward. Fatal if a wall is in the way."),
+    ("turn-left", "Turn 90 degrees to the left."),
+    ("take", "Take one beeper from the current tile. Fatal if there is none."),
+    ("put", "Put one beeper on the current tile. Fatal if it already holds 8."),
+    ("beep", "Emit one beep from the current tile. Goals can count beeps."),
+    ("die", "Switch the robot off; the program ends here."),
+    ("call", "`call name` runs the procedure `name` and returns."),
+    ("if", "`if condition` runs the block up to `endif` when the condition holds. `if!` negates it."),
+    ("endif", "Ends an `if` block."),
+    ("while", "`while condition` repeats the block up to `endwhile`. `while!` negates the condition."),
+    ("endwhile", "Ends a `while` block."),
+    ("repeat", "`repeat n` runs the block up to `endrepeat` exactly `n` times."),
+    ("endrepeat", "Ends a `repeat` block."),
+    ("wall", "Condition: is there a wall (or the world edge) directly ahead?"),
+    ("north", "Condition: is the robot facing north?"),
+    ("south", "Condition: is the robot facing south?"),
+    ("east", "Condition: is the robot facing east?"),
+    ("west", "Condition: is the robot facing west?"),
+    ("beeper", "Condition: is there at least one beeper on the current tile?"),
+];
+
+/// An open document: the raw text for positional requests, and the
+/// incrementally maintained preprocessed lines for diagnostics.
+struct Document {
+    text: String,
+    parsed: parser::Incremental,
+}
+
+impl Document {
+    fn new(text: String) -> Document {
+        Document {
+            parsed: parser::Incremental::new(&text),
+            text,
+        }
+    }
+
+    fn update(&mut self, text: String) {
+        self.parsed.update(&text);
+        self.text = text;
+    }
+}
+
+/// The language server: open documents plus the request dispatch.
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown_requested: bool,
+}
+
+impl Default for Server {
+    fn default() -> Server {
+        Server::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server {
+            documents: HashMap::new(),
+            shutdown_requested: false,
+        }
+    }
+
+    /// Should the serve loop exit?
+    pub fn exited(&self) -> bool {
+        self.shutdown_requested
+    }
+
+    /// Handle one incoming message and return the messages to send back
+    /// (a response for requests, possibly a diagnostics notification).
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Value::Object(entry) = message else {
+            return Vec::new();
+        };
+        let method = match entry.get("method") {
+            Some(Value::String(method)) => method.as_str(),
+            _ => return Vec::new(),
+        };
+        let id = entry.get("id").cloned();
+        let params = entry.get("params");
+
+        match method {
+            "initialize" => vec![response(id, initialize_result())],
+            "shutdown" => vec![response(id, Value::Null)],
+            "exit" => {
+                self.shutdown_requested = true;
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let Some((uri, text)) = open_params(params) else {
+                    return Vec::new();
+                };
+                self.documents.insert(uri.clone(), Document::new(text));
+                vec![self.diagnostics_notification(&uri)]
+            }
+            "textDocument/didChange" => {
+                let Some((uri, text)) = change_params(params) else {
+                    return Vec::new();
+                };
+                match self.documents.get_mut(&uri) {
+                    Some(document) => document.update(text),
+                    None => {
+                        self.documents.insert(uri.clone(), Document::new(text));
+                    }
+                }
+                vec![self.diagnostics_notification(&uri)]
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = document_uri(params) {
+                    self.documents.remove(&uri);
+                }
+                Vec::new()
+            }
+            "textDocument/definition" => {
+                let result = self
+                    .with_position(params, definition)
+                    .unwrap_or(Value::Null);
+                vec![response(id, result)]
+            }
+            "textDocument/documentSymbol" => {
+                let result = document_uri(params)
+                    .and_then(|uri| {
+                        self.documents
+                            .get(&uri)
+                            .map(|document| document_symbols(&uri, &document.text))
+                    })
+                    .unwrap_or(Value::Array(Vec::new()));
+                vec![response(id, result)]
+            }
+            "textDocument/hover" => {
+                let result = self.with_position(params, hover).unwrap_or(Value::Null);
+                vec![response(id, result)]
+            }
+            "textDocument/completion" => {
+                let result = document_uri(params)
+                    .and_then(|uri| {
+                        self.documents
+                            .get(&uri)
+                            .map(|document| completions(&document.text))
+                    })
+                    .unwrap_or(Value::Array(Vec::new()));
+                vec![response(id, result)]
+            }
+            // Unknown requests (with an id) get an empty result so clients
+            // do not wait forever; notifications are simply ignored.
+            _ => match id {
+                Some(id) => vec![response(Some(id), Value::Null)],
+                None => Vec::new(),
+            },
+        }
+    }
+
+    /// Run `f(uri text, line, character)` for a positional request.
+    fn with_position(
+        &self,
+        params: Option<&Value>,
+        f: impl Fn(&str, &str, usize, usize) -> Option<Value>,
+    ) -> Option<Value> {
+        let uri = document_uri(params)?;
+        let text = &self.documents.get(&uri)?.text;
+        let Some(Value::Object(params)) = params else {
+            return None;
+        };
+        let Some(Value::Object(position)) = params.get("position") else {
+            return None;
+        };
+        let line = number_field(position.get("line"))?;
+        let character = number_field(position.get("character"))?;
+        f(&uri, text, line, character)
+    }
+
+    fn diagnostics_notification(&self, uri: &str) -> Value {
+        let diagnostics = self
+            .documents
+            .get(uri)
+            .map(|document| {
+                let lines = document.parsed.lines();
+                let mut values = diagnostic_values(parser::check(&lines));
+                values.extend(lint_values(lint::lint(&lines, &lint::Config::default())));
+                values
+            })
+            .unwrap_or_default();
+        Value::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Value::object([
+                    ("uri", uri.into()),
+                    ("diagnostics", Value::Array(diagnostics)),
+                ]),
+            ),
+        ])
+    }
+}
+
+fn initialize_result() -> Value {
+    Value::object([(
+        "capabilities",
+        Value::object([
+            // 1 = full document sync on every change.
+            ("textDocumentSync", 1usize.into()),
+            ("definitionProvider", true.into()),
+            ("documentSymbolProvider", true.into()),
+            ("hoverProvider", true.into()),
+            ("completionProvider", Value::object([])),
+        ]),
+    )])
+}
+
+fn response(id: Option<Value>, result: Value) -> Value {
+    Value::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id.unwrap_or(Value::Null)),
+        ("result", result),
+    ])
+}
+
+fn number_field(value: Option<&Value>) -> Option<usize> {
+    match value {
+        Some(Value::Number(number)) if *number >= 0.0 => Some(*number as usize),
+        _ => None,
+    }
+}
+
+fn document_uri(params: Option<&Value>) -> Option<String> {
+    let Some(Value::Object(params)) = params else {
+        return None;
+    };
+    let Some(Value::Object(document)) = params.get("textDocument") else {
+        return None;
+    };
+    match document.get("uri") {
+        Some(Value::String(uri)) => Some(uri.clone()),
+        _ => None,
+    }
+}
+
+fn open_params(params: Option<&Value>) -> Option<(String, String)> {
+    let Some(Value::Object(params)) = params else {
+        return None;
+    };
+    let Some(Value::Object(document)) = params.get("textDocument") else {
+        return None;
+    };
+    match (document.get("uri"), document.get("text")) {
+        (Some(Value::String(uri)), Some(Value::String(text))) => {
+            Some((uri.clone(), text.clone()))
+        }
+        _ => None,
+    }
+}
+
+fn change_params(params: Option<&Value>) -> Option<(String, String)> {
+    let uri = document_uri(params)?;
+    let Some(Value::Object(params)) = params else {
+        return None;
+    };
+    // Full sync: the last content change carries the whole text.
+    let Some(Value::Array(changes)) = params.get("contentChanges") else {
+        return None;
+    };
+    let Some(Value::Object(change)) = changes.last() else {
+        return None;
+    };
+    match change.get("text") {
+        Some(Value::String(text)) => Some((uri, text.clone())),
+        _ => None,
+    }
+}
+
+/// An LSP range covering `length` characters of a (1-based) line/column.
+fn lsp_range(line: usize, column: usize, length: usize) -> Value {
+    let position = |character: usize| {
+        Value::object([
+            ("line", line.saturating_sub(1).into()),
+            ("character", character.into()),
+        ])
+    };
+    Value::object([
+        ("start", position(column.saturating_sub(1))),
+        ("end", position(column.saturating_sub(1) + length)),
+    ])
+}
+
+/// The validation pass and the style linter as LSP diagnostics.
+pub fn diagnostics(text: &str) -> Vec<Value> {
+    let lines = parser::preprocess(text);
+    let mut values = diagnostic_values(parser::check(&lines));
+    values.extend(lint_values(lint::lint(&lines, &lint::Config::default())));
+    values
+}
+
+/// Parser diagnostics rendered as LSP diagnostic objects.
+fn diagnostic_values(diagnostics: Vec<parser::Diagnostic>) -> Vec<Value> {
+    diagnostics
+        .into_iter()
+        .map(|diagnostic| {
+            let line = diagnostic.error.line().unwrap_or(1);
+            let length = quoted_token(&diagnostic.error.to_string())
+                .map(str::len)
+                .unwrap_or(1);
+            Value::object([
+                ("range", lsp_range(line, diagnostic.column, length)),
+                ("severity", 1usize.into()), // error
+                ("source", "karel".into()),
+                ("message", diagnostic.error.to_string().into()),
+            ])
+        })
+        .collect()
+}
+
+/// Style lints rendered as LSP diagnostic objects, warnings by default so
+/// they sit visibly below real errors.
+fn lint_values(lints: Vec<lint::Lint>) -> Vec<Value> {
+    lints
+        .into_iter()
+        .map(|lint| {
+            let severity = match lint.severity {
+                lint::Severity::Error => 1usize,
+                lint::Severity::Warning => 2usize,
+            };
+            Value::object([
+                ("range", lsp_range(lint.line, lint.column, 1)),
+                ("severity", severity.into()),
+                ("source", "karel-lint".into()),
+                ("code", lint.rule.name().into()),
+                ("message", lint.message.into()),
+            ])
+        })
+        .collect()
+}
+
+/// The token inside the first pair of backticks of an error message, which
+/// is the thing the diagnostic points at.
+fn quoted_token(message: &str) -> Option<&str> {
+    let start = message.find('`')? + 1;
+    let end = start + message[start..].find('`')?;
+    Some(&message[start..end])
+}
+
+/// The word covering `character` (0-based) on `line` (0-based), if any.
+fn word_at(text: &str, line: usize, character: usize) -> Option<&str> {
+    let line = text.lines().nth(line)?;
+    let is_word = |c: char| !c.is_whitespace() && c != '#';
+    let mut start = None;
+    for (offset, c) in line.char_indices() {
+        if is_word(c) {
+            if start.is_none() {
+                start = Some(offset);
+            }
+            if offset >= character {
+                // Scan on to the end of this word.
+                let end = line[offset..]
+                    .find(|c: char| !is_word(c))
+                    .map(|length| offset + length)
+                    .unwrap_or(line.len());
+                return start.map(|start| &line[start..end]).filter(|_| {
+                    // Only when the cursor is actually inside the word.
+                    character >= start.unwrap()
+                });
+            }
+        } else {
+            if let Some(start_offset) = start {
+                if character < offset && character >= start_offset {
+                    return Some(&line[start_offset..offset]);
+                }
+            }
+            start = None;
+        }
+    }
+    None
+}
+
+/// Go-to-definition: on a `call target` line (or any occurrence of a defined
+/// procedure name), jump to its `def` line.
+fn definition(uri: &str, text: &str, line: usize, character: usize) -> Option<Value> {
+    let word = word_at(text, line, character)?;
+    for (index, source_line) in text.lines().enumerate() {
+        let mut words = source_line.split_whitespace();
+        if words.next() == Some("def") && words.next() == Some(word) {
+            let column = source_line.find(word).unwrap_or(0);
+            return Some(Value::object([
+                ("uri", uri.into()),
+                ("range", lsp_range(index + 1, column + 1, word.len())),
+            ]));
+        }
+    }
+    None
+}
+
+/// Document symbols: one Function symbol per `def`.
+fn document_symbols(uri: &str, text: &str) -> Value {
+    let mut symbols = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("def") {
+            continue;
+        }
+        let Some(name) = words.next() else { continue };
+        let column = line.find(name).unwrap_or(0);
+        symbols.push(Value::object([
+            ("name", name.into()),
+            ("kind", 12usize.into()), // Function
+            (
+                "location",
+                Value::object([
+                    ("uri", uri.into()),
+                    ("range", lsp_range(index + 1, column + 1, name.len())),
+                ]),
+            ),
+        ]));
+    }
+    Value::Array(symbols)
+}
+
+/// Hover: documentation for the keyword or condition under the cursor.
+fn hover(_uri: &str, text: &str, line: usize, character: usize) -> Option<Value> {
+    let word = word_at(text, line, character)?;
+    let word = word.trim_end_matches('!');
+    let (_, docs) = KEYWORD_DOCS.iter().find(|(keyword, _)| *keyword == word)?;
+    Some(Value::object([(
+        "contents",
+        Value::object([("kind", "markdown".into()), ("value", (*docs).into())]),
+    )]))
+}
+
+/// Completion: all keywords, conditions and the procedures defined in the
+/// document.
+fn completions(text: &str) -> Value {
+    let mut items: Vec<Value> = KEYWORD_DOCS
+        .iter()
+        .map(|(keyword, docs)| {
+            Value::object([
+                ("label", (*keyword).into()),
+                ("kind", 14usize.into()), // Keyword
+                ("documentation", (*docs).into()),
+            ])
+        })
+        .collect();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() == Some("def") {
+            if let Some(name) = words.next() {
+                items.push(Value::object([
+                    ("label", name.into()),
+                    ("kind", 3usize.into()), // Function
+                ]));
+            }
+        }
+    }
+    Value::Array(items)
+}
+
+/// Read framed JSON-RPC messages from `reader` and write the replies to
+/// `writer` until the client sends `exit`.
+pub fn serve(reader: impl Read, mut writer: impl Write) -> io::Result<()> {
+    let mut reader = io::BufReader::new(reader);
+    let mut server = Server::new();
+    while !server.exited() {
+        let Some(message) = read_message(&mut reader)? else {
+            break; // client hung up
+        };
+        let Ok(message) = json::parse(&message) else {
+            continue;
+        };
+        for reply in server.handle(&message) {
+            write_message(&mut writer, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write one `Content-Length`-framed message. Shared with the DAP side,
+/// which uses the same framing.
+pub(crate) fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Read one `Content-Length`-framed message, or `None` at end of input.
+pub(crate) fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse().ok();
+        }
+    }
+    let Some(length) = content_length else {
+        return Ok(None);
+    };
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str =
+        "def main\n  call helper\n  fly\n  die\nenddef\n\ndef helper\n  turn-left\nenddef\n";
+
+    fn request(method: &str, params: Value) -> Value {
+        Value::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", 1usize.into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+
+    fn open_document(server: &mut Server) -> Vec<Value> {
+        server.handle(&Value::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/didOpen".into()),
+            (
+                "params",
+                Value::object([(
+                    "textDocument",
+                    Value::object([
+                        ("uri", "file:///p.kl".into()),
+                        ("text", PROGRAM.into()),
+                    ]),
+                )]),
+            ),
+        ]))
+    }
+
+    fn result_of(replies: &[Value]) -> &Value {
+        let Value::Object(reply) = &replies[0] else { panic!() };
+        &reply["result"]
+    }
+
+    #[test]
+    fn did_open_publishes_diagnostics() {
+        let mut server = Server::new();
+        let replies = open_document(&mut server);
+        let text = replies[0].to_string();
+        assert!(text.contains("publishDiagnostics"));
+        assert!(text.contains("unknown instruction `fly`"));
+    }
+
+    #[test]
+    fn definition_finds_the_called_procedure() {
+        let mut server = Server::new();
+        open_document(&mut server);
+        // Cursor on "helper" in "  call helper" (line 1, character 8).
+        let replies = server.handle(&request(
+            "textDocument/definition",
+            Value::object([
+                (
+                    "textDocument",
+                    Value::object([("uri", "file:///p.kl".into())]),
+                ),
+                (
+                    "position",
+                    Value::object([("line", 1usize.into()), ("character", 8usize.into())]),
+                ),
+            ]),
+        ));
+        let text = result_of(&replies).to_string();
+        // `def helper` is on line 6 (0-based).
+        assert!(text.contains("\"line\":6"), "{text}");
+    }
+
+    #[test]
+    fn document_symbols_lists_defs() {
+        let mut server = Server::new();
+        open_document(&mut server);
+        let replies = server.handle(&request(
+            "textDocument/documentSymbol",
+            Value::object([(
+                "textDocument",
+                Value::object([("uri", "file:///p.kl".into())]),
+            )]),
+        ));
+        let Value::Array(symbols) = result_of(&replies) else { panic!() };
+        assert_eq!(symbols.len(), 2);
+        assert!(symbols[0].to_string().contains("main"));
+        assert!(symbols[1].to_string().contains("helper"));
+    }
+
+    #[test]
+    fn hover_documents_keywords() {
+        let mut server = Server::new();
+        open_document(&mut server);
+        // Cursor on "die" (line 3, character 3).
+        let replies = server.handle(&request(
+            "textDocument/hover",
+            Value::object([
+                (
+                    "textDocument",
+                    Value::object([("uri", "file:///p.kl".into())]),
+                ),
+                (
+                    "position",
+                    Value::object([("line", 3usize.into()), ("character", 3usize.into())]),
+                ),
+            ]),
+        ));
+        assert!(result_of(&replies).to_string().contains("Switch the robot off"));
+    }
+
+    #[test]
+    fn completion_offers_keywords_and_procedures() {
+        let mut server = Server::new();
+        open_document(&mut server);
+        let replies = server.handle(&request(
+            "textDocument/completion",
+            Value::object([(
+                "textDocument",
+                Value::object([("uri", "file:///p.kl".into())]),
+            )]),
+        ));
+        let text = result_of(&replies).to_string();
+        assert!(text.contains("\"label\":\"while\""));
+        assert!(text.contains("\"label\":\"helper\""));
+    }
+
+    #[test]
+    fn serve_speaks_content_length_framing() {
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let input = format!(
+            "Content-Length: {}\r\n\r\n{}Content-Length: 44\r\n\r\n{}",
+            request.len(),
+            request,
+            r#"{"jsonrpc":"2.0","method":"exit","params":{}}"#,
+        );
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Content-Length:"));
+        assert!(output.contains("definitionProvider"));
+    }
+
+    #[test]
+    fn word_at_finds_words_and_rejects_gaps() {
+        let text = "  call helper\n";
+        assert_eq!(word_at(text, 0, 3), Some("call"));
+        assert_eq!(word_at(text, 0, 8), Some("helper"));
+        assert_eq!(word_at(text, 0, 1), None);
+    }
+}
@@ -0,0 +1,1631 @@
+//! The `karel` command line tool.
+
+use std::fs;
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
+
+use karel::{interpreter::Interpreter, locale, parser, render, worldfile, RenderStyle, World};
+
+const USAGE: &str = "\
+usage: karel <command> [arguments]
+
+commands:
+  run <program.kl> [--world <world.txt>]     run a program and print the final world
+  check <program.kl> [--strict] [--lang cs]  validate a program and print diagnostics
+  transpile <program.kl>                     print the program as a Python script
+  import <program.java>                      convert Java-style Karel to native source
+  watch <program.kl> [--world <world.txt>]   re-run the program whenever a file changes
+  repl [--world <world.txt>]                 drive the robot interactively
+  grade --task <task.toml> <file.kl>...      grade submissions against a task
+  contest --task <task.toml> <file.kl>...    rank submissions and print standings
+  edit <world.txt|world.json>                edit a world in the terminal
+  new <template> <directory>                 create a starter exercise (new --list)
+  play <campaign.toml> [--solution <f.kl>]   progress through a course of tasks
+  replay <trace.jsonl> [--delay <ms>] [--jump <step>]   play back a recorded trace
+  bench <program.kl> [--world <w.txt>] [--iterations <n>]   time repeated runs
+  lsp                                        run a language server on stdio
+  dap                                        run a debug adapter on stdio
+  race [--world <file>] [--port <n>]         host a shared world over TCP
+  serve --tasks <dir> [--port <n>]           grade submissions over HTTP
+
+options:
+  --world <file>          world to run in (default: empty 10x10 world)
+  --ascii                 force plain ASCII output
+  --format <human|json>   output for people (default) or for scripts
+  --strict                also reject trailing tokens on statements (check only)
+  --dialect <hash|slashes>   comment syntax of the program (run, check)
+  --lang <en|cs>          explain errors in this language (run, check;
+                          default: $KAREL_LANG, or the terse English messages)
+  --bell                  ring the terminal bell once per `beep` (run only)
+  --profile               report per-line and per-procedure cost (run only)
+  --folded <file>         write folded call stacks for flamegraph tools (run only)
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(command) = args.first() else {
+        eprint!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    match command.as_str() {
+        "run" => run(&args[1..]),
+        "check" => check(&args[1..]),
+        "transpile" => transpile(&args[1..]),
+        "import" => import(&args[1..]),
+        "watch" => watch(&args[1..]),
+        "repl" => repl(&args[1..]),
+        "grade" => grade(&args[1..]),
+        "contest" => contest(&args[1..]),
+        "edit" => edit(&args[1..]),
+        "new" => new(&args[1..]),
+        "play" => play(&args[1..]),
+        "replay" => replay(&args[1..]),
+        "bench" => bench(&args[1..]),
+        "lsp" => lsp(&args[1..]),
+        "dap" => dap(&args[1..]),
+        "race" => race(&args[1..]),
+        "serve" => serve(&args[1..]),
+        "--help" | "-h" | "help" => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        other => {
+            eprintln!("karel: unknown command `{other}`");
+            eprint!("{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// How a subcommand should talk to the outside world: text for people, or
+/// one JSON document on stdout for scripts and grading pipelines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+fn parse_format(value: Option<&String>) -> Result<OutputFormat, ExitCode> {
+    match value.map(String::as_str) {
+        Some("human") => Ok(OutputFormat::Human),
+        Some("json") => Ok(OutputFormat::Json),
+        _ => Err(usage_error("--format takes `human` or `json`")),
+    }
+}
+
+fn parse_dialect(value: Option<&String>) -> Result<parser::Dialect, ExitCode> {
+    value
+        .and_then(|name| parser::Dialect::from_name(name))
+        .ok_or_else(|| usage_error("--dialect takes `hash` or `slashes`"))
+}
+
+fn parse_lang(value: Option<&String>) -> Result<&'static locale::Catalog, ExitCode> {
+    value
+        .and_then(|name| locale::Catalog::from_name(name))
+        .ok_or_else(|| usage_error("--lang takes `en` or `cs`"))
+}
+
+/// The catalog `$KAREL_LANG` asks for, if it asks for one we ship. Unset
+/// (or unknown) means the terse English messages, unchanged.
+fn default_lang() -> Option<&'static locale::Catalog> {
+    std::env::var("KAREL_LANG")
+        .ok()
+        .and_then(|name| locale::Catalog::from_name(&name))
+}
+
+/// Print a localized explanation of `feedback` on stderr: what happened,
+/// then one `help:` line (both in the catalog's language) saying what to
+/// try.
+fn explain_on_stderr(catalog: &locale::Catalog, feedback: &karel::feedback::Feedback) {
+    let (what, advice) = catalog.explain(feedback);
+    eprintln!("karel: {what}");
+    let help = catalog.get("cli.help").unwrap_or("help");
+    eprintln!("karel: {help}: {advice}");
+}
+
+/// The arguments shared by `run` and `watch`.
+struct RunArgs<'a> {
+    program_path: &'a str,
+    world_path: Option<&'a str>,
+    trace_path: Option<&'a str>,
+    style: RenderStyle,
+    format: OutputFormat,
+    /// Ring the terminal bell once per `beep` the program emitted.
+    bell: bool,
+    /// Report where the steps went after the run.
+    profile: bool,
+    /// Write the run's folded call stacks here, for flamegraph tools.
+    folded_path: Option<&'a str>,
+    /// Comment syntax of the program file.
+    dialect: parser::Dialect,
+    /// Explain errors through this catalog instead of the terse messages.
+    lang: Option<&'static locale::Catalog>,
+}
+
+fn parse_run_args(args: &[String]) -> Result<RunArgs<'_>, ExitCode> {
+    let mut program_path: Option<&str> = None;
+    let mut world_path: Option<&str> = None;
+    let mut trace_path: Option<&str> = None;
+    let mut style = RenderStyle::detect();
+    let mut format = OutputFormat::Human;
+    let mut bell = false;
+    let mut profile = false;
+    let mut folded_path: Option<&str> = None;
+    let mut dialect = parser::Dialect::default();
+    let mut lang = default_lang();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--world" => match args.next() {
+                Some(path) => world_path = Some(path),
+                None => return Err(usage_error("--world needs a file")),
+            },
+            "--trace" => match args.next() {
+                Some(path) => trace_path = Some(path),
+                None => return Err(usage_error("--trace needs a file")),
+            },
+            "--format" => format = parse_format(args.next())?,
+            "--ascii" => style = RenderStyle::Ascii,
+            "--bell" => bell = true,
+            "--profile" => profile = true,
+            "--folded" => match args.next() {
+                Some(path) => folded_path = Some(path),
+                None => return Err(usage_error("--folded needs a file")),
+            },
+            "--dialect" => dialect = parse_dialect(args.next())?,
+            "--lang" => lang = Some(parse_lang(args.next())?),
+            _ if program_path.is_none() && !arg.starts_with('-') => {
+                program_path = Some(arg);
+            }
+            other => return Err(usage_error(&format!("unexpected argument `{other}`"))),
+        }
+    }
+    match program_path {
+        Some(program_path) => Ok(RunArgs {
+            program_path,
+            world_path,
+            trace_path,
+            style,
+            format,
+            bell,
+            profile,
+            folded_path,
+            dialect,
+            lang,
+        }),
+        None => Err(usage_error("no program file given")),
+    }
+}
+
+fn run(args: &[String]) -> ExitCode {
+    match parse_run_args(args) {
+        Ok(args) => run_once(&args),
+        Err(code) => code,
+    }
+}
+
+/// Load, validate and execute the program, printing the resulting world (or
+/// a JSON run report, depending on the output format).
+fn run_once(args: &RunArgs<'_>) -> ExitCode {
+    let source = match fs::read_to_string(args.program_path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("karel: cannot read `{}`: {error}", args.program_path);
+            return ExitCode::from(2);
+        }
+    };
+    let world = match load_world(args.world_path) {
+        Ok(world) => world,
+        Err(code) => return code,
+    };
+
+    let lines = parser::preprocess_dialect(&source, args.dialect);
+    let validation = parser::validate(&lines).and_then(|()| {
+        // MissingMain is the only error `new` can report and validation
+        // already rules it out, but belt and braces.
+        Interpreter::new(lines.clone(), world).map_err(|_| karel::ParseError::MissingMain)
+    });
+    let mut interpreter = match validation {
+        Ok(interpreter) => interpreter,
+        Err(error) => {
+            if args.format == OutputFormat::Json {
+                let report = karel::json::Value::object([
+                    ("result", karel::json::Value::from("parse-error")),
+                    ("error", karel::json::Value::from(error.to_string())),
+                    ("line", error.line().into()),
+                ]);
+                println!("{report}");
+            } else {
+                match error.line() {
+                    Some(line) => eprintln!("karel: {}:{line}: {error}", args.program_path),
+                    None => eprintln!("karel: {}: {error}", args.program_path),
+                }
+                if let Some(catalog) = args.lang {
+                    explain_on_stderr(catalog, &karel::feedback::syntax(&error));
+                }
+            }
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut profiler =
+        (args.profile || args.folded_path.is_some()).then(karel::profile::Profiler::new);
+    let (result, steps) = match args.trace_path {
+        None => execute(&mut interpreter, None, profiler.as_mut()),
+        Some(trace_path) => {
+            let file = match fs::File::create(trace_path) {
+                Ok(file) => file,
+                Err(error) => {
+                    eprintln!("karel: cannot write `{trace_path}`: {error}");
+                    return ExitCode::from(2);
+                }
+            };
+            match karel::trace::Recorder::new(file, &interpreter.world)
+                .map(|recorder| execute(&mut interpreter, Some(recorder), profiler.as_mut()))
+            {
+                Ok(outcome) => outcome,
+                Err(error) => {
+                    eprintln!("karel: cannot write `{trace_path}`: {error}");
+                    return ExitCode::from(2);
+                }
+            }
+        }
+    };
+
+    let output = interpreter.take_output();
+    let profile = profiler.map(|profiler| {
+        profiler.finish(result.as_ref().err().map(|error| error.to_string()))
+    });
+    if let (Some(folded_path), Some(profile)) = (args.folded_path, &profile) {
+        if let Err(error) = fs::write(folded_path, profile.folded()) {
+            eprintln!("karel: cannot write `{folded_path}`: {error}");
+            return ExitCode::from(2);
+        }
+    }
+    if args.format == OutputFormat::Json {
+        let mut fields = vec![
+            (
+                "result",
+                karel::json::Value::from(match &result {
+                    Ok(()) => "ok",
+                    Err(_) => "runtime-error",
+                }),
+            ),
+            (
+                "output",
+                karel::json::Value::Array(
+                    output.into_iter().map(karel::json::Value::from).collect(),
+                ),
+            ),
+            (
+                "beeps",
+                karel::json::Value::Array(
+                    interpreter
+                        .world
+                        .beeps()
+                        .iter()
+                        .map(|beep| karel::json::Value::from(vec![beep.x, beep.y]))
+                        .collect(),
+                ),
+            ),
+            (
+                "error",
+                result
+                    .as_ref()
+                    .err()
+                    .map(|error| error.to_string())
+                    .into(),
+            ),
+            ("steps", karel::json::Value::from(steps)),
+            ("world", worldfile::to_json(&interpreter.world)),
+        ];
+        if let (true, Some(profile)) = (args.profile, &profile) {
+            fields.push(("profile", profile_json(profile)));
+        }
+        let report = karel::json::Value::object(fields);
+        println!("{report}");
+    } else {
+        for line in output {
+            println!("{line}");
+        }
+        if args.bell {
+            for _ in interpreter.world.beeps() {
+                print!("\u{7}");
+            }
+        }
+        print!("{}", render(&interpreter.world, args.style));
+        if let (true, Some(profile)) = (args.profile, &profile) {
+            print!("{}", profile.report());
+        }
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            if args.format == OutputFormat::Human {
+                eprintln!("karel: runtime error: {error}");
+                if let Some(catalog) = args.lang {
+                    explain_on_stderr(catalog, &karel::feedback::runtime(&error));
+                }
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the interpreter to the end, counting executed instructions and
+/// optionally recording every step into a trace.
+fn execute(
+    interpreter: &mut Interpreter,
+    mut recorder: Option<karel::trace::Recorder<fs::File>>,
+    mut profiler: Option<&mut karel::profile::Profiler>,
+) -> (Result<(), karel::RuntimeError>, usize) {
+    let mut result = Ok(());
+    let mut steps = 0usize;
+    while !interpreter.finished() {
+        let line = interpreter.current_line().unwrap_or(0);
+        let instruction = interpreter
+            .current_instruction()
+            .unwrap_or_default()
+            .to_string();
+        if let Some(profiler) = profiler.as_mut() {
+            profiler.before(interpreter);
+        }
+        let stepped = interpreter.step();
+        if let Some(profiler) = profiler.as_mut() {
+            profiler.after();
+        }
+        steps += 1;
+        if let Some(active) = recorder.as_mut() {
+            if let Err(error) = active.record(line, &instruction, &interpreter.world) {
+                eprintln!("karel: trace write failed: {error}");
+                recorder = None;
+            }
+        }
+        if let Err(error) = stepped {
+            result = Err(error);
+            break;
+        }
+    }
+    if let Some(recorder) = recorder.as_mut() {
+        let _ = recorder
+            .finish(result.as_ref().err().map(|error| error.to_string()).as_deref());
+    }
+    (result, steps)
+}
+
+/// The per-line and per-procedure breakdown for the JSON run report.
+fn profile_json(profile: &karel::profile::Profile) -> karel::json::Value {
+    use karel::json::Value;
+    Value::object([
+        ("steps", Value::from(profile.total.steps)),
+        (
+            "procedures",
+            Value::Array(
+                profile
+                    .by_procedure()
+                    .into_iter()
+                    .map(|(name, sample)| {
+                        Value::object([
+                            ("name", Value::from(name)),
+                            ("steps", Value::from(sample.steps)),
+                            ("percent", Value::from(profile.percentage(sample))),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        (
+            "lines",
+            Value::Array(
+                profile
+                    .by_line()
+                    .into_iter()
+                    .map(|(line, sample)| {
+                        Value::object([
+                            ("line", Value::from(line)),
+                            ("steps", Value::from(sample.steps)),
+                            ("percent", Value::from(profile.percentage(sample))),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+fn load_world(world_path: Option<&str>) -> Result<World, ExitCode> {
+    let Some(path) = world_path else {
+        return Ok(World::default());
+    };
+    worldfile::load(std::path::Path::new(path)).map_err(|error| {
+        eprintln!("karel: {path}: {error}");
+        ExitCode::from(2)
+    })
+}
+
+/// `karel check`: run the static validation pass and print every diagnostic
+/// in the rustc-like `error: ... --> file:line:column` format, so the output
+/// can be consumed by editors as an external linter.
+fn check(args: &[String]) -> ExitCode {
+    let mut program_path: Option<&str> = None;
+    let mut format = OutputFormat::Human;
+    let mut strict = false;
+    let mut dialect = parser::Dialect::default();
+    let mut lang = default_lang();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match parse_format(args.next()) {
+                Ok(parsed) => format = parsed,
+                Err(code) => return code,
+            },
+            "--strict" => strict = true,
+            "--dialect" => match parse_dialect(args.next()) {
+                Ok(parsed) => dialect = parsed,
+                Err(code) => return code,
+            },
+            "--lang" => match parse_lang(args.next()) {
+                Ok(parsed) => lang = Some(parsed),
+                Err(code) => return code,
+            },
+            _ if program_path.is_none() && !arg.starts_with('-') => program_path = Some(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(program_path) = program_path else {
+        return usage_error("no program file given");
+    };
+    let source = match fs::read_to_string(program_path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("karel: cannot read `{program_path}`: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let lines = parser::preprocess_dialect(&source, dialect);
+    let diagnostics = if strict { parser::check_strict(&lines) } else { parser::check(&lines) };
+    match format {
+        OutputFormat::Json => {
+            let report = karel::json::Value::Array(
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| {
+                        karel::json::Value::object([
+                            ("message", diagnostic.error.to_string().into()),
+                            ("line", diagnostic.error.line().into()),
+                            ("column", diagnostic.column.into()),
+                        ])
+                    })
+                    .collect(),
+            );
+            println!("{report}");
+        }
+        OutputFormat::Human => {
+            for diagnostic in &diagnostics {
+                let localized = lang.map(|catalog| {
+                    (catalog, catalog.explain(&karel::feedback::syntax(&diagnostic.error)))
+                });
+                match &localized {
+                    None => println!("error: {}", diagnostic.error),
+                    Some((_, (what, _))) => println!("error: {what}"),
+                }
+                if let Some(line) = diagnostic.error.line() {
+                    println!(" --> {program_path}:{line}:{}", diagnostic.column);
+                } else {
+                    println!(" --> {program_path}");
+                }
+                if let Some((catalog, (_, advice))) = &localized {
+                    let help = catalog.get("cli.help").unwrap_or("help");
+                    println!("  = {help}: {advice}");
+                }
+            }
+            if !diagnostics.is_empty() {
+                match lang.and_then(|catalog| catalog.count("cli.errors", diagnostics.len())) {
+                    Some(summary) => eprintln!("karel: {summary}"),
+                    None => eprintln!(
+                        "karel: {} error{} found",
+                        diagnostics.len(),
+                        if diagnostics.len() == 1 { "" } else { "s" }
+                    ),
+                }
+            }
+        }
+    }
+    if diagnostics.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// `karel transpile`: print the program as a standalone Python script.
+fn transpile(args: &[String]) -> ExitCode {
+    let mut program_path: Option<&str> = None;
+    for arg in args {
+        match arg.as_str() {
+            _ if program_path.is_none() && !arg.starts_with('-') => program_path = Some(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(program_path) = program_path else {
+        return usage_error("no program file given");
+    };
+    let source = match fs::read_to_string(program_path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("karel: cannot read `{program_path}`: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    match karel::transpile::to_python(&parser::preprocess(&source)) {
+        Ok(python) => {
+            print!("{python}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            match error.line() {
+                Some(line) => eprintln!("karel: {program_path}:{line}: {error}"),
+                None => eprintln!("karel: {program_path}: {error}"),
+            }
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// `karel import`: convert a Java-style Karel program to native source.
+fn import(args: &[String]) -> ExitCode {
+    let mut program_path: Option<&str> = None;
+    for arg in args {
+        match arg.as_str() {
+            _ if program_path.is_none() && !arg.starts_with('-') => program_path = Some(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(program_path) = program_path else {
+        return usage_error("no program file given");
+    };
+    let source = match fs::read_to_string(program_path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("karel: cannot read `{program_path}`: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    match karel::importer::from_java(&source) {
+        Ok(karel_source) => {
+            print!("{karel_source}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("karel: {program_path}:{}: {}", error.line, error.message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// How often `watch` polls the files for changes.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// `karel watch`: like `run`, but re-runs the program every time the program
+/// or world file changes on disk. Changes are detected by polling the
+/// modification times, which needs no platform-specific machinery and is
+/// easily fast enough for hand-edited files.
+fn watch(args: &[String]) -> ExitCode {
+    let args = match parse_run_args(args) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+    let mut last_seen = watched_mtimes(&args);
+    loop {
+        // Clear the screen and move the cursor home before each redraw.
+        print!("\x1b[2J\x1b[H");
+        println!("karel watch: {} (Ctrl-C to quit)", args.program_path);
+        println!();
+        run_once(&args);
+        loop {
+            std::thread::sleep(WATCH_POLL_INTERVAL);
+            let current = watched_mtimes(&args);
+            if current != last_seen {
+                last_seen = current;
+                break;
+            }
+        }
+    }
+}
+
+/// `karel grade`: run every submission against every world of a task and
+/// print a verdict table. With `--format json` the same information is
+/// printed as a JSON array instead, one object per submission, for grading
+/// pipelines (`--json` is kept as a shorthand).
+fn grade(args: &[String]) -> ExitCode {
+    let mut task_path: Option<&str> = None;
+    let mut submissions: Vec<&str> = Vec::new();
+    let mut format = OutputFormat::Human;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--task" => match args.next() {
+                Some(path) => task_path = Some(path),
+                None => return usage_error("--task needs a file"),
+            },
+            "--format" => match parse_format(args.next()) {
+                Ok(parsed) => format = parsed,
+                Err(code) => return code,
+            },
+            "--json" => format = OutputFormat::Json,
+            _ if !arg.starts_with('-') => submissions.push(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(task_path) = task_path else {
+        return usage_error("grade needs --task <task.toml>");
+    };
+    if submissions.is_empty() {
+        return usage_error("no submissions given");
+    }
+    let task = match karel::task::Task::load(std::path::Path::new(task_path)) {
+        Ok(task) => task,
+        Err(error) => {
+            eprintln!("karel: {task_path}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut reports = Vec::new();
+    for submission in &submissions {
+        let source = match fs::read_to_string(submission) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("karel: cannot read `{submission}`: {error}");
+                return ExitCode::from(2);
+            }
+        };
+        reports.push(karel::grade::grade(&task, submission, &source));
+    }
+
+    if format == OutputFormat::Json {
+        let summary = karel::json::Value::Array(
+            reports
+                .iter()
+                .map(|report| report.to_json(task.goals.len()))
+                .collect(),
+        );
+        println!("{summary}");
+    } else {
+        let width = reports
+            .iter()
+            .map(|report| report.submission.len())
+            .max()
+            .unwrap_or(0)
+            .max("submission".len());
+        println!("{:width$}  verdict  detail", "submission");
+        for report in &reports {
+            let verdict = if report.passed() { "PASS" } else { "FAIL" };
+            let detail = if let Some(error) = &report.parse_error {
+                error.clone()
+            } else {
+                report
+                    .results
+                    .iter()
+                    .map(|result| match &result.error {
+                        Some(error) => format!("{}: {error}", result.world),
+                        None => format!(
+                            "{}: {}/{} goals",
+                            result.world,
+                            result.goals_met,
+                            task.goals.len()
+                        ),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            };
+            println!("{:width$}  {verdict}     {detail}", report.submission);
+        }
+    }
+    if reports.iter().all(karel::grade::SubmissionReport::passed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// `karel contest`: grade every submission against one task and print the
+/// standings, best entry first.
+fn contest(args: &[String]) -> ExitCode {
+    let mut task_path: Option<&str> = None;
+    let mut submissions: Vec<&str> = Vec::new();
+    let mut format = OutputFormat::Human;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--task" => match args.next() {
+                Some(path) => task_path = Some(path),
+                None => return usage_error("--task needs a file"),
+            },
+            "--format" => match parse_format(args.next()) {
+                Ok(parsed) => format = parsed,
+                Err(code) => return code,
+            },
+            _ if !arg.starts_with('-') => submissions.push(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(task_path) = task_path else {
+        return usage_error("contest needs --task <task.toml>");
+    };
+    if submissions.is_empty() {
+        return usage_error("no submissions given");
+    }
+    let task = match karel::task::Task::load(std::path::Path::new(task_path)) {
+        Ok(task) => task,
+        Err(error) => {
+            eprintln!("karel: {task_path}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut sources = Vec::new();
+    for submission in &submissions {
+        match fs::read_to_string(submission) {
+            Ok(source) => sources.push((*submission, source)),
+            Err(error) => {
+                eprintln!("karel: cannot read `{submission}`: {error}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let entries: Vec<(&str, &str)> = sources
+        .iter()
+        .map(|(name, source)| (*name, source.as_str()))
+        .collect();
+    let standings = karel::tournament::run(&task, &entries);
+
+    if format == OutputFormat::Json {
+        println!("{}", standings.to_json());
+    } else {
+        print!("{}", standings.table());
+    }
+    ExitCode::SUCCESS
+}
+
+/// `karel replay`: play a recorded trace back in the terminal, one frame per
+/// executed instruction. `--delay` sets the time between frames, `--jump`
+/// skips ahead to a step before playback starts.
+fn replay(args: &[String]) -> ExitCode {
+    let mut trace_path: Option<&str> = None;
+    let mut delay = Duration::from_millis(200);
+    let mut jump = 0usize;
+    let mut style = RenderStyle::detect();
+    let mut format = OutputFormat::Human;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--delay" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(milliseconds) => delay = Duration::from_millis(milliseconds),
+                None => return usage_error("--delay needs a number of milliseconds"),
+            },
+            "--format" => match parse_format(args.next()) {
+                Ok(parsed) => format = parsed,
+                Err(code) => return code,
+            },
+            "--jump" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(step) => jump = step,
+                None => return usage_error("--jump needs a step number"),
+            },
+            "--ascii" => style = RenderStyle::Ascii,
+            _ if trace_path.is_none() && !arg.starts_with('-') => trace_path = Some(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(trace_path) = trace_path else {
+        return usage_error("no trace file given");
+    };
+
+    let source = match fs::read_to_string(trace_path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("karel: cannot read `{trace_path}`: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let trace = match karel::trace::parse(&source) {
+        Ok(trace) => trace,
+        Err(error) => {
+            eprintln!("karel: {trace_path}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut world = trace.world.clone();
+    if format == OutputFormat::Json {
+        // No animation in machine mode: report the final state instead.
+        for frame in &trace.frames {
+            frame.apply(&mut world);
+        }
+        let report = karel::json::Value::object([
+            (
+                "result",
+                karel::json::Value::from(match &trace.error {
+                    None => "ok",
+                    Some(_) => "runtime-error",
+                }),
+            ),
+            ("error", trace.error.clone().into()),
+            ("steps", karel::json::Value::from(trace.frames.len())),
+            ("world", worldfile::to_json(&world)),
+        ]);
+        println!("{report}");
+        return match trace.error {
+            None => ExitCode::SUCCESS,
+            Some(_) => ExitCode::FAILURE,
+        };
+    }
+    // Jumps seek through the snapshot index instead of replaying from the
+    // start, so scrubbing deep into a long trace is instant.
+    let timeline = karel::trace::Timeline::new(&trace);
+    let start = trace
+        .frames
+        .iter()
+        .position(|frame| frame.step >= jump)
+        .unwrap_or(trace.frames.len());
+    world = timeline.seek(start);
+    for (index, frame) in trace.frames.iter().enumerate().skip(start) {
+        frame.apply(&mut world);
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "step {}/{}  line {}  {}",
+            frame.step,
+            trace.frames.len(),
+            frame.line,
+            frame.instruction
+        );
+        print!("{}", render(&world, style));
+        if index + 1 < trace.frames.len() {
+            std::thread::sleep(delay);
+        }
+    }
+    match &trace.error {
+        None => ExitCode::SUCCESS,
+        Some(message) => {
+            eprintln!("karel: the recorded run failed: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `karel bench`: run the program many times against the same starting world
+/// and report timing statistics, so interpreter changes can be measured on
+/// real programs (the `benches/` directory covers the synthetic hot loops).
+fn bench(args: &[String]) -> ExitCode {
+    let mut program_path: Option<&str> = None;
+    let mut world_path: Option<&str> = None;
+    let mut iterations = 100usize;
+    let mut format = OutputFormat::Human;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--world" => match args.next() {
+                Some(path) => world_path = Some(path),
+                None => return usage_error("--world needs a file"),
+            },
+            "--iterations" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(count) if count > 0 => iterations = count,
+                _ => return usage_error("--iterations needs a positive number"),
+            },
+            "--format" => match parse_format(args.next()) {
+                Ok(parsed) => format = parsed,
+                Err(code) => return code,
+            },
+            _ if program_path.is_none() && !arg.starts_with('-') => program_path = Some(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(program_path) = program_path else {
+        return usage_error("no program file given");
+    };
+    let source = match fs::read_to_string(program_path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("karel: cannot read `{program_path}`: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let world = match load_world(world_path) {
+        Ok(world) => world,
+        Err(code) => return code,
+    };
+    let lines = parser::preprocess(&source);
+    if let Err(error) = parser::validate(&lines) {
+        eprintln!("karel: {program_path}: {error}");
+        return ExitCode::from(2);
+    }
+
+    let mut durations = Vec::with_capacity(iterations);
+    let mut steps = 0usize;
+    for _ in 0..iterations {
+        let mut interpreter = match Interpreter::new(lines.clone(), world.clone()) {
+            Ok(interpreter) => interpreter,
+            Err(error) => {
+                eprintln!("karel: {program_path}: {error}");
+                return ExitCode::from(2);
+            }
+        };
+        let start = std::time::Instant::now();
+        steps = 0;
+        loop {
+            match interpreter.step() {
+                Ok(karel::StepResult::Running) => steps += 1,
+                Ok(karel::StepResult::Finished) => break,
+                Err(error) => {
+                    eprintln!("karel: runtime error: {error}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        durations.push(start.elapsed());
+    }
+
+    durations.sort();
+    let total: Duration = durations.iter().sum();
+    let mean = total / iterations as u32;
+    let median = durations[iterations / 2];
+    let (min, max) = (durations[0], durations[iterations - 1]);
+    let steps_per_second = if mean.as_secs_f64() > 0.0 {
+        steps as f64 / mean.as_secs_f64()
+    } else {
+        0.0
+    };
+
+    match format {
+        OutputFormat::Json => {
+            let report = karel::json::Value::object([
+                ("iterations", iterations.into()),
+                ("steps", steps.into()),
+                ("mean_ns", (mean.as_nanos() as usize).into()),
+                ("median_ns", (median.as_nanos() as usize).into()),
+                ("min_ns", (min.as_nanos() as usize).into()),
+                ("max_ns", (max.as_nanos() as usize).into()),
+                ("steps_per_second", steps_per_second.into()),
+            ]);
+            println!("{report}");
+        }
+        OutputFormat::Human => {
+            println!("{program_path}: {iterations} iterations, {steps} steps per run");
+            println!(
+                "  time per run:  min {min:?}  median {median:?}  mean {mean:?}  max {max:?}"
+            );
+            println!("  throughput:    {steps_per_second:.0} steps/s");
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// `karel lsp`: serve the Language Server Protocol on stdin/stdout until
+/// the editor disconnects.
+fn lsp(args: &[String]) -> ExitCode {
+    if let Some(arg) = args.first() {
+        return usage_error(&format!("unexpected argument `{arg}`"));
+    }
+    match karel::lsp::serve(std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("karel: lsp: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `karel dap`: serve the Debug Adapter Protocol on stdin/stdout until the
+/// editor disconnects.
+fn dap(args: &[String]) -> ExitCode {
+    if let Some(arg) = args.first() {
+        return usage_error(&format!("unexpected argument `{arg}`"));
+    }
+    match karel::dap::serve(std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("karel: dap: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `karel race`: host a shared world over TCP for multiple robots.
+fn race(args: &[String]) -> ExitCode {
+    let mut world_path: Option<&str> = None;
+    let mut port = 7171u16;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--world" => match args.next() {
+                Some(path) => world_path = Some(path),
+                None => return usage_error("--world needs a file"),
+            },
+            "--port" => match args.next().and_then(|port| port.parse().ok()) {
+                Some(parsed) => port = parsed,
+                None => return usage_error("--port needs a port number"),
+            },
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let world = match load_world(world_path) {
+        Ok(world) => world,
+        Err(code) => return code,
+    };
+    let listener = match std::net::TcpListener::bind(("0.0.0.0", port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("karel: cannot listen on port {port}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("karel: hosting a shared world on port {port}; say `hello <name>` to join");
+    match karel::multiplayer::serve(listener, world) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("karel: race: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `karel serve`: grade submissions over a small HTTP API.
+fn serve(args: &[String]) -> ExitCode {
+    let mut tasks_dir: Option<&str> = None;
+    let mut port = 7172u16;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tasks" => match args.next() {
+                Some(path) => tasks_dir = Some(path),
+                None => return usage_error("--tasks needs a directory"),
+            },
+            "--port" => match args.next().and_then(|port| port.parse().ok()) {
+                Some(parsed) => port = parsed,
+                None => return usage_error("--port needs a port number"),
+            },
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(tasks_dir) = tasks_dir else {
+        return usage_error("--tasks is required");
+    };
+    let server = match karel::server::GradingServer::new(std::path::Path::new(tasks_dir)) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("karel: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let listener = match std::net::TcpListener::bind(("0.0.0.0", port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("karel: cannot listen on port {port}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "karel: grading {} task(s) on port {port}; see `GET /tasks`",
+        server.task_count()
+    );
+    match karel::server::serve(listener, server) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("karel: serve: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `karel new`: write a starter exercise into a fresh directory.
+fn new(args: &[String]) -> ExitCode {
+    let mut positional: Vec<&String> = Vec::new();
+    let mut list = false;
+    let mut format = OutputFormat::Human;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--list" => list = true,
+            "--format" => match parse_format(args.next()) {
+                Ok(parsed) => format = parsed,
+                Err(code) => return code,
+            },
+            _ if !arg.starts_with('-') => positional.push(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+
+    if list {
+        match format {
+            OutputFormat::Json => {
+                let report = karel::json::Value::Array(
+                    karel::scaffold::TEMPLATES
+                        .iter()
+                        .map(|template| {
+                            karel::json::Value::object([
+                                ("name", template.name.into()),
+                                ("description", template.description.into()),
+                            ])
+                        })
+                        .collect(),
+                );
+                println!("{report}");
+            }
+            OutputFormat::Human => {
+                for template in karel::scaffold::TEMPLATES {
+                    println!("{:12} {}", template.name, template.description);
+                }
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let [template, directory] = positional[..] else {
+        return usage_error("new takes a template name and a directory, or --list");
+    };
+    let Some(template) = karel::scaffold::find(template) else {
+        eprintln!("karel: unknown template `{template}` (try `karel new --list`)");
+        return ExitCode::from(2);
+    };
+    match karel::scaffold::create(template, std::path::Path::new(directory)) {
+        Ok(()) => {
+            match format {
+                OutputFormat::Json => {
+                    let report = karel::json::Value::object([
+                        ("template", template.name.into()),
+                        ("directory", directory.as_str().into()),
+                    ]);
+                    println!("{report}");
+                }
+                OutputFormat::Human => {
+                    println!("created `{directory}` from the {} template", template.name);
+                    println!(
+                        "next: cd {directory} && karel run program.kl --world world.txt"
+                    );
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("karel: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// `karel play`: progress through a campaign. Without `--solution` it shows
+/// the course status; with one it grades the file against the next unlocked
+/// level and, on a pass, records it into the profile file (by default the
+/// campaign file with a `.profile` extension).
+fn play(args: &[String]) -> ExitCode {
+    let mut campaign_path: Option<&str> = None;
+    let mut solution_path: Option<&str> = None;
+    let mut profile_override: Option<&str> = None;
+    let mut format = OutputFormat::Human;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--solution" => match args.next() {
+                Some(path) => solution_path = Some(path),
+                None => return usage_error("--solution needs a file"),
+            },
+            "--profile" => match args.next() {
+                Some(path) => profile_override = Some(path),
+                None => return usage_error("--profile needs a file"),
+            },
+            "--format" => match parse_format(args.next()) {
+                Ok(parsed) => format = parsed,
+                Err(code) => return code,
+            },
+            _ if campaign_path.is_none() && !arg.starts_with('-') => campaign_path = Some(arg),
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let Some(campaign_path) = campaign_path else {
+        return usage_error("no campaign file given");
+    };
+    let campaign = match karel::campaign::Campaign::load(std::path::Path::new(campaign_path)) {
+        Ok(campaign) => campaign,
+        Err(error) => {
+            eprintln!("karel: {campaign_path}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let profile_path = match profile_override {
+        Some(path) => std::path::PathBuf::from(path),
+        None => std::path::Path::new(campaign_path).with_extension("profile"),
+    };
+    // Profiles go through the storage abstraction, here backed by the
+    // profile's own directory.
+    let mut storage = karel::storage::DirectoryStorage::new(
+        profile_path.parent().unwrap_or(std::path::Path::new(".")),
+    );
+    let profile_key = profile_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "karel.profile".to_string());
+    let mut progress = match campaign.load_progress(&storage, &profile_key) {
+        Ok(progress) => progress,
+        Err(error) => {
+            eprintln!("karel: {}: {error}", profile_path.display());
+            return ExitCode::from(2);
+        }
+    };
+
+    // Grade the solution against the next open level, if one was given.
+    let mut graded = None;
+    if let Some(solution_path) = solution_path {
+        let Some((_, level)) = campaign.next(&progress) else {
+            println!("{}: the campaign is already finished", campaign.name);
+            return ExitCode::SUCCESS;
+        };
+        let source = match fs::read_to_string(solution_path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("karel: cannot read `{solution_path}`: {error}");
+                return ExitCode::from(2);
+            }
+        };
+        let report = karel::grade::grade(&level.task, solution_path, &source);
+        if report.passed() {
+            progress.record(&level.file);
+            if let Err(error) = progress.save_to(&mut storage, &profile_key) {
+                eprintln!("karel: cannot write `{}`: {error}", profile_path.display());
+                return ExitCode::from(2);
+            }
+        }
+        graded = Some((level.file.clone(), level.task.goals.len(), report));
+    }
+
+    if format == OutputFormat::Json {
+        let report = karel::json::Value::object([
+            ("campaign", campaign.name.as_str().into()),
+            ("passed", progress.count().into()),
+            (
+                "levels",
+                karel::json::Value::Array(
+                    campaign
+                        .levels
+                        .iter()
+                        .enumerate()
+                        .map(|(index, level)| {
+                            karel::json::Value::object([
+                                ("file", level.file.as_str().into()),
+                                ("name", level.task.name.as_str().into()),
+                                ("passed", progress.passed(&level.file).into()),
+                                ("unlocked", campaign.unlocked(index, &progress).into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "graded",
+                match &graded {
+                    Some((_, goals_total, report)) => report.to_json(*goals_total),
+                    None => karel::json::Value::Null,
+                },
+            ),
+        ]);
+        println!("{report}");
+    } else {
+        if let Some((file, goals_total, report)) = &graded {
+            if report.passed() {
+                println!("passed `{file}`!");
+            } else if let Some(error) = &report.parse_error {
+                println!("`{file}` not passed: {error}");
+            } else {
+                for result in &report.results {
+                    match &result.error {
+                        Some(error) => println!("`{file}` ({}): {error}", result.world),
+                        None => println!(
+                            "`{file}` ({}): {}/{goals_total} goals",
+                            result.world, result.goals_met
+                        ),
+                    }
+                }
+            }
+            println!();
+        }
+        println!(
+            "{} -- {}/{} levels passed",
+            campaign.name,
+            progress.count().min(campaign.levels.len()),
+            campaign.levels.len()
+        );
+        for (index, level) in campaign.levels.iter().enumerate() {
+            let marker = if progress.passed(&level.file) {
+                "[x]"
+            } else if campaign.unlocked(index, &progress) {
+                "[ ]"
+            } else {
+                "[.]"
+            };
+            println!("  {marker} {:12} {}", level.file, level.task.name);
+        }
+        match campaign.next(&progress) {
+            Some((_, level)) => println!(
+                "next: karel play {campaign_path} --solution <program.kl>  ({})",
+                level.task.name
+            ),
+            None => println!("the campaign is finished -- congratulations!"),
+        }
+    }
+    match &graded {
+        Some((_, _, report)) if !report.passed() => ExitCode::FAILURE,
+        _ => ExitCode::SUCCESS,
+    }
+}
+
+const EDITOR_HELP: &str =
+    "arrows/hjkl move  w wall  r robot  + - beeper  0-8 set  space clear  s save  q quit";
+
+/// `karel edit`: a terminal world editor. The terminal is switched out of
+/// canonical mode with `stty` (saving the original settings first), so every
+/// keypress arrives immediately; the editing rules themselves live in
+/// [`karel::editor`].
+fn edit(args: &[String]) -> ExitCode {
+    let [world_path] = args else {
+        return usage_error("edit takes exactly one world file");
+    };
+    let path = std::path::Path::new(world_path);
+    let world = match karel::worldfile::load(path) {
+        Ok(world) => world,
+        Err(karel::worldfile::WorldFileError::Io(error))
+            if error.kind() == std::io::ErrorKind::NotFound =>
+        {
+            // Editing a file that does not exist yet starts a fresh world.
+            World::default()
+        }
+        Err(error) => {
+            eprintln!("karel: {world_path}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+    let style = match RenderStyle::detect() {
+        RenderStyle::Ascii => RenderStyle::Unicode,
+        style => style,
+    };
+
+    let Some(saved_settings) = stty(&["-g"]) else {
+        eprintln!("karel: edit needs a terminal (stty failed)");
+        return ExitCode::from(2);
+    };
+    let saved_settings = saved_settings.trim().to_string();
+    // Keypresses without enter, no echo; output processing stays on.
+    stty(&["-icanon", "-echo", "min", "1", "time", "0"]);
+
+    let mut editor = karel::editor::Editor::new(world);
+    let mut stdin = std::io::stdin();
+    let mut status = String::new();
+    let code = loop {
+        print!("\x1b[2J\x1b[H");
+        println!("editing {world_path}{}", if editor.dirty() { " (unsaved)" } else { "" });
+        print!("{}", editor.render(style));
+        println!("{EDITOR_HELP}");
+        if !status.is_empty() {
+            println!("{status}");
+            status.clear();
+        }
+
+        let Some(key) = read_key(&mut stdin) else {
+            break ExitCode::SUCCESS;
+        };
+        use karel::editor::EditorAction;
+        use karel::Direction;
+        let action = match key {
+            Key::Up => Some(EditorAction::MoveCursor(Direction::North)),
+            Key::Down => Some(EditorAction::MoveCursor(Direction::South)),
+            Key::Left => Some(EditorAction::MoveCursor(Direction::West)),
+            Key::Right => Some(EditorAction::MoveCursor(Direction::East)),
+            Key::Char('w') => Some(EditorAction::ToggleWall),
+            Key::Char('r') => Some(EditorAction::PlaceRobot),
+            Key::Char('+' | 'b') => Some(EditorAction::AddBeeper),
+            Key::Char('-') => Some(EditorAction::RemoveBeeper),
+            Key::Char(digit @ '0'..='8') => {
+                Some(EditorAction::SetBeepers(digit as u8 - b'0'))
+            }
+            Key::Char(' ') => Some(EditorAction::ClearTile),
+            Key::Char('s') => {
+                match karel::worldfile::save(editor.world(), path) {
+                    Ok(()) => {
+                        editor.mark_saved();
+                        status = format!("saved to {world_path}");
+                    }
+                    Err(error) => status = format!("cannot save: {error}"),
+                }
+                None
+            }
+            Key::Char('q') => {
+                if editor.dirty() {
+                    status = "unsaved changes: press s to save or Q to quit anyway".to_string();
+                    None
+                } else {
+                    break ExitCode::SUCCESS;
+                }
+            }
+            Key::Char('Q') => break ExitCode::SUCCESS,
+            Key::Char(_) => None,
+        };
+        if let Some(action) = action {
+            editor.apply(action);
+        }
+    };
+    stty(&[&saved_settings]);
+    code
+}
+
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Char(char),
+}
+
+/// Read one keypress, decoding the arrow-key escape sequences.
+fn read_key(stdin: &mut impl std::io::Read) -> Option<Key> {
+    let mut byte = [0u8; 1];
+    stdin.read_exact(&mut byte).ok()?;
+    if byte[0] != 0x1b {
+        return Some(Key::Char(byte[0] as char));
+    }
+    let mut sequence = [0u8; 2];
+    stdin.read_exact(&mut sequence).ok()?;
+    match &sequence {
+        b"[A" => Some(Key::Up),
+        b"[B" => Some(Key::Down),
+        b"[C" => Some(Key::Right),
+        b"[D" => Some(Key::Left),
+        _ => Some(Key::Char('\x1b')),
+    }
+}
+
+/// Run `stty` against the controlling terminal, returning its stdout.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(std::process::Stdio::inherit())
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+const REPL_HELP: &str = "\
+Type instructions (move, turn-left, take, put) or define procedures with
+def ... enddef. Blocks are read until their end before being executed.
+
+meta commands:
+  :undo          take back the last command
+  :reset         restore the starting world
+  :save <file>   save the current world to a file
+  :help          show this help
+  :quit          leave the repl
+";
+
+/// `karel repl`: an interactive session where each typed command is executed
+/// against the world immediately. Multi-line blocks (`repeat`, `def`, ...)
+/// are collected until all of them are closed, like in the Python repl.
+fn repl(args: &[String]) -> ExitCode {
+    use std::io::{BufRead, Write};
+
+    let mut world_path: Option<&str> = None;
+    let mut style = RenderStyle::detect();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--world" => match args.next() {
+                Some(path) => world_path = Some(path),
+                None => return usage_error("--world needs a file"),
+            },
+            "--ascii" => style = RenderStyle::Ascii,
+            other => return usage_error(&format!("unexpected argument `{other}`")),
+        }
+    }
+    let world = match load_world(world_path) {
+        Ok(world) => world,
+        Err(code) => return code,
+    };
+
+    let mut session = karel::interactive::Session::new(world);
+    println!("karel repl -- :help for help, :quit to leave");
+    print!("{}", render(session.world(), style));
+
+    let stdin = std::io::stdin();
+    let mut buffer = String::new();
+    let mut depth = 0usize;
+    loop {
+        print!("{}", if depth == 0 { "karel> " } else { "  ...> " });
+        let _ = std::io::stdout().flush();
+        let mut input = String::new();
+        match stdin.lock().read_line(&mut input) {
+            Ok(0) => return ExitCode::SUCCESS,
+            Ok(_) => {}
+            Err(error) => {
+                eprintln!("karel: cannot read input: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+        let trimmed = input.trim();
+
+        if depth == 0 && buffer.is_empty() {
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some(meta) = trimmed.strip_prefix(':') {
+                match meta.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["undo"] => {
+                        if session.undo() {
+                            print!("{}", render(session.world(), style));
+                        } else {
+                            println!("nothing to undo");
+                        }
+                    }
+                    ["reset"] => {
+                        session.reset();
+                        print!("{}", render(session.world(), style));
+                    }
+                    ["save", path] => match fs::write(path, worldfile::to_text(session.world())) {
+                        Ok(()) => println!("saved to {path}"),
+                        Err(error) => eprintln!("karel: cannot write `{path}`: {error}"),
+                    },
+                    ["help"] => print!("{REPL_HELP}"),
+                    ["quit" | "q" | "exit"] => return ExitCode::SUCCESS,
+                    _ => println!("unknown meta command (:help for help)"),
+                }
+                continue;
+            }
+        }
+
+        buffer.push_str(&input);
+        match trimmed.split_whitespace().next() {
+            Some("def" | "if" | "if!" | "while" | "while!" | "repeat") => depth += 1,
+            Some("enddef" | "endif" | "endwhile" | "endrepeat") => {
+                depth = depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+        if depth > 0 {
+            continue;
+        }
+
+        let snippet = std::mem::take(&mut buffer);
+        let result = if snippet.trim_start().starts_with("def") {
+            session.define(&snippet).map(|name| {
+                println!("defined `{name}`");
+            })
+        } else {
+            session.execute(&snippet).map(|()| {
+                print!("{}", render(session.world(), style));
+            })
+        };
+        if let Err(error) = result {
+            println!("error: {error}");
+        }
+    }
+}
+
+fn watched_mtimes(args: &RunArgs<'_>) -> Vec<Option<SystemTime>> {
+    let mut paths = vec![args.program_path];
+    paths.extend(args.world_path);
+    paths
+        .into_iter()
+        .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("karel: {message}");
+    eprint!("{USAGE}");
+    ExitCode::from(2)
+}
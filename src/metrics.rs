@@ -0,0 +1,174 @@
+//! Structural complexity metrics for Karel programs.
+//!
+//! Teachers grading for style want numbers, not impressions: how deeply
+//! blocks nest, how many procedures the program defines, how branchy each
+//! one is. [`measure`] computes them from preprocessed lines — the language
+//! is line oriented, so no AST beyond the block structure is needed — and
+//! the grade report carries them, so a task can require decomposition
+//! ("`main` must call at least two procedures") next to its goals.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::parser::Line;
+
+/// The metrics of one procedure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcedureMetrics {
+    /// The name after `def`.
+    pub name: String,
+    /// Instruction lines between `def` and `enddef`, block markers included.
+    pub lines: usize,
+    /// The deepest `if`/`while`/`repeat` nesting inside the procedure;
+    /// zero for straight-line code.
+    pub max_nesting: usize,
+    /// One plus the number of `if`, `while` and `repeat` blocks: the
+    /// classic count of independent paths, with `repeat` counted as the
+    /// loop it is.
+    pub cyclomatic: usize,
+    /// Distinct procedures this one `call`s, in first-call order.
+    pub calls: Vec<String>,
+}
+
+/// The metrics of a whole program.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProgramMetrics {
+    /// Per-procedure metrics, in definition order.
+    pub procedures: Vec<ProcedureMetrics>,
+}
+
+impl ProgramMetrics {
+    /// How many procedures the program defines.
+    pub fn procedure_count(&self) -> usize {
+        self.procedures.len()
+    }
+
+    /// The deepest nesting anywhere in the program.
+    pub fn max_nesting(&self) -> usize {
+        self.procedures
+            .iter()
+            .map(|procedure| procedure.max_nesting)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The metrics of the named procedure, if it is defined.
+    pub fn procedure(&self, name: &str) -> Option<&ProcedureMetrics> {
+        self.procedures
+            .iter()
+            .find(|procedure| procedure.name == name)
+    }
+}
+
+/// Measure a preprocessed program. Lines outside any `def` and malformed
+/// block structure are skipped, not errors: metrics are advisory, and
+/// validation owns rejecting broken programs.
+pub fn measure(lines: &[Line<'_>]) -> ProgramMetrics {
+    let mut procedures = Vec::new();
+    let mut current: Option<ProcedureMetrics> = None;
+    let mut depth = 0usize;
+
+    for line in lines {
+        let mut words = line.text.split_whitespace();
+        let first = words.next().unwrap_or("");
+        match first {
+            "def" => {
+                if let Some(name) = words.next() {
+                    current = Some(ProcedureMetrics {
+                        name: name.to_string(),
+                        lines: 0,
+                        max_nesting: 0,
+                        cyclomatic: 1,
+                        calls: Vec::new(),
+                    });
+                    depth = 0;
+                }
+            }
+            "enddef" => {
+                if let Some(procedure) = current.take() {
+                    procedures.push(procedure);
+                }
+            }
+            _ => {
+                let Some(procedure) = current.as_mut() else {
+                    continue;
+                };
+                procedure.lines += 1;
+                match first {
+                    "if" | "if!" | "while" | "while!" | "repeat" => {
+                        depth += 1;
+                        procedure.cyclomatic += 1;
+                        procedure.max_nesting = procedure.max_nesting.max(depth);
+                    }
+                    "endif" | "endwhile" | "endrepeat" => depth = depth.saturating_sub(1),
+                    "call" => {
+                        if let Some(target) = words.next() {
+                            if !procedure.calls.iter().any(|call| call == target) {
+                                procedure.calls.push(target.to_string());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    ProgramMetrics { procedures }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::preprocess;
+
+    #[test]
+    fn metrics_count_nesting_branches_and_calls() {
+        let source = "\
+def main
+ call sweep
+ call sweep
+ call park
+enddef
+def sweep
+ while! wall
+  if beeper
+   take
+  endif
+  move
+ endwhile
+enddef
+def park
+ repeat 2
+  turn-left
+ endrepeat
+enddef";
+        let metrics = measure(&preprocess(source));
+        assert_eq!(metrics.procedure_count(), 3);
+        assert_eq!(metrics.max_nesting(), 2);
+
+        let main = metrics.procedure("main").unwrap();
+        assert_eq!(main.cyclomatic, 1);
+        assert_eq!(main.max_nesting, 0);
+        // Two distinct callees: the repeated `call sweep` counts once.
+        assert_eq!(main.calls, ["sweep", "park"]);
+
+        let sweep = metrics.procedure("sweep").unwrap();
+        assert_eq!(sweep.cyclomatic, 3);
+        assert_eq!(sweep.max_nesting, 2);
+        assert_eq!(sweep.lines, 6);
+
+        let park = metrics.procedure("park").unwrap();
+        assert_eq!(park.cyclomatic, 2);
+        assert_eq!(park.max_nesting, 1);
+    }
+
+    #[test]
+    fn straight_line_code_is_as_simple_as_it_gets() {
+        let metrics = measure(&preprocess("def main\n move\n move\n die\nenddef"));
+        let main = metrics.procedure("main").unwrap();
+        assert_eq!(main.cyclomatic, 1);
+        assert_eq!(main.max_nesting, 0);
+        assert_eq!(main.lines, 3);
+        assert!(main.calls.is_empty());
+    }
+}
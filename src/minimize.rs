@@ -0,0 +1,144 @@
+//! Shrinking a program to a minimal reproducer.
+//!
+//! A bug report is most useful at its smallest: given a program and a
+//! predicate ("still triggers this runtime error", "still takes a second to
+//! grade"), [`minimize`] deletes everything the predicate does not need —
+//! line by line, whole blocks at a time — and returns the remainder. The
+//! same tool builds teaching examples: start from a messy student program
+//! and keep only what reproduces the mistake worth discussing.
+//!
+//! The predicate should include validity if it cares about it (for example
+//! "parses cleanly *and* still fails on line N"); the minimizer itself only
+//! promises that the predicate holds for what it returns.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Shrink `source` to a smaller program for which `predicate` still holds.
+///
+/// Works greedily to a fixpoint: every line, and every block together with
+/// its end, is dropped if the predicate survives the deletion. If the
+/// predicate does not hold for `source` itself there is nothing to
+/// preserve, and `source` comes back unchanged.
+pub fn minimize<P>(source: &str, mut predicate: P) -> String
+where
+    P: FnMut(&str) -> bool,
+{
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    if !predicate(&lines.join("\n")) {
+        return source.to_string();
+    }
+
+    let mut shrunk = true;
+    while shrunk {
+        shrunk = false;
+        let mut index = 0;
+        while index < lines.len() {
+            let end = chunk_end(&lines, index);
+            let mut candidate = lines.clone();
+            candidate.drain(index..=end);
+            if predicate(&candidate.join("\n")) {
+                lines = candidate;
+                shrunk = true;
+                // The same index now holds the next untried line.
+            } else {
+                index += 1;
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// The last line of the deletion chunk starting at `start`: the matching
+/// end for a block opener, `start` itself for everything else. Deleting an
+/// opener alone would orphan its end and fail every validity-minded
+/// predicate, so blocks go as one piece.
+fn chunk_end(lines: &[String], start: usize) -> usize {
+    if !opens_block(first_word(&lines[start])) {
+        return start;
+    }
+    let mut depth = 0usize;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        let word = first_word(line);
+        if opens_block(word) {
+            depth += 1;
+        } else if matches!(word, "enddef" | "endif" | "endwhile" | "endrepeat") {
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                return index;
+            }
+        }
+    }
+    // An unclosed block: take everything to the end of the file.
+    lines.len() - 1
+}
+
+fn opens_block(word: &str) -> bool {
+    matches!(word, "def" | "if" | "if!" | "while" | "while!" | "repeat")
+}
+
+/// The first instruction word of a raw source line, comments stripped.
+fn first_word(line: &str) -> &str {
+    let text = line.split('#').next().unwrap_or("");
+    text.split_whitespace().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, RuntimeError};
+    use crate::parser;
+    use crate::world::World;
+
+    /// Does the program parse cleanly and still die taking a missing
+    /// beeper?
+    fn still_takes_from_nothing(source: &str) -> bool {
+        let lines = parser::preprocess(source);
+        if !parser::check(&lines).is_empty() {
+            return false;
+        }
+        let mut interpreter = match Interpreter::new(lines, World::new(5, 5)) {
+            Ok(interpreter) => interpreter,
+            Err(_) => return false,
+        };
+        matches!(
+            interpreter.run().into_result(),
+            Err(RuntimeError::NoBeeperToTake { .. })
+        )
+    }
+
+    #[test]
+    fn the_reproducer_keeps_only_the_failing_line() {
+        let source = "# student homework\n\
+                      def main\n\
+                      \x20repeat 4\n\
+                      \x20 turn-left\n\
+                      \x20endrepeat\n\
+                      \x20move\n\
+                      \x20take\n\
+                      enddef\n\
+                      def spare\n\
+                      \x20beep\n\
+                      enddef";
+        let minimal = minimize(source, still_takes_from_nothing);
+        assert_eq!(minimal, "def main\n take\nenddef");
+        assert!(still_takes_from_nothing(&minimal));
+    }
+
+    #[test]
+    fn blocks_are_deleted_whole() {
+        // Dropping `repeat` alone would orphan its `endrepeat`; the chunk
+        // covers both, so the loop around the bug disappears in one step.
+        let source = "def main\n repeat 3\n  move\n endrepeat\n take\nenddef";
+        assert_eq!(
+            minimize(source, still_takes_from_nothing),
+            "def main\n take\nenddef"
+        );
+    }
+
+    #[test]
+    fn an_unreproducible_predicate_changes_nothing() {
+        let source = "def main\n move\nenddef";
+        assert_eq!(minimize(source, |_| false), source);
+    }
+}
@@ -0,0 +1,299 @@
+//! Linking several source files into one program.
+//!
+//! A lesson often ships a library file next to the student's own: the
+//! student writes `main.kl`, the teacher provides `lib.kl` with helpers.
+//! [`link`] merges the files so the ordinary pipeline (validate, run,
+//! grade) sees one program, while keeping the files from silently stepping
+//! on each other: every procedure of a library file is namespaced as
+//! `lib.turn-right`, callable under that full name from anywhere. A file
+//! that says `use lib` gets the unqualified names too — and a loud error,
+//! not a silent winner, when an imported name collides with one of its own.
+//!
+//! The first file is the entry point and keeps its names as written, so
+//! `def main` stays `def main`. Line [`file`](Line::file) ids point into
+//! the returned [`SourceMap`], so diagnostics name the right file.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::parser::{self, Line, SourceMap};
+
+/// The file name the bundled standard library is linked under, making its
+/// module name `std`.
+pub const STDLIB_FILE: &str = "std.kl";
+
+/// The bundled standard library: the handful of procedures every classroom
+/// re-derives in the first week, as plain Karel source. Link it as an extra
+/// file (under [`STDLIB_FILE`]) and programs can `call std.turn-right` —
+/// or `use std` and drop the prefix.
+pub const STDLIB: &str = "\
+def turn-right
+ repeat 3
+  turn-left
+ endrepeat
+enddef
+
+def turn-around
+ repeat 2
+  turn-left
+ endrepeat
+enddef
+
+def go-to-wall
+ while! wall
+  move
+ endwhile
+enddef
+
+def face-north
+ while! north
+  turn-left
+ endwhile
+enddef
+";
+
+/// Why a set of files could not be linked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// Two files would share one module name.
+    DuplicateModule { module: String },
+    /// A `use` of a module that is not among the files.
+    UnknownModule { file: String, line: usize, module: String },
+    /// A `use` line that is not `use MODULE`.
+    BadUse { file: String, line: usize },
+    /// An unqualified name that could mean two things: defined both in the
+    /// file itself and in a module it `use`s, or in two `use`d modules.
+    Collision { file: String, line: usize, name: String },
+}
+
+impl core::fmt::Display for LinkError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LinkError::DuplicateModule { module } => {
+                write!(f, "two files are both called `{module}`")
+            }
+            LinkError::UnknownModule { file, line, module } => {
+                write!(f, "{file}:{line}: no file provides module `{module}`")
+            }
+            LinkError::BadUse { file, line } => {
+                write!(f, "{file}:{line}: expected `use MODULE`")
+            }
+            LinkError::Collision { file, line, name } => {
+                write!(
+                    f,
+                    "{file}:{line}: `{name}` is ambiguous here; call it by its full name"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LinkError {}
+
+/// Link files given as (file name, source) into one program. The first file
+/// is the entry point; the others become modules named after their file
+/// stem (`lib.kl` provides `lib.`-prefixed procedures). Returns the merged
+/// lines and the map resolving their file ids back to names.
+pub fn link<'s>(files: &[(&str, &'s str)]) -> Result<(Vec<Line<'s>>, SourceMap), LinkError> {
+    let mut map = SourceMap::default();
+    let modules: Vec<&str> = files.iter().map(|(name, _)| module_name(name)).collect();
+    for (index, module) in modules.iter().enumerate() {
+        if modules[..index].contains(module) {
+            return Err(LinkError::DuplicateModule {
+                module: module.to_string(),
+            });
+        }
+    }
+
+    // The entry file is the student's; everything linked beside it is
+    // scaffolding, so errors in it are attributed to its provider.
+    let mut per_file: Vec<Vec<Line<'s>>> = Vec::new();
+    for (index, (name, source)) in files.iter().enumerate() {
+        let id = if index == 0 { map.add(name) } else { map.add_scaffold(name) };
+        per_file.push(parser::preprocess_file(source, id));
+    }
+
+    // What each file defines (unqualified), and what it `use`s.
+    let definitions: Vec<Vec<&str>> = per_file.iter().map(|lines| defined_names(lines)).collect();
+    let mut uses: Vec<Vec<usize>> = Vec::new();
+    for (index, lines) in per_file.iter().enumerate() {
+        let mut used = Vec::new();
+        for line in lines {
+            let words: Vec<&str> = line.text.split_whitespace().collect();
+            match words[..] {
+                ["use", module] => match modules.iter().position(|known| *known == module) {
+                    Some(target) if target != index => used.push(target),
+                    Some(_) => {}
+                    None => {
+                        return Err(LinkError::UnknownModule {
+                            file: files[index].0.to_string(),
+                            line: line.number,
+                            module: module.to_string(),
+                        })
+                    }
+                },
+                ["use", ..] => {
+                    return Err(LinkError::BadUse {
+                        file: files[index].0.to_string(),
+                        line: line.number,
+                    })
+                }
+                _ => {}
+            }
+        }
+        uses.push(used);
+    }
+
+    let mut linked = Vec::new();
+    for (index, lines) in per_file.iter().enumerate() {
+        for line in lines {
+            let words: Vec<&str> = line.text.split_whitespace().collect();
+            match words[..] {
+                ["use", _] => {} // consumed above
+                ["def", name] if index > 0 => {
+                    linked.push(requalified(line, "def", modules[index], name));
+                }
+                ["call", target] if !target.contains('.') => {
+                    let local = definitions[index].contains(&target);
+                    let providers: Vec<usize> = uses[index]
+                        .iter()
+                        .copied()
+                        .filter(|&provider| definitions[provider].contains(&target))
+                        .collect();
+                    if (local && !providers.is_empty()) || providers.len() > 1 {
+                        return Err(LinkError::Collision {
+                            file: files[index].0.to_string(),
+                            line: line.number,
+                            name: target.to_string(),
+                        });
+                    }
+                    if local && index > 0 {
+                        linked.push(requalified(line, "call", modules[index], target));
+                    } else if let (false, Some(&provider)) = (local, providers.first()) {
+                        linked.push(requalified(line, "call", modules[provider], target));
+                    } else {
+                        linked.push(line.clone());
+                    }
+                }
+                _ => linked.push(line.clone()),
+            }
+        }
+    }
+    Ok((linked, map))
+}
+
+/// The module a file name provides: its stem, without directories or the
+/// extension.
+pub(crate) fn module_name(file: &str) -> &str {
+    let base = file.rsplit(['/', '\\']).next().unwrap_or(file);
+    base.split_once('.').map(|(stem, _)| stem).unwrap_or(base)
+}
+
+fn defined_names<'l>(lines: &'l [Line<'_>]) -> Vec<&'l str> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let mut words = line.text.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("def"), Some(name)) => Some(name),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The line with its `def`/`call` target qualified as `module.name`.
+fn requalified<'s>(line: &Line<'s>, keyword: &str, module: &str, name: &str) -> Line<'s> {
+    Line {
+        file: line.file,
+        number: line.number,
+        column: line.column,
+        text: format!("{keyword} {module}.{name}").into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::world::{Direction, World};
+
+    const LIB: &str = "def turn-right\n repeat 3\n  call spin\n endrepeat\nenddef\ndef spin\n turn-left\nenddef";
+
+    #[test]
+    fn library_procedures_are_callable_by_their_full_names() {
+        let main = "def main\n call lib.turn-right\n die\nenddef";
+        let (lines, map) = link(&[("main.kl", main), ("lib.kl", LIB)]).unwrap();
+        assert!(parser::check(&lines).is_empty());
+        assert_eq!(map.name(lines.last().unwrap().file), "lib.kl");
+        // The entry file is the student's; the library is scaffolding.
+        assert_eq!(map.origin(lines[0].file), parser::Origin::Student);
+        assert_eq!(map.origin(lines.last().unwrap().file), parser::Origin::Scaffold);
+
+        let mut interpreter = Interpreter::new(lines, World::new(3, 3)).unwrap();
+        interpreter.run().into_result().unwrap();
+        assert_eq!(interpreter.world.robot.direction, Direction::South);
+    }
+
+    #[test]
+    fn use_imports_unqualified_names() {
+        let main = "use lib\ndef main\n call turn-right\n die\nenddef";
+        let (lines, _) = link(&[("main.kl", main), ("lib.kl", LIB)]).unwrap();
+        assert!(parser::check(&lines).is_empty());
+        let mut interpreter = Interpreter::new(lines, World::new(3, 3)).unwrap();
+        interpreter.run().into_result().unwrap();
+        assert_eq!(interpreter.world.robot.direction, Direction::South);
+    }
+
+    #[test]
+    fn colliding_names_are_loud_not_silent() {
+        let main = "use lib\ndef main\n call turn-right\nenddef\ndef turn-right\n turn-left\nenddef";
+        assert_eq!(
+            link(&[("main.kl", main), ("lib.kl", LIB)]),
+            Err(LinkError::Collision {
+                file: "main.kl".to_string(),
+                line: 3,
+                name: "turn-right".to_string(),
+            })
+        );
+        // Without `use` the same two definitions coexist: the student's
+        // name is unqualified, the library's is not.
+        let main = "def main\n call turn-right\nenddef\ndef turn-right\n turn-left\nenddef";
+        let (lines, _) = link(&[("main.kl", main), ("lib.kl", LIB)]).unwrap();
+        assert!(parser::check(&lines).is_empty());
+    }
+
+    #[test]
+    fn the_standard_library_saves_the_first_week() {
+        let main = "use std\ndef main\n call go-to-wall\n call turn-around\n call face-north\n die\nenddef";
+        let (lines, _) = link(&[("main.kl", main), (STDLIB_FILE, STDLIB)]).unwrap();
+        assert!(parser::check(&lines).is_empty());
+
+        let mut interpreter = Interpreter::new(lines, World::new(4, 1)).unwrap();
+        interpreter.run().into_result().unwrap();
+        assert_eq!(interpreter.world.robot.position.x, 3);
+        assert_eq!(interpreter.world.robot.direction, Direction::North);
+    }
+
+    #[test]
+    fn bad_uses_are_rejected() {
+        assert_eq!(
+            link(&[("main.kl", "use stdlib\ndef main\n move\nenddef")]),
+            Err(LinkError::UnknownModule {
+                file: "main.kl".to_string(),
+                line: 1,
+                module: "stdlib".to_string(),
+            })
+        );
+        assert_eq!(
+            link(&[("main.kl", "use\ndef main\n move\nenddef")]),
+            Err(LinkError::BadUse { file: "main.kl".to_string(), line: 1 })
+        );
+        assert_eq!(
+            link(&[("a/lib.kl", ""), ("b/lib.kl", "")]),
+            Err(LinkError::DuplicateModule { module: "lib".to_string() })
+        );
+    }
+}
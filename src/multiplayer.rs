@@ -0,0 +1,312 @@
+//! A shared world served over TCP for classroom "robot races".
+//!
+//! Every client controls its own robot in one world hosted by the server.
+//! The protocol is plain lines of text, easy to drive from `nc` or a few
+//! lines of any language:
+//!
+//! ```text
+//! client: hello alice
+//! server: welcome 1
+//! client: move
+//! server: ok
+//! server: state {"beepers":...,"robots":[...],...}   (broadcast to all)
+//! ```
+//!
+//! Commands are `move`, `turn-left`, `take` and `put`; a rejected action
+//! gets `error <reason>` instead of `ok`. The server serializes all actions
+//! through one lock, so races stay fair and the world stays consistent.
+
+use std::collections::BTreeMap;
+use std::io::{BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use crate::environment::{Action, ActionFailure, Environment};
+use crate::json::Value;
+use crate::server::{read_line_capped, LineOutcome};
+use crate::world::{Position, Robot, World};
+use crate::worldfile;
+
+/// The most bytes accepted per command line. Commands are a word or two;
+/// a client that streams bytes without a newline gets disconnected
+/// instead of growing a buffer without bound.
+const MAX_LINE: usize = 256;
+
+/// The world and every connected robot, behind the server's lock.
+pub struct SharedWorld {
+    world: World,
+    robots: BTreeMap<usize, (String, Robot)>,
+    next_id: usize,
+}
+
+impl SharedWorld {
+    /// Host the given world. The world's own robot is ignored; every robot
+    /// in play belongs to a client.
+    pub fn new(world: World) -> SharedWorld {
+        SharedWorld {
+            world,
+            robots: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Add a robot for a new client and return its id. Robots spawn on the
+    /// first free tiles in reading order.
+    pub fn join(&mut self, name: &str) -> Option<usize> {
+        let spawn = self.spawn_position()?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.robots.insert(
+            id,
+            (
+                name.to_string(),
+                Robot::new(spawn, crate::world::Direction::East),
+            ),
+        );
+        Some(id)
+    }
+
+    /// Remove a disconnected client's robot.
+    pub fn leave(&mut self, id: usize) {
+        self.robots.remove(&id);
+    }
+
+    /// Perform one action with the given client's robot.
+    pub fn act(&mut self, id: usize, action: Action) -> Result<(), String> {
+        let Some((_, robot)) = self.robots.get(&id) else {
+            return Err("unknown robot".to_string());
+        };
+        // Reuse the grid rules by letting the world's robot seat stand in
+        // for this client's robot.
+        let parked = self.world.robot;
+        self.world.robot = *robot;
+        let result = self.world.perform(action);
+        let moved = self.world.robot;
+        self.world.robot = parked;
+        match result {
+            Ok(()) => {
+                self.robots.get_mut(&id).expect("checked above").1 = moved;
+                Ok(())
+            }
+            Err(ActionFailure::Blocked) => Err("a wall is in the way".to_string()),
+            Err(ActionFailure::NoBeeper) => Err("there is no beeper here".to_string()),
+            Err(ActionFailure::TileFull) => Err("this tile is full".to_string()),
+            Err(ActionFailure::Collision) => Err("another robot is in the way".to_string()),
+        }
+    }
+
+    /// The whole state as one JSON object: the world plus every robot.
+    pub fn state(&self) -> Value {
+        let robots: Vec<Value> = self
+            .robots
+            .iter()
+            .map(|(id, (name, robot))| {
+                Value::object([
+                    ("id", (*id).into()),
+                    ("name", name.as_str().into()),
+                    ("x", robot.position.x.into()),
+                    ("y", robot.position.y.into()),
+                    ("direction", worldfile::direction_name(robot.direction).into()),
+                ])
+            })
+            .collect();
+        let mut state = match worldfile::to_json(&self.world) {
+            Value::Object(world) => world,
+            _ => unreachable!("worlds serialize to objects"),
+        };
+        // The host world's own robot is not in play.
+        state.remove("robot");
+        state.insert("robots".to_string(), Value::Array(robots));
+        Value::Object(state)
+    }
+
+    fn spawn_position(&self) -> Option<Position> {
+        let taken: Vec<Position> = self
+            .robots
+            .values()
+            .map(|(_, robot)| robot.position)
+            .collect();
+        for y in 0..self.world.height() {
+            for x in 0..self.world.width() {
+                let position = Position::new(x, y);
+                if !self.world.is_wall(position) && !taken.contains(&position) {
+                    return Some(position);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Parse one client command line into an action.
+fn parse_action(line: &str) -> Option<Action> {
+    match line.trim() {
+        "move" => Some(Action::Move),
+        "turn-left" => Some(Action::TurnLeft),
+        "take" => Some(Action::Take),
+        "put" => Some(Action::Put),
+        "beep" => Some(Action::Beep),
+        _ => None,
+    }
+}
+
+/// Everything the connection threads share: the world plus the broadcast
+/// list of client sockets.
+struct Server {
+    shared: Mutex<SharedWorld>,
+    clients: Mutex<Vec<TcpStream>>,
+}
+
+impl Server {
+    /// Send one line to every connected client; drop sockets that fail.
+    fn broadcast(&self, line: &str) {
+        let mut clients = self.clients.lock().expect("no poisoned locks");
+        clients.retain_mut(|stream| writeln!(stream, "{line}").is_ok());
+    }
+}
+
+/// Serve `world` to every client that connects to `listener`, forever.
+pub fn serve(listener: TcpListener, world: World) -> std::io::Result<()> {
+    let server = Arc::new(Server {
+        shared: Mutex::new(SharedWorld::new(world)),
+        clients: Mutex::new(Vec::new()),
+    });
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || {
+            let _ = handle_client(&server, stream);
+        });
+    }
+    Ok(())
+}
+
+fn handle_client(server: &Server, stream: TcpStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    // The handshake: `hello <name>` before anything else.
+    let id = loop {
+        let line = match read_line_capped(&mut reader, MAX_LINE)? {
+            LineOutcome::Line(line) => line,
+            LineOutcome::Eof => return Ok(()),
+            LineOutcome::TooLong => {
+                writeln!(writer, "error line too long")?;
+                return Ok(());
+            }
+        };
+        match line.trim().strip_prefix("hello ") {
+            Some(name) if !name.trim().is_empty() => {
+                let joined = server
+                    .shared
+                    .lock()
+                    .expect("no poisoned locks")
+                    .join(name.trim());
+                match joined {
+                    Some(id) => {
+                        writeln!(writer, "welcome {id}")?;
+                        break id;
+                    }
+                    None => {
+                        writeln!(writer, "error the world is full")?;
+                        return Ok(());
+                    }
+                }
+            }
+            _ => writeln!(writer, "error say `hello <name>` first")?,
+        }
+    };
+    server
+        .clients
+        .lock()
+        .expect("no poisoned locks")
+        .push(writer.try_clone()?);
+    server.broadcast(&format!(
+        "state {}",
+        server.shared.lock().expect("no poisoned locks").state()
+    ));
+
+    loop {
+        let line = match read_line_capped(&mut reader, MAX_LINE) {
+            Ok(LineOutcome::Line(line)) => line,
+            Ok(LineOutcome::TooLong) => {
+                let _ = writeln!(writer, "error line too long");
+                break;
+            }
+            _ => break,
+        };
+        match parse_action(&line) {
+            Some(action) => {
+                let result = server
+                    .shared
+                    .lock()
+                    .expect("no poisoned locks")
+                    .act(id, action);
+                match result {
+                    Ok(()) => {
+                        writeln!(writer, "ok")?;
+                        server.broadcast(&format!(
+                            "state {}",
+                            server.shared.lock().expect("no poisoned locks").state()
+                        ));
+                    }
+                    Err(reason) => writeln!(writer, "error {reason}")?,
+                }
+            }
+            None => writeln!(writer, "error unknown command `{}`", line.trim())?,
+        }
+    }
+
+    server.shared.lock().expect("no poisoned locks").leave(id);
+    server.broadcast(&format!(
+        "state {}",
+        server.shared.lock().expect("no poisoned locks").state()
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn robots_spawn_on_free_tiles_and_act_independently() {
+        let mut world = World::new(3, 1);
+        world.set_wall(Position::new(1, 0), true);
+        let mut shared = SharedWorld::new(world);
+        let alice = shared.join("alice").unwrap();
+        let bob = shared.join("bob").unwrap();
+
+        // Alice spawned at (0,0) and is blocked by the wall at (1,0);
+        // Bob spawned behind it at (2,0).
+        assert!(shared.act(alice, Action::Move).is_err());
+        assert!(shared.act(bob, Action::Put).is_ok());
+        let state = shared.state().to_string();
+        assert!(state.contains("\"name\":\"alice\""), "{state}");
+        assert!(state.contains("\"name\":\"bob\""), "{state}");
+        assert!(state.contains("[2,0,1]"), "{state}");
+    }
+
+    #[test]
+    fn a_full_world_rejects_new_robots() {
+        let mut shared = SharedWorld::new(World::new(1, 1));
+        assert!(shared.join("only").is_some());
+        assert!(shared.join("late").is_none());
+    }
+
+    #[test]
+    fn leaving_frees_the_spawn_tile() {
+        let mut shared = SharedWorld::new(World::new(1, 1));
+        let id = shared.join("first").unwrap();
+        shared.leave(id);
+        assert!(shared.join("second").is_some());
+    }
+
+    #[test]
+    fn commands_map_to_actions() {
+        assert_eq!(parse_action(" move \n"), Some(Action::Move));
+        assert_eq!(parse_action("turn-left"), Some(Action::TurnLeft));
+        assert_eq!(parse_action("beep"), Some(Action::Beep));
+        assert_eq!(parse_action("dance"), None);
+    }
+}
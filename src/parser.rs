@@ -0,0 +1,1165 @@
+//! Turning Karel source text into something the interpreter can run.
+//!
+//! The language is line oriented: every line holds at most one instruction.
+//! Parsing therefore consists of a preprocessing pass that strips comments
+//! and blank lines, and a validation pass that checks the block structure
+//! (`def`/`enddef`, `if`/`endif`, ...) without executing anything.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A single preprocessed source line: the instruction text with comments and
+/// surrounding whitespace removed, plus the 1-based line and column it came
+/// from so errors can point back into the original file.
+///
+/// The text normally borrows straight from the source string — preprocessing
+/// a program allocates nothing per line. [`Line::into_owned`] detaches a line
+/// from its source for the few places that outlive it (a stored program, a
+/// synthesized line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line<'a> {
+    /// 0-based id of the file this line came from: always 0 for a program
+    /// preprocessed from one source, the [`SourceMap`] id when several
+    /// files were merged with [`preprocess_file`].
+    pub file: usize,
+    pub number: usize,
+    /// 1-based column of the first instruction character in the original line.
+    pub column: usize,
+    pub text: Cow<'a, str>,
+}
+
+impl Line<'_> {
+    /// Copy the text so the line no longer borrows from its source.
+    pub fn into_owned(self) -> Line<'static> {
+        Line {
+            file: self.file,
+            number: self.number,
+            column: self.column,
+            text: Cow::Owned(self.text.into_owned()),
+        }
+    }
+}
+
+/// Strip comments (everything after `#`) and whitespace from the source and
+/// drop lines that end up empty. The surviving lines keep their original
+/// line numbers and borrow their text from `source`.
+pub fn preprocess(source: &str) -> Vec<Line<'_>> {
+    preprocess_file(source, 0)
+}
+
+/// The comment syntax a source file uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// `#` to the end of the line — native Karel source.
+    #[default]
+    Hash,
+    /// `//` line comments and `/* ... */` block comments, as programs
+    /// imported from the Stanford/Java tradition carry them.
+    Slashes,
+}
+
+impl Dialect {
+    /// The dialect behind a command-line name.
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        match name {
+            "hash" => Some(Dialect::Hash),
+            "slashes" => Some(Dialect::Slashes),
+            _ => None,
+        }
+    }
+}
+
+/// [`preprocess`] with a chosen comment syntax. [`Dialect::Hash`] is
+/// exactly `preprocess`; [`Dialect::Slashes`] additionally splices out
+/// `/* ... */` spans, which may run over several lines, so its lines own
+/// their text instead of borrowing it.
+pub fn preprocess_dialect(source: &str, dialect: Dialect) -> Vec<Line<'_>> {
+    match dialect {
+        Dialect::Hash => preprocess(source),
+        Dialect::Slashes => {
+            let mut lines = Vec::new();
+            let mut in_block = false;
+            for (index, raw) in source.lines().enumerate() {
+                if let Some((column, text)) = strip_slashes_line(raw, &mut in_block) {
+                    lines.push(Line { file: 0, number: index + 1, column, text: Cow::Owned(text) });
+                }
+            }
+            lines
+        }
+    }
+}
+
+/// [`strip_line`] for the slash dialect, carrying the "inside `/* ... */`"
+/// state from line to line.
+fn strip_slashes_line(raw: &str, in_block: &mut bool) -> Option<(usize, String)> {
+    let mut kept = String::new();
+    // Byte offset in `raw` of the first retained instruction character.
+    let mut first: Option<usize> = None;
+    let mut offset = 0;
+    while offset <= raw.len() {
+        let rest = &raw[offset..];
+        if *in_block {
+            match rest.find("*/") {
+                Some(end) => {
+                    *in_block = false;
+                    offset += end + 2;
+                }
+                None => break,
+            }
+            continue;
+        }
+        let line_comment = rest.find("//");
+        let block_comment = rest.find("/*").filter(|start| line_comment.is_none_or(|l| *start < l));
+        let keep_end = block_comment.or(line_comment).unwrap_or(rest.len());
+        let piece = &rest[..keep_end];
+        if first.is_none() {
+            first = piece
+                .find(|c: char| !c.is_whitespace())
+                .map(|position| offset + position);
+        }
+        kept.push_str(piece);
+        // Splicing an inline block comment out must not glue its
+        // neighbours into one word.
+        kept.push(' ');
+        match block_comment {
+            Some(start) => {
+                *in_block = true;
+                offset += start + 2;
+            }
+            None => break,
+        }
+    }
+    let trimmed = kept.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some((first.unwrap_or(0) + 1, trimmed.to_string()))
+}
+
+/// [`preprocess`] for one file of a multi-file program: the retained lines
+/// carry `file` so diagnostics can point into the right file after the
+/// per-file line vectors are concatenated. Pair the ids with a
+/// [`SourceMap`] to render them.
+pub fn preprocess_file(source: &str, file: usize) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    for (index, raw) in source.lines().enumerate() {
+        if let Some((column, text)) = strip_line(raw) {
+            lines.push(Line {
+                file,
+                number: index + 1,
+                column,
+                text: Cow::Borrowed(text),
+            });
+        }
+    }
+    lines
+}
+
+/// Where a source file came from, for blaming errors fairly when a program
+/// was merged from several files: the code under grading, or scaffolding
+/// (teacher hooks, linked libraries) the student did not write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// The student's own file — errors in it are theirs to fix.
+    #[default]
+    Student,
+    /// Provided code linked beside the student's file; an error here is the
+    /// provider's bug, and feedback should say so.
+    Scaffold,
+}
+
+/// The file names behind the `file` ids of [`Line`]s, in id order. Built by
+/// whoever merges the files and consulted wherever a bare line number would
+/// be ambiguous.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    files: Vec<(String, Origin)>,
+}
+
+impl SourceMap {
+    /// Register the next file and return its id, for [`preprocess_file`].
+    pub fn add(&mut self, name: &str) -> usize {
+        self.files.push((name.to_string(), Origin::Student));
+        self.files.len() - 1
+    }
+
+    /// [`add`](SourceMap::add), but tagging the file as provided code
+    /// rather than the student's own.
+    pub fn add_scaffold(&mut self, name: &str) -> usize {
+        self.files.push((name.to_string(), Origin::Scaffold));
+        self.files.len() - 1
+    }
+
+    /// The name behind a file id; `"?"` for ids this map never issued.
+    pub fn name(&self, file: usize) -> &str {
+        self.files
+            .get(file)
+            .map(|(name, _)| name.as_str())
+            .unwrap_or("?")
+    }
+
+    /// The origin of a file; [`Origin::Student`] for ids this map never
+    /// issued, so a single-file program needs no map at all.
+    pub fn origin(&self, file: usize) -> Origin {
+        self.files
+            .get(file)
+            .map(|(_, origin)| *origin)
+            .unwrap_or_default()
+    }
+
+    /// Pin an error message on the file it arose in: scaffolding errors are
+    /// prefixed with the file they belong to, the student's own pass
+    /// through unchanged. Use this on runtime errors and trace verdicts of
+    /// merged programs so feedback does not blame students for bugs in
+    /// provided code.
+    pub fn attribute(&self, file: usize, message: &str) -> String {
+        match self.origin(file) {
+            Origin::Student => message.to_string(),
+            Origin::Scaffold => {
+                alloc::format!("in provided code (`{}`): {message}", self.name(file))
+            }
+        }
+    }
+
+    /// A location as the user knows it: `name:line` when several files are
+    /// in play, plain `line N` when there is at most one.
+    pub fn locate(&self, file: usize, line: usize) -> String {
+        if self.files.len() > 1 {
+            alloc::format!("{}:{line}", self.name(file))
+        } else {
+            alloc::format!("line {line}")
+        }
+    }
+}
+
+/// Strip the comment and whitespace off one raw line, returning the 1-based
+/// column of the instruction and its text, or `None` for lines that are all
+/// blank or comment.
+fn strip_line(raw: &str) -> Option<(usize, &str)> {
+    let text = match raw.find('#') {
+        Some(comment_start) => &raw[..comment_start],
+        None => raw,
+    };
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some((text.len() - text.trim_start().len() + 1, trimmed))
+}
+
+/// The preprocessed form of one raw source line, kept by [`Incremental`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProcessedLine {
+    column: usize,
+    text: String,
+}
+
+/// Preprocess one raw line on its own: the per-line half of [`preprocess`],
+/// owning its text so it can outlive the source.
+fn process_line(raw: &str) -> Option<ProcessedLine> {
+    strip_line(raw).map(|(column, text)| ProcessedLine {
+        column,
+        text: text.to_string(),
+    })
+}
+
+/// Preprocessing that survives edits: keeps the processed form of every
+/// source line and, on an update, re-tokenizes only the lines that actually
+/// changed. Editors re-sending a large lesson file after a one-line edit
+/// then pay for one line, not the whole program; unchanged lines are reused
+/// and merely renumbered.
+#[derive(Debug, Clone, Default)]
+pub struct Incremental {
+    /// The raw source lines, for diffing against the next update.
+    raw: Vec<String>,
+    /// Per raw line: its preprocessed form, or `None` for blanks/comments.
+    processed: Vec<Option<ProcessedLine>>,
+}
+
+impl Incremental {
+    pub fn new(source: &str) -> Incremental {
+        let mut incremental = Incremental::default();
+        incremental.update(source);
+        incremental
+    }
+
+    /// Replace the source with `source`, re-tokenizing only the changed
+    /// region (the lines between the longest common prefix and suffix of
+    /// the old and new text). Returns how many lines were re-tokenized.
+    pub fn update(&mut self, source: &str) -> usize {
+        let new_raw: Vec<&str> = source.lines().collect();
+        let prefix = self
+            .raw
+            .iter()
+            .zip(&new_raw)
+            .take_while(|(old, new)| old.as_str() == **new)
+            .count();
+        let comparable = self.raw.len().min(new_raw.len()) - prefix;
+        let suffix = self.raw[self.raw.len() - comparable..]
+            .iter()
+            .rev()
+            .zip(new_raw[new_raw.len() - comparable..].iter().rev())
+            .take_while(|(old, new)| old.as_str() == **new)
+            .count();
+
+        let changed = new_raw.len() - suffix - prefix;
+        let mut processed = Vec::with_capacity(new_raw.len());
+        processed.extend_from_slice(&self.processed[..prefix]);
+        processed.extend(
+            new_raw[prefix..prefix + changed]
+                .iter()
+                .map(|raw| process_line(raw)),
+        );
+        processed.extend_from_slice(&self.processed[self.processed.len() - suffix..]);
+
+        self.raw = new_raw.into_iter().map(str::to_string).collect();
+        self.processed = processed;
+        changed
+    }
+
+    /// The preprocessed lines, exactly as [`preprocess`] would produce them,
+    /// borrowing from the cache.
+    pub fn lines(&self) -> Vec<Line<'_>> {
+        self.processed
+            .iter()
+            .enumerate()
+            .filter_map(|(index, processed)| {
+                processed.as_ref().map(|line| Line {
+                    file: 0,
+                    number: index + 1,
+                    column: line.column,
+                    text: Cow::Borrowed(line.text.as_str()),
+                })
+            })
+            .collect()
+    }
+}
+
+/// An error found while validating a program, before it is ever run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// `def` appeared inside another `def`.
+    NestedDefinition { line: usize },
+    /// An instruction appeared outside any `def` block.
+    InstructionOutsideDefinition { line: usize },
+    /// A block end (`enddef`, `endif`, ...) without a matching opener.
+    UnmatchedBlockEnd { line: usize, keyword: String },
+    /// A block opener whose end was never found.
+    UnclosedBlock { line: usize, keyword: String },
+    /// The line is not any known instruction.
+    UnknownInstruction { line: usize, instruction: String },
+    /// `if`/`while` with a condition that does not exist.
+    UnknownCondition { line: usize, condition: String },
+    /// `repeat` without a positive number of repetitions.
+    BadRepeatCount { line: usize },
+    /// `clear` without a positive number of tiles to look ahead.
+    BadClearDistance { line: usize },
+    /// `print` with something it cannot print.
+    BadPrintItem { line: usize },
+    /// A `beeper` comparison that is not `beeper OP count`.
+    BadBeeperComparison { line: usize },
+    /// `in-region` without exactly one region name.
+    BadRegionName { line: usize },
+    /// `call` or `def` without a name, or with several.
+    BadName { line: usize },
+    /// The same procedure is defined twice.
+    DuplicateDefinition { line: usize, name: String },
+    /// `call` of a procedure that is defined nowhere.
+    UnknownProcedure { line: usize, name: String },
+    /// The same label is defined twice.
+    DuplicateLabel { line: usize, name: String },
+    /// `goto` of a label that is defined nowhere.
+    UnknownLabel { line: usize, name: String },
+    /// Strict mode only: a statement that takes no arguments was given
+    /// some (`turn-left left`, `enddef now`).
+    TrailingTokens { line: usize, keyword: String },
+    /// A definition that is `main` up to letter case (`def Main`).
+    MisnamedMain { line: usize, name: String },
+    /// The file has no instructions at all (blank or only comments).
+    EmptyProgram,
+    /// There is no `def main` to start from.
+    MissingMain,
+}
+
+impl ParseError {
+    /// The source line the error points at, if it points at one.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ParseError::NestedDefinition { line }
+            | ParseError::InstructionOutsideDefinition { line }
+            | ParseError::UnmatchedBlockEnd { line, .. }
+            | ParseError::UnclosedBlock { line, .. }
+            | ParseError::UnknownInstruction { line, .. }
+            | ParseError::UnknownCondition { line, .. }
+            | ParseError::BadRepeatCount { line }
+            | ParseError::BadClearDistance { line }
+            | ParseError::BadPrintItem { line }
+            | ParseError::BadBeeperComparison { line }
+            | ParseError::BadRegionName { line }
+            | ParseError::BadName { line }
+            | ParseError::DuplicateDefinition { line, .. }
+            | ParseError::UnknownProcedure { line, .. }
+            | ParseError::DuplicateLabel { line, .. }
+            | ParseError::UnknownLabel { line, .. }
+            | ParseError::TrailingTokens { line, .. }
+            | ParseError::MisnamedMain { line, .. } => Some(*line),
+            ParseError::EmptyProgram | ParseError::MissingMain => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NestedDefinition { .. } => {
+                write!(f, "`def` inside another `def`")
+            }
+            ParseError::InstructionOutsideDefinition { .. } => {
+                write!(f, "instruction outside of any `def` block")
+            }
+            ParseError::UnmatchedBlockEnd { keyword, .. } => {
+                write!(f, "`{keyword}` without a matching block start")
+            }
+            ParseError::UnclosedBlock { keyword, .. } => {
+                write!(f, "`{keyword}` block is never closed")
+            }
+            ParseError::UnknownInstruction { instruction, .. } => {
+                write!(f, "unknown instruction `{instruction}`")
+            }
+            ParseError::UnknownCondition { condition, .. } => {
+                write!(f, "unknown condition `{condition}`")
+            }
+            ParseError::BadRepeatCount { .. } => {
+                write!(f, "`repeat` needs a positive number")
+            }
+            ParseError::BadClearDistance { .. } => {
+                write!(f, "`clear` needs a positive number of tiles")
+            }
+            ParseError::BadPrintItem { .. } => {
+                write!(f, "`print` can only print `direction`")
+            }
+            ParseError::BadBeeperComparison { .. } => {
+                write!(f, "`beeper` comparisons look like `beeper >= 3`")
+            }
+            ParseError::BadRegionName { .. } => {
+                write!(f, "`in-region` needs a region name")
+            }
+            ParseError::BadName { .. } => {
+                write!(f, "expected exactly one name")
+            }
+            ParseError::DuplicateDefinition { name, .. } => {
+                write!(f, "procedure `{name}` is defined twice")
+            }
+            ParseError::UnknownProcedure { name, .. } => {
+                write!(f, "call of unknown procedure `{name}`")
+            }
+            ParseError::DuplicateLabel { name, .. } => {
+                write!(f, "label `{name}` is defined twice")
+            }
+            ParseError::UnknownLabel { name, .. } => {
+                write!(f, "goto of unknown label `{name}`")
+            }
+            ParseError::TrailingTokens { keyword, .. } => {
+                write!(f, "`{keyword}` takes no arguments; delete the rest of the line")
+            }
+            ParseError::MisnamedMain { name, .. } => {
+                write!(
+                    f,
+                    "`def {name}` is almost `def main`, but names are case-sensitive; rename it to `def main`"
+                )
+            }
+            ParseError::EmptyProgram => {
+                write!(
+                    f,
+                    "the program has no instructions; write them inside a `def main ... enddef` block"
+                )
+            }
+            ParseError::MissingMain => write!(
+                f,
+                "there is no `def main` to start from; wrap the program's first steps in `def main ... enddef`"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+pub(crate) const CONDITIONS: &[&str] =
+    &["wall", "north", "south", "east", "west", "beeper", "beeper-ahead"];
+
+fn is_condition(word: &str) -> bool {
+    CONDITIONS.contains(&word)
+}
+
+/// A [`ParseError`] together with the 1-based column it points at, for
+/// editors and rustc-style terminal output. Errors that do not point at a
+/// source location (like [`ParseError::MissingMain`]) have `line()` `None`
+/// and a column of 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The [`Line::file`] id of the offending line; 0 for single-file
+    /// programs and for errors with no location at all.
+    pub file: usize,
+    pub column: usize,
+    pub error: ParseError,
+}
+
+impl Diagnostic {
+    fn at(file: usize, column: usize, error: ParseError) -> Diagnostic {
+        Diagnostic { file, column, error }
+    }
+}
+
+/// 1-based column of the `index`-th whitespace-separated word of the line,
+/// in the original source.
+fn word_column(line: &Line<'_>, index: usize) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for (offset, character) in line.text.char_indices() {
+        if character.is_whitespace() {
+            in_word = false;
+        } else if !in_word {
+            if words == index {
+                return line.column + offset;
+            }
+            words += 1;
+            in_word = true;
+        }
+    }
+    line.column
+}
+
+/// Check that the program is well formed: blocks match up, all instructions
+/// and conditions exist, every `call` target is defined and there is a
+/// `def main`. Collects every problem it can find instead of stopping at the
+/// first one, recovering as well as it can after each.
+pub fn check(lines: &[Line<'_>]) -> Vec<Diagnostic> {
+    check_with(lines, false)
+}
+
+/// [`check`], but also rejecting trailing tokens on statements that take no
+/// arguments. The lenient checker inspects only what it needs — `enddef now`
+/// passes because the keyword matches, and `turn-left left` draws a generic
+/// "unknown instruction" — while strict mode pins both down as
+/// [`ParseError::TrailingTokens`]. Worth turning on in classrooms, where
+/// the extra words are almost always a typo.
+pub fn check_strict(lines: &[Line<'_>]) -> Vec<Diagnostic> {
+    check_with(lines, true)
+}
+
+fn check_with(lines: &[Line<'_>], strict: bool) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut definitions: Vec<String> = Vec::new();
+    let mut calls: Vec<(usize, usize, usize, String)> = Vec::new();
+    let mut labels: Vec<String> = Vec::new();
+    let mut gotos: Vec<(usize, usize, usize, String)> = Vec::new();
+    // Stack of open blocks as (file, line number, column, keyword).
+    let mut blocks: Vec<(usize, usize, usize, &str)> = Vec::new();
+
+    for line in lines {
+        let mut words = line.text.split_whitespace();
+        let keyword = words.next().expect("preprocessed lines are not empty");
+        let rest: Vec<&str> = words.collect();
+        let in_definition = !blocks.is_empty();
+
+        match keyword {
+            "def" => {
+                if in_definition {
+                    diagnostics.push(Diagnostic::at(
+                        line.file,
+                        line.column,
+                        ParseError::NestedDefinition { line: line.number },
+                    ));
+                    continue;
+                }
+                let [name] = rest[..] else {
+                    diagnostics.push(Diagnostic::at(
+                        line.file,
+                        line.column,
+                        ParseError::BadName { line: line.number },
+                    ));
+                    blocks.push((line.file, line.number, line.column, "def"));
+                    continue;
+                };
+                if definitions.iter().any(|known| known == name) {
+                    diagnostics.push(Diagnostic::at(
+                        line.file,
+                        word_column(line, 1),
+                        ParseError::DuplicateDefinition {
+                            line: line.number,
+                            name: name.to_string(),
+                        },
+                    ));
+                } else {
+                    definitions.push(name.to_string());
+                }
+                blocks.push((line.file, line.number, line.column, "def"));
+            }
+            "enddef" | "endif" | "endwhile" | "endrepeat" => {
+                if strict && !rest.is_empty() {
+                    diagnostics.push(Diagnostic::at(
+                        line.file,
+                        word_column(line, 1),
+                        ParseError::TrailingTokens {
+                            line: line.number,
+                            keyword: keyword.to_string(),
+                        },
+                    ));
+                }
+                let expected = keyword.strip_prefix("end").unwrap();
+                match blocks.last() {
+                    Some((_, _, _, open)) if *open == expected => {
+                        blocks.pop();
+                    }
+                    _ => {
+                        diagnostics.push(Diagnostic::at(
+                            line.file,
+                            line.column,
+                            ParseError::UnmatchedBlockEnd {
+                                line: line.number,
+                                keyword: keyword.to_string(),
+                            },
+                        ));
+                        // Recover by dropping open blocks up to a matching
+                        // opener, so one stray end does not cascade.
+                        if let Some(matching) =
+                            blocks.iter().rposition(|(_, _, _, open)| *open == expected)
+                        {
+                            blocks.truncate(matching);
+                        }
+                    }
+                }
+            }
+            _ if !in_definition => {
+                diagnostics.push(Diagnostic::at(
+                    line.file,
+                    line.column,
+                    ParseError::InstructionOutsideDefinition { line: line.number },
+                ));
+            }
+            "if" | "if!" | "while" | "while!" => {
+                match rest[..] {
+                    [condition] if is_condition(condition) => {}
+                    ["beeper", operator, count]
+                        if crate::environment::Comparison::parse(operator).is_some()
+                            && count.parse::<usize>().is_ok() => {}
+                    ["beeper", _, ..] => {
+                        diagnostics.push(Diagnostic::at(
+                            line.file,
+                            word_column(line, 1),
+                            ParseError::BadBeeperComparison { line: line.number },
+                        ));
+                    }
+                    ["in-region", _name] => {}
+                    ["in-region", ..] => {
+                        diagnostics.push(Diagnostic::at(
+                            line.file,
+                            word_column(line, 1),
+                            ParseError::BadRegionName { line: line.number },
+                        ));
+                    }
+                    ["clear", ref distance @ ..] => {
+                        let tiles = match distance {
+                            [tiles] => tiles.parse::<usize>().ok(),
+                            _ => None,
+                        };
+                        if tiles.is_none_or(|tiles| tiles == 0) {
+                            diagnostics.push(Diagnostic::at(
+                                line.file,
+                                word_column(line, 1),
+                                ParseError::BadClearDistance { line: line.number },
+                            ));
+                        }
+                    }
+                    [condition] => diagnostics.push(Diagnostic::at(
+                        line.file,
+                        word_column(line, 1),
+                        ParseError::UnknownCondition {
+                            line: line.number,
+                            condition: condition.to_string(),
+                        },
+                    )),
+                    _ => diagnostics.push(Diagnostic::at(
+                        line.file,
+                        line.column,
+                        ParseError::UnknownCondition {
+                            line: line.number,
+                            condition: rest.join(" "),
+                        },
+                    )),
+                }
+                // Open the block even on a bad condition so its end matches.
+                blocks.push((line.file, line.number, line.column, keyword.trim_end_matches('!')));
+            }
+            "repeat" => {
+                let count = match rest[..] {
+                    [count] => count.parse::<usize>().ok(),
+                    _ => None,
+                };
+                if count.is_none_or(|count| count == 0) {
+                    diagnostics.push(Diagnostic::at(
+                        line.file,
+                        line.column,
+                        ParseError::BadRepeatCount { line: line.number },
+                    ));
+                }
+                blocks.push((line.file, line.number, line.column, "repeat"));
+            }
+            "call" => match rest[..] {
+                [name] => calls.push((line.file, line.number, word_column(line, 1), name.to_string())),
+                _ => diagnostics.push(Diagnostic::at(
+                    line.file,
+                    line.column,
+                    ParseError::BadName { line: line.number },
+                )),
+            },
+            // Low-level jump targets for generated code (transpilers, the
+            // bytecode backend); student-facing material does not mention
+            // them, but validation holds them to the same standard.
+            "label" => match rest[..] {
+                [name] => {
+                    if labels.iter().any(|known| known == name) {
+                        diagnostics.push(Diagnostic::at(
+                            line.file,
+                            word_column(line, 1),
+                            ParseError::DuplicateLabel {
+                                line: line.number,
+                                name: name.to_string(),
+                            },
+                        ));
+                    } else {
+                        labels.push(name.to_string());
+                    }
+                }
+                _ => diagnostics.push(Diagnostic::at(
+                    line.file,
+                    line.column,
+                    ParseError::BadName { line: line.number },
+                )),
+            },
+            "goto" => match rest[..] {
+                [name] => {
+                    gotos.push((line.file, line.number, word_column(line, 1), name.to_string()))
+                }
+                _ => diagnostics.push(Diagnostic::at(
+                    line.file,
+                    line.column,
+                    ParseError::BadName { line: line.number },
+                )),
+            },
+            // `???` is the placeholder of a solution template: it parses
+            // like any zero-argument instruction and only errors when the
+            // run actually reaches it.
+            "move" | "turn-left" | "take" | "put" | "beep" | "die" | "???"
+                if rest.is_empty() => {}
+            // A known instruction with extra words: strict mode names the
+            // real mistake instead of the generic "unknown instruction"
+            // the fall-through arm would give it.
+            "move" | "turn-left" | "take" | "put" | "beep" | "die" | "???" if strict => {
+                diagnostics.push(Diagnostic::at(
+                    line.file,
+                    word_column(line, 1),
+                    ParseError::TrailingTokens {
+                        line: line.number,
+                        keyword: keyword.to_string(),
+                    },
+                ));
+            }
+            "print" => {
+                if rest[..] != ["direction"] {
+                    diagnostics.push(Diagnostic::at(
+                        line.file,
+                        line.column,
+                        ParseError::BadPrintItem { line: line.number },
+                    ));
+                }
+            }
+            _ => {
+                diagnostics.push(Diagnostic::at(
+                    line.file,
+                    line.column,
+                    ParseError::UnknownInstruction {
+                        line: line.number,
+                        instruction: line.text.to_string(),
+                    },
+                ));
+            }
+        }
+    }
+
+    for (file, number, column, keyword) in blocks {
+        diagnostics.push(Diagnostic::at(
+            file,
+            column,
+            ParseError::UnclosedBlock {
+                line: number,
+                keyword: keyword.to_string(),
+            },
+        ));
+    }
+    for (file, line, column, name) in calls {
+        if !definitions.contains(&name) {
+            diagnostics.push(Diagnostic::at(
+                file,
+                column,
+                ParseError::UnknownProcedure { line, name },
+            ));
+        }
+    }
+    for (file, line, column, name) in gotos {
+        if !labels.contains(&name) {
+            diagnostics.push(Diagnostic::at(
+                file,
+                column,
+                ParseError::UnknownLabel { line, name },
+            ));
+        }
+    }
+    if !definitions.iter().any(|name| name == "main") {
+        // Be precise about the likely fix: a `def Main`, an empty file and
+        // a file of loose instructions each call for a different edit.
+        let near_miss = lines.iter().find(|line| {
+            let mut words = line.text.split_whitespace();
+            words.next() == Some("def")
+                && words.next().is_some_and(|name| name.eq_ignore_ascii_case("main"))
+        });
+        let diagnostic = match near_miss {
+            Some(line) => Diagnostic::at(
+                line.file,
+                line.column,
+                ParseError::MisnamedMain {
+                    line: line.number,
+                    name: line.text.split_whitespace().nth(1).unwrap_or("").to_string(),
+                },
+            ),
+            None if lines.is_empty() => Diagnostic::at(0, 1, ParseError::EmptyProgram),
+            None => Diagnostic::at(0, 1, ParseError::MissingMain),
+        };
+        diagnostics.push(diagnostic);
+    }
+    #[cfg(feature = "tracing")]
+    if let Some(subscriber) = crate::log::subscriber() {
+        subscriber.checked(lines.len(), diagnostics.len());
+    }
+    diagnostics
+}
+
+/// Entry point for fuzzers: arbitrary bytes in, diagnostics (or a rejection
+/// of invalid UTF-8) out, and never a panic. Untrusted student input goes
+/// through exactly this preprocess-and-check pipeline, so this is the
+/// surface worth hammering.
+pub fn parse_for_fuzzing(data: &[u8]) -> Result<Vec<Diagnostic>, core::str::Utf8Error> {
+    let source = core::str::from_utf8(data)?;
+    Ok(check(&preprocess(source)))
+}
+
+/// Like [`check`], but stops at the first error. Handy when the caller only
+/// wants to know whether the program may run at all.
+pub fn validate(lines: &[Line<'_>]) -> Result<(), ParseError> {
+    match check(lines).into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.error),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preprocess_borrows_from_the_source() {
+        let source = "def main\n  move\nenddef\n";
+        let lines = preprocess(source);
+        assert!(lines.iter().all(|line| matches!(line.text, Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn preprocess_strips_comments_and_blanks() {
+        let lines = preprocess("def main # start\n\n  move\n# whole line\nenddef\n");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], Line { file: 0, number: 1, column: 1, text: "def main".into() });
+        assert_eq!(lines[1], Line { file: 0, number: 3, column: 3, text: "move".into() });
+        assert_eq!(lines[2], Line { file: 0, number: 5, column: 1, text: "enddef".into() });
+    }
+
+    #[test]
+    fn merged_files_keep_their_identity_in_diagnostics() {
+        let mut map = SourceMap::default();
+        let mut lines = preprocess_file("def helper\n fly\nenddef", map.add("lib.kl"));
+        lines.extend(preprocess_file("def main\n call helper\nenddef", map.add("main.kl")));
+
+        let diagnostics = check(&lines);
+        assert_eq!(diagnostics.len(), 1);
+        // `fly` is on line 2 of lib.kl; a bare "line 2" would point at
+        // `call helper` in main.kl just as well.
+        assert_eq!(diagnostics[0].file, 0);
+        let line = diagnostics[0].error.line().unwrap();
+        assert_eq!(map.locate(diagnostics[0].file, line), "lib.kl:2");
+        // With at most one file the classic rendering stays.
+        assert_eq!(SourceMap::default().locate(0, 2), "line 2");
+    }
+
+    #[test]
+    fn scaffold_files_are_attributed_to_their_provider() {
+        let mut map = SourceMap::default();
+        let student = map.add("main.kl");
+        let library = map.add_scaffold("lib.kl");
+        assert_eq!(map.origin(student), Origin::Student);
+        assert_eq!(map.origin(library), Origin::Scaffold);
+        // Ids the map never issued default to the student's own code.
+        assert_eq!(map.origin(9), Origin::Student);
+
+        assert_eq!(map.attribute(student, "line 2: hit a wall"), "line 2: hit a wall");
+        assert_eq!(
+            map.attribute(library, "line 2: hit a wall"),
+            "in provided code (`lib.kl`): line 2: hit a wall"
+        );
+    }
+
+    #[test]
+    fn the_slash_dialect_strips_line_and_block_comments() {
+        let source =
+            "// header\ndef main /* entry */\n move // east\n /* multi\nline */ put\nenddef";
+        let lines = preprocess_dialect(source, Dialect::Slashes);
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_ref()).collect();
+        assert_eq!(texts, ["def main", "move", "put", "enddef"]);
+        let numbers: Vec<usize> = lines.iter().map(|line| line.number).collect();
+        assert_eq!(numbers, [2, 3, 5, 6]);
+        // Columns point into the original line, comments and all.
+        assert_eq!(lines[2].column, 9);
+        assert!(check(&lines).is_empty());
+
+        // The default dialect is untouched: `//` is no comment there.
+        assert_eq!(preprocess_dialect(source, Dialect::Hash), preprocess(source));
+    }
+
+    #[test]
+    fn goto_targets_must_exist() {
+        let source = "def main\n label top\n move\n goto top\nenddef";
+        assert!(check(&preprocess(source)).is_empty());
+
+        let diagnostics = check(&preprocess("def main\n goto nowhere\nenddef"));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].error,
+            ParseError::UnknownLabel { line: 2, name: "nowhere".to_string() }
+        );
+
+        let diagnostics = check(&preprocess("def main\n label twice\n label twice\nenddef"));
+        assert_eq!(
+            diagnostics[0].error,
+            ParseError::DuplicateLabel { line: 3, name: "twice".to_string() }
+        );
+    }
+
+    #[test]
+    fn strict_mode_rejects_trailing_tokens() {
+        let source = "def main\n turn-left left\nenddef now";
+        // Leniently, `enddef now` slips through entirely and the typo is
+        // only a generic unknown instruction.
+        let lenient = check(&preprocess(source));
+        assert_eq!(lenient.len(), 1);
+        assert!(matches!(lenient[0].error, ParseError::UnknownInstruction { .. }));
+
+        let strict = check_strict(&preprocess(source));
+        assert_eq!(strict.len(), 2);
+        assert_eq!(
+            strict[0].error,
+            ParseError::TrailingTokens { line: 2, keyword: "turn-left".to_string() }
+        );
+        assert_eq!(
+            strict[1].error,
+            ParseError::TrailingTokens { line: 3, keyword: "enddef".to_string() }
+        );
+        // The diagnostic points at the first extra word.
+        assert_eq!(strict[0].column, 12);
+
+        // A clean program is clean in both modes.
+        assert!(check_strict(&preprocess("def main\n move\nenddef")).is_empty());
+    }
+
+    #[test]
+    fn incremental_matches_full_preprocessing() {
+        let original = "def main\n  move # east\n\n  put\nenddef\n";
+        let edited = "def main\n  move # east\n  turn-left\n  put\nenddef\n";
+        let mut incremental = Incremental::new(original);
+        assert_eq!(incremental.lines(), preprocess(original));
+        incremental.update(edited);
+        assert_eq!(incremental.lines(), preprocess(edited));
+    }
+
+    #[test]
+    fn incremental_retokenizes_only_the_changed_region() {
+        let mut source = String::from("def main\n");
+        for _ in 0..500 {
+            source.push_str("  move\n");
+        }
+        source.push_str("enddef\n");
+        let mut incremental = Incremental::new(&source);
+        // Change one line in the middle: exactly one line is re-tokenized.
+        let edited = source.replacen("  move\n", "  put\n", 1);
+        assert_eq!(incremental.update(&edited), 1);
+        // Insert a line: only the insertion is new work, and the lines
+        // after it are renumbered correctly.
+        let inserted = edited.replacen("enddef", "  take\nenddef", 1);
+        assert_eq!(incremental.update(&inserted), 1);
+        assert_eq!(incremental.lines(), preprocess(&inserted));
+    }
+
+    fn first_error(source: &str) -> Result<(), ParseError> {
+        validate(&preprocess(source))
+    }
+
+    #[test]
+    fn valid_program_passes() {
+        let source = "def main\n  if! beeper\n    put\n  endif\n  repeat 3\n    call spin\n  endrepeat\n  die\nenddef\ndef spin\n  turn-left\nenddef\n";
+        assert_eq!(first_error(source), Ok(()));
+    }
+
+    #[test]
+    fn missing_main_is_reported() {
+        assert_eq!(first_error("def helper\n move\nenddef"), Err(ParseError::MissingMain));
+    }
+
+    #[test]
+    fn near_misses_of_main_get_their_own_guidance() {
+        // A case slip points at the definition to rename.
+        assert_eq!(
+            first_error("def Main\n move\nenddef"),
+            Err(ParseError::MisnamedMain { line: 1, name: "Main".to_string() })
+        );
+        // Nothing but comments is not a missing name, it is an empty file.
+        assert_eq!(first_error(""), Err(ParseError::EmptyProgram));
+        assert_eq!(first_error("# thoughts only\n\n"), Err(ParseError::EmptyProgram));
+        // Loose instructions are flagged line by line, and the missing
+        // `def main` skeleton is still asked for.
+        let diagnostics = check(&preprocess("move\nmove\n"));
+        assert_eq!(
+            diagnostics[0].error,
+            ParseError::InstructionOutsideDefinition { line: 1 }
+        );
+        assert!(diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.error == ParseError::MissingMain));
+    }
+
+    #[test]
+    fn unclosed_block_is_reported() {
+        assert_eq!(
+            first_error("def main\n while wall\n move\nenddef"),
+            Err(ParseError::UnmatchedBlockEnd { line: 4, keyword: "enddef".to_string() })
+        );
+    }
+
+    #[test]
+    fn unknown_instruction_is_reported() {
+        assert_eq!(
+            first_error("def main\n fly\nenddef"),
+            Err(ParseError::UnknownInstruction { line: 2, instruction: "fly".to_string() })
+        );
+    }
+
+    #[test]
+    fn unknown_procedure_is_reported() {
+        assert_eq!(
+            first_error("def main\n call nowhere\nenddef"),
+            Err(ParseError::UnknownProcedure { line: 2, name: "nowhere".to_string() })
+        );
+    }
+
+    #[test]
+    fn check_collects_every_diagnostic_with_columns() {
+        let lines = preprocess("def main\n  fly\n  if goblin\n    move\n  endif\nenddef\n");
+        let diagnostics = check(&lines);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].column, 3);
+        assert_eq!(
+            diagnostics[0].error,
+            ParseError::UnknownInstruction { line: 2, instruction: "fly".to_string() }
+        );
+        assert_eq!(diagnostics[1].column, 6);
+        assert_eq!(
+            diagnostics[1].error,
+            ParseError::UnknownCondition { line: 3, condition: "goblin".to_string() }
+        );
+    }
+
+    #[test]
+    fn clear_takes_a_positive_distance() {
+        assert_eq!(first_error("def main\n if clear 3\n move\n endif\nenddef"), Ok(()));
+        for bad in ["clear", "clear 0", "clear x", "clear 1 2"] {
+            assert_eq!(
+                first_error(&format!("def main\n if {bad}\n move\n endif\nenddef")),
+                Err(ParseError::BadClearDistance { line: 2 })
+            );
+        }
+    }
+
+    #[test]
+    fn beeper_comparisons_need_an_operator_and_a_count() {
+        for good in ["beeper < 1", "beeper <= 2", "beeper = 3", "beeper >= 4", "beeper > 5"] {
+            assert_eq!(
+                first_error(&format!("def main\n if {good}\n move\n endif\nenddef")),
+                Ok(())
+            );
+        }
+        for bad in ["beeper >=", "beeper => 3", "beeper >= x", "beeper >= 3 4"] {
+            assert_eq!(
+                first_error(&format!("def main\n if {bad}\n move\n endif\nenddef")),
+                Err(ParseError::BadBeeperComparison { line: 2 })
+            );
+        }
+    }
+
+    #[test]
+    fn in_region_needs_exactly_one_name() {
+        assert_eq!(
+            first_error("def main\n if in-region home\n move\n endif\nenddef"),
+            Ok(())
+        );
+        for bad in ["in-region", "in-region a b"] {
+            assert_eq!(
+                first_error(&format!("def main\n if {bad}\n move\n endif\nenddef")),
+                Err(ParseError::BadRegionName { line: 2 })
+            );
+        }
+    }
+
+    #[test]
+    fn print_only_knows_direction() {
+        assert_eq!(first_error("def main\n print direction\nenddef"), Ok(()));
+        assert_eq!(
+            first_error("def main\n print beepers\nenddef"),
+            Err(ParseError::BadPrintItem { line: 2 })
+        );
+    }
+
+    #[test]
+    fn unknown_condition_is_reported() {
+        assert_eq!(
+            first_error("def main\n if goblin\n move\n endif\nenddef"),
+            Err(ParseError::UnknownCondition { line: 2, condition: "goblin".to_string() })
+        );
+    }
+
+    #[test]
+    fn fuzzing_entry_point_survives_arbitrary_bytes() {
+        // A deterministic stand-in for the real fuzzer: xorshift-generated
+        // byte soup must come back as diagnostics, never a panic.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for length in 0..512 {
+            let mut data = Vec::with_capacity(length);
+            for _ in 0..length {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                data.push(state as u8);
+            }
+            let _ = parse_for_fuzzing(&data);
+        }
+        assert!(parse_for_fuzzing(b"def main\n move\nenddef").is_ok_and(|d| d.is_empty()));
+    }
+}
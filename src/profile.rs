@@ -0,0 +1,270 @@
+//! Where the steps went: per-line and per-procedure cost of one run.
+//!
+//! A slow solution is rarely slow everywhere; it loops in one place. The
+//! profiler attributes every executed instruction — and the wall time it
+//! took — to its source line and to the innermost procedure it ran in, so
+//! the report can say "80% of the steps were inside `find-beeper`" instead
+//! of leaving the student to guess. It also tells interpreter work where to
+//! aim: the hottest lines are the ones worth making cheaper.
+//!
+//! Drive a run yourself with [`Profiler::before`]/[`Profiler::after`]
+//! around each step, or hand an interpreter to [`profile`] and get the
+//! finished [`Profile`] back.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+
+/// The cost attributed to one line or one procedure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sample {
+    /// Instructions executed, control flow included.
+    pub steps: usize,
+    /// Wall time those steps took, profiler overhead included.
+    pub time: Duration,
+}
+
+/// What one run cost, broken down by source line and by procedure.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// The whole run.
+    pub total: Sample,
+    /// The error that ended the run, if one did; the samples up to it are
+    /// still valid, and often point straight at the culprit.
+    pub error: Option<String>,
+    lines: BTreeMap<usize, Sample>,
+    procedures: BTreeMap<String, Sample>,
+    /// Steps per full call stack, keyed root-first and `;`-joined
+    /// ("main;find-beeper"), as the folded-stacks tools expect.
+    stacks: BTreeMap<String, usize>,
+}
+
+impl Profile {
+    /// Per-line samples, most steps first.
+    pub fn by_line(&self) -> Vec<(usize, Sample)> {
+        let mut lines: Vec<(usize, Sample)> =
+            self.lines.iter().map(|(line, sample)| (*line, *sample)).collect();
+        lines.sort_by(|a, b| b.1.steps.cmp(&a.1.steps).then(a.0.cmp(&b.0)));
+        lines
+    }
+
+    /// Per-procedure samples, most steps first.
+    pub fn by_procedure(&self) -> Vec<(String, Sample)> {
+        let mut procedures: Vec<(String, Sample)> = self
+            .procedures
+            .iter()
+            .map(|(name, sample)| (name.clone(), *sample))
+            .collect();
+        procedures.sort_by(|a, b| b.1.steps.cmp(&a.1.steps).then(a.0.cmp(&b.0)));
+        procedures
+    }
+
+    /// The share of the run's steps the sample accounts for, in percent.
+    pub fn percentage(&self, sample: Sample) -> usize {
+        match self.total.steps {
+            0 => 0,
+            total => sample.steps * 100 / total,
+        }
+    }
+
+    /// The run as folded stacks: one line per distinct call stack,
+    /// root-first with `;` between frames, then a space and the number of
+    /// steps spent there. This is the input format of `inferno` and the
+    /// original flamegraph scripts, so
+    /// `karel run long.kl --folded out.txt && inferno-flamegraph out.txt`
+    /// turns a long run into a picture.
+    pub fn folded(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut out = String::new();
+        for (stack, steps) in &self.stacks {
+            let _ = writeln!(out, "{stack} {steps}");
+        }
+        out
+    }
+
+    /// A human-readable breakdown: the whole run, every procedure, and the
+    /// ten hottest lines.
+    pub fn report(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut out = String::new();
+        let _ = writeln!(out, "{} steps in {:?}", self.total.steps, self.total.time);
+        let _ = writeln!(out, "by procedure:");
+        for (name, sample) in self.by_procedure() {
+            let _ = writeln!(
+                out,
+                "  {:3}%  {} steps  {name}",
+                self.percentage(sample),
+                sample.steps
+            );
+        }
+        let _ = writeln!(out, "hottest lines:");
+        for (line, sample) in self.by_line().into_iter().take(10) {
+            let _ = writeln!(
+                out,
+                "  {:3}%  {} steps  line {line}",
+                self.percentage(sample),
+                sample.steps
+            );
+        }
+        if let Some(error) = &self.error {
+            let _ = writeln!(out, "ended with an error: {error}");
+        }
+        out
+    }
+}
+
+/// Collects a [`Profile`] while someone else drives the interpreter.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    profile: Profile,
+    /// Where the step about to run will be charged (line, innermost
+    /// procedure, folded call stack), and when it started.
+    pending: Option<(usize, String, String, Instant)>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Note where the interpreter stands; call right before each `step`.
+    pub fn before<E: Environment>(&mut self, interpreter: &Interpreter<'_, E>) {
+        let line = interpreter.current_line().unwrap_or(0);
+        // Innermost first from the interpreter; folded stacks want the
+        // root first.
+        let frames = interpreter.backtrace();
+        let procedure = frames
+            .first()
+            .map(|(name, _line)| name.clone())
+            .unwrap_or_else(|| "?".to_string());
+        let stack = frames
+            .iter()
+            .rev()
+            .map(|(name, _line)| name.as_str())
+            .collect::<Vec<&str>>()
+            .join(";");
+        self.pending = Some((line, procedure, stack, Instant::now()));
+    }
+
+    /// Charge the step begun by the last [`before`](Profiler::before); call
+    /// right after it, whether it succeeded or not.
+    pub fn after(&mut self) {
+        let Some((line, procedure, stack, started)) = self.pending.take() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        for sample in [
+            &mut self.profile.total,
+            self.profile.lines.entry(line).or_default(),
+            self.profile.procedures.entry(procedure).or_default(),
+        ] {
+            sample.steps += 1;
+            sample.time += elapsed;
+        }
+        *self.profile.stacks.entry(stack).or_default() += 1;
+    }
+
+    /// The finished profile. `error` is whatever ended the run early, in
+    /// the words the caller would report it with.
+    pub fn finish(mut self, error: Option<String>) -> Profile {
+        self.profile.error = error;
+        self.profile
+    }
+}
+
+/// Profile a run to its end (or to `limit` steps, so an endless loop still
+/// yields a report — usually the one that explains it).
+pub fn profile<E: Environment>(interpreter: &mut Interpreter<'_, E>, limit: usize) -> Profile {
+    let mut profiler = Profiler::new();
+    let mut steps = 0usize;
+    let mut error = None;
+    while !interpreter.finished() && steps < limit {
+        profiler.before(interpreter);
+        let result = interpreter.step();
+        profiler.after();
+        steps += 1;
+        if let Err(runtime_error) = result {
+            error = Some(runtime_error.to_string());
+            break;
+        }
+    }
+    profiler.finish(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::preprocess;
+    use crate::world::World;
+
+    #[test]
+    fn steps_are_charged_to_lines_and_procedures() {
+        // `spin` does 4 steps per call (3 turns + enddef), called 3 times;
+        // main contributes the repeat bookkeeping and the calls.
+        let source = "def main\n repeat 3\n  call spin\n endrepeat\nenddef\n\
+                      def spin\n turn-left\n turn-left\n turn-left\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        let profile = profile(&mut interpreter, 1_000);
+
+        assert!(profile.error.is_none());
+        let by_procedure = profile.by_procedure();
+        assert_eq!(by_procedure[0].0, "spin");
+        assert_eq!(by_procedure[0].1.steps, 12);
+        // The turn lines were each executed three times.
+        let turns = profile
+            .by_line()
+            .into_iter()
+            .find(|(line, _)| *line == 7)
+            .unwrap();
+        assert_eq!(turns.1.steps, 3);
+        assert_eq!(
+            profile.total.steps,
+            profile.by_procedure().iter().map(|(_, s)| s.steps).sum()
+        );
+    }
+
+    #[test]
+    fn the_report_names_the_hot_procedure() {
+        let source = "def main\n repeat 20\n  call noise\n endrepeat\nenddef\n\
+                      def noise\n beep\n beep\n beep\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        let profile = profile(&mut interpreter, 1_000);
+        let report = profile.report();
+        assert!(report.contains("noise"), "{report}");
+        assert!(report.contains("by procedure"), "{report}");
+    }
+
+    #[test]
+    fn folded_stacks_follow_the_call_chain() {
+        let source = "def main\n call outer\nenddef\n\
+                      def outer\n call inner\nenddef\n\
+                      def inner\n beep\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        let profile = profile(&mut interpreter, 1_000);
+        let folded = profile.folded();
+        assert!(folded.contains("main;outer;inner "), "{folded}");
+        // Every step lands in exactly one stack.
+        let counted: usize = folded
+            .lines()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(counted, profile.total.steps);
+    }
+
+    #[test]
+    fn an_endless_run_is_cut_off_at_the_limit() {
+        let source = "def main\n while! beeper\n  turn-left\n endwhile\nenddef";
+        let mut interpreter =
+            Interpreter::new(preprocess(source), World::new(3, 3)).unwrap();
+        let profile = profile(&mut interpreter, 100);
+        assert_eq!(profile.total.steps, 100);
+        assert!(!interpreter.finished());
+    }
+}
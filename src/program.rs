@@ -0,0 +1,220 @@
+//! A program compiled once and run many times.
+//!
+//! [`Interpreter`] is built per run; a [`Program`] is the thing worth
+//! keeping around. It preprocesses and checks the source once, detaches the
+//! lines from the source string and shares them behind an [`Arc`], so a
+//! grading server can hold one compiled submission and throw it at world
+//! after world — clones are reference-count bumps.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::environment::Environment;
+use crate::interpreter::{Interpreter, RuntimeError, StepResult};
+use crate::parser::{self, Diagnostic, Line};
+
+/// A checked, reusable Karel program.
+#[derive(Debug, Clone)]
+pub struct Program {
+    lines: Arc<Vec<Line<'static>>>,
+}
+
+impl Program {
+    /// Preprocess and check `source`, keeping the lines only if the program
+    /// is completely well formed. On failure every problem found is
+    /// returned, in source order.
+    pub fn compile(source: &str) -> Result<Program, Vec<Diagnostic>> {
+        let lines = parser::preprocess(source);
+        let diagnostics = parser::check(&lines);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        let lines = lines.into_iter().map(Line::into_owned).collect();
+        Ok(Program { lines: Arc::new(lines) })
+    }
+
+    /// The preprocessed lines, for graders and transpilers.
+    pub fn lines(&self) -> &[Line<'static>] {
+        &self.lines
+    }
+
+    /// Run the program over a borrowed world to its end. The world is only
+    /// lent for this call; afterwards it holds whatever state the run
+    /// reached, errors included.
+    pub fn run<E: Environment>(&self, world: &mut E) -> Result<(), RuntimeError> {
+        let mut interpreter = self.start(&mut *world);
+        while interpreter.step()? == StepResult::Running {}
+        Ok(())
+    }
+
+    /// Like [`run`](Program::run), but starting from `def name` instead of
+    /// `def main` — so a platform can run a task's `setup`, then the
+    /// student's `solve`, over the same borrowed world.
+    pub fn run_procedure<E: Environment>(
+        &self,
+        world: &mut E,
+        name: &str,
+    ) -> Result<(), RuntimeError> {
+        let mut interpreter = self.start_at(&mut *world, name)?;
+        while interpreter.step()? == StepResult::Running {}
+        Ok(())
+    }
+
+    /// Like [`start`](Program::start), but with `def name` as the entry
+    /// point; fails with [`RuntimeError::UnknownEntryPoint`] if there is no
+    /// such procedure.
+    pub fn start_at<E: Environment>(
+        &self,
+        world: E,
+        name: &str,
+    ) -> Result<Interpreter<'static, E>, RuntimeError> {
+        Interpreter::with_entry(self.lines.as_ref().clone(), world, name)
+    }
+
+    /// Start a step-by-step run against `world` (owned or `&mut`-borrowed).
+    pub fn start<E: Environment>(&self, world: E) -> Interpreter<'static, E> {
+        Interpreter::new(self.lines.as_ref().clone(), world)
+            // `compile` rejected programs without a `def main`.
+            .expect("compiled programs always have a main")
+    }
+
+    /// Run against `world` as an iterator: one item per executed
+    /// instruction, so ordinary adapters drive and observe the run —
+    /// `take(budget)` bounds it, `inspect` watches it, `collect` records
+    /// it. The iterator ends after yielding [`StepResult::Finished`] or the
+    /// error that killed the robot; the world is reachable through
+    /// [`Execution::interpreter`] at any point.
+    pub fn execute<E: Environment>(&self, world: E) -> Execution<E> {
+        Execution {
+            interpreter: self.start(world),
+            done: false,
+        }
+    }
+}
+
+/// A run in progress, driven by pulling on it. See [`Program::execute`].
+pub struct Execution<E: Environment = crate::world::World> {
+    interpreter: Interpreter<'static, E>,
+    done: bool,
+}
+
+impl<E: Environment> Execution<E> {
+    /// The interpreter behind the iterator, for inspecting the world or the
+    /// current line between items.
+    pub fn interpreter(&self) -> &Interpreter<'static, E> {
+        &self.interpreter
+    }
+
+    /// Take the interpreter back out, e.g. to keep the final world after
+    /// the iterator has been exhausted.
+    pub fn into_interpreter(self) -> Interpreter<'static, E> {
+        self.interpreter
+    }
+}
+
+impl<E: Environment> Iterator for Execution<E> {
+    type Item = Result<StepResult, RuntimeError>;
+
+    fn next(&mut self) -> Option<Result<StepResult, RuntimeError>> {
+        if self.done {
+            return None;
+        }
+        match self.interpreter.step() {
+            Ok(StepResult::Running) => Some(Ok(StepResult::Running)),
+            Ok(StepResult::Finished) => {
+                self.done = true;
+                Some(Ok(StepResult::Finished))
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParseError;
+    use crate::world::{Position, World};
+
+    #[test]
+    fn a_program_compiles_once_and_runs_on_many_worlds() {
+        let program = Program::compile("def main\n move\n move\nenddef").unwrap();
+        let mut near = World::new(10, 10);
+        let mut far = World::new(10, 10);
+        far.robot.position = Position::new(5, 5);
+
+        program.run(&mut near).unwrap();
+        program.run(&mut far).unwrap();
+        assert_eq!(near.robot.position, Position::new(2, 0));
+        assert_eq!(far.robot.position, Position::new(7, 5));
+    }
+
+    #[test]
+    fn compilation_collects_every_diagnostic() {
+        let diagnostics = Program::compile("def main\n fly\n if goblin\n endif\nenddef")
+            .unwrap_err();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].error,
+            ParseError::UnknownInstruction { line: 2, instruction: "fly".into() }
+        );
+    }
+
+    #[test]
+    fn any_procedure_can_be_the_entry_point() {
+        // The platform's `setup` lays a beeper, the student's `solve`
+        // fetches it; `main` exists but is never used here.
+        let source = "def main\nenddef\n\
+                      def setup\n move\n put\n turn-left\n turn-left\n move\n turn-left\n turn-left\nenddef\n\
+                      def solve\n move\n take\nenddef";
+        let program = Program::compile(source).unwrap();
+        let mut world = World::new(3, 1);
+        program.run_procedure(&mut world, "setup").unwrap();
+        assert_eq!(world.beepers_at(Position::new(1, 0)), 1);
+        program.run_procedure(&mut world, "solve").unwrap();
+        assert_eq!(world.beepers_at(Position::new(1, 0)), 0);
+
+        assert_eq!(
+            program.run_procedure(&mut world, "cleanup"),
+            Err(RuntimeError::UnknownEntryPoint { name: "cleanup".to_string() })
+        );
+    }
+
+    #[test]
+    fn execution_is_an_ordinary_iterator() {
+        let program = Program::compile("def main\n move\n move\n move\nenddef").unwrap();
+        // Three moves plus the closing `enddef`.
+        assert_eq!(program.execute(World::new(10, 1)).count(), 4);
+
+        // `take` is a step budget; the world is inspectable mid-run.
+        let mut execution = program.execute(World::new(10, 1));
+        assert!(execution.by_ref().take(2).all(|step| step.is_ok()));
+        let interpreter = execution.into_interpreter();
+        assert_eq!(interpreter.world.robot.position, Position::new(2, 0));
+        assert!(!interpreter.finished());
+    }
+
+    #[test]
+    fn execution_ends_with_the_error_that_killed_the_robot() {
+        let program = Program::compile("def main\n move\n move\nenddef").unwrap();
+        let steps: Vec<_> = program.execute(World::new(2, 1)).collect();
+        assert_eq!(
+            steps.last(),
+            Some(&Err(RuntimeError::HitWall { line: 3 }))
+        );
+        // Fused: the iterator stays empty after the error.
+        let mut execution = program.execute(World::new(2, 1));
+        assert!(execution.any(|step| step.is_err()));
+        assert_eq!(execution.next(), None);
+    }
+
+    #[test]
+    fn clones_share_the_compiled_lines() {
+        let program = Program::compile("def main\n move\nenddef").unwrap();
+        let clone = program.clone();
+        assert!(Arc::ptr_eq(&program.lines, &clone.lines));
+    }
+}
@@ -0,0 +1,353 @@
+//! Source-to-source refactorings: extract and rename procedures.
+//!
+//! [`extract_procedure`] takes a line range of the original source, checks
+//! the extraction is legal, and returns the edited source: the selected
+//! statements become a new `def`/`enddef` at the end of the file and a
+//! `call` replaces them at the original site. [`rename`] changes a `def`
+//! and every `call` to it. Both work on the raw text but decide what to
+//! touch from the parsed lines — a comment mentioning a procedure's name
+//! is left alone, and comments and indentation inside an extracted
+//! selection move with it unchanged. This is the engine behind an editor's
+//! refactoring actions; deciding *what* to extract or rename is the
+//! front-end's (or the [linter](crate::lint)'s) job.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::parser::{self, Line};
+
+/// Why a selection cannot be extracted into a procedure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefactorError {
+    /// The range selects no instructions at all.
+    EmptySelection,
+    /// A selected line lies outside any procedure body.
+    OutsideProcedure { line: usize },
+    /// The selection reaches into more than one procedure, or includes a
+    /// `def`/`enddef` line itself.
+    SpansProcedures { line: usize },
+    /// The selection cuts a block in half: an `if`, `while` or `repeat`
+    /// opens or closes inside it without its other end.
+    CutsBlock { line: usize },
+    /// The new procedure's name is empty, more than one word, a keyword,
+    /// or taken.
+    BadName { name: String },
+    /// There is no procedure of the given name to rename.
+    UnknownProcedure { name: String },
+}
+
+impl core::fmt::Display for RefactorError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RefactorError::EmptySelection => write!(f, "the selection holds no instructions"),
+            RefactorError::OutsideProcedure { line } => {
+                write!(f, "line {line} is outside any procedure")
+            }
+            RefactorError::SpansProcedures { line } => {
+                write!(f, "line {line}: the selection must stay inside one procedure")
+            }
+            RefactorError::CutsBlock { line } => {
+                write!(f, "line {line}: the selection cuts a block in half")
+            }
+            RefactorError::BadName { name } => {
+                write!(f, "`{name}` is not a usable procedure name")
+            }
+            RefactorError::UnknownProcedure { name } => {
+                write!(f, "there is no procedure `{name}` to rename")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RefactorError {}
+
+/// Extract source lines `first..=last` (1-based, inclusive) into a new
+/// procedure called `name`, returning the edited source. The selection must
+/// be a whole number of statements inside a single procedure body; blank
+/// and comment lines in the range move along with it.
+pub fn extract_procedure(
+    source: &str,
+    first: usize,
+    last: usize,
+    name: &str,
+) -> Result<String, RefactorError> {
+    let lines = parser::preprocess(source);
+    validate_name(name, &lines)?;
+    let selected: Vec<&Line<'_>> = lines
+        .iter()
+        .filter(|line| (first..=last).contains(&line.number))
+        .collect();
+    if selected.is_empty() {
+        return Err(RefactorError::EmptySelection);
+    }
+    validate_selection(&lines, &selected)?;
+
+    // Splice the raw text: the `call` takes the place of the first selected
+    // line, at its indentation; the selection moves to a new procedure at
+    // the end of the file.
+    let indent: String = source
+        .lines()
+        .nth(first - 1)
+        .unwrap_or("")
+        .chars()
+        .take_while(|character| character.is_whitespace())
+        .collect();
+    let mut edited = String::new();
+    for (index, raw) in source.lines().enumerate() {
+        let number = index + 1;
+        if number == first {
+            edited.push_str(&format!("{indent}call {name}\n"));
+        }
+        if !(first..=last).contains(&number) {
+            edited.push_str(raw);
+            edited.push('\n');
+        }
+    }
+    edited.push_str(&format!("\ndef {name}\n"));
+    for raw in source
+        .lines()
+        .skip(first - 1)
+        .take(last.saturating_sub(first) + 1)
+    {
+        edited.push_str(raw);
+        edited.push('\n');
+    }
+    edited.push_str("enddef\n");
+    Ok(edited)
+}
+
+/// Rename the procedure `old` to `new`: its `def` line and every `call` to
+/// it. Comments that happen to mention the old name are left alone — the
+/// parsed lines decide what is a call site, not a text search.
+pub fn rename(source: &str, old: &str, new: &str) -> Result<String, RefactorError> {
+    let lines = parser::preprocess(source);
+    if !is_defined(old, &lines) {
+        return Err(RefactorError::UnknownProcedure {
+            name: old.to_string(),
+        });
+    }
+    validate_name(new, &lines)?;
+
+    let sites: Vec<usize> = lines
+        .iter()
+        .filter(|line| {
+            let mut words = line.text.split_whitespace();
+            matches!(words.next(), Some("def" | "call")) && words.next() == Some(old)
+        })
+        .map(|line| line.number)
+        .collect();
+
+    let mut edited = String::new();
+    for (index, raw) in source.lines().enumerate() {
+        if sites.contains(&(index + 1)) {
+            edited.push_str(&replace_second_word(raw, new));
+        } else {
+            edited.push_str(raw);
+        }
+        edited.push('\n');
+    }
+    Ok(edited)
+}
+
+/// Swap the second word of the line's code (before any `#` comment) for
+/// `new`, leaving spacing and the comment as they are.
+fn replace_second_word(raw: &str, new: &str) -> String {
+    let code_end = raw.find('#').unwrap_or(raw.len());
+    let code = &raw[..code_end];
+    let mut words = 0usize;
+    let mut start = code.len();
+    let mut end = code.len();
+    let mut in_word = false;
+    for (offset, character) in code.char_indices() {
+        if character.is_whitespace() {
+            if in_word && words == 2 {
+                end = offset;
+                break;
+            }
+            in_word = false;
+        } else if !in_word {
+            words += 1;
+            in_word = true;
+            if words == 2 {
+                start = offset;
+            }
+        }
+    }
+    format!("{}{new}{}", &raw[..start], &raw[end..])
+}
+
+fn is_defined(name: &str, lines: &[Line<'_>]) -> bool {
+    lines.iter().any(|line| {
+        let mut words = line.text.split_whitespace();
+        words.next() == Some("def") && words.next() == Some(name)
+    })
+}
+
+fn validate_name(name: &str, lines: &[Line<'_>]) -> Result<(), RefactorError> {
+    if name.is_empty()
+        || name.split_whitespace().count() != 1
+        || crate::highlight::KEYWORDS.contains(&name)
+        || is_defined(name, lines)
+    {
+        return Err(RefactorError::BadName {
+            name: name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn validate_selection(
+    lines: &[Line<'_>],
+    selected: &[&Line<'_>],
+) -> Result<(), RefactorError> {
+    // Every selected line must sit in the same procedure body, located by
+    // walking the whole program and remembering the `def` each line is in.
+    let mut current: Option<usize> = None;
+    let mut homes = Vec::with_capacity(lines.len());
+    for line in lines {
+        match line.text.split_whitespace().next() {
+            Some("def") => current = Some(line.number),
+            Some("enddef") => {
+                homes.push(None);
+                current = None;
+                continue;
+            }
+            _ => {}
+        }
+        homes.push(if first_word(line) == "def" { None } else { current });
+    }
+
+    let mut home = None;
+    for line in selected.iter() {
+        if matches!(first_word(line), "def" | "enddef") {
+            return Err(RefactorError::SpansProcedures { line: line.number });
+        }
+        let index = lines
+            .iter()
+            .position(|candidate| candidate.number == line.number)
+            .expect("selected lines come from `lines`");
+        match (home, homes[index]) {
+            (_, None) => return Err(RefactorError::OutsideProcedure { line: line.number }),
+            (None, Some(this)) => home = Some(this),
+            (Some(first), Some(this)) if first != this => {
+                return Err(RefactorError::SpansProcedures { line: line.number })
+            }
+            _ => {}
+        }
+    }
+
+    // Blocks must open and close in pairs within the selection.
+    let mut depth = 0usize;
+    for line in selected.iter() {
+        match first_word(line) {
+            "if" | "if!" | "while" | "while!" | "repeat" => depth += 1,
+            "endif" | "endwhile" | "endrepeat" => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or(RefactorError::CutsBlock { line: line.number })?;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        let last = selected.last().expect("selection is not empty");
+        return Err(RefactorError::CutsBlock { line: last.number });
+    }
+    Ok(())
+}
+
+fn first_word<'l>(line: &'l Line<'_>) -> &'l str {
+    line.text.split_whitespace().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_selection_becomes_a_procedure_and_a_call() {
+        let source = "def main\n move\n take  # the beeper\n put\n die\nenddef";
+        let edited = extract_procedure(source, 2, 4, "shuffle").unwrap();
+        assert_eq!(
+            edited,
+            "def main\n call shuffle\n die\nenddef\n\ndef shuffle\n move\n take  # the beeper\n put\nenddef\n"
+        );
+        // The result is still a valid program.
+        assert!(parser::validate(&parser::preprocess(&edited)).is_ok());
+    }
+
+    #[test]
+    fn whole_blocks_may_move_but_half_blocks_may_not() {
+        let source = "def main\n if beeper\n  take\n endif\n move\nenddef";
+        assert!(extract_procedure(source, 2, 4, "grab").is_ok());
+        assert_eq!(
+            extract_procedure(source, 2, 3, "grab"),
+            Err(RefactorError::CutsBlock { line: 3 })
+        );
+        assert_eq!(
+            extract_procedure(source, 3, 4, "grab"),
+            Err(RefactorError::CutsBlock { line: 4 })
+        );
+    }
+
+    #[test]
+    fn selections_stay_inside_one_procedure() {
+        let source = "def main\n move\nenddef\ndef other\n put\nenddef";
+        assert_eq!(
+            extract_procedure(source, 2, 5, "mix"),
+            Err(RefactorError::SpansProcedures { line: 3 })
+        );
+        assert_eq!(
+            extract_procedure(source, 1, 2, "mix"),
+            Err(RefactorError::SpansProcedures { line: 1 })
+        );
+    }
+
+    #[test]
+    fn rename_touches_defs_and_calls_but_not_comments() {
+        let source = "def main\n call turn  # turn right via turn\n call turn\nenddef\ndef turn\n turn-left\nenddef";
+        let edited = rename(source, "turn", "turn-right").unwrap();
+        assert_eq!(
+            edited,
+            "def main\n call turn-right  # turn right via turn\n call turn-right\nenddef\ndef turn-right\n turn-left\nenddef\n"
+        );
+        assert!(parser::validate(&parser::preprocess(&edited)).is_ok());
+    }
+
+    #[test]
+    fn rename_detects_conflicts() {
+        let source = "def main\n call helper\nenddef\ndef helper\n move\nenddef";
+        assert_eq!(
+            rename(source, "nobody", "anything"),
+            Err(RefactorError::UnknownProcedure { name: "nobody".to_string() })
+        );
+        for conflict in ["main", "while!", ""] {
+            assert_eq!(
+                rename(source, "helper", conflict),
+                Err(RefactorError::BadName { name: conflict.to_string() }),
+                "`{conflict}` should conflict"
+            );
+        }
+    }
+
+    #[test]
+    fn bad_names_and_empty_selections_are_rejected() {
+        let source = "def main\n move\nenddef";
+        assert_eq!(
+            extract_procedure(source, 2, 2, "main"),
+            Err(RefactorError::BadName { name: "main".to_string() })
+        );
+        assert_eq!(
+            extract_procedure(source, 2, 2, "two words"),
+            Err(RefactorError::BadName { name: "two words".to_string() })
+        );
+        // Only a comment in range: nothing to extract.
+        let commented = "def main\n # nothing\n move\nenddef";
+        assert_eq!(
+            extract_procedure(commented, 2, 2, "helper"),
+            Err(RefactorError::EmptySelection)
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Terminal rendering of a [`World`].
+//!
+//! Two visual styles are supported: a plain ASCII one that works everywhere,
+//! and a prettier one using Unicode box-drawing characters, optionally with
+//! ANSI colors for the robot and beepers. [`RenderStyle::detect`] picks the
+//! best style the current terminal is likely to handle.
+
+use std::env;
+use std::io::IsTerminal;
+
+use crate::world::{Direction, Position, World};
+
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_ROBOT: &str = "\x1b[1;36m";
+const ANSI_BEEPER: &str = "\x1b[1;33m";
+const ANSI_WALL: &str = "\x1b[90m";
+const ANSI_INVERSE: &str = "\x1b[7m";
+const ANSI_INVERSE_OFF: &str = "\x1b[27m";
+
+/// How a world should be drawn into a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderStyle {
+    /// Pure ASCII, safe for any terminal, log file or test.
+    Ascii,
+    /// Unicode box-drawing border and block walls, no colors.
+    Unicode,
+    /// Unicode drawing plus ANSI colors for the robot, beepers and walls.
+    UnicodeColor,
+}
+
+impl RenderStyle {
+    /// Guess the best style for stdout.
+    ///
+    /// Falls back to [`RenderStyle::Ascii`] when stdout is not a terminal or
+    /// `TERM` looks too dumb for Unicode; honors the `NO_COLOR` convention
+    /// by dropping down to [`RenderStyle::Unicode`].
+    pub fn detect() -> RenderStyle {
+        if !std::io::stdout().is_terminal() {
+            return RenderStyle::Ascii;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        if term.is_empty() || term == "dumb" {
+            return RenderStyle::Ascii;
+        }
+        if env::var_os("NO_COLOR").is_some() {
+            return RenderStyle::Unicode;
+        }
+        RenderStyle::UnicodeColor
+    }
+}
+
+/// Draw the world into a multi-line string in the given style.
+///
+/// The robot is drawn as an arrow showing its facing (`^`, `>`, `v`, `<`),
+/// beepers as their count on the tile, walls as `#` (ASCII) or `█` (Unicode).
+/// A tile with both the robot and beepers shows the robot.
+pub fn render(world: &World, style: RenderStyle) -> String {
+    render_with_cursor(world, style, None)
+}
+
+/// Like [`render`], but highlights one tile with inverse video. Used by the
+/// world editor to show its cursor.
+pub fn render_with_cursor(
+    world: &World,
+    style: RenderStyle,
+    cursor: Option<Position>,
+) -> String {
+    let mut out = String::new();
+    let color = style == RenderStyle::UnicodeColor;
+
+    let (top_left, top_right, bottom_left, bottom_right, horizontal, vertical) = match style {
+        RenderStyle::Ascii => ('+', '+', '+', '+', '-', '|'),
+        RenderStyle::Unicode | RenderStyle::UnicodeColor => ('┌', '┐', '└', '┘', '─', '│'),
+    };
+
+    out.push(top_left);
+    for _ in 0..world.width() {
+        out.push(horizontal);
+    }
+    out.push(top_right);
+    out.push('\n');
+
+    for y in 0..world.height() {
+        out.push(vertical);
+        for x in 0..world.width() {
+            let position = Position::new(x, y);
+            let highlighted = cursor == Some(position);
+            if highlighted {
+                out.push_str(ANSI_INVERSE);
+            }
+            let robot = world.robot;
+            if robot.position == position {
+                if color {
+                    out.push_str(ANSI_ROBOT);
+                }
+                out.push(if robot.alive {
+                    match robot.direction {
+                        Direction::North => '^',
+                        Direction::East => '>',
+                        Direction::South => 'v',
+                        Direction::West => '<',
+                    }
+                } else {
+                    // A switched-off robot still shows where it ended up.
+                    'x'
+                });
+                if color {
+                    out.push_str(ANSI_RESET);
+                }
+            } else if world.is_wall(position) {
+                if color {
+                    out.push_str(ANSI_WALL);
+                }
+                out.push(match style {
+                    RenderStyle::Ascii => '#',
+                    _ => '█',
+                });
+                if color {
+                    out.push_str(ANSI_RESET);
+                }
+            } else if world.beepers_at(position) > 0 {
+                if color {
+                    out.push_str(ANSI_BEEPER);
+                }
+                out.push((b'0' + world.beepers_at(position)) as char);
+                if color {
+                    out.push_str(ANSI_RESET);
+                }
+            } else {
+                out.push(match style {
+                    RenderStyle::Ascii => '.',
+                    _ => '·',
+                });
+            }
+            if highlighted {
+                out.push_str(ANSI_INVERSE_OFF);
+            }
+        }
+        out.push(vertical);
+        out.push('\n');
+    }
+
+    out.push(bottom_left);
+    for _ in 0..world.width() {
+        out.push(horizontal);
+    }
+    out.push(bottom_right);
+    out.push('\n');
+    out
+}
+
+/// Size of one tile in [`render_svg`] output, in SVG user units.
+const SVG_TILE: usize = 32;
+
+/// Draw the world as a standalone SVG document, one `SVG_TILE`-sized square
+/// per tile. This is what the browser front-end embeds; terminals should use
+/// [`render`] instead.
+pub fn render_svg(world: &World) -> String {
+    use std::fmt::Write;
+
+    let width = world.width() * SVG_TILE;
+    let height = world.height() * SVG_TILE;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {height}\" \
+         width=\"{width}\" height=\"{height}\">"
+    );
+    let _ = writeln!(
+        out,
+        "  <rect width=\"{width}\" height=\"{height}\" fill=\"#fdf6e3\" stroke=\"#657b83\"/>"
+    );
+
+    for y in 0..world.height() {
+        for x in 0..world.width() {
+            let position = Position::new(x, y);
+            let (left, top) = (x * SVG_TILE, y * SVG_TILE);
+            if world.is_wall(position) {
+                let _ = writeln!(
+                    out,
+                    "  <rect x=\"{left}\" y=\"{top}\" width=\"{SVG_TILE}\" height=\"{SVG_TILE}\" \
+                     fill=\"#586e75\"/>"
+                );
+            } else if world.beepers_at(position) > 0 {
+                let (cx, cy) = (left + SVG_TILE / 2, top + SVG_TILE / 2);
+                let radius = SVG_TILE * 3 / 8;
+                let _ = writeln!(
+                    out,
+                    "  <circle cx=\"{cx}\" cy=\"{cy}\" r=\"{radius}\" fill=\"#b58900\"/>"
+                );
+                let _ = writeln!(
+                    out,
+                    "  <text x=\"{cx}\" y=\"{cy}\" text-anchor=\"middle\" \
+                     dominant-baseline=\"central\" fill=\"#fdf6e3\">{}</text>",
+                    world.beepers_at(position)
+                );
+            }
+        }
+    }
+
+    // The robot: a triangle pointing the way it faces, gray when dead.
+    let robot = world.robot;
+    let (left, top) = (robot.position.x * SVG_TILE, robot.position.y * SVG_TILE);
+    let (cx, cy) = (left + SVG_TILE / 2, top + SVG_TILE / 2);
+    let reach = SVG_TILE * 3 / 8;
+    // Tip, then the two base corners, as offsets from the tile center.
+    let points: [(isize, isize); 3] = match robot.direction {
+        Direction::North => [(0, -1), (-1, 1), (1, 1)],
+        Direction::South => [(0, 1), (1, -1), (-1, -1)],
+        Direction::East => [(1, 0), (-1, -1), (-1, 1)],
+        Direction::West => [(-1, 0), (1, 1), (1, -1)],
+    };
+    let points: Vec<String> = points
+        .iter()
+        .map(|(dx, dy)| {
+            format!(
+                "{},{}",
+                cx as isize + dx * reach as isize,
+                cy as isize + dy * reach as isize
+            )
+        })
+        .collect();
+    let fill = if robot.alive { "#268bd2" } else { "#93a1a1" };
+    let _ = writeln!(
+        out,
+        "  <polygon points=\"{}\" fill=\"{fill}\"/>",
+        points.join(" ")
+    );
+
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+
+    fn sample_world() -> World {
+        let mut world = World::new(3, 2);
+        world.set_wall(Position::new(2, 0), true);
+        world.set_beepers(Position::new(1, 1), 3);
+        world
+    }
+
+    #[test]
+    fn ascii_rendering() {
+        let rendered = render(&sample_world(), RenderStyle::Ascii);
+        assert_eq!(rendered, "+---+\n|>.#|\n|.3.|\n+---+\n");
+    }
+
+    #[test]
+    fn unicode_rendering_uses_box_drawing() {
+        let rendered = render(&sample_world(), RenderStyle::Unicode);
+        assert_eq!(rendered, "┌───┐\n│>·█│\n│·3·│\n└───┘\n");
+    }
+
+    #[test]
+    fn color_rendering_contains_ansi_escapes() {
+        let rendered = render(&sample_world(), RenderStyle::UnicodeColor);
+        assert!(rendered.contains(ANSI_ROBOT));
+        assert!(rendered.contains(ANSI_BEEPER));
+        assert!(rendered.contains(ANSI_RESET));
+    }
+
+    #[test]
+    fn svg_rendering_contains_walls_beepers_and_the_robot() {
+        let svg = render_svg(&sample_world());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("viewBox=\"0 0 96 64\""));
+        assert!(svg.contains("<rect x=\"64\" y=\"0\""), "{svg}");
+        assert!(svg.contains(">3</text>"), "{svg}");
+        assert!(svg.contains("<polygon"), "{svg}");
+    }
+
+    #[test]
+    fn dead_robot_is_drawn_as_x() {
+        let mut world = sample_world();
+        world.robot.alive = false;
+        let rendered = render(&world, RenderStyle::Ascii);
+        assert!(!rendered.contains('>'));
+        assert!(rendered.contains('x'));
+    }
+}
@@ -0,0 +1,153 @@
+//! Starter projects for `karel new`: a handful of built-in exercise
+//! templates, each a small directory with a program stub, a world, a task
+//! file and a README telling the student what to do.
+
+use std::io;
+use std::path::Path;
+
+/// A built-in exercise template.
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The files to create, as (relative path, contents).
+    files: &'static [(&'static str, &'static str)],
+}
+
+/// All built-in templates, in the order `karel new --list` shows them.
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "maze",
+        description: "walk a winding corridor to its end",
+        files: &[
+            (
+                "README.md",
+                "# Maze\n\nGuide Karel through the corridor to the south-eastern corner.\n\
+                 The walls are closer than they appear: `move` into one and the robot\n\
+                 is gone. The `wall` condition tells you whether it is safe to move.\n\n\
+                 Run your program with:\n\n    karel run program.kl --world world.txt\n\n\
+                 Check your solution with:\n\n    karel grade --task task.toml program.kl\n",
+            ),
+            (
+                "program.kl",
+                "def main # Find your way to the corner\n  # Your code here.\n  \
+                 # Useful: while! wall / move / endwhile, turn-left\n  die\nenddef\n",
+            ),
+            ("world.txt", ">....\n####.\n.....\n.####\n.....\n"),
+            (
+                "task.toml",
+                "name = \"Maze\"\nworlds = [\"world.txt\"]\ngoals = [\"robot-at 4 4\"]\n",
+            ),
+        ],
+    },
+    Template {
+        name: "harvest",
+        description: "pick every beeper from the field",
+        files: &[
+            (
+                "README.md",
+                "# Harvest\n\nThe field is full of beepers. Collect every last one of them.\n\
+                 `take` picks a beeper up, and the `beeper` condition tells you whether\n\
+                 there is one on the current tile.\n\n\
+                 Run your program with:\n\n    karel run program.kl --world world.txt\n\n\
+                 Check your solution with:\n\n    karel grade --task task.toml program.kl\n",
+            ),
+            (
+                "program.kl",
+                "def main # Harvest the whole field\n  # Your code here.\n  \
+                 # Hint: a procedure that harvests one row keeps main short.\n  die\nenddef\n",
+            ),
+            ("world.txt", ">.....\n.1111.\n.1111.\n......\n"),
+            (
+                "task.toml",
+                "name = \"Harvest\"\nworlds = [\"world.txt\"]\ngoals = [\"no-beepers\"]\n",
+            ),
+        ],
+    },
+    Template {
+        name: "newspaper",
+        description: "deliver a beeper to the doorstep",
+        files: &[
+            (
+                "README.md",
+                "# Newspaper\n\nDeliver the newspaper: walk to the doorstep (the tile right\n\
+                 before the wall) and `put` exactly one beeper there. Then go back\n\
+                 to where you started.\n\n\
+                 Run your program with:\n\n    karel run program.kl --world world.txt\n\n\
+                 Check your solution with:\n\n    karel grade --task task.toml program.kl\n",
+            ),
+            (
+                "program.kl",
+                "def main # Deliver a beeper to the doorstep and return\n  # Your code here.\n  die\nenddef\n",
+            ),
+            ("world.txt", ">..#.\n...#.\n.....\n"),
+            (
+                "task.toml",
+                "name = \"Newspaper\"\nworlds = [\"world.txt\"]\n\
+                 goals = [\"beepers-at 2 0 1\", \"robot-at 0 0\"]\n",
+            ),
+        ],
+    },
+];
+
+/// Look a template up by name.
+pub fn find(name: &str) -> Option<&'static Template> {
+    TEMPLATES.iter().find(|template| template.name == name)
+}
+
+/// Create the template's files inside `directory`, which must not exist yet
+/// (so an existing project cannot be overwritten by accident).
+pub fn create(template: &Template, directory: &Path) -> io::Result<()> {
+    if directory.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("`{}` already exists", directory.display()),
+        ));
+    }
+    std::fs::create_dir_all(directory)?;
+    for (name, contents) in template.files {
+        std::fs::write(directory.join(name), contents)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Task;
+
+    #[test]
+    fn every_template_is_complete_and_valid() {
+        for template in TEMPLATES {
+            let directory =
+                std::env::temp_dir().join(format!("karel-scaffold-{}", template.name));
+            let _ = std::fs::remove_dir_all(&directory);
+            create(template, &directory).unwrap();
+
+            // The program stub must validate and the task file must load.
+            let source =
+                std::fs::read_to_string(directory.join("program.kl")).unwrap();
+            crate::parser::validate(&crate::parser::preprocess(&source)).unwrap();
+            let task = Task::load(&directory.join("task.toml")).unwrap();
+            assert!(!task.worlds.is_empty(), "{}", template.name);
+            assert!(!task.goals.is_empty(), "{}", template.name);
+            assert!(directory.join("README.md").exists());
+
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn creating_over_an_existing_directory_fails() {
+        let directory = std::env::temp_dir().join("karel-scaffold-existing");
+        std::fs::create_dir_all(&directory).unwrap();
+        let error = create(find("maze").unwrap(), &directory).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unknown_template_is_none() {
+        assert!(find("maze").is_some());
+        assert!(find("labyrinth").is_none());
+    }
+}
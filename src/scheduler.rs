@@ -0,0 +1,216 @@
+//! Turn-based execution of several programs in one world.
+//!
+//! Where [`multiplayer`](crate::multiplayer) interleaves human clients, the
+//! scheduler interleaves compiled [`Program`]s: every robot takes one
+//! interpreter step per round, in round-robin or priority order, all acting
+//! on the same grid. This is the execution backbone for NPC puzzles and
+//! offline tournaments — run it to the end and read the per-robot
+//! [`Outcome`]s.
+//!
+//! Robots share beepers, walls, the tick clock and scripted events, but do
+//! not block each other; collision rules are the environment's business,
+//! not the scheduler's.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::mem;
+
+use crate::interpreter::{Interpreter, RunStatus, StepResult};
+use crate::program::Program;
+use crate::world::{Robot, World};
+
+/// The order robots take their turns in within a round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ordering {
+    /// The order the robots were added in.
+    #[default]
+    RoundRobin,
+    /// Higher priority first; ties keep the order robots were added in.
+    Priority,
+}
+
+/// How one robot's program ended, and where the robot ended up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub name: String,
+    pub status: RunStatus,
+    /// Interpreter steps this robot took, control flow included.
+    pub steps: usize,
+    pub robot: Robot,
+}
+
+struct Runner {
+    name: String,
+    priority: i32,
+    robot: Robot,
+    /// Holds a placeholder world between turns; the shared world is swapped
+    /// in for the duration of each step.
+    interpreter: Interpreter<'static>,
+    steps: usize,
+    status: Option<RunStatus>,
+}
+
+/// Interleaves several programs step-by-step in one world.
+pub struct Scheduler {
+    world: World,
+    ordering: Ordering,
+    runners: Vec<Runner>,
+}
+
+impl Scheduler {
+    /// A scheduler over `world`. The world's own robot seat is only a
+    /// stand-in; every robot in play is added with [`add`](Scheduler::add).
+    pub fn new(world: World, ordering: Ordering) -> Scheduler {
+        Scheduler {
+            world,
+            ordering,
+            runners: Vec::new(),
+        }
+    }
+
+    /// Enter `robot` into the world, driven by `program`. `priority` only
+    /// matters under [`Ordering::Priority`]; higher goes first.
+    pub fn add(&mut self, name: &str, program: &Program, robot: Robot, priority: i32) {
+        self.runners.push(Runner {
+            name: name.to_string(),
+            priority,
+            robot,
+            interpreter: program.start(World::new(0, 0)),
+            steps: 0,
+            status: None,
+        });
+    }
+
+    /// The shared world as it currently stands.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Run every program to its end, stopping any single robot after
+    /// `step_limit` steps with [`RunStatus::LimitHit`]. Outcomes come back
+    /// in the order the robots were added.
+    pub fn run(&mut self, step_limit: usize) -> Vec<Outcome> {
+        let turn_order = self.turn_order();
+        loop {
+            let mut any_running = false;
+            for &index in &turn_order {
+                let runner = &mut self.runners[index];
+                if runner.status.is_some() {
+                    continue;
+                }
+                if runner.steps == step_limit {
+                    runner.status = Some(RunStatus::LimitHit);
+                    continue;
+                }
+                step_runner(&mut self.world, runner);
+                any_running |= runner.status.is_none();
+            }
+            if !any_running {
+                break;
+            }
+        }
+        self.runners
+            .iter()
+            .map(|runner| Outcome {
+                name: runner.name.clone(),
+                status: runner.status.clone().expect("every runner has ended"),
+                steps: runner.steps,
+                robot: runner.robot,
+            })
+            .collect()
+    }
+
+    /// Indices of the runners in the order they act within a round.
+    fn turn_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.runners.len()).collect();
+        if self.ordering == Ordering::Priority {
+            order.sort_by_key(|&index| core::cmp::Reverse(self.runners[index].priority));
+        }
+        order
+    }
+}
+
+/// Seat the runner's robot in the shared world, take one step, and put
+/// everything back.
+fn step_runner(world: &mut World, runner: &mut Runner) {
+    world.robot = runner.robot;
+    mem::swap(world, &mut runner.interpreter.world);
+    let result = runner.interpreter.step();
+    mem::swap(world, &mut runner.interpreter.world);
+    runner.robot = world.robot;
+
+    runner.steps += 1;
+    runner.status = match result {
+        Ok(StepResult::Running) => None,
+        Ok(StepResult::Finished) if !runner.robot.alive => Some(RunStatus::Halted),
+        Ok(StepResult::Finished) => Some(RunStatus::Completed),
+        Err(error) => Some(RunStatus::Failed(error)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::RuntimeError;
+    use crate::world::{Direction, Position};
+
+    fn race_world() -> World {
+        let mut world = World::new(3, 1);
+        world.set_beepers(Position::new(1, 0), 1);
+        world
+    }
+
+    fn racer() -> Program {
+        Program::compile("def main\n move\n take\nenddef").unwrap()
+    }
+
+    #[test]
+    fn round_robin_gives_the_first_robot_the_first_turn() {
+        let mut scheduler = Scheduler::new(race_world(), Ordering::RoundRobin);
+        let program = racer();
+        scheduler.add("alice", &program, Robot::new(Position::new(0, 0), Direction::East), 0);
+        scheduler.add("bob", &program, Robot::new(Position::new(2, 0), Direction::West), 0);
+
+        let outcomes = scheduler.run(100);
+        // Both reach the middle tile; Alice takes the one beeper first and
+        // Bob's `take` then finds nothing.
+        assert_eq!(outcomes[0].status, RunStatus::Completed);
+        assert_eq!(outcomes[0].robot.position, Position::new(1, 0));
+        assert_eq!(
+            outcomes[1].status,
+            RunStatus::Failed(RuntimeError::NoBeeperToTake { line: 3 })
+        );
+        assert_eq!(scheduler.world().beepers_at(Position::new(1, 0)), 0);
+    }
+
+    #[test]
+    fn priority_ordering_lets_the_favourite_act_first() {
+        let mut scheduler = Scheduler::new(race_world(), Ordering::Priority);
+        let program = racer();
+        scheduler.add("alice", &program, Robot::new(Position::new(0, 0), Direction::East), 0);
+        scheduler.add("bob", &program, Robot::new(Position::new(2, 0), Direction::West), 1);
+
+        let outcomes = scheduler.run(100);
+        assert_eq!(
+            outcomes[0].status,
+            RunStatus::Failed(RuntimeError::NoBeeperToTake { line: 3 })
+        );
+        assert_eq!(outcomes[1].name, "bob");
+        assert_eq!(outcomes[1].status, RunStatus::Completed);
+    }
+
+    #[test]
+    fn runaway_robots_hit_the_step_limit_without_stalling_others() {
+        let mut scheduler = Scheduler::new(race_world(), Ordering::RoundRobin);
+        let spinner = Program::compile("def main\n while! beeper\n  turn-left\n endwhile\nenddef")
+            .unwrap();
+        let program = racer();
+        scheduler.add("spinner", &spinner, Robot::new(Position::new(0, 0), Direction::East), 0);
+        scheduler.add("walker", &program, Robot::new(Position::new(2, 0), Direction::West), 0);
+
+        let outcomes = scheduler.run(50);
+        assert_eq!(outcomes[0].status, RunStatus::LimitHit);
+        assert_eq!(outcomes[0].steps, 50);
+        assert_eq!(outcomes[1].status, RunStatus::Completed);
+    }
+}
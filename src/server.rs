@@ -0,0 +1,468 @@
+//! A headless HTTP grading server.
+//!
+//! Learning platforms post student programs and get grade reports back
+//! without shelling out to the CLI per submission. The HTTP/1.1 handling is
+//! hand-rolled on `std::net` like the rest of the crate; it supports exactly
+//! what the API needs and nothing more.
+//!
+//! The API, with task names taken from the `.toml` file stems in the tasks
+//! directory:
+//!
+//! | Method | Path                  | Body     | Response                  |
+//! |--------|-----------------------|----------|---------------------------|
+//! | GET    | `/tasks`              | —        | JSON list of tasks        |
+//! | POST   | `/tasks/<name>/grade` | program  | JSON grade report         |
+//! | POST   | `/tasks/<name>/trace` | program  | JSONL replay trace        |
+//!
+//! The trace runs in the task's first world and can be played back with
+//! `karel replay`.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::grade;
+use crate::interpreter::{Interpreter, StepResult};
+use crate::json::Value;
+use crate::parser;
+use crate::task::Task;
+use crate::trace::Recorder;
+
+/// The most request body bytes the server accepts. Submissions are a few
+/// kilobytes of Karel source; a multi-gigabyte `Content-Length` must be
+/// answered with 413, not allocated.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// The most bytes accepted for the request line and headers together, so a
+/// client that streams bytes without ever sending a newline cannot grow a
+/// header string without bound.
+const MAX_HEADERS: usize = 8 * 1024;
+
+/// A parsed HTTP request: just the parts the API looks at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+/// A response ready to serialize.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn json(body: Value) -> Response {
+        Response {
+            status: 200,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            body: Value::object([("error", message.into())]).to_string(),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// The tasks directory, loaded once and served read-only.
+pub struct GradingServer {
+    tasks: BTreeMap<String, Task>,
+}
+
+impl GradingServer {
+    /// Load every `.toml` task in `directory`. Unreadable tasks fail the
+    /// whole startup: a grading server with silently missing tasks would
+    /// grade submissions against the wrong set.
+    pub fn new(directory: &Path) -> Result<GradingServer, String> {
+        let mut tasks = BTreeMap::new();
+        let entries = std::fs::read_dir(directory)
+            .map_err(|error| format!("cannot read `{}`: {error}", directory.display()))?;
+        for entry in entries {
+            let entry = entry.map_err(|error| error.to_string())?;
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "toml") {
+                let name = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or("task")
+                    .to_string();
+                let task = Task::load(&path)
+                    .map_err(|error| format!("{}: {error}", path.display()))?;
+                tasks.insert(name, task);
+            }
+        }
+        if tasks.is_empty() {
+            return Err(format!("no .toml tasks in `{}`", directory.display()));
+        }
+        Ok(GradingServer { tasks })
+    }
+
+    /// How many tasks are being served.
+    pub fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Dispatch one request.
+    pub fn handle(&self, request: &Request) -> Response {
+        let segments: Vec<&str> = request
+            .path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["tasks"]) => Response::json(Value::Array(
+                self.tasks
+                    .iter()
+                    .map(|(name, task)| {
+                        Value::object([
+                            ("id", name.as_str().into()),
+                            ("name", task.name.as_str().into()),
+                            ("worlds", task.worlds.len().into()),
+                            ("goals", task.goals.len().into()),
+                        ])
+                    })
+                    .collect(),
+            )),
+            ("POST", ["tasks", name, "grade"]) => match self.tasks.get(*name) {
+                Some(task) => {
+                    let report = grade::grade(task, "submission", &request.body);
+                    Response::json(report.to_json(task.goals.len()))
+                }
+                None => Response::error(404, "no such task"),
+            },
+            ("POST", ["tasks", name, "trace"]) => match self.tasks.get(*name) {
+                Some(task) => trace_response(task, &request.body),
+                None => Response::error(404, "no such task"),
+            },
+            ("GET", _) | ("POST", _) => Response::error(404, "no such endpoint"),
+            _ => Response::error(405, "use GET or POST"),
+        }
+    }
+}
+
+/// Run the submission in the task's first world and return the trace.
+fn trace_response(task: &Task, source: &str) -> Response {
+    let lines = parser::preprocess(source);
+    if let Err(error) = parser::validate(&lines) {
+        return Response::error(400, &error.to_string());
+    }
+    let (_, world) = &task.worlds[0];
+    let mut interpreter = match Interpreter::new(lines, world.clone()) {
+        Ok(interpreter) => interpreter,
+        Err(error) => return Response::error(400, &error.to_string()),
+    };
+    let mut buffer = Vec::new();
+    let mut recorder = match Recorder::new(&mut buffer, &interpreter.world) {
+        Ok(recorder) => recorder,
+        Err(error) => return Response::error(500, &error.to_string()),
+    };
+    let mut steps = 0usize;
+    let outcome = loop {
+        if steps >= grade::STEP_BUDGET {
+            break Some(format!("step limit of {} exceeded", grade::STEP_BUDGET));
+        }
+        let line = interpreter.current_line().unwrap_or(0);
+        let instruction = interpreter
+            .current_instruction()
+            .unwrap_or_default()
+            .to_string();
+        match interpreter.step() {
+            Ok(StepResult::Running) => {
+                steps += 1;
+                if recorder
+                    .record(line, &instruction, &interpreter.world)
+                    .is_err()
+                {
+                    return Response::error(500, "trace recording failed");
+                }
+            }
+            Ok(StepResult::Finished) => break None,
+            Err(error) => break Some(error.to_string()),
+        }
+    };
+    if recorder.finish(outcome.as_deref()).is_err() {
+        return Response::error(500, "trace recording failed");
+    }
+    Response {
+        status: 200,
+        content_type: "application/jsonl",
+        body: String::from_utf8_lossy(&buffer).to_string(),
+    }
+}
+
+/// Accept connections forever, one thread per request.
+pub fn serve(listener: TcpListener, server: GradingServer) -> io::Result<()> {
+    let server = Arc::new(server);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || {
+            let _ = handle_connection(&server, stream);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(server: &GradingServer, stream: TcpStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let response = match read_request(&mut reader)? {
+        RequestOutcome::Parsed(request) => server.handle(&request),
+        RequestOutcome::TooLarge => Response::error(413, "request body too large"),
+        RequestOutcome::Malformed => Response::error(400, "malformed request"),
+        RequestOutcome::Closed => return Ok(()),
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len(),
+        response.body,
+    )?;
+    writer.flush()
+}
+
+/// One bounded line off a socket.
+pub(crate) enum LineOutcome {
+    Line(String),
+    /// The peer hung up before sending anything.
+    Eof,
+    /// No newline within the limit; the peer is flooding.
+    TooLong,
+}
+
+/// Read one newline-terminated line of at most `limit` bytes. The buffered
+/// `read_line` would otherwise grow its string for as long as the peer
+/// keeps bytes coming without a newline.
+pub(crate) fn read_line_capped(
+    reader: &mut impl BufRead,
+    limit: usize,
+) -> io::Result<LineOutcome> {
+    let mut line = String::new();
+    // One byte of slack so a line of exactly `limit` bytes still gets its
+    // newline read.
+    if reader.take(limit as u64 + 1).read_line(&mut line)? == 0 {
+        return Ok(LineOutcome::Eof);
+    }
+    if line.len() > limit {
+        return Ok(LineOutcome::TooLong);
+    }
+    Ok(LineOutcome::Line(line))
+}
+
+/// What reading one connection's request produced.
+enum RequestOutcome {
+    Parsed(Request),
+    /// The headers ran past [`MAX_HEADERS`], or claim a body past
+    /// [`MAX_BODY`]; neither was allocated.
+    TooLarge,
+    /// No parseable request line, or the body fell short of its headers.
+    Malformed,
+    /// The peer hung up before sending anything.
+    Closed,
+}
+
+fn read_request(reader: &mut impl BufRead) -> io::Result<RequestOutcome> {
+    let mut budget = MAX_HEADERS;
+    let request_line = match read_line_capped(reader, budget)? {
+        LineOutcome::Line(line) => line,
+        LineOutcome::Eof => return Ok(RequestOutcome::Closed),
+        LineOutcome::TooLong => return Ok(RequestOutcome::TooLarge),
+    };
+    budget -= request_line.len();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(RequestOutcome::Malformed);
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut content_length = 0usize;
+    loop {
+        let line = match read_line_capped(reader, budget)? {
+            LineOutcome::Line(line) => line,
+            LineOutcome::Eof => return Ok(RequestOutcome::Malformed),
+            LineOutcome::TooLong => return Ok(RequestOutcome::TooLarge),
+        };
+        budget -= line.len();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                // An unparseable length counts as oversized, not as zero:
+                // the client asked for something we will not do.
+                content_length = value.trim().parse().unwrap_or(usize::MAX);
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Ok(RequestOutcome::TooLarge);
+    }
+    let mut body = Vec::new();
+    reader.take(content_length as u64).read_to_end(&mut body)?;
+    if body.len() < content_length {
+        return Ok(RequestOutcome::Malformed);
+    }
+    Ok(RequestOutcome::Parsed(Request {
+        method,
+        path,
+        body: String::from_utf8_lossy(&body).to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A tasks directory with one simple task, cleaned up on drop.
+    struct TasksDir {
+        path: PathBuf,
+    }
+
+    impl Drop for TasksDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn tasks_dir() -> TasksDir {
+        let path = std::env::temp_dir().join(format!(
+            "karel-server-test-{}-{:?}",
+            std::process::id(),
+            std::thread::current().id(),
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("walk.world.txt"), ">...\n").unwrap();
+        std::fs::write(
+            path.join("walk.toml"),
+            "name = \"Walk east\"\nworlds = [\"walk.world.txt\"]\ngoals = [\"robot-at 3 0\"]\n",
+        )
+        .unwrap();
+        TasksDir { path }
+    }
+
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn tasks_are_listed() {
+        let directory = tasks_dir();
+        let server = GradingServer::new(&directory.path).unwrap();
+        let response = server.handle(&request("GET", "/tasks", ""));
+        assert_eq!(response.status, 200);
+        assert!(response.body.contains("\"id\":\"walk\""), "{}", response.body);
+        assert!(response.body.contains("Walk east"), "{}", response.body);
+    }
+
+    #[test]
+    fn grading_reports_pass_and_fail() {
+        let directory = tasks_dir();
+        let server = GradingServer::new(&directory.path).unwrap();
+        let passed = server.handle(&request(
+            "POST",
+            "/tasks/walk/grade",
+            "def main\n move\n move\n move\n die\nenddef",
+        ));
+        assert!(passed.body.contains("\"passed\":true"), "{}", passed.body);
+        let failed = server.handle(&request(
+            "POST",
+            "/tasks/walk/grade",
+            "def main\n move\n die\nenddef",
+        ));
+        assert!(failed.body.contains("\"passed\":false"), "{}", failed.body);
+    }
+
+    #[test]
+    fn traces_replay_the_submission() {
+        let directory = tasks_dir();
+        let server = GradingServer::new(&directory.path).unwrap();
+        let response = server.handle(&request(
+            "POST",
+            "/tasks/walk/trace",
+            "def main\n move\n die\nenddef",
+        ));
+        assert_eq!(response.status, 200);
+        let trace = crate::trace::parse(&response.body).unwrap();
+        assert_eq!(trace.frames.len(), 1);
+        assert!(trace.error.is_none());
+    }
+
+    #[test]
+    fn huge_content_lengths_are_rejected_unread() {
+        let raw = b"POST /tasks/walk/grade HTTP/1.1\r\nContent-Length: 9999999999\r\n\r\n";
+        let mut reader = std::io::Cursor::new(&raw[..]);
+        assert!(matches!(
+            read_request(&mut reader).unwrap(),
+            RequestOutcome::TooLarge
+        ));
+    }
+
+    #[test]
+    fn endless_header_lines_are_rejected() {
+        // A request line that never ends...
+        let mut reader = std::io::Cursor::new(vec![b'a'; 64 * 1024]);
+        assert!(matches!(
+            read_request(&mut reader).unwrap(),
+            RequestOutcome::TooLarge
+        ));
+        // ...and a header block that never ends.
+        let mut raw = b"POST /tasks/walk/grade HTTP/1.1\r\n".to_vec();
+        raw.extend(vec![b'x'; 64 * 1024]);
+        let mut reader = std::io::Cursor::new(raw);
+        assert!(matches!(
+            read_request(&mut reader).unwrap(),
+            RequestOutcome::TooLarge
+        ));
+    }
+
+    #[test]
+    fn short_bodies_are_malformed_not_hung() {
+        let raw = b"POST /tasks/walk/grade HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc";
+        let mut reader = std::io::Cursor::new(&raw[..]);
+        assert!(matches!(
+            read_request(&mut reader).unwrap(),
+            RequestOutcome::Malformed
+        ));
+    }
+
+    #[test]
+    fn unknown_tasks_and_endpoints_are_404() {
+        let directory = tasks_dir();
+        let server = GradingServer::new(&directory.path).unwrap();
+        assert_eq!(
+            server.handle(&request("POST", "/tasks/maze/grade", "")).status,
+            404
+        );
+        assert_eq!(server.handle(&request("GET", "/elsewhere", "")).status, 404);
+    }
+}
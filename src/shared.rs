@@ -0,0 +1,143 @@
+//! A [`World`] handle that several threads can hold at once.
+//!
+//! The interpreter mutates its environment and everything else reads it, so
+//! the design so far has been single-threaded: whoever drives the run owns
+//! the world. [`SharedWorld`] lifts that restriction with a clonable handle
+//! over `Arc<RwLock<World>>` — one thread steps an interpreter over one
+//! handle while a renderer thread takes [snapshots](SharedWorld::snapshot)
+//! through another. The lock never leaks into the API: readers get owned
+//! snapshots (cheap, since cloning a [`World`] only bumps reference counts)
+//! and the interpreter goes through [`Environment`] as it would with any
+//! other world.
+
+use std::sync::{Arc, RwLock};
+
+use crate::environment::{Action, ActionFailure, Check, Environment};
+use crate::world::{Direction, World};
+
+/// A clonable, thread-safe handle to one world. All clones see and mutate
+/// the same state; drop the last one and the world goes with it.
+#[derive(Debug, Clone, Default)]
+pub struct SharedWorld {
+    world: Arc<RwLock<World>>,
+}
+
+impl SharedWorld {
+    /// Wrap a world for sharing across threads.
+    pub fn new(world: World) -> SharedWorld {
+        SharedWorld {
+            world: Arc::new(RwLock::new(world)),
+        }
+    }
+
+    /// An owned copy of the current state, for rendering or inspection.
+    /// The read lock is held only for the clone, which is cheap (see the
+    /// [`World`] docs on copy-on-write), so snapshotting in a tight loop
+    /// barely delays the interpreter thread.
+    pub fn snapshot(&self) -> World {
+        self.read().clone()
+    }
+
+    /// Run a closure over the current state under the read lock, for
+    /// queries where even a cheap clone is too much. Keep it short: the
+    /// interpreter thread blocks on writes while it runs.
+    pub fn with<T>(&self, f: impl FnOnce(&World) -> T) -> T {
+        f(&self.read())
+    }
+
+    /// Run a closure over the state under the write lock, for setup a
+    /// plain [`Environment`] action cannot express (placing beepers,
+    /// editing walls) while the handle is already shared.
+    pub fn with_mut<T>(&self, f: impl FnOnce(&mut World) -> T) -> T {
+        f(&mut self.write())
+    }
+
+    /// Take the world back out, cloning only if other handles still exist.
+    pub fn into_inner(self) -> World {
+        match Arc::try_unwrap(self.world) {
+            Ok(lock) => lock.into_inner().unwrap_or_else(|poison| poison.into_inner()),
+            Err(shared) => shared
+                .read()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .clone(),
+        }
+    }
+
+    // A poisoned lock means some thread panicked mid-action; the world is
+    // still structurally sound (actions are small and self-contained), so
+    // both accessors shrug the poison off rather than spread the panic.
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, World> {
+        self.world.read().unwrap_or_else(|poison| poison.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, World> {
+        self.world.write().unwrap_or_else(|poison| poison.into_inner())
+    }
+}
+
+impl Environment for SharedWorld {
+    fn perform(&mut self, action: Action) -> Result<(), ActionFailure> {
+        self.write().perform(action)
+    }
+
+    fn check(&self, check: Check) -> bool {
+        self.read().check(check)
+    }
+
+    fn facing(&self) -> Option<Direction> {
+        self.read().facing()
+    }
+
+    fn in_region(&self, name: &str) -> bool {
+        self.read().in_region(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::preprocess;
+    use crate::world::Position;
+
+    #[test]
+    fn snapshots_are_independent_of_later_mutation() {
+        let shared = SharedWorld::new(World::new(3, 1));
+        let before = shared.snapshot();
+        shared.with_mut(|world| world.set_beepers(Position::new(1, 0), 2));
+        assert_eq!(before.beepers_at(Position::new(1, 0)), 0);
+        assert_eq!(shared.snapshot().beepers_at(Position::new(1, 0)), 2);
+    }
+
+    #[test]
+    fn a_reader_thread_watches_the_interpreter_make_progress() {
+        let shared = SharedWorld::new(World::new(10, 1));
+        let lines = preprocess("def main\n repeat 9\n  move\n endrepeat\nenddef");
+        let handle = shared.clone();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut interpreter = Interpreter::new(lines, handle).unwrap();
+                interpreter.run().into_result().unwrap();
+            });
+            // The "renderer": poll snapshots until the run visibly ends.
+            loop {
+                if shared.snapshot().robot.position == Position::new(9, 0) {
+                    break;
+                }
+                std::thread::yield_now();
+            }
+        });
+    }
+
+    #[test]
+    fn into_inner_returns_the_final_world() {
+        let shared = SharedWorld::new(World::new(3, 1));
+        let mut interpreter = Interpreter::new(
+            preprocess("def main\n move\nenddef"),
+            shared.clone(),
+        )
+        .unwrap();
+        interpreter.run().into_result().unwrap();
+        assert_eq!(shared.into_inner().robot.position, Position::new(1, 0));
+    }
+}
@@ -0,0 +1,282 @@
+//! Per-user statistics and the badges they earn.
+//!
+//! Front-ends that show a dashboard — the campaign player, a classroom
+//! portal — need somewhere to keep "how much has this student done":
+//! total runs and steps, which tasks were solved, and the fewest-step
+//! record per task. [`Stats`] is that ledger, [`Stats::record_run`] feeds
+//! it one run at a time, and [`Badge`]s are derived from it, never stored,
+//! so adding a badge later retroactively awards it.
+//!
+//! The profile is a plain text file, one fact per line
+//! (`runs 12`, `steps 3400`, `solved collect`, `record 24 collect`),
+//! read and written whole; task names go last on their line so they may
+//! contain spaces.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::storage::Storage;
+
+/// Everything tracked about one user across all their runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// How many runs were recorded, passing or not.
+    pub runs: usize,
+    /// Total steps executed across all runs.
+    pub total_steps: usize,
+    /// How often an existing fewest-step record was beaten.
+    pub improvements: usize,
+    /// Tasks solved at least once.
+    solved: Vec<String>,
+    /// Fewest steps of any passing run, per task.
+    records: Vec<(String, usize)>,
+}
+
+impl Stats {
+    /// Fold one run into the ledger: `steps` executed against `task`,
+    /// passing or not. A passing run marks the task solved and claims or
+    /// improves its fewest-step record.
+    pub fn record_run(&mut self, task: &str, steps: usize, passed: bool) {
+        self.runs += 1;
+        self.total_steps += steps;
+        if !passed {
+            return;
+        }
+        if !self.solved(task) {
+            self.solved.push(task.to_string());
+        }
+        match self.records.iter_mut().find(|(name, _)| name == task) {
+            Some((_, record)) if steps < *record => {
+                *record = steps;
+                self.improvements += 1;
+            }
+            Some(_) => {}
+            None => self.records.push((task.to_string(), steps)),
+        }
+    }
+
+    /// Has this task ever been solved?
+    pub fn solved(&self, task: &str) -> bool {
+        self.solved.iter().any(|done| done == task)
+    }
+
+    /// How many distinct tasks were solved.
+    pub fn solved_count(&self) -> usize {
+        self.solved.len()
+    }
+
+    /// The fewest steps any passing run of the task took, if it was solved.
+    pub fn record(&self, task: &str) -> Option<usize> {
+        self.records
+            .iter()
+            .find(|(name, _)| name == task)
+            .map(|(_, record)| *record)
+    }
+
+    /// The badges this ledger has earned, in [`Badge::ALL`] order.
+    pub fn badges(&self) -> Vec<Badge> {
+        Badge::ALL
+            .into_iter()
+            .filter(|badge| badge.earned(self))
+            .collect()
+    }
+
+    /// Parse a profile file; see the module docs for the format.
+    pub fn from_profile(source: &str) -> Result<Stats, StatsError> {
+        let mut stats = Stats::default();
+        for (index, raw) in source.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = || StatsError::BadProfile { line: index + 1 };
+            let (key, rest) = line.split_once(' ').ok_or_else(bad)?;
+            match key {
+                "runs" => stats.runs = rest.parse().map_err(|_| bad())?,
+                "steps" => stats.total_steps = rest.parse().map_err(|_| bad())?,
+                "improvements" => stats.improvements = rest.parse().map_err(|_| bad())?,
+                "solved" => stats.solved.push(rest.to_string()),
+                "record" => {
+                    let (steps, task) = rest.split_once(' ').ok_or_else(bad)?;
+                    let steps = steps.parse().map_err(|_| bad())?;
+                    stats.records.push((task.to_string(), steps));
+                }
+                _ => return Err(bad()),
+            }
+        }
+        Ok(stats)
+    }
+
+    /// The profile file contents for this ledger.
+    pub fn to_profile(&self) -> String {
+        let mut out = String::from("# karel statistics\n");
+        out.push_str(&format!("runs {}\n", self.runs));
+        out.push_str(&format!("steps {}\n", self.total_steps));
+        out.push_str(&format!("improvements {}\n", self.improvements));
+        for task in &self.solved {
+            out.push_str(&format!("solved {task}\n"));
+        }
+        for (task, record) in &self.records {
+            out.push_str(&format!("record {record} {task}\n"));
+        }
+        out
+    }
+
+    /// Read a profile from disk; a file that does not exist yet is an empty
+    /// ledger, so the first run needs no setup.
+    pub fn load(path: &Path) -> Result<Stats, StatsError> {
+        match std::fs::read_to_string(path) {
+            Ok(source) => Stats::from_profile(&source),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Stats::default()),
+            Err(error) => Err(StatsError::Io(error)),
+        }
+    }
+
+    /// Write the profile back to disk.
+    pub fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.to_profile())
+    }
+
+    /// Read a profile from a [`Storage`] backend; a key with nothing stored
+    /// is an empty ledger, like a missing file.
+    pub fn load_from(storage: &dyn Storage, key: &str) -> Result<Stats, StatsError> {
+        match storage.read(key).map_err(StatsError::Io)? {
+            Some(source) => Stats::from_profile(&source),
+            None => Ok(Stats::default()),
+        }
+    }
+
+    /// Write the profile into a [`Storage`] backend.
+    pub fn save_to(&self, storage: &mut dyn Storage, key: &str) -> Result<(), std::io::Error> {
+        storage.write(key, &self.to_profile())
+    }
+}
+
+/// An error while reading a statistics profile.
+#[derive(Debug)]
+pub enum StatsError {
+    Io(std::io::Error),
+    /// A profile line that is not one of the known facts.
+    BadProfile { line: usize },
+}
+
+impl fmt::Display for StatsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatsError::Io(error) => write!(f, "{error}"),
+            StatsError::BadProfile { line } => {
+                write!(f, "profile line {line} is not a known statistic")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StatsError {}
+
+/// A milestone worth a badge on the dashboard. Badges are computed from
+/// [`Stats`], never stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+    /// Solved a first task.
+    FirstSteps,
+    /// Solved ten distinct tasks.
+    TenTasks,
+    /// Executed 42,195 steps in total, passing or not.
+    Marathon,
+    /// Beat one of their own fewest-step records.
+    RecordBreaker,
+}
+
+impl Badge {
+    /// Every badge, in display order.
+    pub const ALL: [Badge; 4] = [
+        Badge::FirstSteps,
+        Badge::TenTasks,
+        Badge::Marathon,
+        Badge::RecordBreaker,
+    ];
+
+    /// The badge's stable, kebab-case name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Badge::FirstSteps => "first-steps",
+            Badge::TenTasks => "ten-tasks",
+            Badge::Marathon => "marathon",
+            Badge::RecordBreaker => "record-breaker",
+        }
+    }
+
+    /// What the badge is for, as shown on a dashboard.
+    pub fn description(self) -> &'static str {
+        match self {
+            Badge::FirstSteps => "solve your first task",
+            Badge::TenTasks => "solve ten different tasks",
+            Badge::Marathon => "run 42,195 steps in total",
+            Badge::RecordBreaker => "beat one of your own records",
+        }
+    }
+
+    /// Has this ledger earned the badge?
+    pub fn earned(self, stats: &Stats) -> bool {
+        match self {
+            Badge::FirstSteps => stats.solved_count() >= 1,
+            Badge::TenTasks => stats.solved_count() >= 10,
+            Badge::Marathon => stats.total_steps >= 42_195,
+            Badge::RecordBreaker => stats.improvements >= 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_accumulate_into_records() {
+        let mut stats = Stats::default();
+        stats.record_run("collect", 30, false);
+        assert_eq!(stats.runs, 1);
+        assert!(!stats.solved("collect"));
+        assert_eq!(stats.record("collect"), None);
+
+        stats.record_run("collect", 28, true);
+        stats.record_run("collect", 24, true);
+        stats.record_run("collect", 27, true);
+        assert_eq!(stats.runs, 4);
+        assert_eq!(stats.total_steps, 109);
+        assert_eq!(stats.solved_count(), 1);
+        // The record keeps the best run; only genuine improvements count.
+        assert_eq!(stats.record("collect"), Some(24));
+        assert_eq!(stats.improvements, 1);
+    }
+
+    #[test]
+    fn badges_are_derived_not_stored() {
+        let mut stats = Stats::default();
+        assert!(stats.badges().is_empty());
+        stats.record_run("collect", 50_000, true);
+        assert_eq!(stats.badges(), [Badge::FirstSteps, Badge::Marathon]);
+        stats.record_run("collect", 10, true);
+        assert!(stats.badges().contains(&Badge::RecordBreaker));
+        assert!(!Badge::TenTasks.earned(&stats));
+    }
+
+    #[test]
+    fn the_profile_roundtrips() {
+        let mut stats = Stats::default();
+        stats.record_run("collect the field", 24, true);
+        stats.record_run("maze", 80, false);
+        let profile = stats.to_profile();
+        assert_eq!(Stats::from_profile(&profile).unwrap(), stats);
+        // A task name with spaces survives: the number goes first.
+        assert!(profile.contains("record 24 collect the field\n"));
+        assert!(matches!(
+            Stats::from_profile("runs twelve\n"),
+            Err(StatsError::BadProfile { line: 1 })
+        ));
+        assert!(matches!(
+            Stats::from_profile("streak 3\n"),
+            Err(StatsError::BadProfile { line: 1 })
+        ));
+    }
+}
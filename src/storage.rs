@@ -0,0 +1,116 @@
+//! Where profiles and progress are kept.
+//!
+//! The campaign [`Progress`](crate::campaign::Progress) and the statistics
+//! [`Stats`](crate::stats::Stats) ledger both persist as small text
+//! documents. [`Storage`] abstracts where those documents live, so the
+//! command line keeps them in files while a web deployment backs the same
+//! code with its own database: implement two methods and every profile
+//! reader and writer in the crate works unchanged.
+//!
+//! Two implementations ship here: [`DirectoryStorage`] (documents are files
+//! in one directory) and [`MemoryStorage`] (documents live and die with the
+//! process — also the natural test double).
+
+use std::io;
+use std::path::PathBuf;
+
+/// A keyed store of small text documents. Keys are short names like
+/// `course.profile`; what they mean is up to the caller.
+pub trait Storage {
+    /// The document stored under the key, or `None` when nothing is —
+    /// a fresh profile, not an error.
+    fn read(&self, key: &str) -> io::Result<Option<String>>;
+
+    /// Store a document under the key, replacing what was there.
+    fn write(&mut self, key: &str, contents: &str) -> io::Result<()>;
+}
+
+/// [`Storage`] as files in one directory, named by their keys: what the
+/// command line uses.
+#[derive(Debug, Clone)]
+pub struct DirectoryStorage {
+    root: PathBuf,
+}
+
+impl DirectoryStorage {
+    /// Store documents in the given directory; it is created on the first
+    /// write.
+    pub fn new(root: impl Into<PathBuf>) -> DirectoryStorage {
+        DirectoryStorage { root: root.into() }
+    }
+}
+
+impl Storage for DirectoryStorage {
+    fn read(&self, key: &str) -> io::Result<Option<String>> {
+        match std::fs::read_to_string(self.root.join(key)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn write(&mut self, key: &str, contents: &str) -> io::Result<()> {
+        std::fs::create_dir_all(&self.root)?;
+        std::fs::write(self.root.join(key), contents)
+    }
+}
+
+/// [`Storage`] that forgets everything when dropped: for tests, and for
+/// embedding the crate where nothing should touch the disk.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    entries: Vec<(String, String)>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> io::Result<Option<String>> {
+        Ok(self
+            .entries
+            .iter()
+            .find(|(stored, _)| stored == key)
+            .map(|(_, contents)| contents.clone()))
+    }
+
+    fn write(&mut self, key: &str, contents: &str) -> io::Result<()> {
+        match self.entries.iter_mut().find(|(stored, _)| stored == key) {
+            Some(entry) => entry.1 = contents.to_string(),
+            None => self.entries.push((key.to_string(), contents.to_string())),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_storage_roundtrips() {
+        let mut storage = MemoryStorage::new();
+        assert_eq!(storage.read("a.profile").unwrap(), None);
+        storage.write("a.profile", "runs 1\n").unwrap();
+        storage.write("a.profile", "runs 2\n").unwrap();
+        assert_eq!(storage.read("a.profile").unwrap().as_deref(), Some("runs 2\n"));
+    }
+
+    #[test]
+    fn directory_storage_keeps_files() {
+        let root = std::env::temp_dir().join("karel-storage-test");
+        let _ = std::fs::remove_dir_all(&root);
+        let mut storage = DirectoryStorage::new(&root);
+        assert_eq!(storage.read("a.profile").unwrap(), None);
+        storage.write("a.profile", "runs 1\n").unwrap();
+        assert_eq!(storage.read("a.profile").unwrap().as_deref(), Some("runs 1\n"));
+        // A second handle over the same directory sees the same documents.
+        assert_eq!(
+            DirectoryStorage::new(&root).read("a.profile").unwrap().as_deref(),
+            Some("runs 1\n")
+        );
+    }
+}
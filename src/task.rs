@@ -0,0 +1,800 @@
+//! Task files: what a student's program has to achieve.
+//!
+//! A task is described by a small TOML file next to its world files:
+//!
+//! ```toml
+//! name = "Harvest the field"
+//! worlds = ["field1.txt", "field2.txt"]
+//! goals = ["no-beepers", "robot-at 0 0"]
+//! events = ["at 5 wall 2 0", "at 9 beepers 4 0 2"]
+//! costs = ["move 1", "put 2"]
+//! lints = ["dead-code error", "magic-repeat off"]
+//! stdlib = true
+//! hooks = "hooks.kl"
+//! before = "scatter"
+//! after = "sweep"
+//! starter = "starter.kl"
+//! ```
+//!
+//! The program is run once in every world and passes when all goals hold in
+//! the final state of each of them. Only the small slice of TOML shown above
+//! is understood (top-level `key = value` with strings and string arrays);
+//! that keeps the crate dependency-free and the format obvious.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::environment::Action;
+use crate::lint;
+use crate::lock;
+use crate::world::{Change, Event, Position, World};
+use crate::worldfile;
+
+/// One thing that must hold in the final world for the task to count as
+/// solved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Goal {
+    /// `no-beepers`: every beeper has been collected.
+    NoBeepers,
+    /// `robot-at X Y`: the robot ends on the given tile.
+    RobotAt(Position),
+    /// `beepers-at X Y N`: exactly `N` beepers lie on the given tile.
+    BeepersAt(Position, u8),
+    /// `match FILE`: beepers and robot position equal those of the world in
+    /// `FILE`. Boxed so the one goal that carries a whole world does not
+    /// size every other goal up to match.
+    Match(Box<World>),
+    /// `within-ticks N`: the run performed at most `N` actions.
+    WithinTicks(usize),
+    /// `in-region NAME`: the robot ends inside the named region of the
+    /// world it ran in.
+    InRegion(String),
+    /// `beeps N`: the robot beeped exactly `N` times.
+    Beeps(usize),
+    /// `beep-at X Y`: at least one beep sounded on the given tile.
+    BeepAt(Position),
+    /// A predicate supplied in Rust; see [`Goal::custom`]. Has no task-file
+    /// spelling, so it only appears in tasks embedders build in code.
+    Custom(CustomGoal),
+}
+
+/// The closure behind [`Goal::custom`], wrapped so [`Goal`] can stay
+/// cloneable and comparable: clones share the closure, and two custom goals
+/// are equal exactly when they share it.
+#[derive(Clone)]
+pub struct CustomGoal {
+    predicate: std::sync::Arc<dyn Fn(&World) -> bool + Send + Sync>,
+}
+
+impl fmt::Debug for CustomGoal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomGoal")
+    }
+}
+
+impl PartialEq for CustomGoal {
+    fn eq(&self, other: &CustomGoal) -> bool {
+        std::sync::Arc::ptr_eq(&self.predicate, &other.predicate)
+    }
+}
+
+impl Eq for CustomGoal {}
+
+impl Goal {
+    /// A goal that holds whenever the predicate says so, for conditions the
+    /// declarative forms cannot express ("the beepers along row 1 form a
+    /// palindrome"). Only constructible from Rust; the task-file format is
+    /// deliberately not extended.
+    pub fn custom<F>(predicate: F) -> Goal
+    where
+        F: Fn(&World) -> bool + Send + Sync + 'static,
+    {
+        Goal::Custom(CustomGoal {
+            predicate: std::sync::Arc::new(predicate),
+        })
+    }
+
+    /// Does this goal hold in the given final world? For weighted scoring
+    /// across all of a task's goals, see [`Task::score`].
+    pub fn is_met(&self, world: &World) -> bool {
+        match self {
+            Goal::NoBeepers => {
+                (0..world.height()).all(|y| {
+                    (0..world.width()).all(|x| world.beepers_at(Position::new(x, y)) == 0)
+                })
+            }
+            Goal::RobotAt(position) => world.robot.position == *position,
+            Goal::BeepersAt(position, count) => world.beepers_at(*position) == *count,
+            Goal::Match(expected) => {
+                world.robot.position == expected.robot.position
+                    && (0..world.height().max(expected.height())).all(|y| {
+                        (0..world.width().max(expected.width())).all(|x| {
+                            let position = Position::new(x, y);
+                            world.beepers_at(position) == expected.beepers_at(position)
+                        })
+                    })
+            }
+            Goal::WithinTicks(ticks) => world.ticks() <= *ticks,
+            Goal::InRegion(name) => world.region_contains(name, world.robot.position),
+            Goal::Beeps(count) => world.beeps().len() == *count,
+            Goal::BeepAt(position) => world.beeps().contains(position),
+            Goal::Custom(custom) => (custom.predicate)(world),
+        }
+    }
+}
+
+/// The weighted outcome of checking a task's goals against one final world;
+/// see [`Task::score`]. Pass/fail stays all-or-nothing — this is the
+/// partial-credit view next to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoalScore {
+    /// Per-goal (weight, met), in the task's goal order.
+    pub breakdown: Vec<(usize, bool)>,
+}
+
+impl GoalScore {
+    /// The weight of the goals that held.
+    pub fn earned(&self) -> usize {
+        self.breakdown.iter().filter(|(_, met)| *met).map(|(weight, _)| weight).sum()
+    }
+
+    /// The weight of all goals together.
+    pub fn total(&self) -> usize {
+        self.breakdown.iter().map(|(weight, _)| weight).sum()
+    }
+
+    /// The score as a fraction of the total weight, 0.0 to 1.0. A task
+    /// with no goals (or only zero-weight ones) scores full marks, matching
+    /// how [`Goal::is_met`] over an empty list passes.
+    pub fn fraction(&self) -> f64 {
+        match self.total() {
+            0 => 1.0,
+            total => self.earned() as f64 / total as f64,
+        }
+    }
+}
+
+/// Per-action costs for efficiency grading; see the `costs` task key.
+///
+/// Every action costs 1 unless the task says otherwise
+/// (`costs = ["move 1", "put 2"]`), so the default total is simply the
+/// tick count and teachers only list the weights they care about.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CostModel {
+    /// Overrides as (action, cost); actions not listed cost 1.
+    overrides: Vec<(Action, usize)>,
+}
+
+impl CostModel {
+    /// The cost of one performance of the action.
+    pub fn cost_of(&self, action: Action) -> usize {
+        self.overrides
+            .iter()
+            .find(|(overridden, _)| *overridden == action)
+            .map(|(_, cost)| *cost)
+            .unwrap_or(1)
+    }
+
+    /// Set the cost of one action, replacing any earlier override.
+    pub fn set(&mut self, action: Action, cost: usize) {
+        match self
+            .overrides
+            .iter_mut()
+            .find(|(overridden, _)| *overridden == action)
+        {
+            Some(existing) => existing.1 = cost,
+            None => self.overrides.push((action, cost)),
+        }
+    }
+
+    /// The total cost of a run, from the world's per-action counts.
+    pub fn total(&self, world: &World) -> usize {
+        Action::ALL
+            .into_iter()
+            .map(|action| self.cost_of(action) * world.action_count(action))
+            .sum()
+    }
+}
+
+/// A task: a name, the worlds to run in, and the goals to reach.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub name: String,
+    /// Worlds as (file name, parsed world), in file order.
+    pub worlds: Vec<(String, World)>,
+    pub goals: Vec<Goal>,
+    /// Relative weight of each goal for partial credit, aligned with
+    /// [`goals`](Task::goals); goals beyond the end of this list weigh 1.
+    /// Written in the task file as a percentage prefix on the goal string
+    /// (`"60% no-beepers"`), though the numbers need not add up to
+    /// anything — scoring is by share of the total.
+    pub goal_weights: Vec<usize>,
+    /// Scripted world changes, scheduled into every world before the run.
+    pub events: Vec<Event>,
+    /// Per-action costs for the run's total-cost report.
+    pub costs: CostModel,
+    /// Which style lints apply to submissions and how seriously; the
+    /// default warns on everything.
+    pub lints: lint::Config,
+    /// Link the bundled [standard library](crate::modules::STDLIB) into
+    /// submissions, so `use std` works. Off by default.
+    pub stdlib: bool,
+    /// Teacher-side procedures linked beside every submission, as
+    /// (file name, source); see the `hooks` task key.
+    pub hooks: Option<(String, String)>,
+    /// Procedure to run before the student's `main`, e.g. to scatter
+    /// beepers; normally defined in the [hooks](Task::hooks) file.
+    pub before: Option<String>,
+    /// Procedure to run after the student's `main`, e.g. to perform final
+    /// cleanup checks; normally defined in the [hooks](Task::hooks) file.
+    pub after: Option<String>,
+    /// The starter file submissions grew out of, as (file name, source).
+    /// Its [locked regions](crate::lock) must survive into every
+    /// submission unmodified; see the `starter` task key.
+    pub starter: Option<(String, String)>,
+}
+
+/// An error in a task file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskError {
+    /// A line that is not `key = value` or uses an unknown key.
+    BadSyntax { line: usize },
+    /// A goal string that is not one of the known goal forms.
+    BadGoal { goal: String },
+    /// An event string that is not one of the known event forms.
+    BadEvent { event: String },
+    /// A cost string that is not `action N`.
+    BadCost { cost: String },
+    /// A lint string that is not `rule off|warn|error`.
+    BadLint { lint: String },
+    /// A referenced world file could not be read or parsed.
+    BadWorld { file: String, reason: String },
+    /// The referenced hooks file could not be read.
+    BadHooks { file: String, reason: String },
+    /// The referenced starter file could not be read, or its lock markers
+    /// do not pair up.
+    BadStarter { file: String, reason: String },
+    /// The task has no worlds to run in.
+    NoWorlds,
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::BadSyntax { line } => write!(f, "line {line}: expected `key = value`"),
+            TaskError::BadGoal { goal } => write!(f, "unknown goal `{goal}`"),
+            TaskError::BadEvent { event } => write!(f, "unknown event `{event}`"),
+            TaskError::BadCost { cost } => write!(f, "bad cost `{cost}` (expected `action N`)"),
+            TaskError::BadLint { lint } => {
+                write!(f, "bad lint `{lint}` (expected `rule off|warn|error`)")
+            }
+            TaskError::BadWorld { file, reason } => write!(f, "world `{file}`: {reason}"),
+            TaskError::BadHooks { file, reason } => write!(f, "hooks `{file}`: {reason}"),
+            TaskError::BadStarter { file, reason } => write!(f, "starter `{file}`: {reason}"),
+            TaskError::NoWorlds => write!(f, "the task lists no worlds"),
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+impl Task {
+    /// Parse a task file. World files are resolved relative to `directory`
+    /// (normally the directory the task file lives in) and loaded eagerly, so
+    /// a broken task fails here and not in the middle of grading.
+    pub fn parse(source: &str, directory: &Path) -> Result<Task, TaskError> {
+        let mut name = String::new();
+        let mut world_files: Vec<String> = Vec::new();
+        let mut goal_strings: Vec<String> = Vec::new();
+        let mut event_strings: Vec<String> = Vec::new();
+        let mut cost_strings: Vec<String> = Vec::new();
+        let mut lint_strings: Vec<String> = Vec::new();
+        let mut stdlib = false;
+        let mut hooks_file: Option<String> = None;
+        let mut before = None;
+        let mut after = None;
+        let mut starter_file: Option<String> = None;
+
+        for (index, raw) in source.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(TaskError::BadSyntax { line: index + 1 });
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "name" => {
+                    name = parse_string(value).ok_or(TaskError::BadSyntax { line: index + 1 })?;
+                }
+                "worlds" => {
+                    world_files = parse_string_array(value)
+                        .ok_or(TaskError::BadSyntax { line: index + 1 })?;
+                }
+                "goals" => {
+                    goal_strings = parse_string_array(value)
+                        .ok_or(TaskError::BadSyntax { line: index + 1 })?;
+                }
+                "events" => {
+                    event_strings = parse_string_array(value)
+                        .ok_or(TaskError::BadSyntax { line: index + 1 })?;
+                }
+                "costs" => {
+                    cost_strings = parse_string_array(value)
+                        .ok_or(TaskError::BadSyntax { line: index + 1 })?;
+                }
+                "lints" => {
+                    lint_strings = parse_string_array(value)
+                        .ok_or(TaskError::BadSyntax { line: index + 1 })?;
+                }
+                "stdlib" => {
+                    stdlib = parse_bool(value).ok_or(TaskError::BadSyntax { line: index + 1 })?;
+                }
+                "hooks" => {
+                    hooks_file =
+                        Some(parse_string(value).ok_or(TaskError::BadSyntax { line: index + 1 })?);
+                }
+                "before" => {
+                    before =
+                        Some(parse_string(value).ok_or(TaskError::BadSyntax { line: index + 1 })?);
+                }
+                "after" => {
+                    after =
+                        Some(parse_string(value).ok_or(TaskError::BadSyntax { line: index + 1 })?);
+                }
+                "starter" => {
+                    starter_file =
+                        Some(parse_string(value).ok_or(TaskError::BadSyntax { line: index + 1 })?);
+                }
+                _ => return Err(TaskError::BadSyntax { line: index + 1 }),
+            }
+        }
+
+        if world_files.is_empty() {
+            return Err(TaskError::NoWorlds);
+        }
+
+        let mut worlds = Vec::new();
+        for file in world_files {
+            let path = directory.join(&file);
+            let text = std::fs::read_to_string(&path).map_err(|error| TaskError::BadWorld {
+                file: file.clone(),
+                reason: error.to_string(),
+            })?;
+            let world = worldfile::parse(&text).map_err(|error| TaskError::BadWorld {
+                file: file.clone(),
+                reason: error.to_string(),
+            })?;
+            worlds.push((file, world));
+        }
+
+        let mut goals = Vec::new();
+        let mut goal_weights = Vec::new();
+        for goal in goal_strings {
+            // An optional percentage prefix weights the goal for partial
+            // credit: `"60% no-beepers"`.
+            let (weight, rest) = match goal.split_once(char::is_whitespace) {
+                Some((first, rest)) => match first.strip_suffix('%') {
+                    Some(number) => match number.parse::<usize>() {
+                        Ok(weight) => (weight, rest),
+                        Err(_) => return Err(TaskError::BadGoal { goal: goal.clone() }),
+                    },
+                    None => (1, goal.as_str()),
+                },
+                None => (1, goal.as_str()),
+            };
+            goals.push(parse_goal(rest, directory)?);
+            goal_weights.push(weight);
+        }
+        let mut events = Vec::new();
+        for event in event_strings {
+            events.push(parse_event(&event)?);
+        }
+        let mut costs = CostModel::default();
+        for cost in cost_strings {
+            let (action, weight) = parse_cost(&cost)?;
+            costs.set(action, weight);
+        }
+        let mut lints = lint::Config::default();
+        for setting in lint_strings {
+            apply_lint(&setting, &mut lints)?;
+        }
+        let hooks = match hooks_file {
+            Some(file) => {
+                let source = std::fs::read_to_string(directory.join(&file)).map_err(|error| {
+                    TaskError::BadHooks { file: file.clone(), reason: error.to_string() }
+                })?;
+                Some((file, source))
+            }
+            None => None,
+        };
+        let starter = match starter_file {
+            Some(file) => {
+                let source = std::fs::read_to_string(directory.join(&file)).map_err(|error| {
+                    TaskError::BadStarter { file: file.clone(), reason: error.to_string() }
+                })?;
+                // Broken lock markers should fail the task, not every
+                // submission graded against it.
+                lock::regions(&source).map_err(|error| TaskError::BadStarter {
+                    file: file.clone(),
+                    reason: error.to_string(),
+                })?;
+                Some((file, source))
+            }
+            None => None,
+        };
+
+        Ok(Task {
+            name,
+            worlds,
+            goals,
+            goal_weights,
+            events,
+            costs,
+            lints,
+            stdlib,
+            hooks,
+            before,
+            after,
+            starter,
+        })
+    }
+
+    /// Score the task's goals against a final world, goal by weighted goal.
+    pub fn score(&self, world: &World) -> GoalScore {
+        GoalScore {
+            breakdown: self
+                .goals
+                .iter()
+                .enumerate()
+                .map(|(index, goal)| (self.weight_of(index), goal.is_met(world)))
+                .collect(),
+        }
+    }
+
+    /// The weight of the goal at `index`; 1 unless the task says otherwise.
+    pub(crate) fn weight_of(&self, index: usize) -> usize {
+        self.goal_weights.get(index).copied().unwrap_or(1)
+    }
+
+    /// Read and parse a task file from disk.
+    pub fn load(path: &Path) -> Result<Task, TaskError> {
+        let source = std::fs::read_to_string(path).map_err(|error| TaskError::BadWorld {
+            file: path.display().to_string(),
+            reason: error.to_string(),
+        })?;
+        let directory = path.parent().unwrap_or(Path::new("."));
+        Task::parse(&source, directory)
+    }
+}
+
+fn parse_goal(goal: &str, directory: &Path) -> Result<Goal, TaskError> {
+    let words: Vec<&str> = goal.split_whitespace().collect();
+    let parsed = match words[..] {
+        ["no-beepers"] => Some(Goal::NoBeepers),
+        ["robot-at", x, y] => match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => Some(Goal::RobotAt(Position::new(x, y))),
+            _ => None,
+        },
+        ["beepers-at", x, y, count] => match (x.parse(), y.parse(), count.parse()) {
+            (Ok(x), Ok(y), Ok(count)) => Some(Goal::BeepersAt(Position::new(x, y), count)),
+            _ => None,
+        },
+        ["within-ticks", ticks] => ticks.parse().ok().map(Goal::WithinTicks),
+        ["in-region", name] => Some(Goal::InRegion(name.to_string())),
+        ["beeps", count] => count.parse().ok().map(Goal::Beeps),
+        ["beep-at", x, y] => match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => Some(Goal::BeepAt(Position::new(x, y))),
+            _ => None,
+        },
+        ["match", file] => {
+            let path = directory.join(file);
+            let text = std::fs::read_to_string(&path).map_err(|error| TaskError::BadWorld {
+                file: file.to_string(),
+                reason: error.to_string(),
+            })?;
+            let world = worldfile::parse(&text).map_err(|error| TaskError::BadWorld {
+                file: file.to_string(),
+                reason: error.to_string(),
+            })?;
+            Some(Goal::Match(Box::new(world)))
+        }
+        _ => None,
+    };
+    parsed.ok_or_else(|| TaskError::BadGoal {
+        goal: goal.to_string(),
+    })
+}
+
+fn parse_cost(cost: &str) -> Result<(Action, usize), TaskError> {
+    let words: Vec<&str> = cost.split_whitespace().collect();
+    let parsed = match words[..] {
+        [action, weight] => match (Action::parse(action), weight.parse()) {
+            (Some(action), Ok(weight)) => Some((action, weight)),
+            _ => None,
+        },
+        _ => None,
+    };
+    parsed.ok_or_else(|| TaskError::BadCost {
+        cost: cost.to_string(),
+    })
+}
+
+fn apply_lint(setting: &str, config: &mut lint::Config) -> Result<(), TaskError> {
+    let words: Vec<&str> = setting.split_whitespace().collect();
+    let applied = match words[..] {
+        [rule, "off"] => lint::Rule::from_name(rule).map(|rule| config.disable(rule)),
+        [rule, severity] => lint::Rule::from_name(rule)
+            .zip(lint::Severity::from_name(severity))
+            .map(|(rule, severity)| config.set(rule, severity)),
+        _ => None,
+    };
+    applied.ok_or_else(|| TaskError::BadLint {
+        lint: setting.to_string(),
+    })
+}
+
+fn parse_event(event: &str) -> Result<Event, TaskError> {
+    let words: Vec<&str> = event.split_whitespace().collect();
+    let position = |x: &str, y: &str| -> Option<Position> {
+        match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => Some(Position::new(x, y)),
+            _ => None,
+        }
+    };
+    let parsed = match words[..] {
+        ["at", tick, "wall", x, y] => tick
+            .parse()
+            .ok()
+            .zip(position(x, y))
+            .map(|(at, position)| Event { at, change: Change::WallAppears(position) }),
+        ["at", tick, "no-wall", x, y] => tick
+            .parse()
+            .ok()
+            .zip(position(x, y))
+            .map(|(at, position)| Event { at, change: Change::WallDisappears(position) }),
+        ["at", tick, "beepers", x, y, count] => {
+            match (tick.parse().ok(), position(x, y), count.parse().ok()) {
+                (Some(at), Some(position), Some(count)) => {
+                    Some(Event { at, change: Change::BeepersSpawn(position, count) })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    parsed.ok_or_else(|| TaskError::BadEvent {
+        event: event.to_string(),
+    })
+}
+
+/// Parse a TOML boolean.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a double-quoted TOML string. No escape sequences; world file names
+/// do not need them.
+pub(crate) fn parse_string(value: &str) -> Option<String> {
+    value
+        .strip_prefix('"')?
+        .strip_suffix('"')
+        .map(str::to_string)
+}
+
+/// Parse `["a", "b", ...]`.
+pub(crate) fn parse_string_array(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?.trim();
+    if inner.is_empty() {
+        return Some(Vec::new());
+    }
+    inner
+        .split(',')
+        .map(|item| parse_string(item.trim()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{Action, Environment};
+    use crate::world::Direction;
+
+    #[test]
+    fn weighted_goals_earn_partial_credit() {
+        let mut task = Task {
+            name: "weighted".to_string(),
+            worlds: Vec::new(),
+            goals: vec![
+                Goal::NoBeepers,
+                Goal::RobotAt(Position::new(1, 0)),
+                Goal::WithinTicks(5),
+            ],
+            goal_weights: vec![60, 20, 20],
+            events: Vec::new(),
+            costs: CostModel::default(),
+            lints: crate::lint::Config::default(),
+            stdlib: false,
+            hooks: None,
+            before: None,
+            after: None,
+            starter: None,
+        };
+        // Beepers remain, the robot sits right, no time was spent.
+        let mut world = World::new(3, 1);
+        world.set_beepers(Position::new(2, 0), 1);
+        world.robot.position = Position::new(1, 0);
+        let score = task.score(&world);
+        assert_eq!(score.breakdown, vec![(60, false), (20, true), (20, true)]);
+        assert_eq!((score.earned(), score.total()), (40, 100));
+        assert!((score.fraction() - 0.4).abs() < 1e-9);
+        // Unlisted weights default to 1.
+        task.goal_weights.clear();
+        assert_eq!(task.score(&world).total(), 3);
+    }
+
+    #[test]
+    fn custom_goals_run_their_predicate() {
+        let palindrome = Goal::custom(|world: &World| {
+            let row: Vec<u8> = (0..world.width())
+                .map(|x| world.beepers_at(Position::new(x, 1)))
+                .collect();
+            row.iter().eq(row.iter().rev())
+        });
+        let mut world = World::new(3, 3);
+        world.set_beepers(Position::new(0, 1), 2);
+        assert!(!palindrome.is_met(&world));
+        world.set_beepers(Position::new(2, 1), 2);
+        assert!(palindrome.is_met(&world));
+
+        // Clones compare equal to each other, independent closures do not,
+        // so tasks holding custom goals still derive `PartialEq`.
+        assert_eq!(palindrome, palindrome.clone());
+        assert_ne!(palindrome, Goal::custom(|_: &World| true));
+    }
+
+    #[test]
+    fn goals_are_checked_against_the_world() {
+        let mut world = World::new(3, 3);
+        world.robot.position = Position::new(2, 1);
+        world.set_beepers(Position::new(0, 0), 2);
+
+        assert!(!Goal::NoBeepers.is_met(&world));
+        assert!(Goal::RobotAt(Position::new(2, 1)).is_met(&world));
+        assert!(Goal::BeepersAt(Position::new(0, 0), 2).is_met(&world));
+        assert!(!Goal::BeepersAt(Position::new(0, 0), 1).is_met(&world));
+
+        world.add_region("goal", Position::new(2, 0), Position::new(2, 2));
+        assert!(Goal::InRegion("goal".to_string()).is_met(&world));
+        assert!(!Goal::InRegion("nowhere".to_string()).is_met(&world));
+
+        world.perform(Action::Beep).unwrap();
+        assert!(Goal::Beeps(1).is_met(&world));
+        assert!(!Goal::Beeps(2).is_met(&world));
+        assert!(Goal::BeepAt(Position::new(2, 1)).is_met(&world));
+        assert!(!Goal::BeepAt(Position::new(0, 0)).is_met(&world));
+
+        let mut expected = World::new(3, 3);
+        expected.robot.position = Position::new(2, 1);
+        expected.robot.direction = Direction::South;
+        expected.set_beepers(Position::new(0, 0), 2);
+        // Facing and walls are not part of a `match` goal, only beepers and
+        // the robot position.
+        assert!(Goal::Match(Box::new(expected)).is_met(&world));
+
+        // The beep above already ticked once; two turns make three.
+        world.perform(Action::TurnLeft).unwrap();
+        world.perform(Action::TurnLeft).unwrap();
+        assert!(Goal::WithinTicks(3).is_met(&world));
+        assert!(!Goal::WithinTicks(2).is_met(&world));
+    }
+
+    #[test]
+    fn task_files_parse() {
+        let directory = std::env::temp_dir().join("karel-task-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("w.txt"), ">..\n").unwrap();
+
+        let source = "# homework 3\nname = \"Test\"\nworlds = [\"w.txt\"]\ngoals = [\"no-beepers\", \"robot-at 2 0\"]\nevents = [\"at 5 wall 2 0\", \"at 9 beepers 1 0 2\"]\ncosts = [\"move 2\", \"put 3\"]\nlints = [\"dead-code error\", \"magic-repeat off\"]\nstdlib = true\n";
+        let task = Task::parse(source, &directory).unwrap();
+        assert_eq!(task.name, "Test");
+        assert_eq!(task.worlds.len(), 1);
+        assert_eq!(task.worlds[0].0, "w.txt");
+        assert_eq!(
+            task.goals,
+            vec![Goal::NoBeepers, Goal::RobotAt(Position::new(2, 0))]
+        );
+        assert_eq!(
+            task.events,
+            vec![
+                Event { at: 5, change: Change::WallAppears(Position::new(2, 0)) },
+                Event { at: 9, change: Change::BeepersSpawn(Position::new(1, 0), 2) },
+            ]
+        );
+        assert_eq!(task.costs.cost_of(Action::Move), 2);
+        assert_eq!(task.costs.cost_of(Action::Put), 3);
+        assert_eq!(task.costs.cost_of(Action::Take), 1);
+        assert_eq!(
+            task.lints.severity(crate::lint::Rule::DeadCode),
+            Some(crate::lint::Severity::Error)
+        );
+        assert_eq!(task.lints.severity(crate::lint::Rule::MagicRepeat), None);
+        assert!(task.stdlib);
+        assert_eq!(
+            Task::parse("name = \"x\"\nworlds = [\"w.txt\"]\nevents = [\"at noon rain\"]\n", &directory),
+            Err(TaskError::BadEvent { event: "at noon rain".to_string() })
+        );
+        assert_eq!(
+            Task::parse("name = \"x\"\nworlds = [\"w.txt\"]\ncosts = [\"move fast\"]\n", &directory),
+            Err(TaskError::BadCost { cost: "move fast".to_string() })
+        );
+        assert_eq!(
+            Task::parse("name = \"x\"\nworlds = [\"w.txt\"]\nlints = [\"tabs-vs-spaces warn\"]\n", &directory),
+            Err(TaskError::BadLint { lint: "tabs-vs-spaces warn".to_string() })
+        );
+    }
+
+    #[test]
+    fn bad_goal_is_reported() {
+        let directory = std::env::temp_dir();
+        std::fs::write(directory.join("karel-task-w.txt"), ">..\n").unwrap();
+        let source = "worlds = [\"karel-task-w.txt\"]\ngoals = [\"fly-away\"]\n";
+        assert_eq!(
+            Task::parse(source, &directory),
+            Err(TaskError::BadGoal { goal: "fly-away".to_string() })
+        );
+    }
+
+    #[test]
+    fn hook_keys_load_the_hooks_file() {
+        let directory = std::env::temp_dir().join("karel-task-hooks-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("w.txt"), ">..\n").unwrap();
+        std::fs::write(directory.join("hooks.kl"), "def scatter\n put\nenddef\n").unwrap();
+
+        let source =
+            "worlds = [\"w.txt\"]\nhooks = \"hooks.kl\"\nbefore = \"scatter\"\nafter = \"scatter\"\n";
+        let task = Task::parse(source, &directory).unwrap();
+        assert_eq!(
+            task.hooks,
+            Some(("hooks.kl".to_string(), "def scatter\n put\nenddef\n".to_string()))
+        );
+        assert_eq!(task.before.as_deref(), Some("scatter"));
+        assert_eq!(task.after.as_deref(), Some("scatter"));
+
+        assert!(matches!(
+            Task::parse("worlds = [\"w.txt\"]\nhooks = \"gone.kl\"\n", &directory),
+            Err(TaskError::BadHooks { .. })
+        ));
+    }
+
+    #[test]
+    fn percentage_prefixes_become_goal_weights() {
+        let directory = std::env::temp_dir().join("karel-task-weights-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("w.txt"), ">..\n").unwrap();
+
+        let source = "worlds = [\"w.txt\"]\ngoals = [\"60% no-beepers\", \"robot-at 0 0\", \"20% within-ticks 9\"]\n";
+        let task = Task::parse(source, &directory).unwrap();
+        assert_eq!(task.goals.len(), 3);
+        assert_eq!(task.goal_weights, vec![60, 1, 20]);
+
+        assert!(matches!(
+            Task::parse("worlds = [\"w.txt\"]\ngoals = [\"many% no-beepers\"]\n", &directory),
+            Err(TaskError::BadGoal { .. })
+        ));
+    }
+
+    #[test]
+    fn missing_worlds_are_an_error() {
+        assert_eq!(
+            Task::parse("name = \"x\"\n", Path::new(".")),
+            Err(TaskError::NoWorlds)
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Ranking many submissions against one task: a classroom contest.
+//!
+//! Every entry is graded with the ordinary [grader](crate::grade) against
+//! the same set of worlds — typically ones the contestants never saw — and
+//! the standings order by correctness first and economy second: worlds
+//! passed, then goals met, then total cost (the plain step count unless the
+//! task weights actions). Submissions that do not parse rank below every
+//! program that at least ran. Entries with identical scores share a rank,
+//! the way sports tables do.
+
+use crate::grade;
+use crate::json::Value;
+use crate::task::Task;
+
+/// One submission's place in the contest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Standing {
+    /// The submission's name, as handed to [`run`] (usually a file name).
+    pub name: String,
+    /// Worlds passed, out of [`Standings::worlds`].
+    pub passed: usize,
+    /// Goals met, summed over every world.
+    pub goals_met: usize,
+    /// Total cost over every world; the tiebreak among equally correct
+    /// entries — fewer steps wins.
+    pub cost: usize,
+    /// Why the submission never ran, when it did not parse.
+    pub rejected: Option<String>,
+}
+
+impl Standing {
+    /// The comparison key: more passed, more goals, actually ran, fewer
+    /// steps — in that order of importance.
+    fn score(&self) -> (usize, usize, bool, core::cmp::Reverse<usize>) {
+        (
+            self.passed,
+            self.goals_met,
+            self.rejected.is_none(),
+            core::cmp::Reverse(self.cost),
+        )
+    }
+}
+
+/// The finished table of one contest, best entry first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Standings {
+    /// How many worlds the task graded in, for "2/3" style reporting.
+    pub worlds: usize,
+    entries: Vec<Standing>,
+}
+
+/// Grade every submission (as `(name, source)` pairs) and rank them.
+pub fn run(task: &Task, submissions: &[(&str, &str)]) -> Standings {
+    let mut entries: Vec<Standing> = submissions
+        .iter()
+        .map(|(name, source)| {
+            let report = grade::grade(task, name, source);
+            Standing {
+                name: report.submission.clone(),
+                passed: report.results.iter().filter(|result| result.passed).count(),
+                goals_met: report.results.iter().map(|result| result.goals_met).sum(),
+                cost: report.results.iter().map(|result| result.cost).sum(),
+                rejected: report.parse_error,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.score().cmp(&a.score()).then(a.name.cmp(&b.name)));
+    Standings {
+        worlds: task.worlds.len(),
+        entries,
+    }
+}
+
+impl Standings {
+    /// The ranked entries, best first.
+    pub fn entries(&self) -> &[Standing] {
+        &self.entries
+    }
+
+    /// The competition rank of the entry at `index`: 1 plus the number of
+    /// strictly better entries, so ties share a rank.
+    pub fn rank(&self, index: usize) -> usize {
+        let score = self.entries[index].score();
+        1 + self
+            .entries
+            .iter()
+            .filter(|other| other.score() > score)
+            .count()
+    }
+
+    /// The standings as a table for the classroom wall.
+    pub fn table(&self) -> String {
+        use std::fmt::Write as _;
+
+        let width = self
+            .entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("submission".len());
+        let mut out = String::new();
+        let _ = writeln!(out, "rank  {:width$}  passed  goals  cost", "submission");
+        for (index, entry) in self.entries.iter().enumerate() {
+            let _ = write!(
+                out,
+                "{:4}  {:width$}  {:3}/{}  {:5}  {:4}",
+                self.rank(index),
+                entry.name,
+                entry.passed,
+                self.worlds,
+                entry.goals_met,
+                entry.cost,
+            );
+            match &entry.rejected {
+                Some(error) => {
+                    let _ = writeln!(out, "  ({error})");
+                }
+                None => {
+                    let _ = writeln!(out);
+                }
+            }
+        }
+        out
+    }
+
+    /// The standings for scripts and web leaderboards.
+    pub fn to_json(&self) -> Value {
+        Value::Array(
+            self.entries
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    Value::object([
+                        ("rank", Value::from(self.rank(index))),
+                        ("submission", Value::from(entry.name.as_str())),
+                        ("passed", Value::from(entry.passed)),
+                        ("worlds", Value::from(self.worlds)),
+                        ("goals_met", Value::from(entry.goals_met)),
+                        ("cost", Value::from(entry.cost)),
+                        (
+                            "rejected",
+                            entry.rejected.as_ref().map(|error| error.to_string()).into(),
+                        ),
+                    ])
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{CostModel, Goal};
+    use crate::world::{Position, World};
+
+    fn walk_task() -> Task {
+        let world = World::new(4, 1);
+        Task {
+            name: "walk".to_string(),
+            worlds: vec![("w".to_string(), world)],
+            goals: vec![Goal::RobotAt(Position::new(3, 0))],
+            goal_weights: Vec::new(),
+            events: Vec::new(),
+            costs: CostModel::default(),
+            lints: crate::lint::Config::default(),
+            stdlib: false,
+            hooks: None,
+            before: None,
+            after: None,
+            starter: None,
+        }
+    }
+
+    #[test]
+    fn correctness_outranks_economy() {
+        let standings = run(
+            &walk_task(),
+            &[
+                // Correct but wasteful.
+                ("slow.kl", "def main\n turn-left\n turn-left\n turn-left\n turn-left\n move\n move\n move\nenddef"),
+                // Correct and tight.
+                ("fast.kl", "def main\n move\n move\n move\nenddef"),
+                // Runs fine, wrong place.
+                ("lost.kl", "def main\n move\nenddef"),
+            ],
+        );
+        let names: Vec<&str> = standings
+            .entries()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["fast.kl", "slow.kl", "lost.kl"]);
+        assert_eq!(standings.rank(0), 1);
+        assert_eq!(standings.rank(2), 3);
+    }
+
+    #[test]
+    fn programs_that_do_not_parse_rank_last() {
+        let standings = run(
+            &walk_task(),
+            &[
+                ("broken.kl", "def main\n fly\nenddef"),
+                ("lost.kl", "def main\n move\nenddef"),
+            ],
+        );
+        assert_eq!(standings.entries()[1].name, "broken.kl");
+        assert!(standings.entries()[1].rejected.is_some());
+    }
+
+    #[test]
+    fn equal_scores_share_a_rank() {
+        let standings = run(
+            &walk_task(),
+            &[
+                ("a.kl", "def main\n move\n move\n move\nenddef"),
+                ("b.kl", "def main\n move\n move\n move\nenddef"),
+                ("c.kl", "def main\n move\nenddef"),
+            ],
+        );
+        assert_eq!(standings.rank(0), 1);
+        assert_eq!(standings.rank(1), 1);
+        assert_eq!(standings.rank(2), 3);
+        assert!(standings.table().contains("rank"));
+    }
+}
@@ -0,0 +1,720 @@
+//! Recording a run as a trace file and reading it back.
+//!
+//! A trace is a JSON-lines file: the first line is the starting world in the
+//! JSON world format, every following line is one executed instruction and
+//! the state it left behind, and the last line reports how the run ended.
+//! Each step carries the full robot state and the beeper count of the tile
+//! the robot stood on, which is everything an instruction can change -- so a
+//! trace can be replayed without the original program or world file.
+//!
+//! ```text
+//! {"width":5,...}
+//! {"step":1,"line":2,"instruction":"move","robot":{...},"beepers":[1,0,0]}
+//! {"end":"ok"}
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::json::{self, Value};
+use crate::world::{Direction, Position, World};
+use crate::worldfile;
+
+/// Writes trace lines as the interpreter advances.
+pub struct Recorder<W: Write> {
+    writer: W,
+    step: usize,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a trace by writing the starting world.
+    pub fn new(mut writer: W, world: &World) -> io::Result<Recorder<W>> {
+        writeln!(writer, "{}", worldfile::to_json(world))?;
+        Ok(Recorder { writer, step: 0 })
+    }
+
+    /// Record one executed instruction and the world state after it.
+    pub fn record(&mut self, line: usize, instruction: &str, world: &World) -> io::Result<()> {
+        self.step += 1;
+        let entry = frame_value(&capture(self.step, line, instruction, world));
+        writeln!(self.writer, "{entry}")
+    }
+
+    /// Close the trace, noting whether the run succeeded.
+    pub fn finish(&mut self, error: Option<&str>) -> io::Result<()> {
+        writeln!(self.writer, "{}", end_value(error))
+    }
+}
+
+/// Keeps only the last `capacity` steps of a run in memory, writing them as
+/// a normal trace when the run ends.
+///
+/// A streaming [`Recorder`] makes always-on recording as expensive as the
+/// run is long; a grader that records every submission mostly wants the
+/// *tail* — what the robot did just before it failed. The ring buffer
+/// evicts the oldest frame as each new one arrives, folding it into the
+/// base world, so the file written by [`finish`](TailRecorder::finish)
+/// starts mid-run but still replays exactly: its header world is the state
+/// right before the oldest kept frame, and the frames keep their original
+/// step numbers.
+pub struct TailRecorder<W: Write> {
+    writer: W,
+    /// The starting world with every evicted frame applied.
+    base: World,
+    frames: VecDeque<Frame>,
+    capacity: usize,
+    step: usize,
+}
+
+impl<W: Write> TailRecorder<W> {
+    /// Record into `writer`, keeping at most `capacity` steps (minimum 1).
+    /// Nothing is written until [`finish`](TailRecorder::finish).
+    pub fn new(writer: W, world: &World, capacity: usize) -> TailRecorder<W> {
+        TailRecorder {
+            writer,
+            base: world.clone(),
+            frames: VecDeque::new(),
+            capacity: capacity.max(1),
+            step: 0,
+        }
+    }
+
+    /// Record one executed instruction, evicting the oldest kept step if
+    /// the buffer is full. Never does IO, so always-on recording costs a
+    /// bounded amount of memory and no writes until the run ends.
+    pub fn record(&mut self, line: usize, instruction: &str, world: &World) {
+        self.step += 1;
+        if self.frames.len() == self.capacity {
+            if let Some(evicted) = self.frames.pop_front() {
+                evicted.apply(&mut self.base);
+            }
+        }
+        self.frames.push_back(capture(self.step, line, instruction, world));
+    }
+
+    /// Write the kept tail as a trace file: the mid-run base world, the
+    /// buffered frames, and the verdict.
+    pub fn finish(mut self, error: Option<&str>) -> io::Result<()> {
+        writeln!(self.writer, "{}", worldfile::to_json(&self.base))?;
+        for frame in &self.frames {
+            writeln!(self.writer, "{}", frame_value(frame))?;
+        }
+        writeln!(self.writer, "{}", end_value(error))
+    }
+}
+
+/// The closing line of a trace: `ok`, or the error that ended the run.
+fn end_value(error: Option<&str>) -> Value {
+    match error {
+        None => Value::object([("end", Value::from("ok"))]),
+        Some(message) => Value::object([
+            ("end", Value::from("error")),
+            ("message", Value::from(message)),
+        ]),
+    }
+}
+
+/// Snapshot what a trace frame records about the world after one step.
+fn capture(step: usize, line: usize, instruction: &str, world: &World) -> Frame {
+    Frame {
+        step,
+        line,
+        instruction: instruction.to_string(),
+        ticks: world.ticks(),
+        robot: world.robot,
+        beepers: (
+            world.robot.position,
+            world.beepers_at(world.robot.position),
+        ),
+    }
+}
+
+/// A frame as the trace file spells it.
+fn frame_value(frame: &Frame) -> Value {
+    Value::object([
+        ("step", Value::from(frame.step)),
+        ("line", Value::from(frame.line)),
+        ("instruction", Value::from(frame.instruction.as_str())),
+        ("ticks", Value::from(frame.ticks)),
+        (
+            "robot",
+            Value::object([
+                ("x", Value::from(frame.robot.position.x)),
+                ("y", Value::from(frame.robot.position.y)),
+                ("direction", Value::from(direction_name(frame.robot.direction))),
+                ("alive", Value::from(frame.robot.alive)),
+            ]),
+        ),
+        (
+            "beepers",
+            Value::from(vec![
+                frame.beepers.0.x,
+                frame.beepers.0.y,
+                frame.beepers.1 as usize,
+            ]),
+        ),
+    ])
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::North => "north",
+        Direction::East => "east",
+        Direction::South => "south",
+        Direction::West => "west",
+    }
+}
+
+/// One recorded step of a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub step: usize,
+    pub line: usize,
+    pub instruction: String,
+    /// The world clock after this step.
+    pub ticks: usize,
+    pub robot: crate::world::Robot,
+    /// Beeper count of the tile the robot stands on, as (position, count).
+    pub beepers: (Position, u8),
+}
+
+impl Frame {
+    /// Bring a world up to date with this frame.
+    pub fn apply(&self, world: &mut World) {
+        world.robot = self.robot;
+        world.set_beepers(self.beepers.0, self.beepers.1);
+        world.set_ticks(self.ticks);
+    }
+}
+
+/// A parsed trace: the starting world, the frames, and the final verdict
+/// (`None` when the run succeeded, the error message otherwise).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub world: World,
+    pub frames: Vec<Frame>,
+    pub error: Option<String>,
+}
+
+/// How many frames apart [`Timeline`] keeps its snapshots by default: small
+/// enough that a seek replays a barely-noticeable slice, large enough that a
+/// 100k-step trace stores a few hundred snapshots, not a hundred thousand.
+pub const SNAPSHOT_STRIDE: usize = 256;
+
+/// A seekable index over a trace, for scrubbing a replay timeline.
+///
+/// Replaying from the start makes jumping to step K cost K frame
+/// applications — fine for watching, hopeless for dragging a slider over a
+/// 100k-step run. The timeline keeps a world snapshot every
+/// [`SNAPSHOT_STRIDE`] frames (snapshots are cheap: see [`World`]'s
+/// copy-on-write clone), so [`Timeline::seek`] clones the nearest snapshot
+/// at or before K and replays at most a stride of deltas, whatever the
+/// trace length.
+pub struct Timeline<'t> {
+    trace: &'t Trace,
+    /// `snapshots[i]` is the world after `i * stride` frames.
+    snapshots: Vec<World>,
+    stride: usize,
+}
+
+impl<'t> Timeline<'t> {
+    /// Index a trace with the default stride.
+    pub fn new(trace: &'t Trace) -> Timeline<'t> {
+        Timeline::with_stride(trace, SNAPSHOT_STRIDE)
+    }
+
+    /// Index a trace, snapshotting every `stride` frames (minimum 1). A
+    /// smaller stride seeks faster and stores more snapshots.
+    pub fn with_stride(trace: &'t Trace, stride: usize) -> Timeline<'t> {
+        let stride = stride.max(1);
+        let mut snapshots = vec![trace.world.clone()];
+        let mut world = trace.world.clone();
+        for (index, frame) in trace.frames.iter().enumerate() {
+            frame.apply(&mut world);
+            if (index + 1) % stride == 0 {
+                snapshots.push(world.clone());
+            }
+        }
+        Timeline { trace, snapshots, stride }
+    }
+
+    /// How many steps the timeline covers.
+    pub fn len(&self) -> usize {
+        self.trace.frames.len()
+    }
+
+    /// Is there anything to scrub over?
+    pub fn is_empty(&self) -> bool {
+        self.trace.frames.is_empty()
+    }
+
+    /// The world after the first `step` frames; `seek(0)` is the starting
+    /// world, anything past the end is the final state.
+    pub fn seek(&self, step: usize) -> World {
+        let step = step.min(self.len());
+        let snapshot = step / self.stride;
+        let mut world = self.snapshots[snapshot].clone();
+        for frame in &self.trace.frames[snapshot * self.stride..step] {
+            frame.apply(&mut world);
+        }
+        world
+    }
+}
+
+/// An error in a trace file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "trace line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+/// Parse a whole trace file.
+pub fn parse(source: &str) -> Result<Trace, TraceError> {
+    let mut lines = source.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    let (_, header) = lines.next().ok_or(TraceError {
+        line: 1,
+        message: "empty trace".to_string(),
+    })?;
+    let world = worldfile::from_json(header).map_err(|error| TraceError {
+        line: 1,
+        message: error.to_string(),
+    })?;
+
+    let mut frames = Vec::new();
+    let mut error = None;
+    for (index, line) in lines {
+        let number = index + 1;
+        let bad = |message: &str| TraceError {
+            line: number,
+            message: message.to_string(),
+        };
+        let value = json::parse(line).map_err(|error| TraceError {
+            line: number,
+            message: error.to_string(),
+        })?;
+        let Value::Object(entry) = value else {
+            return Err(bad("expected an object"));
+        };
+
+        if let Some(Value::String(end)) = entry.get("end") {
+            if end == "error" {
+                error = match entry.get("message") {
+                    Some(Value::String(message)) => Some(message.clone()),
+                    _ => Some("unknown error".to_string()),
+                };
+            }
+            break;
+        }
+
+        let number_field = |key: &str| -> Result<usize, TraceError> {
+            match entry.get(key) {
+                Some(Value::Number(value)) if *value >= 0.0 => Ok(*value as usize),
+                _ => Err(bad("missing numeric field")),
+            }
+        };
+        let Some(Value::String(instruction)) = entry.get("instruction") else {
+            return Err(bad("missing instruction"));
+        };
+        let Some(Value::Object(robot)) = entry.get("robot") else {
+            return Err(bad("missing robot"));
+        };
+        let robot_number = |key: &str| -> Result<usize, TraceError> {
+            match robot.get(key) {
+                Some(Value::Number(value)) if *value >= 0.0 => Ok(*value as usize),
+                _ => Err(bad("missing robot coordinate")),
+            }
+        };
+        let direction = match robot.get("direction") {
+            Some(Value::String(direction)) => match direction.as_str() {
+                "north" => Direction::North,
+                "east" => Direction::East,
+                "south" => Direction::South,
+                "west" => Direction::West,
+                _ => return Err(bad("bad robot direction")),
+            },
+            _ => return Err(bad("missing robot direction")),
+        };
+        let alive = match robot.get("alive") {
+            Some(Value::Bool(alive)) => *alive,
+            _ => true,
+        };
+        let beepers = match entry.get("beepers") {
+            Some(Value::Array(triple)) if triple.len() == 3 => {
+                let coordinate = |value: &Value| -> Result<usize, TraceError> {
+                    match value {
+                        Value::Number(value) if *value >= 0.0 => Ok(*value as usize),
+                        _ => Err(bad("bad beeper entry")),
+                    }
+                };
+                (
+                    Position::new(coordinate(&triple[0])?, coordinate(&triple[1])?),
+                    coordinate(&triple[2])? as u8,
+                )
+            }
+            _ => return Err(bad("missing beepers")),
+        };
+
+        frames.push(Frame {
+            step: number_field("step")?,
+            line: number_field("line")?,
+            // Traces recorded before the world clock existed have no
+            // `ticks`; treat their frames as tickless.
+            ticks: number_field("ticks").unwrap_or(0),
+            instruction: instruction.clone(),
+            robot: crate::world::Robot {
+                position: Position::new(robot_number("x")?, robot_number("y")?),
+                direction,
+                alive,
+            },
+            beepers,
+        });
+    }
+
+    Ok(Trace {
+        world,
+        frames,
+        error,
+    })
+}
+
+/// Why a trace does not match a re-execution; see [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The given starting world is not the one the trace was recorded in.
+    WorldMismatch,
+    /// A recorded frame disagrees with what the program actually did.
+    FrameMismatch { step: usize },
+    /// The trace records a different number of steps than the program runs.
+    LengthMismatch { recorded: usize, executed: usize },
+    /// The trace claims a different ending (ok vs. which error) than the
+    /// re-execution produced.
+    VerdictMismatch {
+        recorded: Option<String>,
+        executed: Option<String>,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::WorldMismatch => {
+                write!(f, "the trace was recorded in a different starting world")
+            }
+            ReplayError::FrameMismatch { step } => {
+                write!(f, "step {step} of the trace does not match the program")
+            }
+            ReplayError::LengthMismatch { recorded, executed } => {
+                write!(
+                    f,
+                    "the trace records {recorded} steps but execution reached step {executed}"
+                )
+            }
+            ReplayError::VerdictMismatch { recorded, executed } => {
+                let spell = |error: &Option<String>| match error {
+                    None => "ok".to_string(),
+                    Some(message) => format!("`{message}`"),
+                };
+                write!(
+                    f,
+                    "the trace ends {} but execution ends {}",
+                    spell(recorded),
+                    spell(executed)
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Check that a trace is exactly what running `program` in `world` records.
+///
+/// Execution is deterministic, so a competition or leaderboard need not
+/// trust a submitted trace: re-run the program and compare every frame.
+/// Comparing the frames themselves is stronger than any hash of them —
+/// a mismatch also says *where* the trace diverges. Only full traces
+/// verify; a [`TailRecorder`] tail starts mid-run and fails on the world
+/// comparison.
+///
+/// The re-execution runs at most one step past the recorded length, so a
+/// tampered trace cannot make verification loop forever.
+pub fn verify(
+    trace: &Trace,
+    program: &crate::program::Program,
+    world: &World,
+) -> Result<(), ReplayError> {
+    if *world != trace.world {
+        return Err(ReplayError::WorldMismatch);
+    }
+    let mut interpreter = program.start(world.clone());
+    let mut executed = 0usize;
+    let mut error: Option<String> = None;
+    while !interpreter.finished() {
+        if executed == trace.frames.len() {
+            return Err(ReplayError::LengthMismatch {
+                recorded: trace.frames.len(),
+                executed: executed + 1,
+            });
+        }
+        let line = interpreter.current_line().unwrap_or(0);
+        let instruction = interpreter
+            .current_instruction()
+            .unwrap_or_default()
+            .to_string();
+        let stepped = interpreter.step();
+        executed += 1;
+        let frame = capture(executed, line, &instruction, &interpreter.world);
+        if frame != trace.frames[executed - 1] {
+            return Err(ReplayError::FrameMismatch { step: executed });
+        }
+        if let Err(runtime_error) = stepped {
+            error = Some(runtime_error.to_string());
+            break;
+        }
+    }
+    if executed != trace.frames.len() {
+        return Err(ReplayError::LengthMismatch {
+            recorded: trace.frames.len(),
+            executed,
+        });
+    }
+    if error != trace.error {
+        return Err(ReplayError::VerdictMismatch {
+            recorded: trace.error.clone(),
+            executed: error,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::preprocess;
+    use crate::program::Program;
+
+    /// Run a program while recording it, then parse the trace back.
+    fn record_run(source: &str, world: World) -> Trace {
+        let mut buffer = Vec::new();
+        let mut recorder = Recorder::new(&mut buffer, &world).unwrap();
+        let mut interpreter = Interpreter::new(preprocess(source), world).unwrap();
+        let mut result = Ok(());
+        while !interpreter.finished() {
+            let line = interpreter.current_line().unwrap_or(0);
+            let instruction = interpreter.current_instruction().unwrap_or_default().to_string();
+            match interpreter.step() {
+                Ok(_) => recorder.record(line, &instruction, &interpreter.world).unwrap(),
+                Err(error) => {
+                    recorder.record(line, &instruction, &interpreter.world).unwrap();
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+        recorder
+            .finish(result.as_ref().err().map(|error| error.to_string()).as_deref())
+            .unwrap();
+        parse(&String::from_utf8(buffer).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn recorded_trace_replays_to_the_same_world() {
+        let source = "def main\n put\n move\n put\n move\n take\n die\nenddef";
+        let mut start = World::new(5, 1);
+        start.set_beepers(Position::new(2, 0), 1);
+
+        let mut interpreter =
+            Interpreter::new(preprocess(source), start.clone()).unwrap();
+        interpreter.run().into_result().unwrap();
+        let expected = interpreter.world.clone();
+
+        let trace = record_run(source, start);
+        assert!(trace.error.is_none());
+        let mut replayed = trace.world.clone();
+        for frame in &trace.frames {
+            frame.apply(&mut replayed);
+        }
+        assert_eq!(replayed, expected);
+    }
+
+    #[test]
+    fn failed_run_is_marked_in_the_trace() {
+        let trace = record_run("def main\n take\nenddef", World::new(2, 1));
+        assert!(trace.error.is_some());
+        assert!(!trace.frames.last().unwrap().robot.alive);
+    }
+
+    #[test]
+    fn frames_carry_instruction_and_line() {
+        let trace = record_run("def main\n move\n die\nenddef", World::new(3, 1));
+        assert_eq!(trace.frames[0].instruction, "move");
+        assert_eq!(trace.frames[0].line, 2);
+    }
+
+    #[test]
+    fn the_timeline_seeks_to_any_step() {
+        let source = "def main\n put\n move\n put\n move\n take\n die\nenddef";
+        let mut start = World::new(5, 1);
+        start.set_beepers(Position::new(2, 0), 1);
+        let trace = record_run(source, start);
+
+        // Whatever the stride, a seek matches the sequential replay.
+        for stride in [1, 2, 100] {
+            let timeline = Timeline::with_stride(&trace, stride);
+            assert_eq!(timeline.len(), trace.frames.len());
+            let mut expected = trace.world.clone();
+            assert_eq!(timeline.seek(0), expected);
+            for (index, frame) in trace.frames.iter().enumerate() {
+                frame.apply(&mut expected);
+                assert_eq!(timeline.seek(index + 1), expected, "stride {stride}");
+            }
+            // Past the end is the final state, not a panic.
+            assert_eq!(timeline.seek(9999), expected);
+        }
+    }
+
+    /// Like [`record_run`], but through a [`TailRecorder`] of `capacity`.
+    fn record_tail(source: &str, world: World, capacity: usize) -> Trace {
+        let mut buffer = Vec::new();
+        let mut recorder = TailRecorder::new(&mut buffer, &world, capacity);
+        let mut interpreter = Interpreter::new(preprocess(source), world).unwrap();
+        let mut result = Ok(());
+        while !interpreter.finished() {
+            let line = interpreter.current_line().unwrap_or(0);
+            let instruction = interpreter.current_instruction().unwrap_or_default().to_string();
+            let stepped = interpreter.step();
+            recorder.record(line, &instruction, &interpreter.world);
+            if let Err(error) = stepped {
+                result = Err(error);
+                break;
+            }
+        }
+        recorder
+            .finish(result.as_ref().err().map(|error| error.to_string()).as_deref())
+            .unwrap();
+        parse(&String::from_utf8(buffer).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn the_tail_recorder_keeps_the_last_steps_and_still_replays() {
+        let source = "def main\n put\n move\n put\n move\n take\n die\nenddef";
+        let mut start = World::new(5, 1);
+        start.set_beepers(Position::new(2, 0), 1);
+
+        let full = record_run(source, start.clone());
+        let tail = record_tail(source, start, 3);
+
+        // Only the newest three frames survive, original numbering intact.
+        assert_eq!(tail.frames.len(), 3);
+        let total = full.frames.len();
+        assert_eq!(tail.frames[0].step, total - 2);
+
+        // The tail's header world absorbed the evicted frames, so the
+        // replay still ends exactly where the full one does.
+        let replay = |trace: &Trace| {
+            let mut world = trace.world.clone();
+            for frame in &trace.frames {
+                frame.apply(&mut world);
+            }
+            world
+        };
+        assert_eq!(replay(&tail), replay(&full));
+    }
+
+    #[test]
+    fn a_roomy_tail_recorder_matches_the_streaming_one() {
+        let source = "def main\n put\n move\n die\nenddef";
+        let full = record_run(source, World::new(3, 1));
+        let tail = record_tail(source, World::new(3, 1), 100);
+        assert_eq!(tail, full);
+    }
+
+    #[test]
+    fn the_tail_of_a_failed_run_ends_at_the_failure() {
+        // Five puts succeed; the sixth take finds nothing and kills the run.
+        let tail = record_tail(
+            "def main\n repeat 5\n  put\n endrepeat\n repeat 6\n  take\n endrepeat\nenddef",
+            World::new(2, 1),
+            4,
+        );
+        assert!(tail.error.is_some());
+        assert_eq!(tail.frames.last().unwrap().instruction, "take");
+    }
+
+    #[test]
+    fn an_honest_trace_verifies() {
+        let source = "def main\n put\n move\n put\n move\n take\n die\nenddef";
+        let mut start = World::new(5, 1);
+        start.set_beepers(Position::new(2, 0), 1);
+        let trace = record_run(source, start.clone());
+        let program = Program::compile(source).unwrap();
+        assert_eq!(verify(&trace, &program, &start), Ok(()));
+
+        // A failed run verifies too: the verdicts match.
+        let source = "def main\n take\nenddef";
+        let trace = record_run(source, World::new(2, 1));
+        let program = Program::compile(source).unwrap();
+        assert_eq!(verify(&trace, &program, &World::new(2, 1)), Ok(()));
+    }
+
+    #[test]
+    fn tampering_is_caught() {
+        let source = "def main\n move\n move\n die\nenddef";
+        let start = World::new(5, 1);
+        let trace = record_run(source, start.clone());
+        let program = Program::compile(source).unwrap();
+
+        // A touched frame.
+        let mut tampered = trace.clone();
+        tampered.frames[1].robot.position = Position::new(4, 0);
+        assert_eq!(
+            verify(&tampered, &program, &start),
+            Err(ReplayError::FrameMismatch { step: 2 })
+        );
+
+        // A shortened trace.
+        let mut shortened = trace.clone();
+        shortened.frames.pop();
+        assert_eq!(
+            verify(&shortened, &program, &start),
+            Err(ReplayError::LengthMismatch { recorded: 2, executed: 3 })
+        );
+
+        // A different world, a different program.
+        assert_eq!(
+            verify(&trace, &program, &World::new(6, 1)),
+            Err(ReplayError::WorldMismatch)
+        );
+        let other = Program::compile("def main\n move\n turn-left\n die\nenddef").unwrap();
+        assert!(verify(&trace, &other, &start).is_err());
+    }
+
+    #[test]
+    fn a_forged_verdict_is_caught() {
+        let source = "def main\n move\n die\nenddef";
+        let mut trace = record_run(source, World::new(3, 1));
+        trace.error = Some("line 2: Karel walked into a wall".to_string());
+        let program = Program::compile(source).unwrap();
+        assert!(matches!(
+            verify(&trace, &program, &World::new(3, 1)),
+            Err(ReplayError::VerdictMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert!(parse("").is_err());
+        assert!(parse("not json\n").is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Converting a validated Karel program into runnable Python.
+//!
+//! The point is pedagogical: a student who has outgrown Karel can see their
+//! own program as ordinary Python, with `def`s as functions, `repeat` as a
+//! `for` loop and conditions as method calls. The output is a standalone
+//! script: a small `Karel` class re-implements the world semantics, then the
+//! translated procedures follow, then a `__main__` block runs `main`.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::parser::{self, Line, ParseError};
+
+/// The runtime shim emitted at the top of every script: the world semantics
+/// from the README, in ~60 lines of plain Python.
+const PYTHON_SHIM: &str = r#"class KarelError(Exception):
+    """A fatal instruction: the robot switches off."""
+
+
+class _ProgramEnd(Exception):
+    """Raised by die() to end the program."""
+
+
+class Karel:
+    """A rectangular world with one robot, matching the Karel semantics."""
+
+    # north, east, south, west
+    DIRECTIONS = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+
+    def __init__(self, width=10, height=10):
+        self.width, self.height = width, height
+        self.x, self.y = 0, 0
+        self.facing = 1  # east
+        self.walls = set()
+        self.beepers = {}
+        self.regions = {}  # name -> ((x1, y1), (x2, y2)), inclusive
+        self.beeps = []
+
+    def _ahead(self):
+        dx, dy = self.DIRECTIONS[self.facing]
+        return self.x + dx, self.y + dy
+
+    def wall(self):
+        x, y = self._ahead()
+        outside = not (0 <= x < self.width and 0 <= y < self.height)
+        return outside or (x, y) in self.walls
+
+    def north(self):
+        return self.facing == 0
+
+    def east(self):
+        return self.facing == 1
+
+    def south(self):
+        return self.facing == 2
+
+    def west(self):
+        return self.facing == 3
+
+    def beeper(self):
+        return self.beepers.get((self.x, self.y), 0) > 0
+
+    def beeper_count(self):
+        return self.beepers.get((self.x, self.y), 0)
+
+    def beeper_ahead(self):
+        x, y = self._ahead()
+        outside = not (0 <= x < self.width and 0 <= y < self.height)
+        return not outside and self.beepers.get((x, y), 0) > 0
+
+    def in_region(self, name):
+        if name not in self.regions:
+            return False
+        (x1, y1), (x2, y2) = self.regions[name]
+        return x1 <= self.x <= x2 and y1 <= self.y <= y2
+
+    def clear(self, tiles):
+        x, y = self.x, self.y
+        dx, dy = self.DIRECTIONS[self.facing]
+        for _ in range(tiles):
+            x, y = x + dx, y + dy
+            outside = not (0 <= x < self.width and 0 <= y < self.height)
+            if outside or (x, y) in self.walls:
+                return False
+        return True
+
+    def move(self):
+        if self.wall():
+            raise KarelError("Karel walked into a wall")
+        self.x, self.y = self._ahead()
+
+    def turn_left(self):
+        self.facing = (self.facing - 1) % 4
+
+    def take(self):
+        count = self.beepers.get((self.x, self.y), 0)
+        if count == 0:
+            raise KarelError("there is no beeper here to take")
+        self.beepers[(self.x, self.y)] = count - 1
+
+    def put(self):
+        count = self.beepers.get((self.x, self.y), 0)
+        if count == 8:
+            raise KarelError("this tile cannot hold any more beepers")
+        self.beepers[(self.x, self.y)] = count + 1
+
+    def beep(self):
+        self.beeps.append((self.x, self.y))
+
+    def direction(self):
+        return ["north", "east", "south", "west"][self.facing]
+
+    def die(self):
+        raise _ProgramEnd
+"#;
+
+/// Translate validated, preprocessed lines into a standalone Python script.
+///
+/// The program is validated again first, so callers can hand over raw
+/// [`parser::preprocess`] output; an invalid program is refused rather than
+/// turned into broken Python.
+pub fn to_python(lines: &[Line<'_>]) -> Result<String, ParseError> {
+    parser::validate(lines)?;
+
+    let mut out = String::from("#!/usr/bin/env python3\n");
+    out.push_str("# Generated from a Karel program; see the Karel class for the rules.\n\n");
+    out.push_str(PYTHON_SHIM);
+
+    let mut indent = 0usize;
+    // Statements emitted since the innermost block opened, to know when a
+    // closing keyword needs a `pass`.
+    let mut statements: Vec<usize> = Vec::new();
+
+    for line in lines {
+        let mut words = line.text.split_whitespace();
+        let keyword = words.next().expect("preprocessed lines are not empty");
+        let argument = words.next();
+
+        let statement = |out: &mut String, indent: usize, text: &str| {
+            out.push_str(&"    ".repeat(indent));
+            out.push_str(text);
+            out.push('\n');
+        };
+
+        match keyword {
+            "def" => {
+                let name = python_name(argument.expect("validated"));
+                out.push('\n');
+                out.push('\n');
+                statement(&mut out, 0, &format!("def {name}(karel):"));
+                indent = 1;
+                statements.push(0);
+            }
+            "enddef" | "endif" | "endrepeat" | "endwhile" => {
+                if statements.pop() == Some(0) {
+                    statement(&mut out, indent, "pass");
+                }
+                indent -= 1;
+                if let Some(outer) = statements.last_mut() {
+                    *outer += 1;
+                }
+            }
+            "if" | "if!" | "while" | "while!" => {
+                let condition = match argument.expect("validated") {
+                    "clear" => {
+                        format!("karel.clear({})", words.next().expect("validated"))
+                    }
+                    "in-region" => {
+                        format!("karel.in_region(\"{}\")", words.next().expect("validated"))
+                    }
+                    "beeper" => match words.next() {
+                        Some(operator) => {
+                            let operator = if operator == "=" { "==" } else { operator };
+                            let count = words.next().expect("validated");
+                            format!("karel.beeper_count() {operator} {count}")
+                        }
+                        None => condition_call("beeper"),
+                    },
+                    condition => condition_call(condition),
+                };
+                let python_keyword = if keyword.starts_with("if") { "if" } else { "while" };
+                let negation = if keyword.ends_with('!') { "not " } else { "" };
+                statement(
+                    &mut out,
+                    indent,
+                    &format!("{python_keyword} {negation}{condition}:"),
+                );
+                indent += 1;
+                statements.push(0);
+            }
+            "repeat" => {
+                let count = argument.expect("validated");
+                statement(&mut out, indent, &format!("for _ in range({count}):"));
+                indent += 1;
+                statements.push(0);
+            }
+            "print" => {
+                statement(&mut out, indent, "print(karel.direction())");
+                *statements.last_mut().expect("inside a def") += 1;
+            }
+            "call" => {
+                let name = python_name(argument.expect("validated"));
+                statement(&mut out, indent, &format!("{name}(karel)"));
+                *statements.last_mut().expect("inside a def") += 1;
+            }
+            instruction => {
+                let method = match instruction {
+                    "turn-left" => "turn_left".to_string(),
+                    other => other.to_string(),
+                };
+                statement(&mut out, indent, &format!("karel.{method}()"));
+                *statements.last_mut().expect("inside a def") += 1;
+            }
+        }
+    }
+
+    out.push_str("\n\n");
+    out.push_str("if __name__ == \"__main__\":\n");
+    out.push_str("    karel = Karel()\n");
+    out.push_str("    try:\n");
+    out.push_str(&format!("        {}(karel)\n", python_name("main")));
+    out.push_str("    except _ProgramEnd:\n");
+    out.push_str("        pass\n");
+    out.push_str("    print(f\"Karel ended at ({karel.x}, {karel.y})\")\n");
+    Ok(out)
+}
+
+/// Turn a Karel procedure name into a Python identifier: `-` (legal here,
+/// not there) becomes `_`, and a leading digit gets a `k_` prefix.
+fn python_name(name: &str) -> String {
+    let mut python: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if python.starts_with(|c: char| c.is_ascii_digit()) {
+        python.insert_str(0, "k_");
+    }
+    python
+}
+
+fn condition_call(condition: &str) -> String {
+    // `-` is legal in condition names but not in Python identifiers.
+    format!("karel.{}()", condition.replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::preprocess;
+
+    fn transpile(source: &str) -> String {
+        to_python(&preprocess(source)).unwrap()
+    }
+
+    #[test]
+    fn instructions_and_blocks_become_python() {
+        let python = transpile(
+            "def main\n repeat 3\n  move\n endrepeat\n while! wall\n  move\n endwhile\n if beeper\n  take\n endif\n die\nenddef",
+        );
+        assert!(python.contains("def main(karel):"), "{python}");
+        assert!(python.contains("    for _ in range(3):"), "{python}");
+        assert!(python.contains("        karel.move()"), "{python}");
+        assert!(python.contains("    while not karel.wall():"), "{python}");
+        assert!(python.contains("    if karel.beeper():"), "{python}");
+        assert!(python.contains("    karel.die()"), "{python}");
+    }
+
+    #[test]
+    fn calls_and_hyphenated_names_are_translated() {
+        let python = transpile(
+            "def main\n call turn-right\nenddef\ndef turn-right\n turn-left\n turn-left\n turn-left\nenddef",
+        );
+        assert!(python.contains("def turn_right(karel):"), "{python}");
+        assert!(python.contains("    turn_right(karel)"), "{python}");
+        assert!(python.contains("    karel.turn_left()"), "{python}");
+    }
+
+    #[test]
+    fn empty_blocks_get_a_pass() {
+        let python = transpile("def main\n if wall\n endif\nenddef");
+        assert!(python.contains("    if karel.wall():\n        pass"), "{python}");
+    }
+
+    #[test]
+    fn invalid_programs_are_refused() {
+        let result = to_python(&preprocess("def main\n fly\nenddef"));
+        assert!(matches!(
+            result,
+            Err(ParseError::UnknownInstruction { .. })
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+//! Watch expressions: observations a debugger re-evaluates every step.
+//!
+//! A debugging session registers expressions once and asks after every step
+//! whether any of them fired; the debugger pauses when one does. A flag
+//! expression fires when it *becomes* true, a value expression when its
+//! value *changes* — so `beeper >= 3` stops at the moment the pile grows
+//! big enough, and `position` stops on every move but not on a turn.
+//!
+//! The expression language is the language's own condition grammar (`wall`,
+//! `north`, `beeper >= 3`, `in-region home`, ...) plus observations a
+//! condition cannot ask: `beepers-at X Y`, `bag`, `bag OP N` and
+//! `position`. Students debug with the vocabulary they already write
+//! programs in. The same expressions guard
+//! [conditional breakpoints](crate::dap), where only the flag-valued forms
+//! make sense (see [`Expression::is_condition`]).
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::environment::{Check, Comparison, Environment};
+use crate::interpreter::condition_check;
+use crate::world::{Position, World};
+
+/// A parsed watch expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    /// A language condition, evaluated like an `if` would.
+    Condition(Check),
+    /// `in-region NAME`: the name cannot ride inside [`Check`], so it lives
+    /// here.
+    InRegion(String),
+    /// `beepers-at X Y`: the beeper count of a fixed tile.
+    BeepersAt(Position),
+    /// `bag`: how many beepers the robot carries.
+    Bag,
+    /// `bag OP N`: does the carried count compare as asked (`bag = 0`)?
+    BagCompare(Comparison, usize),
+    /// `position`: the tile the robot stands on.
+    RobotPosition,
+}
+
+/// Why a watch expression could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchError {
+    /// Not a condition and not one of the watch-only observations.
+    BadExpression { expression: String },
+}
+
+impl core::fmt::Display for WatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WatchError::BadExpression { expression } => {
+                write!(f, "`{expression}` is not a watchable expression")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WatchError {}
+
+impl Expression {
+    /// Parse a watch expression; see the module docs for the forms.
+    pub fn parse(text: &str) -> Result<Expression, WatchError> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let parsed = match words[..] {
+            ["in-region", name] => Some(Expression::InRegion(name.to_string())),
+            ["beepers-at", x, y] => match (x.parse(), y.parse()) {
+                (Ok(x), Ok(y)) => Some(Expression::BeepersAt(Position::new(x, y))),
+                _ => None,
+            },
+            ["bag"] => Some(Expression::Bag),
+            ["bag", operator, count] => match (Comparison::parse(operator), count.parse()) {
+                (Some(comparison), Ok(count)) => {
+                    Some(Expression::BagCompare(comparison, count))
+                }
+                _ => None,
+            },
+            ["position"] => Some(Expression::RobotPosition),
+            _ => condition_check(&words).map(Expression::Condition),
+        };
+        parsed.ok_or_else(|| WatchError::BadExpression {
+            expression: text.to_string(),
+        })
+    }
+
+    /// Does the expression evaluate to a true/false [`Observation::Flag`]?
+    /// Breakpoint conditions must; value expressions are for watching.
+    pub fn is_condition(&self) -> bool {
+        matches!(
+            self,
+            Expression::Condition(_) | Expression::InRegion(_) | Expression::BagCompare(..)
+        )
+    }
+
+    /// What the expression sees in the given world.
+    pub fn observe(&self, world: &World) -> Observation {
+        match self {
+            Expression::Condition(check) => Observation::Flag(world.check(*check)),
+            Expression::InRegion(name) => Observation::Flag(world.in_region(name)),
+            Expression::BeepersAt(position) => {
+                Observation::Count(world.beepers_at(*position) as usize)
+            }
+            Expression::Bag => Observation::Count(world.bag()),
+            Expression::BagCompare(comparison, count) => {
+                Observation::Flag(comparison.holds(world.bag(), *count))
+            }
+            Expression::RobotPosition => {
+                Observation::Tile(world.robot.position.x, world.robot.position.y)
+            }
+        }
+    }
+}
+
+/// What a watch expression evaluated to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Observation {
+    Flag(bool),
+    Count(usize),
+    Tile(usize, usize),
+}
+
+impl core::fmt::Display for Observation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Observation::Flag(flag) => write!(f, "{flag}"),
+            Observation::Count(count) => write!(f, "{count}"),
+            Observation::Tile(x, y) => write!(f, "({x}, {y})"),
+        }
+    }
+}
+
+/// One registered watch and its last seen value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Watch {
+    text: String,
+    expression: Expression,
+    last: Option<Observation>,
+}
+
+/// A watch that fired: what it reads now and what it read before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trigger {
+    /// The expression as the user wrote it.
+    pub expression: String,
+    pub from: Observation,
+    pub to: Observation,
+}
+
+/// The set of watches of one debugging session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Watches {
+    watches: Vec<Watch>,
+}
+
+impl Watches {
+    pub fn new() -> Watches {
+        Watches::default()
+    }
+
+    /// Register an expression. Registering one twice is harmless.
+    pub fn add(&mut self, text: &str) -> Result<(), WatchError> {
+        if self.watches.iter().any(|watch| watch.text == text) {
+            return Ok(());
+        }
+        self.watches.push(Watch {
+            text: text.to_string(),
+            expression: Expression::parse(text)?,
+            last: None,
+        });
+        Ok(())
+    }
+
+    /// Drop a registered expression; `true` if it was there.
+    pub fn remove(&mut self, text: &str) -> bool {
+        let before = self.watches.len();
+        self.watches.retain(|watch| watch.text != text);
+        self.watches.len() < before
+    }
+
+    /// Drop everything, for a fresh `setWatches`-style handover.
+    pub fn clear(&mut self) {
+        self.watches.clear();
+    }
+
+    /// How many watches are registered.
+    pub fn len(&self) -> usize {
+        self.watches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Evaluate every watch against the world and return the ones that
+    /// fired: a flag that became true, or a value that changed. The first
+    /// observation of a watch only records its baseline and never fires.
+    pub fn observe(&mut self, world: &World) -> Vec<Trigger> {
+        let mut triggers = Vec::new();
+        for watch in &mut self.watches {
+            let now = watch.expression.observe(world);
+            if let Some(before) = watch.last {
+                let fired = match now {
+                    Observation::Flag(flag) => flag && before != now,
+                    _ => before != now,
+                };
+                if fired {
+                    triggers.push(Trigger {
+                        expression: watch.text.clone(),
+                        from: before,
+                        to: now,
+                    });
+                }
+            }
+            watch.last = Some(now);
+        }
+        triggers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Action;
+
+    #[test]
+    fn flags_fire_when_they_become_true() {
+        let mut world = World::new(3, 1);
+        world.set_beepers(Position::new(1, 0), 1);
+        let mut watches = Watches::new();
+        watches.add("beeper").unwrap();
+
+        // Baseline, then a move onto the beeper.
+        assert!(watches.observe(&world).is_empty());
+        world.perform(Action::Move).unwrap();
+        let fired = watches.observe(&world);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].expression, "beeper");
+        assert_eq!(fired[0].to, Observation::Flag(true));
+
+        // Staying true is not news; becoming false is not either.
+        assert!(watches.observe(&world).is_empty());
+        world.perform(Action::Move).unwrap();
+        assert!(watches.observe(&world).is_empty());
+    }
+
+    #[test]
+    fn values_fire_on_every_change() {
+        let mut world = World::new(3, 1);
+        let mut watches = Watches::new();
+        watches.add("position").unwrap();
+        watches.add("beepers-at 0 0").unwrap();
+        assert!(watches.observe(&world).is_empty());
+
+        // Turning is not a position change.
+        world.perform(Action::TurnLeft).unwrap();
+        world.perform(Action::TurnLeft).unwrap();
+        world.perform(Action::TurnLeft).unwrap();
+        world.perform(Action::TurnLeft).unwrap();
+        assert!(watches.observe(&world).is_empty());
+        world.set_beepers(Position::new(0, 0), 2);
+        world.perform(Action::Move).unwrap();
+        let fired = watches.observe(&world);
+        assert_eq!(fired.len(), 2);
+        assert_eq!(fired[0].to, Observation::Tile(1, 0));
+        assert_eq!(fired[1].from, Observation::Count(0));
+        assert_eq!(fired[1].to, Observation::Count(2));
+    }
+
+    #[test]
+    fn the_condition_grammar_is_the_expression_language() {
+        assert_eq!(
+            Expression::parse("beeper >= 3"),
+            Ok(Expression::Condition(Check::Beepers {
+                comparison: crate::environment::Comparison::GreaterOrEqual,
+                count: 3,
+            }))
+        );
+        assert_eq!(
+            Expression::parse("in-region home"),
+            Ok(Expression::InRegion("home".to_string()))
+        );
+        assert_eq!(
+            Expression::parse("bag = 0"),
+            Ok(Expression::BagCompare(
+                crate::environment::Comparison::Equal,
+                0
+            ))
+        );
+        assert!(Expression::parse("bag = 0").unwrap().is_condition());
+        assert!(!Expression::parse("bag").unwrap().is_condition());
+        assert_eq!(
+            Expression::parse("robot.facing"),
+            Err(WatchError::BadExpression {
+                expression: "robot.facing".to_string(),
+            })
+        );
+    }
+}
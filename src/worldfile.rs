@@ -0,0 +1,852 @@
+//! Loading and saving worlds as plain text or JSON files.
+//!
+//! The text format is one character per tile, one line per row:
+//!
+//! ```text
+//! .....
+//! .#.2.
+//! .>...
+//! ```
+//!
+//! `.` is an empty tile, `#` a wall, a digit `1`-`8` that many beepers, and
+//! exactly one of `^ > v <` places the robot with its facing. Lines starting
+//! with `;` are comments.
+//!
+//! Two directives keep large structured worlds from being drawn cell by
+//! cell. `repeat-row COUNT ROW` stands for `COUNT` copies of the row, and
+//! `fill-rect X Y WIDTH HEIGHT TILE` overwrites a rectangle of the
+//! assembled grid (top-left corner at column `X` of row `Y`, zero-based)
+//! with one tile once all rows are in place:
+//!
+//! ```text
+//! repeat-row 3 #.....#
+//! fill-rect 2 0 3 2 8
+//! >......
+//! ```
+//!
+//! The JSON format carries the same information explicitly, which survives a
+//! robot standing on a beeper pile (the text format cannot show both):
+//!
+//! ```json
+//! {"width": 4, "height": 3,
+//!  "robot": {"x": 2, "y": 2, "direction": "north"},
+//!  "walls": [[1, 1]],
+//!  "beepers": [[2, 1, 2]],
+//!  "collisions": "stack",
+//!  "regions": [{"name": "home", "from": [0, 0], "to": [1, 1]}]}
+//! ```
+//!
+//! `collisions` is the world's [`CollisionPolicy`] (`stack`, `block`, `swap`
+//! or `error`), optional and `stack` when absent. `regions` are the world's
+//! named rectangular [`Region`](crate::world::Region)s, also optional. The
+//! text format cannot carry either, so saving as text loses them.
+//!
+//! [`load`] and [`save`] pick the format from the file extension.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::json::{self, Value};
+use crate::world::{CollisionPolicy, Direction, Position, World};
+
+/// The most tiles a deserialized world may have. World files come from
+/// untrusted places — submissions, the C FFI, the fuzzer — and without a
+/// bound a single `{"width":1e30,"height":1e30}` would overflow or exhaust
+/// memory inside [`World::new`] instead of returning an error.
+pub const MAX_TILES: usize = 1_000_000;
+
+/// An error in a world file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorldParseError {
+    /// The file contains no tile rows at all.
+    Empty,
+    /// A row is shorter or longer than the first one.
+    RaggedRow { row: usize },
+    /// A character that is not a known tile.
+    UnknownTile { row: usize, column: usize, tile: char },
+    /// No `^ > v <` anywhere in the file.
+    MissingRobot,
+    /// More than one robot marker.
+    DuplicateRobot { row: usize, column: usize },
+    /// A `repeat-row` or `fill-rect` line that does not make sense.
+    BadDirective { row: usize, reason: String },
+    /// The dimensions multiply out past [`MAX_TILES`] (or past `usize`).
+    TooLarge { width: usize, height: usize },
+    /// A JSON world that is not valid JSON or misses required fields.
+    BadJson { reason: String },
+}
+
+impl fmt::Display for WorldParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldParseError::Empty => write!(f, "the world file contains no rows"),
+            WorldParseError::RaggedRow { row } => {
+                write!(f, "row {row}: all rows must have the same width")
+            }
+            WorldParseError::UnknownTile { row, column, tile } => {
+                write!(f, "row {row}, column {column}: unknown tile `{tile}`")
+            }
+            WorldParseError::MissingRobot => {
+                write!(f, "the world has no robot (one of `^ > v <`)")
+            }
+            WorldParseError::DuplicateRobot { row, column } => {
+                write!(f, "row {row}, column {column}: the world already has a robot")
+            }
+            WorldParseError::BadDirective { row, reason } => {
+                write!(f, "row {row}: {reason}")
+            }
+            WorldParseError::TooLarge { width, height } => {
+                write!(f, "a {width}x{height} world exceeds the {MAX_TILES}-tile limit")
+            }
+            WorldParseError::BadJson { reason } => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for WorldParseError {}
+
+/// Parse a world from the text format described in the module docs,
+/// expanding `repeat-row` and `fill-rect` directives first.
+pub fn parse(source: &str) -> Result<World, WorldParseError> {
+    let rows = expand(source)?;
+    if rows.is_empty() {
+        return Err(WorldParseError::Empty);
+    }
+
+    let width = rows[0].len();
+    check_size(width, rows.len())?;
+    let mut world = World::new(width, rows.len());
+    let mut robot: Option<(Position, Direction)> = None;
+
+    for (y, row) in rows.iter().enumerate() {
+        if row.len() != width {
+            return Err(WorldParseError::RaggedRow { row: y + 1 });
+        }
+        for (x, &tile) in row.iter().enumerate() {
+            let position = Position::new(x, y);
+            let direction = match tile {
+                '.' => continue,
+                '#' => {
+                    world.set_wall(position, true);
+                    continue;
+                }
+                '1'..='8' => {
+                    world.set_beepers(position, tile as u8 - b'0');
+                    continue;
+                }
+                '^' => Direction::North,
+                '>' => Direction::East,
+                'v' => Direction::South,
+                '<' => Direction::West,
+                _ => {
+                    return Err(WorldParseError::UnknownTile {
+                        row: y + 1,
+                        column: x + 1,
+                        tile,
+                    })
+                }
+            };
+            if robot.is_some() {
+                return Err(WorldParseError::DuplicateRobot {
+                    row: y + 1,
+                    column: x + 1,
+                });
+            }
+            robot = Some((position, direction));
+        }
+    }
+
+    let (position, direction) = robot.ok_or(WorldParseError::MissingRobot)?;
+    world.robot.position = position;
+    world.robot.direction = direction;
+    Ok(world)
+}
+
+/// Expand the directives of a text world into the plain tile grid. Plain
+/// rows never contain whitespace, so any line of several words is a
+/// directive; directive rows in errors count the file's non-comment lines.
+fn expand(source: &str) -> Result<Vec<Vec<char>>, WorldParseError> {
+    let mut grid: Vec<Vec<char>> = Vec::new();
+    let mut fills: Vec<(usize, [usize; 4], char)> = Vec::new();
+    for (index, line) in source
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with(';'))
+        .enumerate()
+    {
+        let row = index + 1;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| {
+            word.parse::<usize>()
+                .map_err(|_| WorldParseError::BadDirective {
+                    row,
+                    reason: format!("`{word}` is not a number"),
+                })
+        };
+        match words[..] {
+            ["repeat-row", count, pattern] => {
+                let count = number(count)?;
+                // Bound the expansion before allocating: a single
+                // `repeat-row 4000000000 ...` must error, not exhaust
+                // memory.
+                let cells: usize = grid.iter().map(Vec::len).sum();
+                let added = count.saturating_mul(pattern.chars().count());
+                if cells.saturating_add(added) > MAX_TILES {
+                    return Err(WorldParseError::TooLarge {
+                        width: pattern.chars().count(),
+                        height: count,
+                    });
+                }
+                for _ in 0..count {
+                    grid.push(pattern.chars().collect());
+                }
+            }
+            ["fill-rect", x, y, width, height, tile] => {
+                let rect = [number(x)?, number(y)?, number(width)?, number(height)?];
+                let tile = match tile.chars().collect::<Vec<char>>()[..] {
+                    [tile @ ('.' | '#' | '1'..='8')] => tile,
+                    _ => {
+                        return Err(WorldParseError::BadDirective {
+                            row,
+                            reason: format!("`{tile}` is not a fillable tile (`.`, `#` or `1`-`8`)"),
+                        })
+                    }
+                };
+                fills.push((row, rect, tile));
+            }
+            [_, _, ..] => {
+                return Err(WorldParseError::BadDirective {
+                    row,
+                    reason: "expected `repeat-row COUNT ROW` or `fill-rect X Y WIDTH HEIGHT TILE`"
+                        .to_string(),
+                })
+            }
+            _ => grid.push(line.chars().collect()),
+        }
+    }
+
+    for &(row, [x, y, width, height], tile) in &fills {
+        let fits = y + height <= grid.len()
+            && grid[y..y + height].iter().all(|grid_row| x + width <= grid_row.len());
+        if !fits {
+            return Err(WorldParseError::BadDirective {
+                row,
+                reason: "fill-rect reaches outside the world".to_string(),
+            });
+        }
+        for grid_row in &mut grid[y..y + height] {
+            for cell in &mut grid_row[x..x + width] {
+                *cell = tile;
+            }
+        }
+    }
+    Ok(grid)
+}
+
+/// Write the world back out in the text format. The robot marker overrides
+/// whatever lies on its tile, exactly as in the renderer.
+pub fn to_text(world: &World) -> String {
+    let mut out = String::new();
+    for y in 0..world.height() {
+        for x in 0..world.width() {
+            let position = Position::new(x, y);
+            if world.robot.position == position {
+                out.push(match world.robot.direction {
+                    Direction::North => '^',
+                    Direction::East => '>',
+                    Direction::South => 'v',
+                    Direction::West => '<',
+                });
+            } else if world.is_wall(position) {
+                out.push('#');
+            } else if world.beepers_at(position) > 0 {
+                out.push((b'0' + world.beepers_at(position)) as char);
+            } else {
+                out.push('.');
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Serialize the world into the JSON format.
+pub fn to_json(world: &World) -> Value {
+    let mut walls = Vec::new();
+    let mut beepers = Vec::new();
+    for y in 0..world.height() {
+        for x in 0..world.width() {
+            let position = Position::new(x, y);
+            if world.is_wall(position) {
+                walls.push(Value::from(vec![x, y]));
+            }
+            let count = world.beepers_at(position);
+            if count > 0 {
+                beepers.push(Value::from(vec![x, y, count as usize]));
+            }
+        }
+    }
+    Value::object([
+        ("width", Value::from(world.width())),
+        ("height", Value::from(world.height())),
+        (
+            "robot",
+            Value::object([
+                ("x", Value::from(world.robot.position.x)),
+                ("y", Value::from(world.robot.position.y)),
+                ("direction", Value::from(direction_name(world.robot.direction))),
+            ]),
+        ),
+        ("walls", Value::Array(walls)),
+        ("beepers", Value::Array(beepers)),
+        ("collisions", Value::from(world.collision_policy().name())),
+        (
+            "regions",
+            Value::Array(
+                world
+                    .regions()
+                    .iter()
+                    .map(|region| {
+                        Value::object([
+                            ("name", Value::from(region.name.clone())),
+                            ("from", Value::from(vec![region.from.x, region.from.y])),
+                            ("to", Value::from(vec![region.to.x, region.to.y])),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+/// The lowercase name of a direction, as used in the JSON format.
+pub fn direction_name(direction: Direction) -> &'static str {
+    direction.name()
+}
+
+/// Reject dimensions before they reach [`World::new`], which trusts them.
+fn check_size(width: usize, height: usize) -> Result<(), WorldParseError> {
+    match width.checked_mul(height) {
+        Some(tiles) if tiles <= MAX_TILES => Ok(()),
+        _ => Err(WorldParseError::TooLarge { width, height }),
+    }
+}
+
+fn bad_json(reason: &str) -> WorldParseError {
+    WorldParseError::BadJson {
+        reason: reason.to_string(),
+    }
+}
+
+fn json_usize(value: Option<&Value>) -> Result<usize, WorldParseError> {
+    match value {
+        Some(Value::Number(number)) if *number >= 0.0 && number.fract() == 0.0 => {
+            Ok(*number as usize)
+        }
+        _ => Err(bad_json("expected a non-negative integer")),
+    }
+}
+
+/// Parse a world from the JSON format.
+pub fn from_json(source: &str) -> Result<World, WorldParseError> {
+    let value = json::parse(source).map_err(|error| bad_json(&error.to_string()))?;
+    let Value::Object(entries) = value else {
+        return Err(bad_json("expected a JSON object"));
+    };
+
+    let width = json_usize(entries.get("width"))?;
+    let height = json_usize(entries.get("height"))?;
+    check_size(width, height)?;
+    let mut world = World::new(width, height);
+
+    let Some(Value::Object(robot)) = entries.get("robot") else {
+        return Err(bad_json("expected a `robot` object"));
+    };
+    let position = Position::new(json_usize(robot.get("x"))?, json_usize(robot.get("y"))?);
+    if !world.in_bounds(position) {
+        return Err(bad_json("robot is outside the world"));
+    }
+    world.robot.position = position;
+    world.robot.direction = match robot.get("direction") {
+        Some(Value::String(direction)) => match direction.as_str() {
+            "north" => Direction::North,
+            "east" => Direction::East,
+            "south" => Direction::South,
+            "west" => Direction::West,
+            _ => return Err(bad_json("bad robot direction")),
+        },
+        _ => return Err(bad_json("expected a robot `direction` string")),
+    };
+
+    if let Some(Value::Array(walls)) = entries.get("walls") {
+        for wall in walls {
+            let Value::Array(pair) = wall else {
+                return Err(bad_json("expected `[x, y]` wall entries"));
+            };
+            let position =
+                Position::new(json_usize(pair.first())?, json_usize(pair.get(1))?);
+            if !world.in_bounds(position) {
+                return Err(bad_json("wall is outside the world"));
+            }
+            world.set_wall(position, true);
+        }
+    }
+    if let Some(Value::Array(beepers)) = entries.get("beepers") {
+        for beeper in beepers {
+            let Value::Array(triple) = beeper else {
+                return Err(bad_json("expected `[x, y, count]` beeper entries"));
+            };
+            let position =
+                Position::new(json_usize(triple.first())?, json_usize(triple.get(1))?);
+            if !world.in_bounds(position) {
+                return Err(bad_json("beeper is outside the world"));
+            }
+            world.set_beepers(position, json_usize(triple.get(2))?.min(u8::MAX as usize) as u8);
+        }
+    }
+    if let Some(Value::Array(regions)) = entries.get("regions") {
+        for region in regions {
+            let Value::Object(entries) = region else {
+                return Err(bad_json("expected region objects"));
+            };
+            let Some(Value::String(name)) = entries.get("name") else {
+                return Err(bad_json("expected a region `name` string"));
+            };
+            let corner = |key: &str| match entries.get(key) {
+                Some(Value::Array(pair)) => Ok(Position::new(
+                    json_usize(pair.first())?,
+                    json_usize(pair.get(1))?,
+                )),
+                _ => Err(bad_json("expected `[x, y]` region corners")),
+            };
+            let from = corner("from")?;
+            let to = corner("to")?;
+            if !world.in_bounds(from) || !world.in_bounds(to) {
+                return Err(bad_json("region is outside the world"));
+            }
+            world.add_region(name, from, to);
+        }
+    }
+    match entries.get("collisions") {
+        Some(Value::String(name)) => match CollisionPolicy::from_name(name) {
+            Some(policy) => world.set_collision_policy(policy),
+            None => return Err(bad_json("bad collision policy")),
+        },
+        Some(_) => return Err(bad_json("expected a `collisions` string")),
+        None => {}
+    }
+    Ok(world)
+}
+
+/// Why a set of rooms could not be stitched into one world.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComposeError {
+    /// No rooms were given.
+    Empty,
+    /// Two rooms share a name.
+    DuplicateRoom { name: String },
+    /// Two rooms claim the same tiles.
+    Overlap { first: String, second: String },
+    /// Two rooms carry a region of the same name.
+    DuplicateRegion { name: String },
+}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComposeError::Empty => write!(f, "there are no rooms to compose"),
+            ComposeError::DuplicateRoom { name } => {
+                write!(f, "two rooms are both called `{name}`")
+            }
+            ComposeError::Overlap { first, second } => {
+                write!(f, "rooms `{first}` and `{second}` overlap")
+            }
+            ComposeError::DuplicateRegion { name } => {
+                write!(f, "two rooms both define a region `{name}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ComposeError {}
+
+/// Stitch rooms — smaller worlds, typically loaded from their own files —
+/// into one map. Each room is placed with its north-western corner at the
+/// given position; rooms may not overlap. The composed world takes its
+/// robot and collision policy from the first room (the entry room; the
+/// robots the other rooms needed to parse on their own are dropped), and
+/// every room becomes a region named after it, so programs and goals can
+/// say `in-region cellar` without pinning coordinates. The rooms' own
+/// regions are carried over translated, and must not share names.
+pub fn compose(rooms: &[(&str, &World, Position)]) -> Result<World, ComposeError> {
+    if rooms.is_empty() {
+        return Err(ComposeError::Empty);
+    }
+    for (index, (name, room, at)) in rooms.iter().enumerate() {
+        if rooms[..index].iter().any(|(earlier, ..)| earlier == name) {
+            return Err(ComposeError::DuplicateRoom { name: name.to_string() });
+        }
+        for (earlier, other, other_at) in &rooms[..index] {
+            let disjoint = at.x + room.width() <= other_at.x
+                || other_at.x + other.width() <= at.x
+                || at.y + room.height() <= other_at.y
+                || other_at.y + other.height() <= at.y;
+            if !disjoint {
+                return Err(ComposeError::Overlap {
+                    first: earlier.to_string(),
+                    second: name.to_string(),
+                });
+            }
+        }
+    }
+
+    let width = rooms.iter().map(|(_, room, at)| at.x + room.width()).max().unwrap();
+    let height = rooms.iter().map(|(_, room, at)| at.y + room.height()).max().unwrap();
+    let mut world = World::new(width, height);
+    let mut region_names = Vec::new();
+    for (name, room, at) in rooms {
+        for y in 0..room.height() {
+            for x in 0..room.width() {
+                let from = Position::new(x, y);
+                let to = Position::new(at.x + x, at.y + y);
+                world.set_wall(to, room.is_wall(from));
+                world.set_beepers(to, room.beepers_at(from));
+            }
+        }
+        let far = Position::new(at.x + room.width() - 1, at.y + room.height() - 1);
+        for region in room.regions().iter().map(|region| region.name.as_str()).chain([*name]) {
+            if region_names.contains(&region.to_string()) {
+                return Err(ComposeError::DuplicateRegion { name: region.to_string() });
+            }
+            region_names.push(region.to_string());
+        }
+        world.add_region(name, *at, far);
+        for region in room.regions() {
+            world.add_region(
+                &region.name,
+                Position::new(at.x + region.from.x, at.y + region.from.y),
+                Position::new(at.x + region.to.x, at.y + region.to.y),
+            );
+        }
+    }
+
+    let (_, entry, at) = rooms[0];
+    world.robot.position =
+        Position::new(at.x + entry.robot.position.x, at.y + entry.robot.position.y);
+    world.robot.direction = entry.robot.direction;
+    world.set_collision_policy(entry.collision_policy());
+    Ok(world)
+}
+
+/// Entry point for fuzzers: try arbitrary bytes against both world formats.
+/// A parsed world (or `None`) comes back; a panic never does.
+pub fn parse_for_fuzzing(data: &[u8]) -> Option<World> {
+    let source = std::str::from_utf8(data).ok()?;
+    parse(source).or_else(|_| from_json(source)).ok()
+}
+
+/// An error while loading or saving a world file.
+#[derive(Debug)]
+pub enum WorldFileError {
+    Io(std::io::Error),
+    Parse(WorldParseError),
+}
+
+impl fmt::Display for WorldFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldFileError::Io(error) => write!(f, "{error}"),
+            WorldFileError::Parse(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for WorldFileError {}
+
+fn is_json_path(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "json")
+}
+
+/// Read a world file, choosing the format by the file extension (`.json`
+/// for JSON, everything else is the text format).
+pub fn load(path: &Path) -> Result<World, WorldFileError> {
+    let source = std::fs::read_to_string(path).map_err(WorldFileError::Io)?;
+    let result = if is_json_path(path) {
+        from_json(&source)
+    } else {
+        parse(&source)
+    };
+    result.map_err(WorldFileError::Parse)
+}
+
+/// Write a world file in the format matching the file extension.
+pub fn save(world: &World, path: &Path) -> Result<(), std::io::Error> {
+    let contents = if is_json_path(path) {
+        let mut text = to_json(world).to_string();
+        text.push('\n');
+        text
+    } else {
+        to_text(world)
+    };
+    std::fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_roundtrip() {
+        let mut world = World::new(4, 3);
+        world.set_wall(Position::new(1, 1), true);
+        world.set_beepers(Position::new(2, 1), 2);
+        world.robot.position = Position::new(2, 2);
+        world.robot.direction = Direction::North;
+        assert_eq!(from_json(&to_json(&world).to_string()), Ok(world));
+    }
+
+    #[test]
+    fn json_keeps_beepers_under_the_robot() {
+        let mut world = World::new(2, 1);
+        world.set_beepers(Position::new(0, 0), 3);
+        let reloaded = from_json(&to_json(&world).to_string()).unwrap();
+        assert_eq!(reloaded.beepers_at(Position::new(0, 0)), 3);
+    }
+
+    #[test]
+    fn json_carries_the_collision_policy() {
+        let mut world = World::new(2, 1);
+        world.set_collision_policy(CollisionPolicy::Swap);
+        assert_eq!(from_json(&to_json(&world).to_string()), Ok(world));
+
+        let source = "{\"width\": 1, \"height\": 1, \
+                      \"robot\": {\"x\": 0, \"y\": 0, \"direction\": \"east\"}, \
+                      \"collisions\": \"melt\"}";
+        assert!(matches!(
+            from_json(source),
+            Err(WorldParseError::BadJson { .. })
+        ));
+    }
+
+    #[test]
+    fn json_carries_regions() {
+        let mut world = World::new(4, 4);
+        world.add_region("home", Position::new(0, 0), Position::new(1, 1));
+        world.add_region("goal", Position::new(3, 3), Position::new(3, 3));
+        assert_eq!(from_json(&to_json(&world).to_string()), Ok(world));
+
+        let source = "{\"width\": 2, \"height\": 2, \
+                      \"robot\": {\"x\": 0, \"y\": 0, \"direction\": \"east\"}, \
+                      \"regions\": [{\"name\": \"far\", \"from\": [0, 0], \"to\": [5, 5]}]}";
+        assert!(matches!(
+            from_json(source),
+            Err(WorldParseError::BadJson { .. })
+        ));
+    }
+
+    #[test]
+    fn json_errors_are_reported() {
+        assert!(matches!(
+            from_json("{\"width\": 2}"),
+            Err(WorldParseError::BadJson { .. })
+        ));
+        assert!(matches!(
+            from_json("[1, 2]"),
+            Err(WorldParseError::BadJson { .. })
+        ));
+    }
+
+    #[test]
+    fn oversized_json_dimensions_are_rejected() {
+        let source = "{\"width\":1e30,\"height\":1e30,\
+                      \"robot\":{\"x\":0,\"y\":0,\"direction\":\"east\"}}";
+        assert!(matches!(
+            from_json(source),
+            Err(WorldParseError::TooLarge { .. })
+        ));
+        assert_eq!(parse_for_fuzzing(source.as_bytes()), None);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let source = "; a comment\n....\n.#2.\n..^.\n";
+        let world = parse(source).unwrap();
+        assert_eq!(world.width(), 4);
+        assert_eq!(world.height(), 3);
+        assert!(world.is_wall(Position::new(1, 1)));
+        assert_eq!(world.beepers_at(Position::new(2, 1)), 2);
+        assert_eq!(world.robot.position, Position::new(2, 2));
+        assert_eq!(world.robot.direction, Direction::North);
+        assert_eq!(to_text(&world), "....\n.#2.\n..^.\n");
+    }
+
+    #[test]
+    fn missing_robot_is_an_error() {
+        assert_eq!(parse("...\n...\n"), Err(WorldParseError::MissingRobot));
+    }
+
+    #[test]
+    fn two_robots_are_an_error() {
+        assert_eq!(
+            parse(">.<\n"),
+            Err(WorldParseError::DuplicateRobot { row: 1, column: 3 })
+        );
+    }
+
+    #[test]
+    fn ragged_rows_are_an_error() {
+        assert_eq!(
+            parse(">..\n..\n"),
+            Err(WorldParseError::RaggedRow { row: 2 })
+        );
+    }
+
+    #[test]
+    fn unknown_tile_is_an_error() {
+        assert_eq!(
+            parse(">x\n"),
+            Err(WorldParseError::UnknownTile { row: 1, column: 2, tile: 'x' })
+        );
+    }
+
+    #[test]
+    fn repeat_row_expands_in_place() {
+        let world = parse("repeat-row 3 #.#\n>..\n").unwrap();
+        assert_eq!(world.height(), 4);
+        for y in 0..3 {
+            assert!(world.is_wall(Position::new(0, y)));
+            assert!(!world.is_wall(Position::new(1, y)));
+            assert!(world.is_wall(Position::new(2, y)));
+        }
+        assert_eq!(world.robot.position, Position::new(0, 3));
+    }
+
+    #[test]
+    fn repeat_row_cannot_blow_past_the_tile_limit() {
+        assert!(matches!(
+            parse("repeat-row 4000000000 #.#\n>..\n"),
+            Err(WorldParseError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn fill_rect_paints_the_assembled_grid() {
+        let source = "repeat-row 3 .....\nfill-rect 1 0 3 2 8\n>....\n";
+        let world = parse(source).unwrap();
+        for y in 0..2 {
+            for x in 1..4 {
+                assert_eq!(world.beepers_at(Position::new(x, y)), 8);
+            }
+        }
+        assert_eq!(world.beepers_at(Position::new(0, 0)), 0);
+        assert_eq!(world.beepers_at(Position::new(1, 2)), 0);
+    }
+
+    #[test]
+    fn bad_directives_are_reported_with_their_row() {
+        assert_eq!(
+            parse("repeat-row many ...\n>..\n"),
+            Err(WorldParseError::BadDirective {
+                row: 1,
+                reason: "`many` is not a number".to_string(),
+            })
+        );
+        assert!(matches!(
+            parse(">..\nrepeat 2 ...\n"),
+            Err(WorldParseError::BadDirective { row: 2, .. })
+        ));
+        assert!(matches!(
+            parse("fill-rect 0 0 2 2 ^\n>..\n"),
+            Err(WorldParseError::BadDirective { row: 1, .. })
+        ));
+        // A rectangle may not reach outside the world.
+        assert!(matches!(
+            parse(">..\nfill-rect 2 0 2 1 #\n"),
+            Err(WorldParseError::BadDirective { row: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn rooms_compose_into_one_map() {
+        let hall = parse(">.2\n").unwrap();
+        let mut cellar = parse("#.\n.^\n").unwrap();
+        cellar.add_region("stash", Position::new(1, 0), Position::new(1, 1));
+
+        let world = compose(&[
+            ("hall", &hall, Position::new(0, 0)),
+            ("cellar", &cellar, Position::new(0, 1)),
+        ])
+        .unwrap();
+        assert_eq!((world.width(), world.height()), (3, 3));
+        assert_eq!(world.beepers_at(Position::new(2, 0)), 2);
+        assert!(world.is_wall(Position::new(0, 1)));
+        // The entry room's robot wins; the cellar's was only there so the
+        // cellar parses on its own.
+        assert_eq!(world.robot.position, Position::new(0, 0));
+        assert_eq!(world.robot.direction, Direction::East);
+        // Each room is a region, and the cellar's own region came along.
+        assert!(world.region_contains("cellar", Position::new(1, 2)));
+        assert!(!world.region_contains("cellar", Position::new(1, 0)));
+        assert!(world.region_contains("stash", Position::new(1, 1)));
+    }
+
+    #[test]
+    fn overlapping_rooms_are_rejected() {
+        let room = parse(">.\n..\n").unwrap();
+        assert_eq!(
+            compose(&[
+                ("a", &room, Position::new(0, 0)),
+                ("b", &room, Position::new(1, 1)),
+            ]),
+            Err(ComposeError::Overlap {
+                first: "a".to_string(),
+                second: "b".to_string(),
+            })
+        );
+        // Corner to corner is fine.
+        assert!(compose(&[
+            ("a", &room, Position::new(0, 0)),
+            ("b", &room, Position::new(2, 2)),
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn composed_names_must_be_unambiguous() {
+        let room = parse(">\n").unwrap();
+        assert_eq!(compose(&[]), Err(ComposeError::Empty));
+        assert_eq!(
+            compose(&[
+                ("a", &room, Position::new(0, 0)),
+                ("a", &room, Position::new(2, 0)),
+            ]),
+            Err(ComposeError::DuplicateRoom { name: "a".to_string() })
+        );
+        let mut named = parse(">\n").unwrap();
+        named.add_region("b", Position::new(0, 0), Position::new(0, 0));
+        assert_eq!(
+            compose(&[
+                ("a", &named, Position::new(0, 0)),
+                ("b", &room, Position::new(2, 0)),
+            ]),
+            Err(ComposeError::DuplicateRegion { name: "b".to_string() })
+        );
+    }
+
+    #[test]
+    fn fuzzing_entry_point_survives_arbitrary_bytes() {
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        for length in 0..512 {
+            let mut data = Vec::with_capacity(length);
+            for _ in 0..length {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                data.push(state as u8);
+            }
+            let _ = parse_for_fuzzing(&data);
+        }
+        assert!(parse_for_fuzzing(b">.2\n").is_some());
+        assert!(parse_for_fuzzing(b"\xff\xfe").is_none());
+    }
+}